@@ -0,0 +1,289 @@
+//! CIDR-based IP allow and deny lists.
+//!
+//! Rules are read from a file with one rule per line:
+//!
+//! ```text
+//! # Public API
+//! allow 10.0.0.0/8
+//! deny 10.1.2.3
+//! # Stricter rules for /admin, applied in addition to the public ones
+//! admin-allow 10.0.5.0/24
+//! ```
+//!
+//! A client is rejected if it matches a `deny` rule, or if any `allow` rules
+//! are configured and it matches none of them. Requests under `/admin` must
+//! pass both the public rules and the `admin-` rules. The file can be
+//! reloaded at runtime with [`IpAccessControl::reload`].
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use super::error::ApiError;
+
+/// Path prefix that the admin rules apply to.
+const ADMIN_PATH_PREFIX: &str = "/admin";
+
+/// An IP network in CIDR notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    /// Whether `ip` falls within this network.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for IpNet {
+    type Err = String;
+
+    /// Parse `addr/prefix`, or a bare address as a single-host network.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| format!("invalid IP address: {}", addr))?
+            .to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|&p| p <= max)
+                .ok_or_else(|| format!("invalid prefix length: {}", p))?,
+            None => max,
+        };
+        Ok(IpNet { addr, prefix })
+    }
+}
+
+/// Allow and deny rules for one scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpRules {
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+}
+
+impl IpRules {
+    /// Whether a client at `ip` passes these rules. An unknown address only
+    /// passes when no allow rules are configured.
+    pub fn permits(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => {
+                !self.deny.iter().any(|net| net.contains(ip))
+                    && (self.allow.is_empty() || self.allow.iter().any(|net| net.contains(ip)))
+            }
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+/// Rules for the public API and the admin endpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpAccessList {
+    pub public: IpRules,
+    pub admin: IpRules,
+}
+
+impl IpAccessList {
+    /// Parse the rules file format described in the module documentation.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut list = IpAccessList::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (directive, net) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("line {}: expected '<directive> <cidr>'", i + 1))?;
+            let net: IpNet = net
+                .trim()
+                .parse()
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+            let rules = match directive {
+                "allow" => &mut list.public.allow,
+                "deny" => &mut list.public.deny,
+                "admin-allow" => &mut list.admin.allow,
+                "admin-deny" => &mut list.admin.deny,
+                other => return Err(format!("line {}: unknown directive '{}'", i + 1, other)),
+            };
+            rules.push(net);
+        }
+        Ok(list)
+    }
+
+    /// Whether a client at `ip` may request `path`.
+    pub fn permits(&self, path: &str, ip: Option<IpAddr>) -> bool {
+        let is_admin = path
+            .strip_prefix(ADMIN_PATH_PREFIX)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        self.public.permits(ip) && (!is_admin || self.admin.permits(ip))
+    }
+}
+
+/// Hot-reloadable IP access control backed by a rules file.
+#[derive(Debug)]
+pub struct IpAccessControl {
+    path: PathBuf,
+    trust_forwarded_for: bool,
+    list: RwLock<Arc<IpAccessList>>,
+}
+
+impl IpAccessControl {
+    /// Load rules from `path`. When `trust_forwarded_for` is set, the client
+    /// address is taken from the last `X-Forwarded-For` entry, as appended by
+    /// a reverse proxy in front of the server.
+    pub fn load(path: impl Into<PathBuf>, trust_forwarded_for: bool) -> Result<Self, String> {
+        let path = path.into();
+        let list = read_rules(&path)?;
+        Ok(IpAccessControl {
+            path,
+            trust_forwarded_for,
+            list: RwLock::new(Arc::new(list)),
+        })
+    }
+
+    /// Re-read the rules file. On error the current rules stay in effect.
+    pub fn reload(&self) -> Result<(), String> {
+        let list = read_rules(&self.path)?;
+        *self.list.write().expect("access list lock poisoned") = Arc::new(list);
+        Ok(())
+    }
+
+    fn current(&self) -> Arc<IpAccessList> {
+        Arc::clone(&self.list.read().expect("access list lock poisoned"))
+    }
+
+    fn client_ip(&self, req: &Request) -> Option<IpAddr> {
+        if self.trust_forwarded_for {
+            let forwarded = req
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .and_then(|v| v.trim().parse::<IpAddr>().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+}
+
+fn read_rules(path: &std::path::Path) -> Result<IpAccessList, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    IpAccessList::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Middleware rejecting clients that the access rules do not permit.
+pub async fn ip_filter(
+    State(access): State<Arc<IpAccessControl>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let ip = access.client_ip(&req);
+    if !access.current().permits(req.uri().path(), ip) {
+        metrics::counter!("api_requests_denied_total").increment(1);
+        tracing::debug!(ip = ?ip, path = req.uri().path(), "request denied by IP rules");
+        return ApiError::Forbidden {
+            message: "access denied".to_string(),
+        }
+        .into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_ipnet_contains() {
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains("10.200.3.4".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.1.1".parse().unwrap()));
+
+        let host: IpNet = "192.0.2.1".parse().unwrap();
+        assert!(host.contains("192.0.2.1".parse().unwrap()));
+        assert!(!host.contains("192.0.2.2".parse().unwrap()));
+
+        let all: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains("203.0.113.9".parse().unwrap()));
+
+        let v6: IpNet = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!v6.contains("2001:db9::1".parse().unwrap()));
+        assert!(!v6.contains("10.0.0.1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("not-an-ip".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_access_list_rules() {
+        let list = IpAccessList::parse(
+            "# comment\n\
+             allow 10.0.0.0/8\n\
+             deny 10.0.0.5  # blocked host\n\
+             admin-allow 10.1.0.0/16\n",
+        )
+        .unwrap();
+
+        assert!(list.permits("/events", ip("10.2.3.4")));
+        assert!(!list.permits("/events", ip("10.0.0.5")));
+        assert!(!list.permits("/events", ip("192.0.2.1")));
+        assert!(!list.permits("/events", None));
+
+        assert!(list.permits("/admin/purge", ip("10.1.2.3")));
+        assert!(!list.permits("/admin/purge", ip("10.2.3.4")));
+        assert!(list.permits("/administrator", ip("10.2.3.4")));
+    }
+
+    #[test]
+    fn test_empty_access_list_permits_all() {
+        let list = IpAccessList::parse("").unwrap();
+        assert!(list.permits("/events", ip("192.0.2.1")));
+        assert!(list.permits("/admin", None));
+    }
+
+    #[test]
+    fn test_access_list_parse_errors() {
+        assert!(IpAccessList::parse("allow").is_err());
+        assert!(IpAccessList::parse("permit 10.0.0.0/8").is_err());
+        assert!(IpAccessList::parse("deny 10.0.0.0/99").is_err());
+    }
+}
@@ -0,0 +1,518 @@
+//! Operator endpoints under `/admin`.
+//!
+//! Destructive operations (any method other than GET or HEAD) and partition
+//! exports must be signed with the shared admin secret. An export serializes
+//! every cached partition, so it is signed to keep it from being used to load
+//! the server. A signed request carries two headers:
+//!
+//! - `X-Admin-Timestamp`: the current Unix time in seconds.
+//! - `X-Admin-Signature`: hex-encoded HMAC-SHA256 over
+//!   `"{timestamp}\n{METHOD}\n{path}\n{body}"`, keyed with the admin secret.
+//!
+//! Requests whose timestamp is more than [`MAX_CLOCK_SKEW_SECONDS`] away from
+//! the server clock are rejected, which bounds how long a captured request can
+//! be replayed.
+
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, Query, Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::backfill::{BackfillJobs, MAX_JOB_LEDGERS};
+use super::error::ApiError;
+use super::idempotency::{idempotency, IdempotencyCache};
+use super::types::{CachedLedger, PrettyJson};
+use crate::db::CacheSource;
+use crate::{snapshot, sync, AppState};
+
+/// Header carrying the request timestamp, in Unix seconds.
+pub const TIMESTAMP_HEADER: &str = "x-admin-timestamp";
+
+/// Header carrying the hex-encoded request signature.
+pub const SIGNATURE_HEADER: &str = "x-admin-signature";
+
+/// Maximum allowed difference between the request timestamp and server time.
+pub const MAX_CLOCK_SKEW_SECONDS: i64 = 300;
+
+/// Largest request body accepted, sized for partition snapshot imports.
+pub const MAX_ADMIN_BODY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Shared secret used to verify signed admin requests.
+#[derive(Clone)]
+pub struct AdminAuth {
+    secret: Vec<u8>,
+}
+
+impl AdminAuth {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        AdminAuth {
+            secret: secret.into(),
+        }
+    }
+
+    /// Compute the hex signature for a request. Clients use the same scheme.
+    pub fn sign(&self, timestamp: i64, method: &str, path: &str, body: &[u8]) -> String {
+        hex::encode(
+            self.mac(timestamp, method, path, body)
+                .finalize()
+                .into_bytes(),
+        )
+    }
+
+    /// Sign a GET request for `path` (including any query string) and add
+    /// the signature headers to `request`.
+    pub fn sign_get(
+        &self,
+        request: reqwest::RequestBuilder,
+        path: &str,
+    ) -> reqwest::RequestBuilder {
+        let timestamp = chrono::Utc::now().timestamp();
+        request
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, self.sign(timestamp, "GET", path, b""))
+    }
+
+    fn mac(&self, timestamp: i64, method: &str, path: &str, body: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}\n{}\n{}\n", timestamp, method, path).as_bytes());
+        mac.update(body);
+        mac
+    }
+
+    fn verify(
+        &self,
+        timestamp: i64,
+        method: &str,
+        path: &str,
+        body: &[u8],
+        signature: &str,
+    ) -> bool {
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        self.mac(timestamp, method, path, body)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+}
+
+impl std::fmt::Debug for AdminAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminAuth").finish_non_exhaustive()
+    }
+}
+
+/// Build the admin router. Mutating requests must be signed with `auth`, and
+/// may carry an `Idempotency-Key` header to make retries safe.
+pub fn router(state: Arc<AppState>, auth: AdminAuth) -> Router {
+    let backfill = Router::new()
+        .route("/admin/backfill", axum::routing::post(start_backfill))
+        .route("/admin/backfill/{id}", axum::routing::get(get_backfill))
+        .with_state(BackfillState {
+            app: Arc::clone(&state),
+            jobs: Arc::new(BackfillJobs::default()),
+        });
+
+    // Signatures are checked before idempotency so that unauthenticated
+    // requests cannot claim keys.
+    Router::new()
+        .route("/admin/store", axum::routing::get(store_stats))
+        .route("/admin/purge", axum::routing::post(purge))
+        .route(
+            "/admin/ledgers/{seq}/reload",
+            axum::routing::post(reload_ledger),
+        )
+        .route(snapshot::EXPORT_PATH, axum::routing::get(export_partitions))
+        .route(
+            "/admin/partitions/import",
+            axum::routing::post(import_partitions),
+        )
+        .with_state(state)
+        .merge(backfill)
+        .layer(DefaultBodyLimit::max(MAX_ADMIN_BODY_BYTES))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(IdempotencyCache::default()),
+            idempotency,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(auth),
+            require_signature,
+        ))
+        .layer(axum::middleware::from_fn(super::pretty::json_format))
+        .layer(axum::middleware::from_fn(super::request_id::request_id))
+}
+
+/// Middleware verifying the signature of mutating admin requests and
+/// partition exports.
+async fn require_signature(
+    State(auth): State<Arc<AdminAuth>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let read = req.method() == Method::GET || req.method() == Method::HEAD;
+    if read && req.uri().path() != snapshot::EXPORT_PATH {
+        return next.run(req).await;
+    }
+
+    let unauthorized = |message: &str| {
+        metrics::counter!("admin_signature_failures_total").increment(1);
+        ApiError::Unauthorized {
+            message: message.to_string(),
+        }
+        .into_response()
+    };
+
+    let (Some(timestamp), Some(signature)) = (
+        header_str(&req, TIMESTAMP_HEADER),
+        header_str(&req, SIGNATURE_HEADER),
+    ) else {
+        return unauthorized("missing request signature");
+    };
+    let Ok(timestamp) = timestamp.parse::<i64>() else {
+        return unauthorized("invalid request timestamp");
+    };
+    if (chrono::Utc::now().timestamp() - timestamp).abs() > MAX_CLOCK_SKEW_SECONDS {
+        return unauthorized("request timestamp outside the allowed window");
+    }
+
+    // Buffer the body so it can be both verified and passed on.
+    let (parts, body) = req.into_parts();
+    let body: Bytes = match axum::body::to_bytes(body, MAX_ADMIN_BODY_BYTES).await {
+        Ok(b) => b,
+        Err(_) => {
+            return ApiError::BadRequest {
+                message: "request body too large".to_string(),
+                param: None,
+            }
+            .into_response()
+        }
+    };
+    // Verify against the path as the client sent it, including any base
+    // path the router is nested under.
+    let uri = parts
+        .extensions
+        .get::<axum::extract::OriginalUri>()
+        .map_or(&parts.uri, |original| &original.0);
+    let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+    if !auth.verify(timestamp, parts.method.as_str(), path, &body, &signature) {
+        return unauthorized("invalid request signature");
+    }
+
+    next.run(Request::from_parts(parts, body.into())).await
+}
+
+fn header_str(req: &Request, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+#[derive(Debug, Serialize)]
+struct StoreStatsResponse {
+    object: &'static str,
+    partition_count: usize,
+    event_count: usize,
+    /// Approximate bytes held by the partitions.
+    partition_bytes: usize,
+    /// Approximate bytes held by the contract and transaction indexes, and
+    /// by the indexes shared within compacted segments.
+    index_bytes: usize,
+    total_bytes: usize,
+    partitions: Vec<PartitionStatsResponse>,
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionStatsResponse {
+    ledger: u32,
+    /// `memory`, `segment`, `compressed`, or `spilled`.
+    storage: &'static str,
+    event_count: usize,
+    approx_bytes: usize,
+    expires_at: String,
+}
+
+/// GET /admin/store
+///
+/// Event counts, approximate memory sizes, and expiry times of the cached
+/// partitions, with the store's total footprint, for sizing instances.
+async fn store_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let stats = state.store.stats();
+    let total_bytes = stats.total_bytes();
+    PrettyJson(StoreStatsResponse {
+        object: "store_stats",
+        partition_count: stats.partitions.len(),
+        event_count: stats.partitions.iter().map(|p| p.event_count).sum(),
+        partition_bytes: total_bytes - stats.index_bytes,
+        index_bytes: stats.index_bytes,
+        total_bytes,
+        partitions: stats
+            .partitions
+            .into_iter()
+            .map(|p| PartitionStatsResponse {
+                ledger: p.ledger,
+                storage: p.storage.as_str(),
+                event_count: p.event_count,
+                approx_bytes: p.approx_bytes,
+                expires_at: chrono::DateTime::from_timestamp(p.expires_at, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct PurgeRequest {
+    start: u32,
+    end: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct PurgeResponse {
+    object: &'static str,
+    start: u32,
+    end: u32,
+    purged: usize,
+}
+
+/// POST /admin/purge
+///
+/// Drop the cached partitions for ledgers `start..=end`. They are re-fetched
+/// from the archive on the next query that needs them.
+async fn purge(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PurgeRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if req.start > req.end {
+        return Err(ApiError::BadRequest {
+            message: "start must not be greater than end".to_string(),
+            param: Some("start".to_string()),
+        });
+    }
+    let purged = state.store.purge_range(req.start, req.end);
+    tracing::info!(
+        start = req.start,
+        end = req.end,
+        purged,
+        "purged cached ledgers"
+    );
+    Ok(PrettyJson(PurgeResponse {
+        object: "purge",
+        start: req.start,
+        end: req.end,
+        purged,
+    }))
+}
+
+/// POST /admin/ledgers/{seq}/reload
+///
+/// Re-fetch a ledger from the origin and re-extract its events, replacing
+/// the cached partition. Used after extraction changes, or when a bad fetch
+/// was cached. The peer instance is bypassed, and the existing partition is
+/// kept if the fetch fails.
+async fn reload_ledger(
+    State(state): State<Arc<AppState>>,
+    Path(seq): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let seq = seq.parse::<u32>().map_err(|_| ApiError::BadRequest {
+        message: "ledger must be a ledger sequence".to_string(),
+        param: Some("seq".to_string()),
+    })?;
+    let (info, events) =
+        sync::fetch_and_extract(&state.client, &state.meta_url, &state.config.get(), seq)
+            .await
+            .map_err(|e| match e {
+                crate::Error::LedgerNotFound(_) => ApiError::NotFound {
+                    message: format!("ledger not found: {}", seq),
+                },
+                e => ApiError::Internal {
+                    message: format!("failed to fetch ledger {}: {}", seq, e),
+                },
+            })?;
+    let previous = state.store.ledger_summary(seq).map(|s| s.event_count);
+    // A reload names its ledger, so it is kept like a pinned lookup.
+    state
+        .store
+        .replace_ledger(seq, info, events, CacheSource::Pinned);
+    metrics::counter!("admin_ledger_reloads_total").increment(1);
+
+    let summary = state
+        .store
+        .ledger_summary(seq)
+        .ok_or_else(|| ApiError::Internal {
+            message: format!("reloaded ledger {} is missing", seq),
+        })?;
+    tracing::info!(
+        ledger = seq,
+        previous_events = ?previous,
+        events = summary.event_count,
+        "reloaded ledger"
+    );
+    Ok(PrettyJson(CachedLedger::from(summary)))
+}
+
+#[derive(Clone)]
+struct BackfillState {
+    app: Arc<AppState>,
+    jobs: Arc<BackfillJobs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackfillRequest {
+    start: u32,
+    end: u32,
+}
+
+/// POST /admin/backfill
+///
+/// Start a background job fetching every uncached ledger in `start..=end`.
+/// Responds with `202 Accepted` and the job, whose progress can be polled at
+/// `GET /admin/backfill/{id}`.
+async fn start_backfill(
+    State(state): State<BackfillState>,
+    Json(req): Json<BackfillRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if req.start > req.end {
+        return Err(ApiError::BadRequest {
+            message: "start must not be greater than end".to_string(),
+            param: Some("start".to_string()),
+        });
+    }
+    if req.end - req.start >= MAX_JOB_LEDGERS {
+        return Err(ApiError::BadRequest {
+            message: format!(
+                "a backfill job may cover at most {} ledgers",
+                MAX_JOB_LEDGERS
+            ),
+            param: Some("end".to_string()),
+        });
+    }
+    let id = state.jobs.start(state.app, req.start, req.end);
+    tracing::info!(id = %id, start = req.start, end = req.end, "started backfill job");
+    let status = state.jobs.status(&id).ok_or_else(|| ApiError::Internal {
+        message: "backfill job disappeared".to_string(),
+    })?;
+    Ok((StatusCode::ACCEPTED, PrettyJson(status)))
+}
+
+/// GET /admin/backfill/{id}
+///
+/// Progress of a backfill job. Finished jobs are kept for a day.
+async fn get_backfill(
+    State(state): State<BackfillState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .jobs
+        .status(&id)
+        .map(PrettyJson)
+        .ok_or_else(|| ApiError::NotFound {
+            message: format!("backfill job not found: {}", id),
+        })
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    start: Option<u32>,
+    end: Option<u32>,
+}
+
+/// GET /admin/partitions/export
+///
+/// Download the cached partitions for ledgers `start..=end` (every cached
+/// ledger by default) as a snapshot that another instance can import.
+async fn export_partitions(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let start = query.start.unwrap_or(0);
+    let end = query.end.unwrap_or(u32::MAX);
+    if start > end {
+        return Err(ApiError::BadRequest {
+            message: "start must not be greater than end".to_string(),
+            param: Some("start".to_string()),
+        });
+    }
+    let (data, partitions) =
+        snapshot::export(&state.store, state.config.network_passphrase(), start, end).map_err(
+            |e| ApiError::Internal {
+                message: format!("failed to export partitions: {}", e),
+            },
+        )?;
+    tracing::info!(
+        start,
+        end,
+        partitions,
+        bytes = data.len(),
+        "exported partitions"
+    );
+    Ok(([(header::CONTENT_TYPE, snapshot::CONTENT_TYPE)], data))
+}
+
+#[derive(Debug, Serialize)]
+struct ImportResponse {
+    object: &'static str,
+    imported: usize,
+    skipped: usize,
+}
+
+/// POST /admin/partitions/import
+///
+/// Load a snapshot produced by the export endpoint of an instance serving
+/// the same network. Ledgers that are already cached are skipped.
+async fn import_partitions(
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let summary = snapshot::import(
+        &state.store,
+        state.config.network_passphrase(),
+        &body,
+        CacheSource::Sync,
+    )
+    .map_err(|e| ApiError::BadRequest {
+        message: format!("invalid snapshot: {}", e),
+        param: None,
+    })?;
+    tracing::info!(
+        imported = summary.imported,
+        skipped = summary.skipped,
+        "imported partitions"
+    );
+    Ok(PrettyJson(ImportResponse {
+        object: "partition_import",
+        imported: summary.imported,
+        skipped: summary.skipped,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature() {
+        let auth = AdminAuth::new("secret");
+        let sig = auth.sign(1700000000, "POST", "/admin/purge", b"{}");
+        assert!(auth.verify(1700000000, "POST", "/admin/purge", b"{}", &sig));
+        assert!(auth.verify(
+            1700000000,
+            "POST",
+            "/admin/purge",
+            b"{}",
+            &sig.to_uppercase()
+        ));
+        assert!(!auth.verify(1700000001, "POST", "/admin/purge", b"{}", &sig));
+        assert!(!auth.verify(1700000000, "POST", "/admin/purge", b"{ }", &sig));
+        assert!(!AdminAuth::new("other").verify(1700000000, "POST", "/admin/purge", b"{}", &sig));
+    }
+}
@@ -0,0 +1,168 @@
+//! Operator-triggered backfill jobs.
+//!
+//! Queries backfill the ledgers they need on demand, which makes pre-warming
+//! a known range awkward. A backfill job fetches every uncached ledger in a
+//! range in the background, one batch at a time, and records its progress so
+//! it can be polled by ID.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde::Serialize;
+
+use super::routes::{backfill_batch, BACKFILL_BATCH_SIZE};
+use crate::db::CacheSource;
+use crate::AppState;
+
+/// Largest range a single job may cover, in ledgers.
+pub const MAX_JOB_LEDGERS: u32 = 100_000;
+
+/// How long finished jobs remain queryable.
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Progress of a backfill job, updated as it runs.
+#[derive(Debug)]
+struct Job {
+    start: u32,
+    end: u32,
+    created_at: i64,
+    /// Ledgers checked so far, whether or not they needed fetching.
+    processed: AtomicU32,
+    /// Ledgers that were already cached.
+    skipped: AtomicU32,
+    /// Ledgers fetched and cached.
+    fetched: AtomicU32,
+    /// Ledgers that could not be fetched.
+    failed: AtomicU32,
+    done: AtomicBool,
+    finished_at: std::sync::Mutex<Option<(Instant, i64)>>,
+}
+
+/// A snapshot of a job's progress.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub object: &'static str,
+    pub id: String,
+    /// `running` or `completed`.
+    pub status: &'static str,
+    pub start: u32,
+    pub end: u32,
+    pub total: u32,
+    pub processed: u32,
+    pub skipped: u32,
+    pub fetched: u32,
+    pub failed: u32,
+    /// Unix time the job was created.
+    pub created_at: i64,
+    /// Unix time the job finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<i64>,
+}
+
+/// Running and recently finished backfill jobs.
+#[derive(Debug, Default)]
+pub struct BackfillJobs {
+    jobs: DashMap<String, Arc<Job>>,
+}
+
+impl BackfillJobs {
+    /// Start a job backfilling ledgers `start..=end` and return its ID.
+    pub fn start(&self, state: Arc<AppState>, start: u32, end: u32) -> String {
+        self.evict_finished();
+
+        let id = format!("bf_{:016x}", rand::random::<u64>());
+        let job = Arc::new(Job {
+            start,
+            end,
+            created_at: chrono::Utc::now().timestamp(),
+            processed: AtomicU32::new(0),
+            skipped: AtomicU32::new(0),
+            fetched: AtomicU32::new(0),
+            failed: AtomicU32::new(0),
+            done: AtomicBool::new(false),
+            finished_at: std::sync::Mutex::new(None),
+        });
+        self.jobs.insert(id.clone(), Arc::clone(&job));
+        metrics::counter!("admin_backfill_jobs_total").increment(1);
+
+        let job_id = id.clone();
+        tokio::spawn(async move {
+            run(&state, &job).await;
+            tracing::info!(
+                id = %job_id,
+                start,
+                end,
+                fetched = job.fetched.load(Ordering::Relaxed),
+                failed = job.failed.load(Ordering::Relaxed),
+                "backfill job completed"
+            );
+        });
+        id
+    }
+
+    /// The progress of job `id`, if it exists.
+    pub fn status(&self, id: &str) -> Option<JobStatus> {
+        let job = Arc::clone(self.jobs.get(id)?.value());
+        let completed_at = job
+            .finished_at
+            .lock()
+            .expect("backfill job lock poisoned")
+            .map(|(_, at)| at);
+        Some(JobStatus {
+            object: "backfill_job",
+            id: id.to_string(),
+            status: if job.done.load(Ordering::Acquire) {
+                "completed"
+            } else {
+                "running"
+            },
+            start: job.start,
+            end: job.end,
+            total: job.end - job.start + 1,
+            processed: job.processed.load(Ordering::Relaxed),
+            skipped: job.skipped.load(Ordering::Relaxed),
+            fetched: job.fetched.load(Ordering::Relaxed),
+            failed: job.failed.load(Ordering::Relaxed),
+            created_at: job.created_at,
+            completed_at,
+        })
+    }
+
+    fn evict_finished(&self) {
+        self.jobs.retain(|_, job| {
+            job.finished_at
+                .lock()
+                .expect("backfill job lock poisoned")
+                .is_none_or(|(at, _)| at.elapsed() < FINISHED_JOB_TTL)
+        });
+    }
+}
+
+async fn run(state: &AppState, job: &Job) {
+    let mut batch_start = job.start;
+    loop {
+        let count = BACKFILL_BATCH_SIZE.min(job.end - batch_start + 1);
+        let uncached = state
+            .store
+            .find_uncached_ledgers(batch_start, count)
+            .unwrap_or_default();
+        let result = backfill_batch(state, &uncached, CacheSource::Backfill).await;
+
+        job.skipped
+            .fetch_add(count - uncached.len() as u32, Ordering::Relaxed);
+        job.fetched.fetch_add(result.fetched, Ordering::Relaxed);
+        job.failed
+            .fetch_add(uncached.len() as u32 - result.fetched, Ordering::Relaxed);
+        job.processed.fetch_add(count, Ordering::Relaxed);
+
+        match batch_start.checked_add(count) {
+            Some(next) if next <= job.end => batch_start = next,
+            _ => break,
+        }
+    }
+    *job.finished_at.lock().expect("backfill job lock poisoned") =
+        Some((Instant::now(), chrono::Utc::now().timestamp()));
+    job.done.store(true, Ordering::Release);
+}
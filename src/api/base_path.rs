@@ -0,0 +1,73 @@
+//! Path prefix the API is served under.
+//!
+//! With `--base-path`, every route is mounted below the prefix and the links
+//! in responses (`url` and `next` fields, error `doc_url`s) carry it, so the
+//! service can share a hostname behind a reverse proxy that forwards paths
+//! unchanged.
+
+use std::sync::OnceLock;
+
+static BASE_PATH: OnceLock<String> = OnceLock::new();
+
+/// Normalize a configured base path to `/segment[/segment...]` with no
+/// trailing slash. The root (`/` or empty) normalizes to the empty string.
+pub fn normalize(path: &str) -> Result<String, String> {
+    let trimmed = path.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Ok(String::new());
+    }
+    if !trimmed.starts_with('/') {
+        return Err(format!("base path must start with '/': {}", path));
+    }
+    if trimmed.contains(['?', '#', '{', '}']) || trimmed.contains("//") {
+        return Err(format!("invalid base path: {}", path));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Install the process-wide base path. Only the first call has an effect.
+pub fn init(path: String) {
+    let _ = BASE_PATH.set(path);
+}
+
+/// The installed base path, or the empty string if none was installed.
+pub fn get() -> &'static str {
+    BASE_PATH.get().map(String::as_str).unwrap_or_default()
+}
+
+tokio::task_local! {
+    /// Prefix of the additional network whose routes handle the current
+    /// request, e.g. `/networks/testnet`.
+    static NETWORK_PREFIX: String;
+}
+
+/// Run `future` with links carrying the network `prefix` after the base
+/// path, for routes nested under `/networks/{name}`.
+pub async fn with_network_prefix<F: std::future::Future>(prefix: String, future: F) -> F::Output {
+    NETWORK_PREFIX.scope(prefix, future).await
+}
+
+/// `path` (which starts with `/`) under the base path, and under the
+/// network prefix when handling a request for an additional network.
+pub fn link(path: &str) -> String {
+    let network = NETWORK_PREFIX
+        .try_with(|prefix| prefix.clone())
+        .unwrap_or_default();
+    format!("{}{}{}", get(), network, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("").unwrap(), "");
+        assert_eq!(normalize("/").unwrap(), "");
+        assert_eq!(normalize("/stellar-events").unwrap(), "/stellar-events");
+        assert_eq!(normalize("/a/b/").unwrap(), "/a/b");
+        assert!(normalize("stellar-events").is_err());
+        assert!(normalize("/a//b").is_err());
+        assert!(normalize("/a?b").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Deprecation and sunset signalling.
+//!
+//! Endpoints and query parameters scheduled for removal are listed in
+//! [`DEPRECATIONS`]. Responses to requests that use them carry a
+//! `Deprecation` header (RFC 9745), a `Sunset` header (RFC 8594) when a
+//! removal date is set, and a `Link` to migration notes, so clients get
+//! advance warning before anything breaks.
+
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+
+/// A deprecated endpoint, or a deprecated parameter of an endpoint.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    /// Request path the deprecation applies to, e.g. `/events`.
+    pub path: &'static str,
+    /// Query parameter that is deprecated. `None` deprecates the whole endpoint.
+    pub param: Option<&'static str>,
+    /// When the item was deprecated, as a Unix timestamp.
+    pub deprecated_at: i64,
+    /// When the item stops working, as a Unix timestamp.
+    pub sunset_at: Option<i64>,
+    /// Documentation describing the replacement.
+    pub link: Option<&'static str>,
+}
+
+/// Currently deprecated endpoints and parameters.
+pub const DEPRECATIONS: &[Deprecation] = &[];
+
+impl Deprecation {
+    /// Whether a request for `path` with the given raw query string uses this item.
+    fn applies_to(&self, path: &str, query: Option<&str>) -> bool {
+        if self.path != path {
+            return false;
+        }
+        let Some(param) = self.param else {
+            return true;
+        };
+        query.unwrap_or_default().split('&').any(|pair| {
+            let key = pair.split_once('=').map_or(pair, |(k, _)| k);
+            urlencoding::decode(key).is_ok_and(|k| k == param)
+        })
+    }
+
+    fn name(&self) -> String {
+        match self.param {
+            Some(param) => format!("{}?{}", self.path, param),
+            None => self.path.to_string(),
+        }
+    }
+}
+
+/// Add deprecation headers to `headers` for every item in `deprecations` that
+/// the request uses. When several match, the earliest dates are reported.
+fn apply(deprecations: &[Deprecation], path: &str, query: Option<&str>, headers: &mut HeaderMap) {
+    let matched: Vec<&Deprecation> = deprecations
+        .iter()
+        .filter(|d| d.applies_to(path, query))
+        .collect();
+
+    let Some(deprecated_at) = matched.iter().map(|d| d.deprecated_at).min() else {
+        return;
+    };
+    if let Ok(value) = HeaderValue::from_str(&format!("@{}", deprecated_at)) {
+        headers.insert("deprecation", value);
+    }
+
+    let sunset = matched
+        .iter()
+        .filter_map(|d| d.sunset_at)
+        .min()
+        .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0));
+    if let Some(sunset) = sunset {
+        let date = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert("sunset", value);
+        }
+    }
+
+    for d in &matched {
+        metrics::counter!("api_deprecated_requests_total", "item" => d.name()).increment(1);
+        if let Some(link) = d.link {
+            if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"", link)) {
+                headers.append("link", value);
+            }
+        }
+    }
+}
+
+/// Middleware attaching deprecation headers for requests that use a
+/// deprecated endpoint or parameter.
+pub async fn deprecation_headers(req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    let query = req.uri().query().map(str::to_string);
+    let mut resp = next.run(req).await;
+    apply(DEPRECATIONS, &path, query.as_deref(), resp.headers_mut());
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_DEPRECATIONS: &[Deprecation] = &[
+        Deprecation {
+            path: "/events",
+            param: Some("old_param"),
+            deprecated_at: 1_767_225_600,
+            sunset_at: Some(1_782_864_000),
+            link: Some("https://example.com/migrate"),
+        },
+        Deprecation {
+            path: "/legacy",
+            param: None,
+            deprecated_at: 1_767_225_600,
+            sunset_at: None,
+            link: None,
+        },
+    ];
+
+    #[test]
+    fn test_deprecated_param_headers() {
+        let mut headers = HeaderMap::new();
+        apply(
+            TEST_DEPRECATIONS,
+            "/events",
+            Some("limit=5&old_param=1"),
+            &mut headers,
+        );
+        assert_eq!(headers["deprecation"], "@1767225600");
+        assert_eq!(headers["sunset"], "Wed, 01 Jul 2026 00:00:00 GMT");
+        assert_eq!(
+            headers["link"],
+            "<https://example.com/migrate>; rel=\"deprecation\""
+        );
+    }
+
+    #[test]
+    fn test_deprecated_endpoint_headers() {
+        let mut headers = HeaderMap::new();
+        apply(TEST_DEPRECATIONS, "/legacy", None, &mut headers);
+        assert_eq!(headers["deprecation"], "@1767225600");
+        assert!(headers.get("sunset").is_none());
+        assert!(headers.get("link").is_none());
+    }
+
+    #[test]
+    fn test_no_headers_when_unused() {
+        let mut headers = HeaderMap::new();
+        apply(TEST_DEPRECATIONS, "/events", Some("limit=5"), &mut headers);
+        apply(TEST_DEPRECATIONS, "/events", None, &mut headers);
+        apply(
+            TEST_DEPRECATIONS,
+            "/health",
+            Some("old_param=1"),
+            &mut headers,
+        );
+        assert!(headers.is_empty());
+    }
+}
@@ -1,57 +1,189 @@
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use serde::Serialize;
 
+use super::base_path;
 use super::types::{ErrorBody, ErrorResponse, PrettyJson};
 
+/// Stable, machine-readable error codes returned in the `code` field of error
+/// responses. Each code is documented at `/errors/{code}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidParameter,
+    NetworkMismatch,
+    Unauthorized,
+    AccessDenied,
+    ResourceMissing,
+    Conflict,
+    StaleCursor,
+    InternalError,
+}
+
+impl ErrorCode {
+    /// Every error code, in documentation order.
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::InvalidParameter,
+        ErrorCode::NetworkMismatch,
+        ErrorCode::Unauthorized,
+        ErrorCode::AccessDenied,
+        ErrorCode::ResourceMissing,
+        ErrorCode::Conflict,
+        ErrorCode::StaleCursor,
+        ErrorCode::InternalError,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidParameter => "invalid_parameter",
+            ErrorCode::NetworkMismatch => "network_mismatch",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::AccessDenied => "access_denied",
+            ErrorCode::ResourceMissing => "resource_missing",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::StaleCursor => "stale_cursor",
+            ErrorCode::InternalError => "internal_error",
+        }
+    }
+
+    /// Broad error category, as reported in the `type` field.
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            ErrorCode::InternalError => "api_error",
+            ErrorCode::Unauthorized => "authentication_error",
+            ErrorCode::AccessDenied => "permission_error",
+            _ => "invalid_request_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidParameter | ErrorCode::NetworkMismatch | ErrorCode::StaleCursor => {
+                StatusCode::BAD_REQUEST
+            }
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::AccessDenied => StatusCode::FORBIDDEN,
+            ErrorCode::ResourceMissing => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidParameter => {
+                "A request parameter is missing, malformed, or out of range. The `param` field names the offending parameter."
+            }
+            ErrorCode::NetworkMismatch => {
+                "The `network` parameter does not match the network passphrase this instance serves."
+            }
+            ErrorCode::Unauthorized => {
+                "The request is missing valid credentials, for example an admin request without a valid signature."
+            }
+            ErrorCode::AccessDenied => {
+                "The client is not permitted to access this resource, for example because its IP address is not allowed."
+            }
+            ErrorCode::ResourceMissing => "The requested resource does not exist.",
+            ErrorCode::Conflict => {
+                "The request conflicts with another request, for example an `Idempotency-Key` reused for a different request or one still in progress."
+            }
+            ErrorCode::StaleCursor => {
+                "A pagination cursor refers to a cached ledger that has since been dropped or replaced, so continuing from it could skip or repeat events. Start paginating again without the cursor."
+            }
+            ErrorCode::InternalError => {
+                "The server failed to process the request. Retrying may succeed."
+            }
+        }
+    }
+
+    /// Path of the reference documentation for this code.
+    pub fn doc_url(&self) -> String {
+        base_path::link(&format!("/errors/{}", self.as_str()))
+    }
+}
+
+impl std::str::FromStr for ErrorCode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|code| code.as_str() == s)
+            .ok_or(())
+    }
+}
+
 /// API error type that converts to HTTP responses.
 pub enum ApiError {
     BadRequest {
         message: String,
         param: Option<String>,
     },
+    NetworkMismatch {
+        message: String,
+    },
+    Unauthorized {
+        message: String,
+    },
+    Forbidden {
+        message: String,
+    },
     NotFound {
         message: String,
     },
+    Conflict {
+        message: String,
+    },
+    StaleCursor {
+        message: String,
+        param: Option<String>,
+    },
     Internal {
         message: String,
     },
 }
 
+impl ApiError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::BadRequest { .. } => ErrorCode::InvalidParameter,
+            ApiError::NetworkMismatch { .. } => ErrorCode::NetworkMismatch,
+            ApiError::Unauthorized { .. } => ErrorCode::Unauthorized,
+            ApiError::Forbidden { .. } => ErrorCode::AccessDenied,
+            ApiError::NotFound { .. } => ErrorCode::ResourceMissing,
+            ApiError::Conflict { .. } => ErrorCode::Conflict,
+            ApiError::StaleCursor { .. } => ErrorCode::StaleCursor,
+            ApiError::Internal { .. } => ErrorCode::InternalError,
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, error_type, code, message, param) = match self {
-            ApiError::BadRequest { message, param } => (
-                StatusCode::BAD_REQUEST,
-                "invalid_request_error",
-                Some("invalid_parameter".to_string()),
-                message,
-                param,
-            ),
-            ApiError::NotFound { message } => (
-                StatusCode::NOT_FOUND,
-                "invalid_request_error",
-                Some("resource_missing".to_string()),
-                message,
-                None,
-            ),
-            ApiError::Internal { message } => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "api_error",
-                None,
-                message,
-                None,
-            ),
+        let code = self.code();
+        let (message, param) = match self {
+            ApiError::BadRequest { message, param } | ApiError::StaleCursor { message, param } => {
+                (message, param)
+            }
+            ApiError::NetworkMismatch { message } => (message, Some("network".to_string())),
+            ApiError::Unauthorized { message }
+            | ApiError::Forbidden { message }
+            | ApiError::NotFound { message }
+            | ApiError::Conflict { message }
+            | ApiError::Internal { message } => (message, None),
         };
 
         let body = ErrorResponse {
             error: ErrorBody {
-                error_type: error_type.to_string(),
+                error_type: code.error_type().to_string(),
                 code,
                 message,
                 param,
+                doc_url: code.doc_url(),
+                request_id: super::request_id::current(),
             },
         };
 
-        (status, PrettyJson(body)).into_response()
+        (code.status(), PrettyJson(body)).into_response()
     }
 }
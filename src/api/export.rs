@@ -0,0 +1,181 @@
+//! Bulk export of every event in a ledger range.
+//!
+//! `GET /export` streams the events of a bounded range as NDJSON or CSV,
+//! backfilling uncached ledgers one batch at a time as the response is
+//! written, so a one-shot extract needs no pagination loop and the server
+//! never holds more than a batch of output in memory.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use super::error::ApiError;
+use super::routes::{backfill_batch, BACKFILL_BATCH_SIZE};
+use super::types::{Event, NDJSON_CONTENT_TYPE};
+use crate::db::{CacheSource, EventRow};
+use crate::AppState;
+
+/// Largest range a single export may cover, in ledgers.
+pub const MAX_EXPORT_LEDGERS: u32 = 10_000;
+
+/// Columns of a CSV export.
+const CSV_HEADER: &str = "id,ledger,at,tx,type,contract,topics,data\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ndjson,
+    Csv,
+}
+
+impl Format {
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Ndjson => NDJSON_CONTENT_TYPE,
+            Format::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+        }
+    }
+
+    fn write(self, out: &mut Vec<u8>, row: EventRow) {
+        match self {
+            Format::Ndjson => {
+                if serde_json::to_writer(&mut *out, &Event::from(row)).is_ok() {
+                    out.push(b'\n');
+                }
+            }
+            Format::Csv => {
+                let fields = [
+                    row.id,
+                    row.ledger_sequence.to_string(),
+                    row.ledger_closed_at,
+                    row.tx_hash,
+                    row.event_type.to_string(),
+                    row.contract_id.unwrap_or_default(),
+                    row.topics.to_string(),
+                    row.data.to_string(),
+                ];
+                let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                out.extend_from_slice(line.join(",").as_bytes());
+                out.push(b'\n');
+            }
+        }
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    start: Option<String>,
+    end: Option<String>,
+    format: Option<String>,
+}
+
+/// GET /export
+///
+/// Stream every event in ledgers `start..=end` in ledger order, as NDJSON
+/// (the default) or CSV. Uncached ledgers are backfilled as the export
+/// reaches them; ledgers that cannot be fetched are skipped.
+#[tracing::instrument(skip_all)]
+pub async fn export_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let ledger = |value: Option<&str>, param: &str| {
+        value
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| ApiError::BadRequest {
+                message: format!("{} must be a ledger sequence", param),
+                param: Some(param.to_string()),
+            })
+    };
+    let start = ledger(query.start.as_deref(), "start")?;
+    let end = ledger(query.end.as_deref(), "end")?;
+    if start > end {
+        return Err(ApiError::BadRequest {
+            message: "start must not be greater than end".to_string(),
+            param: Some("start".to_string()),
+        });
+    }
+    if end - start >= MAX_EXPORT_LEDGERS {
+        return Err(ApiError::BadRequest {
+            message: format!("an export may cover at most {} ledgers", MAX_EXPORT_LEDGERS),
+            param: Some("end".to_string()),
+        });
+    }
+    let format = match query.format.as_deref() {
+        None | Some("ndjson") => Format::Ndjson,
+        Some("csv") => Format::Csv,
+        Some(other) => {
+            return Err(ApiError::BadRequest {
+                message: format!("invalid format '{}' (expected: ndjson, csv)", other),
+                param: Some("format".to_string()),
+            })
+        }
+    };
+    metrics::counter!("api_requests_total", "endpoint" => "export").increment(1);
+
+    let header_chunk = match format {
+        Format::Csv => Some(CSV_HEADER.as_bytes().to_vec()),
+        Format::Ndjson => None,
+    };
+    let batches = futures::stream::unfold(Some(start), move |next| {
+        let state = Arc::clone(&state);
+        async move {
+            let batch_start = next?;
+            let count = BACKFILL_BATCH_SIZE.min(end - batch_start + 1);
+            let uncached = state
+                .store
+                .find_uncached_ledgers(batch_start, count)
+                .unwrap_or_default();
+            backfill_batch(&state, &uncached, CacheSource::Backfill).await;
+
+            let mut chunk = Vec::new();
+            for seq in batch_start..=batch_start + (count - 1) {
+                for row in state.store.ledger_events(seq) {
+                    format.write(&mut chunk, row);
+                }
+            }
+            let following = batch_start.checked_add(count).filter(|&n| n <= end);
+            Some((chunk, following))
+        }
+    });
+    let body = futures::StreamExt::map(
+        futures::StreamExt::chain(futures::stream::iter(header_chunk), batches),
+        Ok::<_, Infallible>,
+    );
+
+    tracing::info!(start, end, format = format.extension(), "exporting events");
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"events-{}-{}.{}\"",
+                    start,
+                    end,
+                    format.extension()
+                ),
+            ),
+        ],
+        axum::body::Body::from_stream(body),
+    ))
+}
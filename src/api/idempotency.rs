@@ -0,0 +1,397 @@
+//! Idempotent replay of mutating requests.
+//!
+//! A client retrying a `POST` (or other non-safe method) after a timeout
+//! cannot tell whether the first attempt took effect. Sending the same
+//! `Idempotency-Key` header on each attempt makes the retry safe: the first
+//! response is stored and replayed verbatim for later requests with the same
+//! key, with an `Idempotent-Replayed: true` header added.
+//!
+//! A key is bound to the method, path, and body of the request that first
+//! used it. Reusing a key for a different request, or while the first request
+//! is still being processed, is rejected with `409 conflict`. Server errors
+//! are not stored, so a request that failed with a 5xx can be retried under
+//! the same key. A response too large to store is passed through as is, and
+//! replayed with its status and headers but an empty body, so that the key
+//! still guards against running the request twice.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use bytes::BytesMut;
+use dashmap::mapref::entry::Entry as MapEntry;
+use dashmap::DashMap;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+
+use super::error::ApiError;
+
+/// Request header carrying the client-chosen key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on replayed responses.
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest accepted idempotency key.
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// How long stored responses are kept by default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Largest request body that is buffered for fingerprinting.
+const MAX_REQUEST_BODY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Largest response body that is buffered for replay.
+const MAX_RESPONSE_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug)]
+enum Entry {
+    InFlight {
+        fingerprint: [u8; 32],
+    },
+    Completed {
+        fingerprint: [u8; 32],
+        status: StatusCode,
+        headers: HeaderMap,
+        /// The response body, or `None` if it was too large to store.
+        body: Option<Bytes>,
+        expires_at: Instant,
+    },
+}
+
+impl Entry {
+    fn fingerprint(&self) -> &[u8; 32] {
+        match self {
+            Entry::InFlight { fingerprint } | Entry::Completed { fingerprint, .. } => fingerprint,
+        }
+    }
+}
+
+/// Stored responses keyed by idempotency key.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: DashMap<String, Entry>,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl IdempotencyCache {
+    /// Create a cache that keeps responses for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        IdempotencyCache {
+            ttl,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Number of keys currently held, including in-flight requests.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no keys are held.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn evict_expired(&self) {
+        let now = Instant::now();
+        self.entries.retain(|_, entry| match entry {
+            Entry::InFlight { .. } => true,
+            Entry::Completed { expires_at, .. } => *expires_at > now,
+        });
+    }
+}
+
+/// Outcome of claiming a key for a new request.
+enum Claim {
+    /// The key is new; the request should be processed.
+    Claimed,
+    /// A stored response exists for an identical request.
+    Replay(Response),
+    /// The key is in use by a different or unfinished request.
+    Conflict(&'static str),
+}
+
+fn claim(cache: &IdempotencyCache, key: &str, fingerprint: [u8; 32]) -> Claim {
+    match cache.entries.entry(key.to_string()) {
+        MapEntry::Vacant(vacant) => {
+            vacant.insert(Entry::InFlight { fingerprint });
+            Claim::Claimed
+        }
+        MapEntry::Occupied(occupied) => {
+            if *occupied.get().fingerprint() != fingerprint {
+                return Claim::Conflict("Idempotency-Key was already used for a different request");
+            }
+            match occupied.get() {
+                Entry::InFlight { .. } => {
+                    Claim::Conflict("a request with this Idempotency-Key is still in progress")
+                }
+                Entry::Completed {
+                    status,
+                    headers,
+                    body,
+                    ..
+                } => {
+                    let mut response = Response::new(Body::from(body.clone().unwrap_or_default()));
+                    *response.status_mut() = *status;
+                    *response.headers_mut() = headers.clone();
+                    if body.is_none() {
+                        response.headers_mut().remove(header::CONTENT_LENGTH);
+                    }
+                    response
+                        .headers_mut()
+                        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+                    Claim::Replay(response)
+                }
+            }
+        }
+    }
+}
+
+/// Middleware storing and replaying responses to mutating requests that
+/// carry an `Idempotency-Key` header.
+pub async fn idempotency(
+    State(cache): State<Arc<IdempotencyCache>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.method() == Method::GET || req.method() == Method::HEAD {
+        return next.run(req).await;
+    }
+    let Some(key) = idempotency_key(&req) else {
+        return next.run(req).await;
+    };
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return ApiError::BadRequest {
+            message: format!(
+                "Idempotency-Key must be between 1 and {} characters",
+                MAX_KEY_LENGTH
+            ),
+            param: Some("Idempotency-Key".to_string()),
+        }
+        .into_response();
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_REQUEST_BODY_BYTES).await {
+        Ok(b) => b,
+        Err(_) => {
+            return ApiError::BadRequest {
+                message: "request body too large".to_string(),
+                param: None,
+            }
+            .into_response()
+        }
+    };
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let fingerprint = request_fingerprint(parts.method.as_str(), path, &body);
+
+    cache.evict_expired();
+    match claim(&cache, &key, fingerprint) {
+        Claim::Claimed => {}
+        Claim::Replay(response) => {
+            metrics::counter!("api_idempotent_replays_total").increment(1);
+            return response;
+        }
+        Claim::Conflict(message) => {
+            return ApiError::Conflict {
+                message: message.to_string(),
+            }
+            .into_response()
+        }
+    }
+
+    // Release the key if the request fails or is abandoned before completing.
+    let guard = InFlightGuard {
+        cache: &cache,
+        key: &key,
+    };
+
+    let response = next.run(Request::from_parts(parts, body.into())).await;
+    if response.status().is_server_error() {
+        return response;
+    }
+
+    // The request has taken effect, so the key is kept from here on even if
+    // the body cannot be stored.
+    std::mem::forget(guard);
+    let (parts, body) = response.into_parts();
+    let (body, stored) = buffer_body(body).await;
+    cache.entries.insert(
+        key,
+        Entry::Completed {
+            fingerprint,
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: stored,
+            expires_at: Instant::now() + cache.ttl,
+        },
+    );
+    Response::from_parts(parts, body)
+}
+
+/// Buffer a response body so it can be stored, returning the body to send
+/// and the stored copy. A body larger than [`MAX_RESPONSE_BODY_BYTES`], or
+/// one that fails partway, is sent on as it arrives and not stored.
+async fn buffer_body(body: Body) -> (Body, Option<Bytes>) {
+    let mut stream = body.into_data_stream();
+    let mut chunks: Vec<Bytes> = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(chunk) => {
+                len += chunk.len();
+                chunks.push(chunk);
+                if len > MAX_RESPONSE_BODY_BYTES {
+                    let buffered = futures::stream::iter(chunks.into_iter().map(Ok));
+                    return (Body::from_stream(buffered.chain(stream)), None);
+                }
+            }
+            Err(e) => {
+                let buffered = chunks.into_iter().map(Ok).chain(std::iter::once(Err(e)));
+                return (Body::from_stream(futures::stream::iter(buffered)), None);
+            }
+        }
+    }
+    let mut body = BytesMut::with_capacity(len);
+    for chunk in chunks {
+        body.extend_from_slice(&chunk);
+    }
+    let body = body.freeze();
+    (Body::from(body.clone()), Some(body))
+}
+
+struct InFlightGuard<'a> {
+    cache: &'a IdempotencyCache,
+    key: &'a str,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.cache.entries.remove(self.key);
+    }
+}
+
+fn idempotency_key(req: &Request) -> Option<String> {
+    req.headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|v| v.to_str().unwrap_or_default().trim().to_string())
+}
+
+fn request_fingerprint(method: &str, path: &str, body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(method);
+    hasher.update([0]);
+    hasher.update(path);
+    hasher.update([0]);
+    hasher.update(body);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_replay_and_conflict() {
+        let cache = IdempotencyCache::default();
+        let a = request_fingerprint("POST", "/admin/purge", b"{}");
+        let b = request_fingerprint("POST", "/admin/purge", b"{ }");
+
+        assert!(matches!(claim(&cache, "k", a), Claim::Claimed));
+        assert!(matches!(claim(&cache, "k", a), Claim::Conflict(_)));
+
+        cache.entries.insert(
+            "k".to_string(),
+            Entry::Completed {
+                fingerprint: a,
+                status: StatusCode::CREATED,
+                headers: HeaderMap::new(),
+                body: Some(Bytes::from_static(b"done")),
+                expires_at: Instant::now() + DEFAULT_TTL,
+            },
+        );
+        match claim(&cache, "k", a) {
+            Claim::Replay(response) => {
+                assert_eq!(response.status(), StatusCode::CREATED);
+                assert_eq!(response.headers()[REPLAYED_HEADER], "true");
+            }
+            _ => panic!("expected replay"),
+        }
+        assert!(matches!(claim(&cache, "k", b), Claim::Conflict(_)));
+    }
+
+    #[test]
+    fn test_expired_entries_evicted() {
+        let cache = IdempotencyCache::new(Duration::ZERO);
+        let fp = request_fingerprint("POST", "/", b"");
+        cache.entries.insert(
+            "old".to_string(),
+            Entry::Completed {
+                fingerprint: fp,
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: Some(Bytes::new()),
+                expires_at: Instant::now(),
+            },
+        );
+        cache
+            .entries
+            .insert("pending".to_string(), Entry::InFlight { fingerprint: fp });
+        cache.evict_expired();
+        assert_eq!(cache.len(), 1);
+        assert!(matches!(claim(&cache, "old", fp), Claim::Claimed));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_passed_through_unstored() {
+        let small = Bytes::from_static(b"done");
+        let (body, stored) = buffer_body(Body::from(small.clone())).await;
+        assert_eq!(stored, Some(small.clone()));
+        assert_eq!(axum::body::to_bytes(body, usize::MAX).await.unwrap(), small);
+
+        let large = Bytes::from(vec![7; MAX_RESPONSE_BODY_BYTES + 1]);
+        let (body, stored) = buffer_body(Body::from(large.clone())).await;
+        assert_eq!(stored, None);
+        assert_eq!(axum::body::to_bytes(body, usize::MAX).await.unwrap(), large);
+
+        // The key is still held, and replays the status without a body.
+        let cache = IdempotencyCache::default();
+        let fp = request_fingerprint("POST", "/", b"");
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(large.len()));
+        cache.entries.insert(
+            "k".to_string(),
+            Entry::Completed {
+                fingerprint: fp,
+                status: StatusCode::OK,
+                headers,
+                body: None,
+                expires_at: Instant::now() + DEFAULT_TTL,
+            },
+        );
+        match claim(&cache, "k", fp) {
+            Claim::Replay(response) => {
+                assert_eq!(response.status(), StatusCode::OK);
+                assert!(!response.headers().contains_key(header::CONTENT_LENGTH));
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX);
+                assert!(body.await.unwrap().is_empty());
+            }
+            _ => panic!("expected replay"),
+        }
+    }
+}
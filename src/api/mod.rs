@@ -1,10 +1,24 @@
+pub mod access;
+pub mod admin;
+pub mod backfill;
+pub mod base_path;
+pub mod deprecation;
 pub mod error;
+pub mod export;
+pub mod idempotency;
+pub mod pretty;
 pub mod query_parser;
+pub mod readiness;
+pub mod request_id;
 pub mod routes;
 pub mod types;
 
 use std::sync::Arc;
 
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use axum::Router;
 use metrics_exporter_prometheus::PrometheusHandle;
 use tower_http::cors::CorsLayer;
@@ -18,20 +32,159 @@ pub fn router(state: Arc<AppState>, metrics_handle: Option<PrometheusHandle>) ->
         .route("/", axum::routing::get(routes::home))
         .route(
             "/events",
-            axum::routing::get(routes::list_events_get).post(routes::list_events_post),
+            axum::routing::get(routes::list_events_get)
+                .head(routes::list_events_head)
+                .post(routes::list_events_post),
         )
+        .route("/events/count", axum::routing::get(routes::count_events))
+        .route("/events/latest", axum::routing::get(routes::latest_events))
         .route("/events/{id}", axum::routing::get(routes::get_event))
+        .route(
+            "/events/{id}/raw",
+            axum::routing::get(routes::get_event_raw),
+        )
+        .route(
+            "/accounts/{address}/events",
+            axum::routing::get(routes::list_account_events),
+        )
+        .route(
+            "/transactions/{hash}/events",
+            axum::routing::get(routes::list_transaction_events),
+        )
+        .route("/export", axum::routing::get(export::export_events))
+        .route("/ledgers", axum::routing::get(routes::list_ledgers))
+        .route("/ledgers/{seq}", axum::routing::get(routes::get_ledger))
+        .route("/contracts", axum::routing::get(routes::list_contracts))
+        .route("/topics", axum::routing::get(routes::list_topics))
+        .route(
+            "/topics/suggest",
+            axum::routing::get(routes::suggest_topics),
+        )
+        .route("/assets", axum::routing::get(routes::list_assets))
+        .route("/stats", axum::routing::get(routes::stats))
+        .route("/sync", axum::routing::get(routes::sync_status))
+        .route("/errors", axum::routing::get(routes::list_errors))
+        .route("/errors/{code}", axum::routing::get(routes::get_error))
         .route("/health", axum::routing::get(routes::health))
+        .route("/health/live", axum::routing::get(routes::health_live))
+        .route("/health/ready", axum::routing::get(routes::health_ready))
+        .route(
+            "/query/validate",
+            axum::routing::post(routes::validate_query),
+        )
         .route("/schema", axum::routing::get(routes::schema));
 
     if let Some(handle) = metrics_handle {
-        app = app.route(
-            "/metrics",
-            axum::routing::get(move || std::future::ready(handle.render())),
-        );
+        app = app.route("/metrics", metrics_route(handle));
     }
 
-    app.layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
+    app.layer(axum::middleware::from_fn(pretty::json_format))
+        .layer(axum::middleware::from_fn(deprecation::deprecation_headers))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::clone(&state),
+            network_header,
+        ))
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http().make_span_with(|req: &Request| {
+            let request_id = req
+                .extensions()
+                .get::<request_id::RequestId>()
+                .map(|id| id.0.as_str())
+                .unwrap_or_default();
+            tracing::info_span!(
+                "request",
+                method = %req.method(),
+                uri = %crate::redact::redact_url(&req.uri().to_string()),
+                version = ?req.version(),
+                request_id,
+            )
+        }))
+        .layer(axum::middleware::from_fn(request_id::request_id))
         .with_state(state)
 }
+
+/// Build a router exposing only the Prometheus metrics endpoint, for serving
+/// on a separate (e.g. internal-only) listener.
+pub fn metrics_router(metrics_handle: PrometheusHandle) -> Router {
+    Router::new().route("/metrics", metrics_route(metrics_handle))
+}
+
+/// Serve the metrics in the Prometheus text format, or as OpenMetrics with
+/// latency exemplars when exemplars are kept and the scraper accepts it.
+fn metrics_route<S: Clone + Send + Sync + 'static>(
+    handle: PrometheusHandle,
+) -> axum::routing::MethodRouter<S> {
+    axum::routing::get(move |headers: HeaderMap| {
+        let text = handle.render();
+        let openmetrics = crate::telemetry::exemplars_enabled()
+            && headers
+                .get(header::ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.contains("application/openmetrics-text"));
+        std::future::ready(if openmetrics {
+            (
+                [(
+                    header::CONTENT_TYPE,
+                    crate::telemetry::OPENMETRICS_CONTENT_TYPE,
+                )],
+                crate::telemetry::openmetrics(&text),
+            )
+                .into_response()
+        } else {
+            text.into_response()
+        })
+    })
+}
+
+/// Build the routes for additional networks, each serving the full API from
+/// its own state under `/networks/{name}`, with `/networks` listing them.
+pub fn networks_router(networks: Vec<(String, Arc<AppState>)>) -> Router {
+    let list: Vec<types::Network> = networks
+        .iter()
+        .map(|(name, state)| types::Network {
+            object: "network",
+            name: name.clone(),
+            network_passphrase: state.config.network_passphrase().to_string(),
+            url: base_path::link(&format!("/networks/{}", name)),
+        })
+        .collect();
+    let mut app = Router::new().route(
+        "/networks",
+        axum::routing::get(move || {
+            std::future::ready(types::PrettyJson(types::ListResponse {
+                object: "list",
+                url: base_path::link("/networks"),
+                next: None,
+                total: None,
+                columns: None,
+                data: list.clone(),
+                warnings: Vec::new(),
+                meta: None,
+            }))
+        }),
+    );
+    for (name, state) in networks {
+        let prefix = format!("/networks/{}", name);
+        let network_prefix = prefix.clone();
+        let network = router(state, None).layer(axum::middleware::from_fn(
+            move |req: Request, next: Next| {
+                base_path::with_network_prefix(network_prefix.clone(), next.run(req))
+            },
+        ));
+        app = app.nest(&prefix, network);
+    }
+    app
+}
+
+/// Response header carrying the network passphrase this instance serves.
+pub const NETWORK_PASSPHRASE_HEADER: &str = "x-stellar-network-passphrase";
+
+/// Attach the configured network passphrase to every response so clients can
+/// detect that they are talking to the wrong network's instance.
+async fn network_header(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    let mut resp = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(state.config.network_passphrase()) {
+        resp.headers_mut().insert(NETWORK_PASSPHRASE_HEADER, value);
+    }
+    resp
+}
@@ -0,0 +1,85 @@
+//! Pretty or compact JSON, negotiated per request.
+//!
+//! JSON responses are compact by default, which is what programmatic clients
+//! want. They are pretty-printed for requests that accept `text/html`, as
+//! browsers do, and either way `?pretty=true|false` overrides the choice. The
+//! choice is made once by the [`json_format`] middleware and read by
+//! [`PrettyJson`](super::types::PrettyJson) when it renders a response.
+
+use axum::extract::Request;
+use axum::http::{header, HeaderMap};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use super::error::ApiError;
+
+tokio::task_local! {
+    static PRETTY: bool;
+}
+
+/// Whether a request asks for pretty-printed JSON. `pretty` is the value of
+/// the `pretty` query parameter, if given.
+pub fn negotiate(headers: &HeaderMap, pretty: Option<&str>) -> Result<bool, ApiError> {
+    if let Some(value) = pretty {
+        return value.parse::<bool>().map_err(|_| ApiError::BadRequest {
+            message: "pretty must be true or false".to_string(),
+            param: Some("pretty".to_string()),
+        });
+    }
+    Ok(headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|media| {
+            media
+                .split(';')
+                .next()
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case("text/html"))
+        }))
+}
+
+/// Whether JSON responses to the current request are pretty-printed. Outside
+/// a request handled by [`json_format`], output is pretty.
+pub fn is_pretty() -> bool {
+    PRETTY.try_with(|pretty| *pretty).unwrap_or(true)
+}
+
+/// Middleware choosing pretty or compact JSON for the request's responses.
+pub async fn json_format(req: Request, next: Next) -> Response {
+    let pretty = req.uri().query().and_then(|query| {
+        query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key == "pretty").then(|| value.to_string())
+        })
+    });
+    match negotiate(req.headers(), pretty.as_deref()) {
+        Ok(pretty) => PRETTY.scope(pretty, next.run(req)).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let pretty = |headers: &HeaderMap, param: Option<&str>| negotiate(headers, param).ok();
+        assert_eq!(pretty(&HeaderMap::new(), None), Some(false));
+        assert_eq!(pretty(&accept("application/json"), None), Some(false));
+        assert_eq!(
+            pretty(&accept("text/html,application/xhtml+xml,*/*;q=0.8"), None),
+            Some(true)
+        );
+        assert_eq!(pretty(&HeaderMap::new(), Some("true")), Some(true));
+        assert_eq!(pretty(&accept("text/html"), Some("false")), Some(false));
+        assert_eq!(pretty(&HeaderMap::new(), Some("yes")), None);
+    }
+}
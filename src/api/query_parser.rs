@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::db::EventFilter;
+use crate::db::{AmountFilter, EventFilter, SCVAL_TYPES};
 use crate::ledger::events::EventType;
 
 /// Structured parse error with position information.
@@ -17,7 +17,8 @@ impl fmt::Display for QueryParseError {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum QueryParseErrorKind {
     EmptyQuery,
     UnknownKey,
@@ -47,8 +48,114 @@ const MAX_NESTING_DEPTH: usize = 4;
 /// Maximum number of EventFilter objects after boolean expansion.
 const MAX_FILTERS: usize = 20;
 
-/// Parse a q= filter string into a Vec<EventFilter>.
+/// Minimum and maximum length of a `search` term, in characters.
+const MIN_SEARCH_TERM_LENGTH: usize = 2;
+const MAX_SEARCH_TERM_LENGTH: usize = 64;
+
+/// Number of filters after boolean expansion above which a warning is
+/// attached to the response, since each filter is evaluated per event.
+const FILTER_EXPANSION_WARNING_THRESHOLD: usize = 10;
+
+/// Non-fatal observations about a parsed query, suitable for returning to
+/// clients alongside results.
+pub fn query_warnings(filters: &[EventFilter]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if filters.len() > FILTER_EXPANSION_WARNING_THRESHOLD {
+        warnings.push(format!(
+            "query expanded to {} filters (maximum {}); consider simplifying it",
+            filters.len(),
+            MAX_FILTERS
+        ));
+    }
+    warnings
+}
+
+/// The qualifier keys a filter uses, in [`VALID_KEYS`] order.
+pub fn filter_keys(filter: &EventFilter) -> Vec<&'static str> {
+    let topics = |topics: &Option<Vec<serde_json::Value>>, keys: [&'static str; 4]| {
+        topics
+            .iter()
+            .flatten()
+            .zip(keys)
+            .filter(|(topic, _)| !topic.is_null())
+            .map(|(_, key)| key)
+            .collect::<Vec<_>>()
+    };
+    let mut keys = Vec::new();
+    if filter.event_type.is_some() {
+        keys.push("type");
+    }
+    if filter.contract_id.is_some() {
+        keys.push("contract");
+    }
+    if filter.any_topics.is_some() {
+        keys.push("topic");
+    }
+    keys.extend(topics(
+        &filter.topics,
+        ["topic0", "topic1", "topic2", "topic3"],
+    ));
+    if filter.ledger.is_some() {
+        keys.push("ledger");
+    }
+    if filter.tx.is_some() {
+        keys.push("tx");
+    }
+    if filter.search.is_some() {
+        keys.push("search");
+    }
+    if filter.since.is_some() {
+        keys.push("since");
+    }
+    if filter.until.is_some() {
+        keys.push("until");
+    }
+    if filter.amount.is_some() {
+        keys.push("amount");
+    }
+    keys.extend(topics(
+        &filter.subset_topics,
+        ["topic0~", "topic1~", "topic2~", "topic3~"],
+    ));
+    if filter.function.is_some() {
+        keys.push("fn");
+    }
+    if filter.op.is_some() {
+        keys.push("op");
+    }
+    let types = ["topic0.type", "topic1.type", "topic2.type", "topic3.type"];
+    keys.extend(
+        filter
+            .topic_types
+            .iter()
+            .flatten()
+            .zip(types)
+            .filter(|(t, _)| t.is_some())
+            .map(|(_, key)| key),
+    );
+    keys
+}
+
+/// Store state a query is resolved against when it is executed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueryContext {
+    /// The latest cached ledger, which `ledger:latest` and `ledger:latest-N`
+    /// are relative to. `None` if nothing is cached yet.
+    pub latest_ledger: Option<u32>,
+}
+
+/// Parse a q= filter string into a Vec<EventFilter>, without relative
+/// ledgers.
 pub fn parse_query(input: &str) -> Result<Vec<EventFilter>, QueryParseError> {
+    parse_query_in(input, &QueryContext::default())
+}
+
+/// Parse a q= filter string into a Vec<EventFilter>, resolving relative
+/// ledgers against `ctx`.
+pub fn parse_query_in(
+    input: &str,
+    ctx: &QueryContext,
+) -> Result<Vec<EventFilter>, QueryParseError> {
     if input.len() > MAX_QUERY_LENGTH {
         return Err(QueryParseError {
             kind: QueryParseErrorKind::QueryTooLong,
@@ -115,7 +222,7 @@ pub fn parse_query(input: &str) -> Result<Vec<EventFilter>, QueryParseError> {
 
     let mut filters = Vec::with_capacity(and_groups.len());
     for group in and_groups {
-        filters.push(and_group_to_filter(group)?);
+        filters.push(and_group_to_filter(group, ctx)?);
     }
 
     Ok(filters)
@@ -143,7 +250,31 @@ struct Token {
 }
 
 const VALID_KEYS: &[&str] = &[
-    "type", "contract", "topic", "topic0", "topic1", "topic2", "topic3", "ledger", "tx",
+    "type",
+    "contract",
+    "topic",
+    "topic0",
+    "topic1",
+    "topic2",
+    "topic3",
+    "ledger",
+    "tx",
+    "search",
+    "since",
+    "until",
+    "address",
+    "amount",
+    "topic0~",
+    "topic1~",
+    "topic2~",
+    "topic3~",
+    "fn",
+    "op",
+    "topic0.type",
+    "topic1.type",
+    "topic2.type",
+    "topic3.type",
+    "token_transfer",
 ];
 
 fn tokenize(input: &str) -> Result<Vec<Token>, QueryParseError> {
@@ -238,7 +369,7 @@ fn tokenize(input: &str) -> Result<Vec<Token>, QueryParseError> {
             return Err(QueryParseError {
                 kind: QueryParseErrorKind::UnknownKey,
                 message: format!(
-                    "unknown key '{}' (expected: type, contract, topic, topic0..topic3, ledger, tx)",
+                    "unknown key '{}' (expected: type, contract, topic, topic0..topic3, ledger, tx, search, since, until, address, amount, topic0~..topic3~, fn, op, topic0.type..topic3.type, token_transfer)",
                     key
                 ),
                 position: key_start,
@@ -501,11 +632,7 @@ impl<'a> Parser<'a> {
                 let value = tok.value.clone().unwrap();
                 let position = tok.position;
                 self.pos += 1;
-                Ok(QueryExpr::Qualifier {
-                    key,
-                    value,
-                    position,
-                })
+                expand_qualifier(key, value, position)
             }
             TokenKind::Or => Err(QueryParseError {
                 kind: QueryParseErrorKind::UnexpectedToken,
@@ -521,6 +648,89 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Keys whose value may be a comma-separated list, matching any of its
+/// elements.
+const LIST_KEYS: &[&str] = &["ledger", "tx"];
+
+/// Topic0 symbols of the SEP-41 token events that move balances, matched by
+/// `token_transfer:true`.
+const TOKEN_TRANSFER_SYMBOLS: &[&str] = &["transfer", "mint", "burn"];
+
+/// The expression for a single qualifier: itself, or what it stands for when
+/// its value is a list or its key a shorthand for a longer query.
+fn expand_qualifier(
+    key: String,
+    value: String,
+    position: usize,
+) -> Result<QueryExpr, QueryParseError> {
+    if LIST_KEYS.contains(&key.as_str()) && value.contains(',') {
+        return expand_list(key, &value, position);
+    }
+    if key == "token_transfer" {
+        return expand_token_transfer(&value, position);
+    }
+    Ok(QueryExpr::Qualifier {
+        key,
+        value,
+        position,
+    })
+}
+
+/// Expand `token_transfer:true` into
+/// `type:contract (topic0:{"symbol":"transfer"} OR ...)` over
+/// [`TOKEN_TRANSFER_SYMBOLS`].
+fn expand_token_transfer(value: &str, position: usize) -> Result<QueryExpr, QueryParseError> {
+    if value != "true" {
+        return Err(QueryParseError {
+            kind: QueryParseErrorKind::InvalidValue,
+            message: format!(
+                "invalid value '{}' for key 'token_transfer' (expected true)",
+                value
+            ),
+            position,
+        });
+    }
+    let qualifier = |key: &str, value: String| QueryExpr::Qualifier {
+        key: key.to_string(),
+        value,
+        position,
+    };
+    let symbols = TOKEN_TRANSFER_SYMBOLS
+        .iter()
+        .map(|symbol| {
+            qualifier(
+                "topic0",
+                serde_json::json!({ "symbol": symbol }).to_string(),
+            )
+        })
+        .collect();
+    Ok(QueryExpr::And(vec![
+        qualifier("type", "contract".to_string()),
+        QueryExpr::Or(symbols),
+    ]))
+}
+
+/// Expand `key:a,b,c` into `(key:a OR key:b OR key:c)`. The list counts as a
+/// single term, but each element is a separate filter after expansion.
+fn expand_list(key: String, value: &str, position: usize) -> Result<QueryExpr, QueryParseError> {
+    let mut elements = Vec::new();
+    for element in value.split(',') {
+        if element.is_empty() {
+            return Err(QueryParseError {
+                kind: QueryParseErrorKind::MissingValue,
+                message: format!("empty element in list for key '{}'", key),
+                position,
+            });
+        }
+        elements.push(QueryExpr::Qualifier {
+            key: key.clone(),
+            value: element.to_string(),
+            position,
+        });
+    }
+    Ok(QueryExpr::Or(elements))
+}
+
 // ---------------------------------------------------------------------------
 // DNF conversion
 // ---------------------------------------------------------------------------
@@ -644,18 +854,113 @@ fn flatten_or(expr: QueryExpr) -> Vec<Vec<(String, String, usize)>> {
 // AND-group to EventFilter
 // ---------------------------------------------------------------------------
 
+/// Parse a `ledger` value: a sequence number, `latest`, or `latest-N`.
+fn parse_ledger_value(value: &str, ctx: &QueryContext) -> Result<u32, String> {
+    let Some(relative) = value.strip_prefix("latest") else {
+        return value.parse::<u32>().map_err(|_| {
+            format!(
+                "invalid value '{}' for key 'ledger' (expected a positive integer, latest, or latest-N)",
+                value
+            )
+        });
+    };
+    let offset = match relative.strip_prefix('-') {
+        None if relative.is_empty() => 0,
+        Some(n) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => n
+            .parse::<u32>()
+            .map_err(|_| format!("ledger offset '{}' is too large", n))?,
+        _ => {
+            return Err(format!(
+                "invalid value '{}' for key 'ledger' (expected latest or latest-N)",
+                value
+            ))
+        }
+    };
+    let latest = ctx.latest_ledger.ok_or_else(|| {
+        format!(
+            "cannot resolve 'ledger:{}': no ledgers are cached yet",
+            value
+        )
+    })?;
+    latest
+        .checked_sub(offset)
+        .ok_or_else(|| format!("'ledger:{}' is before the first ledger", value))
+}
+
+/// Parse a `since`/`until` value: an RFC 3339 time or Unix seconds.
+fn parse_time_value(key: &str, value: &str) -> Result<i64, String> {
+    value
+        .parse::<i64>()
+        .ok()
+        .or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|t| t.timestamp())
+        })
+        .ok_or_else(|| {
+            format!(
+                "invalid value '{}' for key '{}' (expected an RFC 3339 time or Unix seconds)",
+                value, key
+            )
+        })
+}
+
+/// Build a positional topics vector from per-position JSON values, with
+/// `null` for the unset positions before the last set one.
+fn positional_topics(topics: &[Option<(String, usize)>; 4]) -> Option<Vec<serde_json::Value>> {
+    let max = topics.iter().rposition(|t| t.is_some())?;
+    Some(
+        topics
+            .iter()
+            .take(max + 1)
+            .map(|topic| match topic {
+                Some((json_str, _)) => serde_json::from_str(json_str).unwrap(),
+                None => serde_json::Value::Null,
+            })
+            .collect(),
+    )
+}
+
 fn and_group_to_filter(
     group: Vec<(String, String, usize)>,
+    ctx: &QueryContext,
 ) -> Result<EventFilter, QueryParseError> {
     let mut event_type: Option<(String, usize)> = None;
     let mut contract_id: Option<(String, usize)> = None;
     let mut ledger: Option<(u32, usize)> = None;
+    let mut since: Option<(i64, usize)> = None;
+    let mut until: Option<(i64, usize)> = None;
     let mut tx: Option<(String, usize)> = None;
+    let mut function: Option<(String, usize)> = None;
+    let mut op: Option<(u32, usize)> = None;
     let mut topics: [Option<(String, usize)>; 4] = [None, None, None, None];
+    let mut subset_topics: [Option<(String, usize)>; 4] = [None, None, None, None];
+    let mut topic_types: [Option<(String, usize)>; 4] = [None, None, None, None];
     let mut any_topics: Vec<String> = Vec::new();
+    let mut search: Vec<String> = Vec::new();
+    let mut amount: Vec<AmountFilter> = Vec::new();
 
     for (key, value, position) in group {
         match key.as_str() {
+            "search" => {
+                let len = value.chars().count();
+                if !(MIN_SEARCH_TERM_LENGTH..=MAX_SEARCH_TERM_LENGTH).contains(&len) {
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::InvalidValue,
+                        message: format!(
+                            "search term must be between {} and {} characters",
+                            MIN_SEARCH_TERM_LENGTH, MAX_SEARCH_TERM_LENGTH
+                        ),
+                        position,
+                    });
+                }
+
+                // Multiple search: terms are allowed (AND'd). Duplicates collapsed.
+                let term = value.to_lowercase();
+                if !search.contains(&term) {
+                    search.push(term);
+                }
+            }
             "topic" => {
                 // Validate the JSON value.
                 serde_json::from_str::<serde_json::Value>(&value).map_err(|_| QueryParseError {
@@ -669,6 +974,49 @@ fn and_group_to_filter(
                     any_topics.push(value);
                 }
             }
+            "amount" => {
+                let parsed = value.parse::<AmountFilter>().map_err(|_| QueryParseError {
+                    kind: QueryParseErrorKind::InvalidValue,
+                    message: format!(
+                        "invalid value '{}' for key 'amount' (expected an integer, optionally prefixed by >, >=, <, <=, or =)",
+                        value
+                    ),
+                    position,
+                })?;
+
+                // Multiple amount: comparisons are allowed (AND'd), e.g. a range.
+                if !amount.contains(&parsed) {
+                    amount.push(parsed);
+                }
+            }
+            "address" => {
+                // Validate the strkey: an account (G), contract (C), or
+                // muxed account (M) address.
+                use stellar_strkey::Strkey;
+                match Strkey::from_string(&value) {
+                    Ok(
+                        Strkey::PublicKeyEd25519(_)
+                        | Strkey::Contract(_)
+                        | Strkey::MuxedAccountEd25519(_),
+                    ) => {}
+                    _ => {
+                        return Err(QueryParseError {
+                            kind: QueryParseErrorKind::InvalidValue,
+                            message: format!(
+                            "invalid value '{}' for key 'address' (expected a G, C, or M address)",
+                            value
+                        ),
+                            position,
+                        })
+                    }
+                }
+
+                // Matches the address in any topic position, like `topic:`.
+                let topic = serde_json::json!({ "address": value }).to_string();
+                if !any_topics.contains(&topic) {
+                    any_topics.push(topic);
+                }
+            }
             "type" => {
                 // Validate event type value.
                 value
@@ -715,29 +1063,109 @@ fn and_group_to_filter(
                 contract_id = Some((value, position));
             }
             "ledger" => {
+                let parsed =
+                    parse_ledger_value(&value, ctx).map_err(|message| QueryParseError {
+                        kind: QueryParseErrorKind::InvalidValue,
+                        message,
+                        position,
+                    })?;
+
+                if let Some((existing, _)) = ledger {
+                    if existing == parsed {
+                        continue;
+                    }
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::ConflictingQualifiers,
+                        message: format!(
+                            "conflicting values for 'ledger': '{}' and '{}' (use OR to match multiple ledgers)",
+                            existing, parsed
+                        ),
+                        position,
+                    });
+                }
+                ledger = Some((parsed, position));
+            }
+            time_key @ ("since" | "until") => {
+                let parsed =
+                    parse_time_value(time_key, &value).map_err(|message| QueryParseError {
+                        kind: QueryParseErrorKind::InvalidValue,
+                        message,
+                        position,
+                    })?;
+                let bound = if time_key == "since" {
+                    &mut since
+                } else {
+                    &mut until
+                };
+                if let Some((existing, _)) = *bound {
+                    if existing == parsed {
+                        continue;
+                    }
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::ConflictingQualifiers,
+                        message: format!(
+                            "conflicting values for '{}' (use OR to match multiple time ranges)",
+                            time_key
+                        ),
+                        position,
+                    });
+                }
+                *bound = Some((parsed, position));
+            }
+            "op" => {
                 let parsed = value.parse::<u32>().map_err(|_| QueryParseError {
                     kind: QueryParseErrorKind::InvalidValue,
                     message: format!(
-                        "invalid value '{}' for key 'ledger' (expected a positive integer)",
+                        "invalid value '{}' for key 'op' (expected an operation index)",
                         value
                     ),
                     position,
                 })?;
-
-                if let Some((existing, _)) = ledger {
+                if let Some((existing, _)) = op {
                     if existing == parsed {
                         continue;
                     }
                     return Err(QueryParseError {
                         kind: QueryParseErrorKind::ConflictingQualifiers,
                         message: format!(
-                            "conflicting values for 'ledger': '{}' and '{}' (use OR to match multiple ledgers)",
+                            "conflicting values for 'op': '{}' and '{}' (use OR to match multiple operations)",
                             existing, parsed
                         ),
                         position,
                     });
                 }
-                ledger = Some((parsed, position));
+                op = Some((parsed, position));
+            }
+            "fn" => {
+                // Function names are symbols: up to 32 of [A-Za-z0-9_].
+                if value.len() > 32
+                    || !value
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'_')
+                {
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::InvalidValue,
+                        message: format!(
+                            "invalid value '{}' for key 'fn' (expected a function name)",
+                            value
+                        ),
+                        position,
+                    });
+                }
+                if let Some((ref existing, _)) = function {
+                    if *existing == value {
+                        continue;
+                    }
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::ConflictingQualifiers,
+                        message: format!(
+                            "conflicting values for 'fn': '{}' and '{}' (use OR to match multiple functions)",
+                            existing, value
+                        ),
+                        position,
+                    });
+                }
+                function = Some((value, position));
             }
             "tx" => {
                 if let Some((ref existing, _)) = tx {
@@ -755,8 +1183,14 @@ fn and_group_to_filter(
                 }
                 tx = Some((value, position));
             }
-            topic_key @ ("topic0" | "topic1" | "topic2" | "topic3") => {
-                let idx: usize = topic_key[5..].parse().unwrap();
+            topic_key @ ("topic0" | "topic1" | "topic2" | "topic3" | "topic0~" | "topic1~"
+            | "topic2~" | "topic3~") => {
+                let idx: usize = topic_key[5..6].parse().unwrap();
+                let topics = if topic_key.ends_with('~') {
+                    &mut subset_topics
+                } else {
+                    &mut topics
+                };
 
                 // Validate the JSON value.
                 serde_json::from_str::<serde_json::Value>(&value).map_err(|_| QueryParseError {
@@ -780,44 +1214,55 @@ fn and_group_to_filter(
                 }
                 topics[idx] = Some((value, position));
             }
+            type_key @ ("topic0.type" | "topic1.type" | "topic2.type" | "topic3.type") => {
+                let idx: usize = type_key[5..6].parse().unwrap();
+                if !SCVAL_TYPES.contains(&value.as_str()) {
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::InvalidValue,
+                        message: format!(
+                            "invalid value '{}' for key '{}' (expected an ScVal type such as symbol, address, i128)",
+                            value, type_key
+                        ),
+                        position,
+                    });
+                }
+                if let Some((ref existing, _)) = topic_types[idx] {
+                    if *existing == value {
+                        continue;
+                    }
+                    return Err(QueryParseError {
+                        kind: QueryParseErrorKind::DuplicateTopicPosition,
+                        message: format!(
+                            "duplicate '{}' in one filter group (use OR to match multiple types)",
+                            type_key
+                        ),
+                        position,
+                    });
+                }
+                topic_types[idx] = Some((value, position));
+            }
             _ => unreachable!("key validated during tokenization"),
         }
     }
 
-    // tx requires ledger
-    if let Some((_, pos)) = &tx {
-        if ledger.is_none() {
+    if let (Some((since, _)), Some((until, pos))) = (since, until) {
+        if since > until {
             return Err(QueryParseError {
                 kind: QueryParseErrorKind::InvalidValue,
-                message: "ledger is required when tx is provided".to_string(),
-                position: *pos,
+                message: "since must not be later than until".to_string(),
+                position: pos,
             });
         }
     }
 
-    // Build topics vector.
-    let topics_vec = {
-        // Find the highest set topic index.
-        let max_idx = topics.iter().rposition(|t| t.is_some());
-        match max_idx {
-            Some(max) => {
-                let mut v = Vec::with_capacity(max + 1);
-                for topic in topics.iter().take(max + 1) {
-                    match topic {
-                        Some((json_str, _)) => {
-                            let val: serde_json::Value = serde_json::from_str(json_str).unwrap();
-                            v.push(val);
-                        }
-                        None => {
-                            v.push(serde_json::Value::Null);
-                        }
-                    }
-                }
-                Some(v)
-            }
-            None => None,
-        }
-    };
+    let topics_vec = positional_topics(&topics);
+    let subset_topics_vec = positional_topics(&subset_topics);
+    let topic_types_vec = topic_types.iter().rposition(Option::is_some).map(|max| {
+        topic_types[..=max]
+            .iter()
+            .map(|t| t.as_ref().map(|(v, _)| v.clone()))
+            .collect()
+    });
 
     let any_topics_vec = if any_topics.is_empty() {
         None
@@ -834,9 +1279,25 @@ fn and_group_to_filter(
         event_type: event_type.map(|(v, _)| v),
         contract_id: contract_id.map(|(v, _)| v),
         topics: topics_vec,
+        subset_topics: subset_topics_vec,
+        topic_types: topic_types_vec,
         any_topics: any_topics_vec,
         ledger: ledger.map(|(v, _)| v),
+        since: since.map(|(v, _)| v),
+        until: until.map(|(v, _)| v),
         tx: tx.map(|(v, _)| v),
+        op: op.map(|(v, _)| v),
+        function: function.map(|(v, _)| v),
+        amount: if amount.is_empty() {
+            None
+        } else {
+            Some(amount)
+        },
+        search: if search.is_empty() {
+            None
+        } else {
+            Some(search)
+        },
     })
 }
 
@@ -850,6 +1311,14 @@ fn and_group_to_filter(
 ///   {"and": [{"type": "contract"}, {"contract": "C..."}]}
 ///   {"or": [{"type": "contract"}, {"type": "system"}]}
 pub fn parse_json_query(value: serde_json::Value) -> Result<Vec<EventFilter>, QueryParseError> {
+    parse_json_query_in(value, &QueryContext::default())
+}
+
+/// Parse a JSON query, resolving relative ledgers against `ctx`.
+pub fn parse_json_query_in(
+    value: serde_json::Value,
+    ctx: &QueryContext,
+) -> Result<Vec<EventFilter>, QueryParseError> {
     let expr = parse_json_expr(&value, 0, &mut 0)?;
 
     let dnf = to_dnf(expr);
@@ -869,7 +1338,7 @@ pub fn parse_json_query(value: serde_json::Value) -> Result<Vec<EventFilter>, Qu
 
     let mut filters = Vec::with_capacity(and_groups.len());
     for group in and_groups {
-        filters.push(and_group_to_filter(group)?);
+        filters.push(and_group_to_filter(group, ctx)?);
     }
 
     Ok(filters)
@@ -973,16 +1442,12 @@ fn parse_json_expr(
                 });
             }
             let value_str = json_qualifier_value_to_string(k, val)?;
-            Ok(QueryExpr::Qualifier {
-                key: k.to_string(),
-                value: value_str,
-                position: 0,
-            })
+            expand_qualifier(k.to_string(), value_str, 0)
         }
         _ => Err(QueryParseError {
             kind: QueryParseErrorKind::UnknownKey,
             message: format!(
-                "unknown key '{}' (expected: type, contract, topic, topic0..topic3, ledger, tx, and, or)",
+                "unknown key '{}' (expected: type, contract, topic, topic0..topic3, ledger, tx, search, since, until, address, amount, topic0~..topic3~, fn, op, topic0.type..topic3.type, token_transfer, and, or)",
                 key
             ),
             position: 0,
@@ -996,18 +1461,27 @@ fn json_qualifier_value_to_string(
     val: &serde_json::Value,
 ) -> Result<String, QueryParseError> {
     match key {
-        "type" | "contract" | "tx" => {
-            val.as_str()
-                .map(|s| s.to_string())
-                .ok_or_else(|| QueryParseError {
-                    kind: QueryParseErrorKind::InvalidValue,
-                    message: format!("value for '{}' must be a string", key),
-                    position: 0,
-                })
-        }
+        "type" | "contract" | "tx" | "search" | "address" | "fn" => val
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| QueryParseError {
+                kind: QueryParseErrorKind::InvalidValue,
+                message: format!("value for '{}' must be a string", key),
+                position: 0,
+            }),
+        "op" => val
+            .as_u64()
+            .map(|n| n.to_string())
+            .ok_or_else(|| QueryParseError {
+                kind: QueryParseErrorKind::InvalidValue,
+                message: "value for 'op' must be a non-negative integer".to_string(),
+                position: 0,
+            }),
         "ledger" => {
             if let Some(n) = val.as_u64() {
                 Ok(n.to_string())
+            } else if let Some(relative) = val.as_str().filter(|s| s.starts_with("latest")) {
+                Ok(relative.to_string())
             } else if let Some(n) = val.as_i64() {
                 if n >= 0 {
                     Ok(n.to_string())
@@ -1026,7 +1500,45 @@ fn json_qualifier_value_to_string(
                 })
             }
         }
-        "topic" | "topic0" | "topic1" | "topic2" | "topic3" => {
+        "amount" => match val {
+            serde_json::Value::String(s) => Ok(s.clone()),
+            serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => Ok(n.to_string()),
+            _ => Err(QueryParseError {
+                kind: QueryParseErrorKind::InvalidValue,
+                message: "value for 'amount' must be a comparison string or an integer".to_string(),
+                position: 0,
+            }),
+        },
+        "since" | "until" => match val {
+            serde_json::Value::String(s) => Ok(s.clone()),
+            serde_json::Value::Number(n) if n.is_i64() => Ok(n.to_string()),
+            _ => Err(QueryParseError {
+                kind: QueryParseErrorKind::InvalidValue,
+                message: format!(
+                    "value for '{}' must be an RFC 3339 string or Unix seconds",
+                    key
+                ),
+                position: 0,
+            }),
+        },
+        "token_transfer" => val
+            .as_bool()
+            .map(|b| b.to_string())
+            .ok_or_else(|| QueryParseError {
+                kind: QueryParseErrorKind::InvalidValue,
+                message: "value for 'token_transfer' must be true".to_string(),
+                position: 0,
+            }),
+        "topic0.type" | "topic1.type" | "topic2.type" | "topic3.type" => val
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| QueryParseError {
+                kind: QueryParseErrorKind::InvalidValue,
+                message: format!("value for '{}' must be a string", key),
+                position: 0,
+            }),
+        "topic" | "topic0" | "topic1" | "topic2" | "topic3" | "topic0~" | "topic1~" | "topic2~"
+        | "topic3~" => {
             // Topic values are JSON objects/values — serialize to compact string.
             Ok(serde_json::to_string(val).unwrap_or_default())
         }
@@ -1056,9 +1568,21 @@ fn filter_to_json_node(filter: &EventFilter) -> serde_json::Value {
     if let Some(ledger) = filter.ledger {
         qualifiers.push(serde_json::json!({ "ledger": ledger }));
     }
+    if let Some(since) = filter.since {
+        qualifiers.push(serde_json::json!({ "since": since }));
+    }
+    if let Some(until) = filter.until {
+        qualifiers.push(serde_json::json!({ "until": until }));
+    }
     if let Some(ref tx) = filter.tx {
         qualifiers.push(serde_json::json!({ "tx": tx }));
     }
+    if let Some(op) = filter.op {
+        qualifiers.push(serde_json::json!({ "op": op }));
+    }
+    if let Some(ref function) = filter.function {
+        qualifiers.push(serde_json::json!({ "fn": function }));
+    }
     if let Some(ref topics) = filter.topics {
         for (i, topic) in topics.iter().enumerate() {
             if topic.is_null() {
@@ -1068,11 +1592,38 @@ fn filter_to_json_node(filter: &EventFilter) -> serde_json::Value {
             qualifiers.push(serde_json::json!({ key: topic }));
         }
     }
+    if let Some(ref topics) = filter.subset_topics {
+        for (i, topic) in topics.iter().enumerate() {
+            if topic.is_null() {
+                continue;
+            }
+            let key = format!("topic{}~", i);
+            qualifiers.push(serde_json::json!({ key: topic }));
+        }
+    }
+    if let Some(ref types) = filter.topic_types {
+        for (i, ty) in types.iter().enumerate() {
+            if let Some(ty) = ty {
+                let key = format!("topic{}.type", i);
+                qualifiers.push(serde_json::json!({ key: ty }));
+            }
+        }
+    }
     if let Some(ref any_topics) = filter.any_topics {
         for topic in any_topics {
             qualifiers.push(serde_json::json!({ "topic": topic }));
         }
     }
+    if let Some(ref comparisons) = filter.amount {
+        for comparison in comparisons {
+            qualifiers.push(serde_json::json!({ "amount": comparison.to_string() }));
+        }
+    }
+    if let Some(ref terms) = filter.search {
+        for term in terms {
+            qualifiers.push(serde_json::json!({ "search": term }));
+        }
+    }
 
     match qualifiers.len() {
         0 => serde_json::Value::Object(serde_json::Map::new()),
@@ -1099,9 +1650,21 @@ fn filter_to_query_group(filter: &EventFilter) -> String {
     if let Some(ledger) = filter.ledger {
         parts.push(format!("ledger:{}", ledger));
     }
+    if let Some(since) = filter.since {
+        parts.push(format!("since:{}", since));
+    }
+    if let Some(until) = filter.until {
+        parts.push(format!("until:{}", until));
+    }
     if let Some(ref tx) = filter.tx {
         parts.push(format!("tx:{}", tx));
     }
+    if let Some(op) = filter.op {
+        parts.push(format!("op:{}", op));
+    }
+    if let Some(ref function) = filter.function {
+        parts.push(format!("fn:{}", function));
+    }
     if let Some(ref topics) = filter.topics {
         for (i, topic) in topics.iter().enumerate() {
             if topic.is_null() {
@@ -1114,11 +1677,44 @@ fn filter_to_query_group(filter: &EventFilter) -> String {
             ));
         }
     }
+    if let Some(ref topics) = filter.subset_topics {
+        for (i, topic) in topics.iter().enumerate() {
+            if topic.is_null() {
+                continue;
+            }
+            parts.push(format!(
+                "topic{}~:{}",
+                i,
+                serde_json::to_string(topic).unwrap()
+            ));
+        }
+    }
+    if let Some(ref types) = filter.topic_types {
+        for (i, ty) in types.iter().enumerate() {
+            if let Some(ty) = ty {
+                parts.push(format!("topic{}.type:{}", i, ty));
+            }
+        }
+    }
     if let Some(ref any_topics) = filter.any_topics {
         for topic in any_topics {
             parts.push(format!("topic:{}", serde_json::to_string(topic).unwrap()));
         }
     }
+    if let Some(ref comparisons) = filter.amount {
+        for comparison in comparisons {
+            parts.push(format!("amount:{}", comparison));
+        }
+    }
+    if let Some(ref terms) = filter.search {
+        for term in terms {
+            if term.contains([' ', '\t', '(', ')']) {
+                parts.push(format!("search:\"{}\"", term.replace('"', "\\\"")));
+            } else {
+                parts.push(format!("search:{}", term));
+            }
+        }
+    }
 
     parts.join(" ")
 }
@@ -1582,6 +2178,226 @@ mod tests {
         assert!(err.message.contains("invalid value"));
     }
 
+    #[test]
+    fn test_parse_ledger_latest() {
+        let ctx = QueryContext {
+            latest_ledger: Some(1000),
+        };
+        let ledger = |q: &str| parse_query_in(q, &ctx).map(|f| f[0].ledger);
+        assert_eq!(ledger("ledger:latest").unwrap(), Some(1000));
+        assert_eq!(ledger("ledger:latest-100").unwrap(), Some(900));
+        assert_eq!(ledger("ledger:latest-1000").unwrap(), Some(0));
+        assert!(ledger("ledger:latest-1001").is_err());
+        assert!(ledger("ledger:latest-").is_err());
+        assert!(ledger("ledger:latest+1").is_err());
+        assert!(ledger("ledger:latestx").is_err());
+
+        let err = parse_query("ledger:latest").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        assert!(err.message.contains("no ledgers are cached"));
+    }
+
+    #[test]
+    fn test_parse_since_until() {
+        let filters =
+            parse_query("since:2023-11-14T22:13:20Z until:1700000060 type:contract").unwrap();
+        assert_eq!(filters[0].since, Some(1700000000));
+        assert_eq!(filters[0].until, Some(1700000060));
+
+        let err = parse_query("since:yesterday").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("since:1700000060 until:1700000000").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("since:1 since:2").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::ConflictingQualifiers);
+
+        let filters = parse_json_query(json!({"and": [
+            {"since": "2023-11-14T22:13:20+00:00"},
+            {"until": 1700000060}
+        ]}))
+        .unwrap();
+        assert_eq!(filters[0].since, Some(1700000000));
+        assert_eq!(filters[0].until, Some(1700000060));
+        assert_eq!(
+            filters_to_query_string(&filters),
+            "since:1700000000 until:1700000060"
+        );
+    }
+
+    #[test]
+    fn test_parse_address() {
+        let account = stellar_strkey::ed25519::PublicKey([1; 32]).to_string();
+        let contract = stellar_strkey::Contract([2; 32]).to_string();
+        let filters = parse_query(&format!("address:{} address:{}", account, contract)).unwrap();
+        assert_eq!(
+            filters[0].any_topics,
+            Some(vec![
+                json!({"address": account}),
+                json!({"address": contract})
+            ])
+        );
+
+        let filters = parse_json_query(json!({"address": account})).unwrap();
+        assert_eq!(
+            filters[0].any_topics,
+            Some(vec![json!({"address": account})])
+        );
+
+        let err = parse_query("address:GABC").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let secret = stellar_strkey::ed25519::PrivateKey([3; 32]).to_string();
+        let err = parse_query(&format!("address:{}", secret)).unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        let filters = parse_query("amount:>=1000 amount:<5000000000000000000000").unwrap();
+        let amount = filters[0].amount.as_ref().unwrap();
+        assert_eq!(amount.len(), 2);
+        assert!(amount.iter().all(|c| c.matches(1000)));
+        assert!(!amount.iter().all(|c| c.matches(999)));
+        assert!(!amount
+            .iter()
+            .all(|c| c.matches(5_000_000_000_000_000_000_000)));
+        assert_eq!(
+            filters_to_query_string(&filters),
+            "amount:>=1000 amount:<5000000000000000000000"
+        );
+
+        let filters = parse_query("amount:7").unwrap();
+        assert!(filters[0].amount.as_ref().unwrap()[0].matches(7));
+        let filters = parse_json_query(json!({"amount": ">-1"})).unwrap();
+        assert!(filters[0].amount.as_ref().unwrap()[0].matches(0));
+
+        let err = parse_query("amount:>lots").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn test_parse_subset_topic() {
+        let q = r#"topic0:{"symbol":"swap"} topic1~:{"map":[{"key":{"symbol":"pool"}}]}"#;
+        let filters = parse_query(q).unwrap();
+        assert_eq!(filters[0].topics, Some(vec![json!({"symbol": "swap"})]));
+        assert_eq!(
+            filters[0].subset_topics,
+            Some(vec![
+                serde_json::Value::Null,
+                json!({"map": [{"key": {"symbol": "pool"}}]})
+            ])
+        );
+        assert_eq!(filters_to_query_string(&filters), q);
+
+        let filters = parse_json_query(json!({"topic0~": {"vec": []}})).unwrap();
+        assert_eq!(filters[0].subset_topics, Some(vec![json!({"vec": []})]));
+
+        let err = parse_query("topic0~:1 topic0~:2").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::DuplicateTopicPosition);
+    }
+
+    #[test]
+    fn test_parse_topic_type() {
+        let q = "topic0.type:symbol topic2.type:i128";
+        let filters = parse_query(q).unwrap();
+        assert_eq!(
+            filters[0].topic_types,
+            Some(vec![
+                Some("symbol".to_string()),
+                None,
+                Some("i128".to_string())
+            ])
+        );
+        assert_eq!(filters_to_query_string(&filters), q);
+        assert_eq!(filter_keys(&filters[0]), vec!["topic0.type", "topic2.type"]);
+
+        let json = filters_to_json(&filters);
+        assert_eq!(
+            json,
+            json!({"and": [{"topic0.type": "symbol"}, {"topic2.type": "i128"}]})
+        );
+        let filters = parse_json_query(json).unwrap();
+        assert_eq!(filters_to_query_string(&filters), q);
+
+        let err = parse_query("topic1.type:number").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("topic1.type:address topic1.type:i128").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::DuplicateTopicPosition);
+    }
+
+    #[test]
+    fn test_parse_token_transfer() {
+        let filters = parse_query("token_transfer:true contract:CABC").unwrap();
+        assert_eq!(
+            filters_to_query_string(&filters),
+            concat!(
+                r#"type:contract contract:CABC topic0:{"symbol":"transfer"} OR "#,
+                r#"type:contract contract:CABC topic0:{"symbol":"mint"} OR "#,
+                r#"type:contract contract:CABC topic0:{"symbol":"burn"}"#,
+            )
+        );
+        let json = parse_json_query(json!({"token_transfer": true})).unwrap();
+        assert_eq!(json.len(), 3);
+
+        let err = parse_query("token_transfer:false").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("token_transfer:true type:system").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::ConflictingQualifiers);
+    }
+
+    #[test]
+    fn test_parse_fn() {
+        let filters = parse_query("fn:swap type:diagnostic").unwrap();
+        assert_eq!(filters[0].function.as_deref(), Some("swap"));
+        assert_eq!(filters_to_query_string(&filters), "type:diagnostic fn:swap");
+        let filters = parse_json_query(json!({"fn": "swap"})).unwrap();
+        assert_eq!(filters[0].function.as_deref(), Some("swap"));
+
+        let err = parse_query("fn:not-a-symbol").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("fn:swap fn:deposit").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::ConflictingQualifiers);
+    }
+
+    #[test]
+    fn test_parse_op() {
+        let filters = parse_query("ledger:100 op:2").unwrap();
+        assert_eq!(filters[0].op, Some(2));
+        assert_eq!(filters_to_query_string(&filters), "ledger:100 op:2");
+        let filters = parse_json_query(json!({"op": 0})).unwrap();
+        assert_eq!(filters[0].op, Some(0));
+
+        let err = parse_query("op:-1").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("op:1 op:2").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::ConflictingQualifiers);
+    }
+
+    #[test]
+    fn test_parse_ledger_list() {
+        let filters = parse_query("type:contract ledger:100,105,110").unwrap();
+        let ledgers: Vec<_> = filters.iter().map(|f| f.ledger).collect();
+        assert_eq!(ledgers, vec![Some(100), Some(105), Some(110)]);
+        assert!(filters
+            .iter()
+            .all(|f| f.event_type.as_deref() == Some("contract")));
+
+        let filters = parse_query("ledger:100 tx:abc,def").unwrap();
+        let txs: Vec<_> = filters.iter().map(|f| f.tx.as_deref()).collect();
+        assert_eq!(txs, vec![Some("abc"), Some("def")]);
+
+        // A list is one term, however many elements it has.
+        let list = (1..=20).map(|l| l.to_string()).collect::<Vec<_>>();
+        let filters = parse_query(&format!("ledger:{}", list.join(","))).unwrap();
+        assert_eq!(filters.len(), 20);
+
+        let err = parse_query("ledger:100,,105").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::MissingValue);
+        let err = parse_query("ledger:100,abc").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+        let err = parse_query("ledger:1,2,3,4,5 ledger:6,7,8,9,10").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::TooManyFilters);
+    }
+
     #[test]
     fn test_parse_ledger_conflicting() {
         let err = parse_query("ledger:100 ledger:200").unwrap_err();
@@ -1605,11 +2421,11 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_tx_without_ledger_error() {
+    fn test_parse_tx_without_ledger() {
         let tx = "a".repeat(64);
-        let err = parse_query(&format!("tx:{}", tx)).unwrap_err();
-        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
-        assert!(err.message.contains("ledger is required"));
+        let filters = parse_query(&format!("tx:{}", tx)).unwrap();
+        assert_eq!(filters[0].tx.as_deref(), Some(tx.as_str()));
+        assert_eq!(filters[0].ledger, None);
     }
 
     #[test]
@@ -1711,6 +2527,16 @@ mod tests {
         assert_eq!(filters[0].ledger, Some(100));
     }
 
+    #[test]
+    fn test_parse_json_latest_ledger() {
+        let ctx = QueryContext {
+            latest_ledger: Some(1000),
+        };
+        let filters = parse_json_query_in(json!({"ledger": "latest-10"}), &ctx).unwrap();
+        assert_eq!(filters[0].ledger, Some(990));
+        assert!(parse_json_query_in(json!({"ledger": "10"}), &ctx).is_err());
+    }
+
     #[test]
     fn test_parse_json_single_tx() {
         let filters = parse_json_query(json!({"and": [{"ledger": 100}, {"tx": "abc"}]})).unwrap();
@@ -1785,9 +2611,10 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_json_tx_requires_ledger() {
-        let err = parse_json_query(json!({"tx": "abc"})).unwrap_err();
-        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+    fn test_parse_json_tx_without_ledger() {
+        let filters = parse_json_query(json!({"tx": "abc"})).unwrap();
+        assert_eq!(filters[0].tx.as_deref(), Some("abc"));
+        assert_eq!(filters[0].ledger, None);
     }
 
     #[test]
@@ -1808,6 +2635,27 @@ mod tests {
         assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
     }
 
+    #[test]
+    fn test_parse_search_terms() {
+        let filters = parse_query("search:Swap search:usdc search:swap").unwrap();
+        assert_eq!(filters.len(), 1);
+        assert_eq!(
+            filters[0].search,
+            Some(vec!["swap".to_string(), "usdc".to_string()])
+        );
+
+        let filters = parse_query(r#"search:"Swap Exact""#).unwrap();
+        assert_eq!(filters[0].search, Some(vec!["swap exact".to_string()]));
+        assert_eq!(filters_to_query_string(&filters), r#"search:"swap exact""#);
+
+        let err = parse_query("search:a").unwrap_err();
+        assert_eq!(err.kind, QueryParseErrorKind::InvalidValue);
+
+        let filters = parse_json_query(json!({"search": "Mint"})).unwrap();
+        assert_eq!(filters[0].search, Some(vec!["mint".to_string()]));
+        assert_eq!(filters_to_json(&filters), json!({"search": "mint"}));
+    }
+
     #[test]
     fn test_parse_json_type_non_string() {
         let err = parse_json_query(json!({"type": 42})).unwrap_err();
@@ -1947,4 +2795,20 @@ mod tests {
             assert_eq!(f1.contract_id, f2.contract_id);
         }
     }
+
+    // --- Warnings ---
+
+    #[test]
+    fn test_query_warnings_expansion() {
+        let filters = parse_query("type:contract OR type:system").unwrap();
+        assert!(query_warnings(&filters).is_empty());
+
+        let filters = parse_query(
+            "(type:contract OR type:system OR type:diagnostic) (ledger:1 OR ledger:2 OR ledger:3 OR ledger:4)",
+        )
+        .unwrap();
+        let warnings = query_warnings(&filters);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("expanded to 12 filters"));
+    }
 }
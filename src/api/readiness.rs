@@ -0,0 +1,103 @@
+//! Readiness of the instance to serve queries.
+//!
+//! An instance is ready once sync has caught up to within
+//! [`ReadinessPolicy::max_lag_ledgers`] of the network tip and the store can
+//! be queried, for as long as the meta store's config stays on the network
+//! the instance started on. Load balancers probing `/health/ready` then stop routing to
+//! instances that are still starting up or have fallen behind, while
+//! `/health/live` keeps reporting the process as up.
+
+use std::sync::OnceLock;
+
+use crate::sync::{END_LEDGER_REACHED_KEY, NETWORK_TIP_KEY};
+use crate::AppState;
+
+/// When an instance counts as ready.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadinessPolicy {
+    /// Largest lag behind the network tip, in ledgers, at which the instance
+    /// is still ready.
+    pub max_lag_ledgers: u32,
+}
+
+impl Default for ReadinessPolicy {
+    fn default() -> Self {
+        ReadinessPolicy {
+            max_lag_ledgers: 10,
+        }
+    }
+}
+
+static POLICY: OnceLock<ReadinessPolicy> = OnceLock::new();
+
+/// Install the process-wide policy. Only the first call has an effect.
+pub fn init(policy: ReadinessPolicy) {
+    let _ = POLICY.set(policy);
+}
+
+/// The installed policy, or the default if none was installed.
+pub fn policy() -> ReadinessPolicy {
+    POLICY.get().copied().unwrap_or_default()
+}
+
+/// Outcome of a readiness check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Readiness {
+    pub latest_ledger: Option<u32>,
+    pub network_tip: Option<u32>,
+    /// Ledgers between the last synced ledger and the network tip.
+    pub lag_ledgers: Option<u32>,
+    /// Why the instance is not ready, or `None` if it is.
+    pub reason: Option<&'static str>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.reason.is_none()
+    }
+}
+
+/// Check the store's sync state against the installed policy.
+pub fn check(state: &AppState) -> Readiness {
+    let store = &state.store;
+    let read = |key: &str| {
+        store
+            .get_sync_state(key)
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse::<u32>().ok())
+    };
+    let last_synced = read("last_synced_ledger");
+    let network_tip = read(NETWORK_TIP_KEY);
+    let lag_ledgers = last_synced
+        .zip(network_tip)
+        .map(|(synced, tip)| tip.saturating_sub(synced));
+
+    let (latest_ledger, queryable) = match store.latest_ledger_sequence() {
+        Ok(latest) => (latest, true),
+        Err(_) => (None, false),
+    };
+
+    let reason = if !queryable {
+        Some("store is not queryable")
+    } else if state.config.foreign_network().is_some() {
+        Some("meta store config is for another network")
+    } else if last_synced.is_none() {
+        Some("no ledgers synced yet")
+    } else if read(END_LEDGER_REACHED_KEY).is_some() {
+        None
+    } else if network_tip.is_none() {
+        Some("network tip not yet known")
+    } else if lag_ledgers.is_some_and(|lag| lag > policy().max_lag_ledgers) {
+        Some("sync is behind the network tip")
+    } else {
+        None
+    };
+
+    Readiness {
+        latest_ledger,
+        network_tip,
+        lag_ledgers,
+        reason,
+    }
+}
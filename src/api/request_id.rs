@@ -0,0 +1,69 @@
+//! Request correlation IDs.
+//!
+//! Every request gets an ID, taken from its `X-Request-Id` header when the
+//! client (or a proxy in front) sent a usable one and generated otherwise.
+//! The ID is recorded on the request's tracing span, echoed in the
+//! `X-Request-Id` response header, and included in error bodies, so a user
+//! reporting a failure can quote an ID that finds the server-side logs.
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Header carrying the request ID, in both directions.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied ID that is kept; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The ID of a request, stored in its extensions.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The ID of the request being handled, if any.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Whether a client-supplied ID can be used as is: non-empty, bounded, and
+/// printable ASCII without spaces, so it is safe to log and echo.
+fn is_usable(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Middleware assigning each request its ID.
+pub async fn request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_usable(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("req_{:016x}", rand::random::<u64>()));
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut resp = CURRENT.scope(id.clone(), next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_usable() {
+        assert!(is_usable("4f1c2a7e-9b3d-4e8a-a1f0-2c6d8e0b5a93"));
+        assert!(is_usable("req_0123456789abcdef"));
+        assert!(!is_usable(""));
+        assert!(!is_usable("has space"));
+        assert!(!is_usable(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+}
@@ -1,21 +1,42 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 use axum::extract::State;
 use axum::response::IntoResponse;
 use axum::Json;
 
-use super::error::ApiError;
-use super::types::{BuildInfo, Event, ListResponse, PrettyJson, StatusResponse};
-use crate::db::{EventQueryParams, EventQueryResult, EventRow};
+use super::base_path;
+use super::error::{ApiError, ErrorCode};
+use super::query_parser::QueryParseError;
+use super::types::{
+    accepts_ndjson, Asset, BuildInfo, CachedLedger, Contract, Encoding, ErrorCodeInfo, Event,
+    EventCount, EventFields, HistoryBackfillStatus, LatestEvents, LedgerBucket, LedgerEventCount,
+    ListResponse, MinuteStats, NdjsonList, PrettyJson, QueryCost, QueryMeta, QueryValidation,
+    QueryValidationError, RawEvent, ReadinessResponse, Stats, StatusResponse, SyncStatus,
+    TopContract, TopTopic, Topic,
+};
+use crate::db::{
+    CacheSource, EventFilter, EventQueryParams, EventQueryResult, EventRow, EventStore, QueryStats,
+    SortOrder,
+};
+use crate::history::HistoryProgress;
+use crate::ledger::sac;
 use crate::{sync, AppState};
 
 /// Maximum number of ledgers to backfill per request.
-const BACKFILL_BATCH_SIZE: u32 = 100;
+pub(crate) const BACKFILL_BATCH_SIZE: u32 = 100;
 
 /// Maximum number of ledgers to search during progressive backfill.
 const MAX_LEDGERS_SEARCHED: u32 = 1000;
 
+/// Approximate ledger close interval, used to translate the cache TTL into a
+/// window of ledgers.
+const APPROX_LEDGER_CLOSE_SECONDS: i64 = 5;
+
+/// Time limit for importing a backfill batch from a peer before falling back
+/// to the origin.
+const PEER_BACKFILL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// Time limit for progressive search across ledgers.
 const PROGRESSIVE_SEARCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
@@ -49,8 +70,36 @@ fn parse_multi_params(query: &str) -> HashMap<String, Vec<String>> {
     map
 }
 
+/// The query of a GET request: `q` in the string syntax, or `query` holding a
+/// JSON query as accepted in the body of `POST /events`.
+fn query_param(
+    multi: &HashMap<String, Vec<String>>,
+) -> Result<Option<serde_json::Value>, ApiError> {
+    let q = multi.get("q").and_then(|v| v.first());
+    let query = multi.get("query").and_then(|v| v.first());
+    match (q, query) {
+        (Some(_), Some(_)) => Err(ApiError::BadRequest {
+            message: "q and query cannot be combined".to_string(),
+            param: Some("query".to_string()),
+        }),
+        (Some(q), None) => Ok(Some(serde_json::Value::String(q.clone()))),
+        (None, Some(query)) => match serde_json::from_str(query) {
+            Ok(value @ serde_json::Value::Object(_)) => Ok(Some(value)),
+            Ok(_) => Err(ApiError::BadRequest {
+                message: "query must be a JSON object".to_string(),
+                param: Some("query".to_string()),
+            }),
+            Err(e) => Err(ApiError::BadRequest {
+                message: format!("query is not valid JSON: {}", e),
+                param: Some("query".to_string()),
+            }),
+        },
+        (None, None) => Ok(None),
+    }
+}
+
 /// JSON request body for POST /events.
-#[derive(Debug, Default, serde::Deserialize)]
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
 pub struct ListEventsRequest {
     #[serde(default)]
     limit: Option<u32>,
@@ -60,16 +109,307 @@ pub struct ListEventsRequest {
     before: Option<String>,
     #[serde(default)]
     q: Option<serde_json::Value>,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    debug: Option<bool>,
+    #[serde(default)]
+    order: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    fields: Option<String>,
+    #[serde(default)]
+    include_total: Option<bool>,
+    #[serde(default)]
+    expand: Option<String>,
+    #[serde(default)]
+    time_format: Option<String>,
+    #[serde(default)]
+    representation: Option<String>,
 }
 
 /// GET /events
 #[tracing::instrument(skip_all, fields(method = "GET"))]
 pub async fn list_events_get(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    axum::extract::RawQuery(raw_query): axum::extract::RawQuery,
+) -> Result<axum::response::Response, ApiError> {
+    let req = parse_list_events_query(&raw_query.unwrap_or_default())?;
+    let etag_key = etag_key(&req);
+    let list = list_events(state, req).await?;
+    Ok(negotiate_list(&headers, etag_key, list))
+}
+
+/// Response header carrying the number of events matching a list query.
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Render an events list as NDJSON when the client asks for it with
+/// `Accept: application/x-ndjson`, and as a JSON envelope otherwise.
+///
+/// With an `etag_key`, the response carries an `ETag`, and a request whose
+/// `If-None-Match` already holds it is answered with `304 Not Modified`. A
+/// list with a `total` also reports it in `X-Total-Count`.
+fn negotiate_list(
+    headers: &axum::http::HeaderMap,
+    etag_key: Option<Vec<u8>>,
+    list: PrettyJson<ListResponse<Event>>,
+) -> axum::response::Response {
+    let ndjson = accepts_ndjson(headers);
+    let etag = etag_key.map(|key| list_etag(&key, ndjson, &list.0));
+
+    if let Some(ref etag) = etag {
+        if if_none_match(headers, etag) {
+            metrics::counter!("api_not_modified_total", "endpoint" => "events").increment(1);
+            return (
+                axum::http::StatusCode::NOT_MODIFIED,
+                [(axum::http::header::ETAG, etag.clone())],
+            )
+                .into_response();
+        }
+    }
+
+    let total = list.0.total;
+    let mut response = if ndjson {
+        NdjsonList(list.0).into_response()
+    } else {
+        list.into_response()
+    };
+    if let Some(total) = total {
+        response
+            .headers_mut()
+            .insert(TOTAL_COUNT_HEADER, axum::http::HeaderValue::from(total));
+    }
+    if let Some(value) = etag.and_then(|e| axum::http::HeaderValue::from_str(&e).ok()) {
+        response
+            .headers_mut()
+            .insert(axum::http::header::ETAG, value);
+    }
+    response
+}
+
+/// What identifies a list request for its ETag, or `None` when its response
+/// is not deterministic (`debug=true` adds timings).
+fn etag_key(req: &ListEventsRequest) -> Option<Vec<u8>> {
+    if req.debug == Some(true) {
+        return None;
+    }
+    serde_json::to_vec(req).ok()
+}
+
+/// A strong ETag for a page of events. Stored events never change, so a page
+/// is identified by the request and the IDs it returned.
+fn list_etag(key: &[u8], ndjson: bool, list: &ListResponse<Event>) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(key);
+    hasher.update([u8::from(ndjson), u8::from(super::pretty::is_pretty())]);
+    for event in &list.data {
+        hasher.update(event.id.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(list.next.as_deref().unwrap_or_default());
+    hasher.update([0]);
+    if let Some(total) = list.total {
+        hasher.update(total.to_le_bytes());
+    }
+    for warning in &list.warnings {
+        hasher.update(warning.as_bytes());
+        hasher.update([0]);
+    }
+    format!("\"{}\"", hex::encode(&hasher.finalize()[..16]))
+}
+
+/// Whether an `If-None-Match` header matches `etag` (weak comparison).
+fn if_none_match(headers: &axum::http::HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(axum::http::header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// HEAD /events
+///
+/// Returns the same headers as GET. When the query is pinned to a ledger, the
+/// number of matching events in that ledger is added as `X-Total-Count`.
+#[tracing::instrument(skip_all, fields(method = "HEAD"))]
+pub async fn list_events_head(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    axum::extract::RawQuery(raw_query): axum::extract::RawQuery,
+) -> Result<axum::response::Response, ApiError> {
+    let req = parse_list_events_query(&raw_query.unwrap_or_default())?;
+    let filters = parse_filters(&state.store, req.q.as_ref())?;
+    let etag_key = etag_key(&req);
+
+    let list = list_events(Arc::clone(&state), req).await?;
+    let mut response = negotiate_list(&headers, etag_key, list);
+
+    if let Some(targets) = searched_ledgers(&filters) {
+        let params = EventQueryParams {
+            filters,
+            ..Default::default()
+        };
+        let total = count_ledgers(&state, &targets, &params)?;
+        response
+            .headers_mut()
+            .insert(TOTAL_COUNT_HEADER, axum::http::HeaderValue::from(total));
+    }
+
+    Ok(response)
+}
+
+/// GET /events/count
+///
+/// Count the events matching `q` in the cached ledgers, in total and per
+/// ledger. A query pinned to a ledger backfills it first, as listing does.
+#[tracing::instrument(skip_all)]
+pub async fn count_events(
+    State(state): State<Arc<AppState>>,
+    axum::extract::RawQuery(raw_query): axum::extract::RawQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let multi = parse_multi_params(&raw_query.unwrap_or_default());
+    if let Some(network) = multi.get("network").and_then(|v| v.first()) {
+        if network != state.config.network_passphrase() {
+            return Err(ApiError::NetworkMismatch {
+                message: format!(
+                    "network mismatch: this instance serves '{}'",
+                    state.config.network_passphrase()
+                ),
+            });
+        }
+    }
+    let q = query_param(&multi)?;
+    let filters = parse_filters(&state.store, q.as_ref())?;
+
+    for target in searched_ledgers(&filters).into_iter().flatten() {
+        backfill_if_needed(&state, target).await;
+    }
+    let params = EventQueryParams {
+        filters,
+        ..Default::default()
+    };
+    let ledgers: Vec<LedgerEventCount> = state
+        .store
+        .count_by_ledger(&params)
+        .into_iter()
+        .map(|(ledger, count)| LedgerEventCount { ledger, count })
+        .collect();
+
+    let url = match q {
+        Some(serde_json::Value::String(ref q)) => {
+            base_path::link(&format!("/events/count?q={}", urlencoding::encode(q)))
+        }
+        Some(ref query) => base_path::link(&format!(
+            "/events/count?query={}",
+            urlencoding::encode(&query.to_string())
+        )),
+        None => base_path::link("/events/count"),
+    };
+    Ok(PrettyJson(EventCount {
+        object: "event_count",
+        url,
+        total: ledgers.iter().map(|l| l.count).sum(),
+        ledgers,
+    }))
+}
+
+/// GET /accounts/{address}/events
+///
+/// Events with the account `address` (`G...` or `M...`) in any topic
+/// position, newest first. Takes the same parameters as `GET /events`; a `q`
+/// given alongside narrows the results further.
+#[tracing::instrument(skip_all, fields(address = %address))]
+pub async fn list_account_events(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(address): axum::extract::Path<String>,
+    axum::extract::RawQuery(raw_query): axum::extract::RawQuery,
+) -> Result<axum::response::Response, ApiError> {
+    use stellar_strkey::Strkey;
+
+    match Strkey::from_string(&address) {
+        Ok(Strkey::PublicKeyEd25519(_) | Strkey::MuxedAccountEd25519(_)) => {}
+        _ => {
+            return Err(ApiError::BadRequest {
+                message: format!("invalid account address: {}", address),
+                param: Some("address".to_string()),
+            })
+        }
+    }
+
+    let mut req = parse_list_events_query(&raw_query.unwrap_or_default())?;
+    let account = format!("topic:{}", serde_json::json!({ "address": address }));
+    let q = match req.q.take() {
+        Some(serde_json::Value::String(q)) if !q.trim().is_empty() => {
+            serde_json::Value::String(format!("{} ({})", account, q))
+        }
+        Some(query @ serde_json::Value::Object(_)) => serde_json::json!({
+            "and": [{ "topic": { "address": address } }, query]
+        }),
+        _ => serde_json::Value::String(account),
+    };
+    req.q = Some(q);
+
+    let etag_key = etag_key(&req);
+    let mut list = list_events(state, req).await?;
+    list.0.url = base_path::link(&format!("/accounts/{}/events", address));
+    Ok(negotiate_list(&headers, etag_key, list))
+}
+
+/// GET /events/latest
+///
+/// The newest `limit` events across the cached ledgers and the latest
+/// cached ledger's details, in one call. Takes `limit`, `q`, `encoding`, and
+/// `fields` as `GET /events` does; cursors and `order` are not accepted. The
+/// response carries a `poll` cursor for fetching newer events with `after`.
+#[tracing::instrument(skip_all)]
+pub async fn latest_events(
     State(state): State<Arc<AppState>>,
     axum::extract::RawQuery(raw_query): axum::extract::RawQuery,
 ) -> Result<impl IntoResponse, ApiError> {
-    let query_str = raw_query.unwrap_or_default();
-    let multi = parse_multi_params(&query_str);
+    let req = parse_list_events_query(&raw_query.unwrap_or_default())?;
+    for (param, given) in [
+        ("after", req.after.is_some()),
+        ("before", req.before.is_some()),
+        ("order", req.order.is_some()),
+    ] {
+        if given {
+            return Err(ApiError::BadRequest {
+                message: format!("{} is not supported on /events/latest; use /events", param),
+                param: Some(param.to_string()),
+            });
+        }
+    }
+
+    let ledger = state
+        .store
+        .latest_ledger_sequence()
+        .ok()
+        .flatten()
+        .and_then(|seq| state.store.ledger_summary(seq))
+        .map(CachedLedger::from);
+    let list = list_events(state, req).await?.0;
+    Ok(PrettyJson(LatestEvents {
+        object: "latest_events",
+        url: base_path::link("/events/latest"),
+        ledger,
+        columns: list.columns,
+        poll: list.data.first().map(|e| e.id.clone()),
+        next: list.next,
+        data: list.data,
+        warnings: list.warnings,
+    }))
+}
+
+/// Build a list request from GET query string parameters.
+fn parse_list_events_query(query_str: &str) -> Result<ListEventsRequest, ApiError> {
+    let multi = parse_multi_params(query_str);
 
     let limit = match multi.get("limit").and_then(|v| v.first()) {
         Some(v) => Some(v.parse::<u32>().map_err(|_| ApiError::BadRequest {
@@ -81,28 +421,134 @@ pub async fn list_events_get(
 
     let after = multi.get("after").and_then(|v| v.first()).cloned();
     let before = multi.get("before").and_then(|v| v.first()).cloned();
-    let q = multi
-        .get("q")
-        .and_then(|v| v.first())
-        .map(|v| serde_json::Value::String(v.to_string()));
+    let q = query_param(&multi)?;
+    let network = multi.get("network").and_then(|v| v.first()).cloned();
+    let debug = match multi.get("debug").and_then(|v| v.first()) {
+        Some(v) => Some(v.parse::<bool>().map_err(|_| ApiError::BadRequest {
+            message: "debug must be true or false".to_string(),
+            param: Some("debug".to_string()),
+        })?),
+        None => None,
+    };
+
+    let include_total = match multi.get("include_total").and_then(|v| v.first()) {
+        Some(v) => Some(v.parse::<bool>().map_err(|_| ApiError::BadRequest {
+            message: "include_total must be true or false".to_string(),
+            param: Some("include_total".to_string()),
+        })?),
+        None => None,
+    };
+
+    let order = multi.get("order").and_then(|v| v.first()).cloned();
+    let encoding = multi.get("encoding").and_then(|v| v.first()).cloned();
+    let fields = multi.get("fields").map(|v| v.join(","));
+    let expand = multi.get("expand").map(|v| v.join(","));
+    let time_format = multi.get("time_format").and_then(|v| v.first()).cloned();
+    let representation = multi.get("representation").and_then(|v| v.first()).cloned();
 
-    let req = ListEventsRequest {
+    Ok(ListEventsRequest {
         limit,
         after,
         before,
         q,
-    };
-
-    list_events(state, req).await
+        network,
+        debug,
+        order,
+        encoding,
+        fields,
+        include_total,
+        expand,
+        time_format,
+        representation,
+    })
 }
 
 /// POST /events
 #[tracing::instrument(skip_all, fields(method = "POST"))]
 pub async fn list_events_post(
     State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
     Json(req): Json<ListEventsRequest>,
-) -> Result<impl IntoResponse, ApiError> {
-    list_events(state, req).await
+) -> Result<axum::response::Response, ApiError> {
+    let etag_key = etag_key(&req);
+    let list = list_events(state, req).await?;
+    Ok(negotiate_list(&headers, etag_key, list))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ValidateQueryRequest {
+    q: Option<serde_json::Value>,
+}
+
+/// POST /query/validate
+///
+/// Parse `q` (a query string or JSON expression) as `/events` would, without
+/// executing it, and describe the result: the filters it expands to in both
+/// syntaxes, the qualifiers used, and an estimate of the work it implies. An
+/// invalid query is described rather than rejected, with the parser's error.
+#[tracing::instrument(skip_all)]
+pub async fn validate_query(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ValidateQueryRequest>,
+) -> impl IntoResponse {
+    metrics::counter!("api_requests_total", "endpoint" => "query_validate").increment(1);
+    let filters = match resolve_filters(&state.store, req.q.as_ref()) {
+        Ok(filters) => filters,
+        Err(e) => {
+            return PrettyJson(QueryValidation {
+                object: "query_validation",
+                valid: false,
+                error: Some(QueryValidationError {
+                    kind: e.kind,
+                    message: e.message,
+                    position: e.position,
+                }),
+                q: None,
+                json: None,
+                filters: Vec::new(),
+                qualifiers: Vec::new(),
+                cost: None,
+                warnings: Vec::new(),
+            })
+        }
+    };
+
+    let mut qualifiers: Vec<&'static str> = filters
+        .iter()
+        .flat_map(super::query_parser::filter_keys)
+        .collect();
+    qualifiers.sort_unstable();
+    qualifiers.dedup();
+
+    // `/events` answers a query pinned to a ledger from that partition alone,
+    // and otherwise scans progressively, within any time window.
+    let pinned_ledger = filters.iter().find_map(|f| f.ledger);
+    let ledgers = match (
+        searched_ledgers(&filters),
+        state.store.ledger_window(&filters),
+    ) {
+        (Some(ledgers), _) => ledgers.len() as u32,
+        (None, (Some(lowest), Some(highest))) => {
+            (highest.saturating_sub(lowest) + 1).min(MAX_LEDGERS_SEARCHED)
+        }
+        (None, _) => MAX_LEDGERS_SEARCHED,
+    };
+
+    PrettyJson(QueryValidation {
+        object: "query_validation",
+        valid: true,
+        error: None,
+        q: Some(super::query_parser::filters_to_query_string(&filters)),
+        json: Some(super::query_parser::filters_to_json(&filters)),
+        warnings: super::query_parser::query_warnings(&filters),
+        cost: Some(QueryCost {
+            filters: filters.len(),
+            pinned_ledger,
+            max_ledgers_scanned: ledgers,
+        }),
+        filters,
+        qualifiers,
+    })
 }
 
 /// Fetch and cache a single ledger on demand, bypassing the latest-synced watermark.
@@ -117,14 +563,20 @@ async fn backfill_ledger(state: &AppState, ledger_seq: u32) {
         return;
     }
 
-    match sync::fetch_and_extract(&state.client, &state.meta_url, &state.config, ledger_seq).await {
-        Ok(events) => {
-            if let Err(e) = state.store.insert_events(events) {
+    match sync::fetch_and_extract(
+        &state.client,
+        &state.meta_url,
+        &state.config.get(),
+        ledger_seq,
+    )
+    .await
+    {
+        Ok((info, events)) => {
+            if let Err(e) = state
+                .store
+                .insert_ledger(ledger_seq, info, events, CacheSource::Pinned)
+            {
                 tracing::warn!(ledger = ledger_seq, error = %e, "backfill_ledger: failed to insert events");
-                return;
-            }
-            if let Err(e) = state.store.record_ledger_cached(ledger_seq, 0) {
-                tracing::warn!(ledger = ledger_seq, error = %e, "backfill_ledger: failed to record cache");
             }
         }
         Err(crate::Error::LedgerNotFound(_)) => {}
@@ -134,18 +586,32 @@ async fn backfill_ledger(state: &AppState, ledger_seq: u32) {
     }
 }
 
-struct BackfillResult {
+pub(crate) struct BackfillResult {
     hit_not_found: bool,
+    /// Number of ledgers fetched and cached.
+    pub(crate) fetched: u32,
 }
 
 /// Fetch and cache a batch of uncached ledgers concurrently from S3.
 #[tracing::instrument(skip_all, fields(count = uncached.len()))]
-async fn backfill_batch(state: &AppState, uncached: &[u32]) -> BackfillResult {
+pub(crate) async fn backfill_batch(
+    state: &AppState,
+    uncached: &[u32],
+    source: CacheSource,
+) -> BackfillResult {
     tracing::debug!(count = uncached.len(), "backfilling uncached ledgers");
 
+    let mut fetched = 0;
+    let mut uncached = uncached.to_vec();
+    if let Some(ref peer) = state.peer {
+        fetched = backfill_from_peer(state, peer, &uncached, source).await;
+        uncached.retain(|&seq| !state.store.is_ledger_cached(seq).unwrap_or(false));
+    }
+
+    let config = state.config.get();
     let futures: Vec<_> = uncached
         .iter()
-        .map(|&seq| sync::fetch_and_extract(&state.client, &state.meta_url, &state.config, seq))
+        .map(|&seq| sync::fetch_and_extract(&state.client, &state.meta_url, &config, seq))
         .collect();
     let results = futures::future::join_all(futures).await;
 
@@ -153,14 +619,12 @@ async fn backfill_batch(state: &AppState, uncached: &[u32]) -> BackfillResult {
     for (i, result) in results.into_iter().enumerate() {
         let seq = uncached[i];
         match result {
-            Ok(events) => {
-                if let Err(e) = state.store.insert_events(events) {
+            Ok((info, events)) => {
+                if let Err(e) = state.store.insert_ledger(seq, info, events, source) {
                     tracing::warn!(ledger = seq, error = %e, "backfill: failed to insert events");
                     continue;
                 }
-                if let Err(e) = state.store.record_ledger_cached(seq, 0) {
-                    tracing::warn!(ledger = seq, error = %e, "backfill: failed to record cache");
-                }
+                fetched += 1;
             }
             Err(crate::Error::LedgerNotFound(_)) => {
                 hit_not_found = true;
@@ -171,12 +635,82 @@ async fn backfill_batch(state: &AppState, uncached: &[u32]) -> BackfillResult {
         }
     }
 
-    BackfillResult { hit_not_found }
+    BackfillResult {
+        hit_not_found,
+        fetched,
+    }
+}
+
+/// Import the peer's cached copies of `uncached`, so that only ledgers the
+/// peer lacks are fetched from the origin, kept for the TTL of `source`.
+/// Returns the number imported.
+async fn backfill_from_peer(
+    state: &AppState,
+    peer: &crate::snapshot::Peer,
+    uncached: &[u32],
+    source: CacheSource,
+) -> u32 {
+    let (Some(&start), Some(&end)) = (uncached.iter().min(), uncached.iter().max()) else {
+        return 0;
+    };
+    match crate::snapshot::import_from_peer(
+        &state.client,
+        &state.store,
+        state.config.network_passphrase(),
+        peer,
+        start..=end,
+        PEER_BACKFILL_TIMEOUT,
+        source,
+    )
+    .await
+    {
+        Ok(summary) => {
+            metrics::counter!("backfill_peer_ledgers_total").increment(summary.imported as u64);
+            summary.imported as u32
+        }
+        Err(e) => {
+            metrics::counter!("backfill_peer_errors_total").increment(1);
+            tracing::warn!(start, end, error = %e, "backfill: peer fetch failed");
+            0
+        }
+    }
+}
+
+/// The ledgers a query searches when it is pinned: every ledger named, if
+/// every filter names one (as `ledger:100,105` does), or otherwise the first
+/// ledger named. `None` for an unpinned query.
+fn searched_ledgers(filters: &[EventFilter]) -> Option<BTreeSet<u32>> {
+    let all: Option<BTreeSet<u32>> = filters.iter().map(|f| f.ledger).collect();
+    match all {
+        Some(ledgers) if !ledgers.is_empty() => Some(ledgers),
+        _ => filters
+            .iter()
+            .find_map(|f| f.ledger)
+            .map(|ledger| BTreeSet::from([ledger])),
+    }
+}
+
+/// Count the matching events in each of `ledgers`.
+fn count_ledgers(
+    state: &AppState,
+    ledgers: &BTreeSet<u32>,
+    params: &EventQueryParams,
+) -> Result<usize, ApiError> {
+    ledgers.iter().try_fold(0, |total, &ledger| {
+        let count = state
+            .store
+            .count_single_ledger(ledger, params)
+            .map_err(|e| ApiError::Internal {
+                message: format!("database error: {}", e),
+            })?;
+        Ok(total + count)
+    })
 }
 
 /// Fetch and cache historical ledgers on demand, starting at `target_ledger`.
+/// Returns the number of ledgers fetched.
 #[tracing::instrument(skip(state))]
-async fn backfill_if_needed(state: &AppState, target_ledger: u32) {
+async fn backfill_if_needed(state: &AppState, target_ledger: u32) -> u32 {
     let latest = state
         .store
         .latest_ledger_sequence()
@@ -184,7 +718,7 @@ async fn backfill_if_needed(state: &AppState, target_ledger: u32) {
         .flatten()
         .unwrap_or(0);
     if target_ledger > latest {
-        return;
+        return 0;
     }
     let range = BACKFILL_BATCH_SIZE.min(latest.saturating_sub(target_ledger) + 1);
     let uncached = state
@@ -193,10 +727,86 @@ async fn backfill_if_needed(state: &AppState, target_ledger: u32) {
         .unwrap_or_default();
 
     if uncached.is_empty() {
-        return;
+        return 0;
+    }
+
+    backfill_batch(state, &uncached, CacheSource::Pinned)
+        .await
+        .fetched
+}
+
+/// Answer a query pinned to several ledgers: each is backfilled and scanned in
+/// turn, newest first, or oldest first from an `after` cursor or in ascending
+/// order, as a single pinned ledger is.
+#[tracing::instrument(skip_all, fields(limit = params.limit, ledgers = ledgers.len()))]
+async fn query_ledger_set(
+    state: &AppState,
+    ledgers: &BTreeSet<u32>,
+    params: &EventQueryParams,
+) -> Result<EventQueryResult, crate::Error> {
+    let forward = params.after.is_some() || params.order == SortOrder::Asc;
+    let cursor = if forward {
+        params.after.as_deref()
+    } else {
+        params.before.as_deref()
+    };
+    let cursor_ledger = cursor
+        .and_then(crate::ledger::event_id::parse_event_id)
+        .map(|(seq, _, _, _, _)| seq);
+    let seqs: Vec<u32> = if forward {
+        let start = cursor_ledger.unwrap_or(0);
+        ledgers.range(start..).copied().collect()
+    } else {
+        let end = cursor_ledger.unwrap_or(u32::MAX);
+        ledgers.range(..=end).rev().copied().collect()
+    };
+
+    let limit = params.limit as usize;
+    let mut results: Vec<EventRow> = Vec::with_capacity(limit);
+    let mut last_examined_id: Option<String> = None;
+    let mut stats = QueryStats::default();
+
+    for seq in seqs {
+        if results.len() >= limit {
+            break;
+        }
+        stats.ledgers_backfilled += backfill_if_needed(state, seq).await;
+
+        let remaining = limit - results.len();
+        let cursor = cursor.filter(|_| cursor_ledger == Some(seq));
+        let examined = if forward {
+            state.store.scan_ledger_forward(
+                seq,
+                cursor,
+                params,
+                &mut results,
+                remaining,
+                &mut stats,
+            )
+        } else {
+            state.store.scan_ledger_backward(
+                seq,
+                cursor,
+                params,
+                &mut results,
+                remaining,
+                &mut stats,
+            )
+        };
+        if let Some(id) = examined {
+            last_examined_id = Some(id);
+        }
+    }
+
+    if forward && params.order == SortOrder::Desc {
+        results.reverse();
     }
 
-    backfill_batch(state, &uncached).await;
+    Ok(EventQueryResult {
+        data: results,
+        next: last_examined_id,
+        stats,
+    })
 }
 
 /// Progressive backward query: iteratively fetch and scan ledgers from newest
@@ -214,19 +824,21 @@ async fn query_progressive_backward(
     } else {
         latest
     };
+    // A `since`/`until` window bounds the ledgers worth scanning.
+    let (lowest, highest) = state.store.ledger_window(&params.filters);
+    let first = highest.map_or(start_ledger, |highest| highest.min(start_ledger));
+    let lowest = lowest.unwrap_or(0);
 
-    if start_ledger == 0 {
-        return Ok(EventQueryResult {
-            data: Vec::new(),
-            next: None,
-        });
+    if first == 0 || first < lowest {
+        return Ok(EventQueryResult::default());
     }
 
     let limit = params.limit as usize;
     let mut results: Vec<EventRow> = Vec::with_capacity(limit);
     let mut last_examined_id: Option<String> = None;
+    let mut stats = QueryStats::default();
     let mut ledgers_searched: u32 = 0;
-    let mut current = start_ledger;
+    let mut current = first;
     let deadline = std::time::Instant::now() + PROGRESSIVE_SEARCH_TIMEOUT;
 
     loop {
@@ -237,12 +849,14 @@ async fn query_progressive_backward(
             break;
         }
 
-        let batch_size = BACKFILL_BATCH_SIZE.min(current + 1);
+        let batch_size = BACKFILL_BATCH_SIZE.min(current + 1 - lowest);
         let batch_start = current + 1 - batch_size;
 
         let uncached = state.store.find_uncached_ledgers(batch_start, batch_size)?;
         let hit_not_found = if !uncached.is_empty() {
-            backfill_batch(state, &uncached).await.hit_not_found
+            let backfill = backfill_batch(state, &uncached, CacheSource::Backfill).await;
+            stats.ledgers_backfilled += backfill.fetched;
+            backfill.hit_not_found
         } else {
             false
         };
@@ -262,16 +876,19 @@ async fn query_progressive_backward(
             } else {
                 None
             };
-            if let Some(id) =
-                state
-                    .store
-                    .scan_ledger_backward(seq, cursor, params, &mut results, remaining)
-            {
+            if let Some(id) = state.store.scan_ledger_backward(
+                seq,
+                cursor,
+                params,
+                &mut results,
+                remaining,
+                &mut stats,
+            ) {
                 last_examined_id = Some(id);
             }
         }
 
-        if hit_not_found || batch_start == 0 {
+        if hit_not_found || batch_start <= lowest {
             break;
         }
         current = batch_start - 1;
@@ -280,6 +897,7 @@ async fn query_progressive_backward(
     Ok(EventQueryResult {
         data: results,
         next: last_examined_id,
+        stats,
     })
 }
 
@@ -295,21 +913,24 @@ async fn query_progressive_forward(
         .as_deref()
         .ok_or_else(|| crate::Error::Internal("forward query requires after cursor".to_string()))?;
     let latest = state.store.latest_ledger_sequence()?.unwrap_or(0);
+    // An `until` bound ends the scan before the latest ledger.
+    let latest = match state.store.ledger_window(&params.filters) {
+        (_, Some(highest)) => highest.min(latest),
+        _ => latest,
+    };
 
     let start_ledger = crate::ledger::event_id::parse_event_id(after)
         .map(|(seq, _, _, _, _)| seq)
         .unwrap_or(0);
 
     if start_ledger == 0 || latest == 0 {
-        return Ok(EventQueryResult {
-            data: Vec::new(),
-            next: None,
-        });
+        return Ok(EventQueryResult::default());
     }
 
     let limit = params.limit as usize;
     let mut results: Vec<EventRow> = Vec::with_capacity(limit);
     let mut last_examined_id: Option<String> = None;
+    let mut stats = QueryStats::default();
     let mut ledgers_searched: u32 = 0;
     let mut current = start_ledger;
     let deadline = std::time::Instant::now() + PROGRESSIVE_SEARCH_TIMEOUT;
@@ -327,7 +948,9 @@ async fn query_progressive_forward(
 
         let uncached = state.store.find_uncached_ledgers(current, batch_size)?;
         let hit_not_found = if !uncached.is_empty() {
-            backfill_batch(state, &uncached).await.hit_not_found
+            let backfill = backfill_batch(state, &uncached, CacheSource::Backfill).await;
+            stats.ledgers_backfilled += backfill.fetched;
+            backfill.hit_not_found
         } else {
             false
         };
@@ -348,11 +971,14 @@ async fn query_progressive_forward(
             } else {
                 None
             };
-            if let Some(id) =
-                state
-                    .store
-                    .scan_ledger_forward(seq, cursor, params, &mut results, remaining)
-            {
+            if let Some(id) = state.store.scan_ledger_forward(
+                seq,
+                cursor,
+                params,
+                &mut results,
+                remaining,
+                &mut stats,
+            ) {
                 last_examined_id = Some(id);
             }
         }
@@ -363,14 +989,158 @@ async fn query_progressive_forward(
         current = batch_end + 1;
     }
 
-    results.reverse();
+    if params.order == SortOrder::Desc {
+        results.reverse();
+    }
 
     Ok(EventQueryResult {
         data: results,
         next: last_examined_id,
+        stats,
+    })
+}
+
+/// Warnings about a query pinned to `target`: other ledgers that will not be
+/// searched, and targets outside the ingested window.
+fn pinned_ledger_warnings(state: &AppState, filters: &[EventFilter], target: u32) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let searched = searched_ledgers(filters).unwrap_or_default();
+    if filters
+        .iter()
+        .any(|f| f.ledger.is_some_and(|l| !searched.contains(&l)))
+    {
+        warnings.push(format!(
+            "query references multiple ledgers; only ledger {} is searched",
+            target
+        ));
+    }
+
+    let latest = state.store.latest_ledger_sequence().ok().flatten();
+    if let Some(latest) = latest {
+        let window = state.store.cache_ttl_seconds() / APPROX_LEDGER_CLOSE_SECONDS;
+        if target > latest {
+            warnings.push(format!(
+                "ledger {} has not been ingested yet (latest is {})",
+                target, latest
+            ));
+        } else if i64::from(latest - target) > window {
+            warnings.push(format!(
+                "ledger {} is older than the cache retention window (~{} ledgers); it is fetched on demand",
+                target, window
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// Parse the `q` parameter (a query string or JSON expression) into filters,
+/// resolving `ledger:latest[-N]` against the store's latest ledger. A `tx`
+/// without a `ledger` is pinned to the ledger the transaction index has for
+/// it; a transaction not in the index is searched for in recent ledgers.
+fn parse_filters(
+    store: &EventStore,
+    q: Option<&serde_json::Value>,
+) -> Result<Vec<EventFilter>, ApiError> {
+    resolve_filters(store, q).map_err(|e| ApiError::BadRequest {
+        message: format!("invalid q parameter: {}", e.message),
+        param: Some("q".to_string()),
     })
 }
 
+/// [`parse_filters`], keeping the parser's error.
+fn resolve_filters(
+    store: &EventStore,
+    q: Option<&serde_json::Value>,
+) -> Result<Vec<EventFilter>, QueryParseError> {
+    let ctx = super::query_parser::QueryContext {
+        latest_ledger: store.latest_ledger_sequence().ok().flatten(),
+    };
+    let mut filters = match q {
+        Some(serde_json::Value::String(s)) => super::query_parser::parse_query_in(s, &ctx)?,
+        None => Vec::new(),
+        Some(json_val) => super::query_parser::parse_json_query_in(json_val.clone(), &ctx)?,
+    };
+    for filter in &mut filters {
+        if filter.ledger.is_none() {
+            filter.ledger = filter
+                .tx
+                .as_deref()
+                .and_then(|tx| store.transaction_ledger(tx));
+        }
+    }
+    Ok(filters)
+}
+
+/// Parse the `encoding` parameter.
+fn parse_encoding(encoding: Option<&str>) -> Result<Encoding, ApiError> {
+    match encoding {
+        None | Some("json") => Ok(Encoding::Json),
+        Some("xdr") => Ok(Encoding::Xdr),
+        Some(other) => Err(ApiError::BadRequest {
+            message: format!("invalid encoding '{}' (expected: json, xdr)", other),
+            param: Some("encoding".to_string()),
+        }),
+    }
+}
+
+/// Parse a comma-separated `expand` list. Returns whether the transaction
+/// summary was requested, the only expansion supported.
+fn parse_expand(expand: Option<&str>) -> Result<bool, ApiError> {
+    let mut transaction = false;
+    for name in expand
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        match name {
+            "transaction" => transaction = true,
+            other => {
+                return Err(ApiError::BadRequest {
+                    message: format!("cannot expand '{}' (expected: transaction)", other),
+                    param: Some("expand".to_string()),
+                })
+            }
+        }
+    }
+    Ok(transaction)
+}
+
+/// Narrow a field selection to one representation of the close time.
+/// Without a `time_format`, both `at` (RFC 3339) and `at_unix` are kept.
+fn apply_time_format(
+    fields: EventFields,
+    time_format: Option<&str>,
+) -> Result<EventFields, ApiError> {
+    match time_format {
+        None => Ok(fields),
+        Some("rfc3339") => Ok(fields.without("at_unix")),
+        Some("unix") => Ok(fields.without("at")),
+        Some(other) => Err(ApiError::BadRequest {
+            message: format!("invalid time_format '{}' (expected: rfc3339, unix)", other),
+            param: Some("time_format".to_string()),
+        }),
+    }
+}
+
+/// Parse the `representation` parameter. Returns whether events are to be
+/// rendered as positional arrays.
+fn parse_representation(representation: Option<&str>) -> Result<bool, ApiError> {
+    match representation {
+        None | Some("object") => Ok(false),
+        Some("compact") => Ok(true),
+        Some(other) => Err(ApiError::BadRequest {
+            message: format!(
+                "invalid representation '{}' (expected: object, compact)",
+                other
+            ),
+            param: Some("representation".to_string()),
+        }),
+    }
+}
+
 #[tracing::instrument(skip_all, fields(limit = req.limit))]
 async fn list_events(
     state: Arc<AppState>,
@@ -378,6 +1148,21 @@ async fn list_events(
 ) -> Result<PrettyJson<ListResponse<Event>>, ApiError> {
     let start = std::time::Instant::now();
     let limit = req.limit.unwrap_or(10);
+    let encoding = parse_encoding(req.encoding.as_deref())?;
+    let expand_transaction = parse_expand(req.expand.as_deref())?;
+    let fields = match req.fields.as_deref() {
+        None => EventFields::ALL,
+        Some(list) => EventFields::parse(list).map_err(|name| ApiError::BadRequest {
+            message: format!(
+                "unknown field '{}' (expected: {})",
+                name,
+                EventFields::NAMES.join(", ")
+            ),
+            param: Some("fields".to_string()),
+        })?,
+    };
+    let fields = apply_time_format(fields, req.time_format.as_deref())?;
+    let compact = parse_representation(req.representation.as_deref())?;
 
     if limit == 0 || limit > 100 {
         return Err(ApiError::BadRequest {
@@ -386,6 +1171,18 @@ async fn list_events(
         });
     }
 
+    // Reject requests intended for a different network.
+    if let Some(ref network) = req.network {
+        if network != state.config.network_passphrase() {
+            return Err(ApiError::NetworkMismatch {
+                message: format!(
+                    "network mismatch: this instance serves '{}'",
+                    state.config.network_passphrase()
+                ),
+            });
+        }
+    }
+
     // Validate mutual exclusivity of after and before.
     if req.after.is_some() && req.before.is_some() {
         return Err(ApiError::BadRequest {
@@ -394,75 +1191,88 @@ async fn list_events(
         });
     }
 
-    // Validate and convert cursor from external to internal format if provided.
-    let after = if let Some(ref cursor) = req.after {
-        // Try decoding as an external (opaque) ID first, fall back to internal format.
-        if let Some(internal) = crate::ledger::event_id::to_internal_id(cursor) {
-            Some(internal)
-        } else if crate::ledger::event_id::parse_event_id(cursor).is_some() {
-            Some(cursor.clone())
-        } else {
+    // Validate and convert cursors from external to internal format if provided.
+    let after = req
+        .after
+        .as_deref()
+        .map(|cursor| parse_cursor(&state.store, cursor, "after"))
+        .transpose()?;
+    let before = req
+        .before
+        .as_deref()
+        .map(|cursor| parse_cursor(&state.store, cursor, "before"))
+        .transpose()?;
+
+    // Parse q parameter into filters.
+    let filters = parse_filters(&state.store, req.q.as_ref())?;
+
+    let filter_ledger = filters.iter().find_map(|f| f.ledger);
+    let ledger_set = searched_ledgers(&filters).filter(|ledgers| ledgers.len() > 1);
+
+    let order = match req.order.as_deref() {
+        None | Some("desc") => SortOrder::Desc,
+        Some("asc") => SortOrder::Asc,
+        Some(other) => {
             return Err(ApiError::BadRequest {
-                message: format!("invalid cursor: {}", cursor),
-                param: Some("after".to_string()),
-            });
+                message: format!("invalid order '{}' (expected: asc, desc)", other),
+                param: Some("order".to_string()),
+            })
         }
-    } else {
-        None
     };
-
-    let before = if let Some(ref cursor) = req.before {
-        if let Some(internal) = crate::ledger::event_id::to_internal_id(cursor) {
-            Some(internal)
-        } else if crate::ledger::event_id::parse_event_id(cursor).is_some() {
-            Some(cursor.clone())
-        } else {
+    if order == SortOrder::Asc {
+        if before.is_some() {
             return Err(ApiError::BadRequest {
-                message: format!("invalid cursor: {}", cursor),
-                param: Some("before".to_string()),
+                message: "order=asc cannot be combined with before".to_string(),
+                param: Some("order".to_string()),
             });
         }
-    } else {
-        None
-    };
+        if after.is_none() && filter_ledger.is_none() {
+            return Err(ApiError::BadRequest {
+                message: "order=asc requires an after cursor or a ledger filter".to_string(),
+                param: Some("order".to_string()),
+            });
+        }
+    }
 
-    // Parse q parameter into filters.
-    let filters = match req.q {
-        Some(serde_json::Value::String(ref s)) => {
-            super::query_parser::parse_query(s).map_err(|e| ApiError::BadRequest {
-                message: format!("invalid q parameter: {}", e.message),
-                param: Some("q".to_string()),
-            })?
+    let mut warnings = super::query_parser::query_warnings(&filters);
+    match (&ledger_set, filter_ledger) {
+        (Some(ledgers), _) => {
+            for &target in ledgers {
+                warnings.extend(pinned_ledger_warnings(&state, &filters, target));
+            }
         }
-        None => Vec::new(),
-        Some(ref json_val) => {
-            super::query_parser::parse_json_query(json_val.clone()).map_err(|e| {
-                ApiError::BadRequest {
-                    message: format!("invalid q parameter: {}", e.message),
-                    param: Some("q".to_string()),
-                }
-            })?
+        (None, Some(target)) => {
+            warnings.extend(pinned_ledger_warnings(&state, &filters, target));
         }
-    };
-
-    let filter_ledger = filters.iter().find_map(|f| f.ledger);
+        (None, None) => {}
+    }
 
     let params = EventQueryParams {
         limit,
         after,
         before,
         filters,
+        order,
     };
 
-    let result = if let Some(target) = filter_ledger {
+    let result = if let Some(ref ledgers) = ledger_set {
+        // Query pinned to several ledgers: backfill and scan each in turn.
+        query_ledger_set(&state, ledgers, &params)
+            .await
+            .map_err(|e| ApiError::Internal {
+                message: format!("database error: {}", e),
+            })?
+    } else if let Some(target) = filter_ledger {
         // Ledger-pinned query: backfill the target range and query that partition.
-        backfill_if_needed(&state, target).await;
-        state
+        let backfilled = backfill_if_needed(&state, target).await;
+        let mut result = state
             .store
             .query_single_ledger(target, &params)
             .map_err(|e| ApiError::Internal {
                 message: format!("database error: {}", e),
-            })?
+            })?;
+        result.stats.ledgers_backfilled = backfilled;
+        result
     } else if params.after.is_some() {
         // Progressive forward from cursor toward latest ledger.
         query_progressive_forward(&state, &params)
@@ -481,23 +1291,125 @@ async fn list_events(
 
     tracing::debug!(events = result.data.len(), "query complete");
 
-    let events: Vec<Event> = result.data.into_iter().map(Event::from).collect();
+    // Count every match in the cached window, independent of the page.
+    let total = if req.include_total == Some(true) {
+        let total = match searched_ledgers(&params.filters) {
+            Some(targets) => count_ledgers(&state, &targets, &params)?,
+            None => state
+                .store
+                .count_by_ledger(&params)
+                .iter()
+                .map(|(_, count)| count)
+                .sum(),
+        };
+        Some(total)
+    } else {
+        None
+    };
+
+    let events: Vec<Event> = result
+        .data
+        .into_iter()
+        .map(|row| {
+            let transaction = expand_transaction.then(|| row.transaction.clone());
+            Event {
+                fields,
+                transaction,
+                compact,
+                ..Event::from_row(row, encoding)
+            }
+        })
+        .collect();
+    let columns = compact.then(|| fields.columns(expand_transaction));
 
     metrics::counter!("api_requests_total", "endpoint" => "events").increment(1);
-    metrics::histogram!("api_request_duration_seconds", "endpoint" => "events")
-        .record(start.elapsed().as_secs_f64());
+    crate::telemetry::record_latency("events", start.elapsed());
     metrics::histogram!("api_events_returned").record(events.len() as f64);
 
+    let meta = req.debug.unwrap_or(false).then(|| QueryMeta {
+        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+        elapsed_us: start.elapsed().as_micros() as u64,
+        partitions_scanned: result.stats.partitions_scanned,
+        events_examined: result.stats.events_examined,
+        events_matched: result.stats.events_matched,
+        backfilled: result.stats.ledgers_backfilled > 0,
+        ledgers_backfilled: result.stats.ledgers_backfilled,
+    });
+
+    // Ascending polls always get a cursor back: with nothing new past `after`,
+    // echo it so the client keeps its position.
+    let next = result
+        .next
+        .map(|next| with_cursor_token(&state.store, next));
+    let next = match order {
+        SortOrder::Asc => next.or(req.after),
+        SortOrder::Desc => next,
+    };
+
     let response = ListResponse {
         object: "list",
-        url: "/events".to_string(),
-        next: result.next,
+        url: base_path::link("/events"),
+        next,
+        total,
+        columns,
         data: events,
+        warnings,
+        meta,
     };
 
     Ok(PrettyJson(response))
 }
 
+/// Convert a pagination cursor to an internal event ID. A cursor is an event
+/// ID, in external or internal form, optionally followed by `.` and the
+/// [`EventStore::cursor_token`] of its ledger when it was issued, which is
+/// rejected as stale if that ledger's partition has since been dropped or
+/// replaced.
+fn parse_cursor(store: &EventStore, cursor: &str, param: &str) -> Result<String, ApiError> {
+    let invalid = || ApiError::BadRequest {
+        message: format!("invalid cursor: {}", cursor),
+        param: Some(param.to_string()),
+    };
+    let (id, token) = match cursor.split_once('.') {
+        Some((id, token)) => (id, Some(token)),
+        None => (cursor, None),
+    };
+    // Try decoding as an external (opaque) ID first, fall back to internal format.
+    let internal = match crate::ledger::event_id::to_internal_id(id) {
+        Some(internal) => internal,
+        None if crate::ledger::event_id::parse_event_id(id).is_some() => id.to_string(),
+        None => return Err(invalid()),
+    };
+    if let Some(token) = token {
+        let (ledger, ..) =
+            crate::ledger::event_id::parse_event_id(&internal).ok_or_else(invalid)?;
+        if !store
+            .cursor_token_is_current(ledger, token)
+            .ok_or_else(invalid)?
+        {
+            return Err(ApiError::StaleCursor {
+                message: format!(
+                    "cursor is stale: ledger {} has been dropped or replaced in the cache since it was issued",
+                    ledger
+                ),
+                param: Some(param.to_string()),
+            });
+        }
+    }
+    Ok(internal)
+}
+
+/// Append the cursor token of the ledger `cursor` points into, if it is
+/// cached, so that a later page can detect the ledger changing under it.
+fn with_cursor_token(store: &EventStore, cursor: String) -> String {
+    match crate::ledger::event_id::decode_event_id(&cursor)
+        .and_then(|(ledger, ..)| store.cursor_token(ledger))
+    {
+        Some(token) => format!("{}.{}", cursor, token),
+        None => cursor,
+    }
+}
+
 /// GET /health
 #[tracing::instrument(skip_all)]
 pub async fn health(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, ApiError> {
@@ -512,7 +1424,7 @@ pub async fn health(State(state): State<Arc<AppState>>) -> Result<impl IntoRespo
         status: "ok".to_string(),
         latest_ledger: latest,
         cached_ledgers: state.store.cached_ledger_count(),
-        network_passphrase: state.config.network_passphrase.clone(),
+        network_passphrase: state.config.network_passphrase().to_string(),
         build: BuildInfo {
             repo: option_env!("BUILD_REPO").unwrap_or(""),
             branch: option_env!("BUILD_BRANCH").unwrap_or(""),
@@ -524,6 +1436,462 @@ pub async fn health(State(state): State<Arc<AppState>>) -> Result<impl IntoRespo
     Ok(PrettyJson(response))
 }
 
+/// GET /health/live
+///
+/// Liveness probe: succeeds whenever the process is serving requests.
+pub async fn health_live() -> impl IntoResponse {
+    PrettyJson(serde_json::json!({ "status": "ok" }))
+}
+
+/// GET /health/ready
+///
+/// Readiness probe: `503` until sync has caught up to within the configured
+/// lag of the network tip and the store is queryable, and while the meta
+/// store's config names another network.
+#[tracing::instrument(skip_all)]
+pub async fn health_ready(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let readiness = super::readiness::check(&state);
+    let status = if readiness.is_ready() {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    let response = ReadinessResponse {
+        status: if readiness.is_ready() {
+            "ready"
+        } else {
+            "not_ready"
+        },
+        reason: readiness.reason,
+        latest_ledger: readiness.latest_ledger,
+        network_tip: readiness.network_tip,
+        lag_ledgers: readiness.lag_ledgers,
+        max_lag_ledgers: super::readiness::policy().max_lag_ledgers,
+    };
+    (status, PrettyJson(response))
+}
+
+/// GET /sync
+///
+/// Progress of the tip-following sync and, when running, the history
+/// backfill, with its estimated time to completion.
+pub async fn sync_status(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let read = |key: &str| {
+        state
+            .store
+            .get_sync_state(key)
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse::<u32>().ok())
+    };
+    let last_synced_ledger = read("last_synced_ledger");
+    let network_tip = read(sync::NETWORK_TIP_KEY);
+    let history_backfill =
+        HistoryProgress::read(&state.store).map(|progress| HistoryBackfillStatus {
+            status: match progress.remaining() {
+                None => "waiting",
+                Some(0) => "completed",
+                Some(_) => "running",
+            },
+            from: progress.from,
+            to: progress.to,
+            next: progress.next,
+            remaining: progress.remaining(),
+            ledgers_per_second: progress.rate,
+            eta_seconds: progress.eta_seconds(),
+        });
+    PrettyJson(SyncStatus {
+        object: "sync_status",
+        start_ledger: read(sync::SYNC_START_KEY),
+        last_synced_ledger,
+        network_tip,
+        lag_ledgers: last_synced_ledger
+            .zip(network_tip)
+            .map(|(synced, tip)| tip.saturating_sub(synced)),
+        end_ledger_reached: read(sync::END_LEDGER_REACHED_KEY),
+        hash_chain_breaks: state
+            .store
+            .get_sync_state(sync::HASH_CHAIN_BREAKS_KEY)
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        last_hash_chain_break: read(sync::HASH_CHAIN_BREAK_LEDGER_KEY),
+        history_backfill,
+    })
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListLedgersQuery {
+    limit: Option<String>,
+    before: Option<String>,
+}
+
+/// GET /ledgers
+///
+/// The ledger partitions currently cached, newest first. Paginated with
+/// `before`, a ledger sequence.
+#[tracing::instrument(skip_all)]
+pub async fn list_ledgers(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<ListLedgersQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invalid = |message: &str, param: &str| ApiError::BadRequest {
+        message: message.to_string(),
+        param: Some(param.to_string()),
+    };
+    let limit = match query.limit {
+        Some(ref l) => l
+            .parse::<usize>()
+            .ok()
+            .filter(|l| (1..=1000).contains(l))
+            .ok_or_else(|| invalid("limit must be between 1 and 1000", "limit"))?,
+        None => 100,
+    };
+    let before = match query.before {
+        Some(ref b) => Some(
+            b.parse::<u32>()
+                .map_err(|_| invalid("before must be a ledger sequence", "before"))?,
+        ),
+        None => None,
+    };
+
+    let ledgers = state.store.ledger_summaries(before, limit + 1);
+    let next = (ledgers.len() > limit).then(|| {
+        base_path::link(&format!(
+            "/ledgers?limit={}&before={}",
+            limit,
+            ledgers[limit - 1].ledger
+        ))
+    });
+    let mut url = base_path::link(&format!("/ledgers?limit={}", limit));
+    if let Some(before) = before {
+        url.push_str(&format!("&before={}", before));
+    }
+
+    Ok(PrettyJson(ListResponse {
+        object: "list",
+        url,
+        next,
+        total: None,
+        columns: None,
+        data: ledgers
+            .into_iter()
+            .take(limit)
+            .map(CachedLedger::from)
+            .collect(),
+        warnings: Vec::new(),
+        meta: None,
+    }))
+}
+
+/// GET /ledgers/{seq}
+///
+/// Header details and cache status of a ledger, fetching it from the archive
+/// first if it is not cached.
+#[tracing::instrument(skip(state))]
+pub async fn get_ledger(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(seq): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let seq = seq.parse::<u32>().map_err(|_| ApiError::BadRequest {
+        message: "ledger must be a ledger sequence".to_string(),
+        param: Some("seq".to_string()),
+    })?;
+    backfill_ledger(&state, seq).await;
+    let summary = state
+        .store
+        .ledger_summary(seq)
+        .ok_or_else(|| ApiError::NotFound {
+            message: format!("ledger not found: {}", seq),
+        })?;
+    Ok(PrettyJson(CachedLedger::from(summary)))
+}
+
+/// GET /contracts
+///
+/// Every contract with events in the cached ledgers, most active first.
+#[tracing::instrument(skip_all)]
+pub async fn list_contracts(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    PrettyJson(ListResponse {
+        object: "list",
+        url: base_path::link("/contracts"),
+        next: None,
+        total: None,
+        columns: None,
+        data: state
+            .store
+            .contract_summaries()
+            .into_iter()
+            .map(Contract::from)
+            .collect(),
+        warnings: Vec::new(),
+        meta: None,
+    })
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListTopicsQuery {
+    contract: Option<String>,
+    prefix: Option<String>,
+    limit: Option<String>,
+}
+
+/// GET /topics
+///
+/// Distinct first-topic symbols in the cached ledgers with their event
+/// counts, most frequent first. Optionally scoped to one `contract` and
+/// narrowed to symbols starting with `prefix`.
+#[tracing::instrument(skip_all)]
+pub async fn list_topics(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<ListTopicsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = parse_topics_limit(query.limit.as_deref(), 100, 1000)?;
+    let prefix = query.prefix.unwrap_or_default();
+    let data = topics(&state, query.contract.as_deref(), &prefix, limit);
+
+    let url = match query.contract {
+        Some(ref contract) => base_path::link(&format!(
+            "/topics?contract={}",
+            urlencoding::encode(contract)
+        )),
+        None => base_path::link("/topics"),
+    };
+    Ok(PrettyJson(ListResponse {
+        object: "list",
+        url,
+        next: None,
+        total: None,
+        columns: None,
+        data,
+        warnings: Vec::new(),
+        meta: None,
+    }))
+}
+
+/// GET /topics/suggest
+///
+/// First-topic symbols starting with `prefix`, most frequent first, for
+/// completing a symbol as it is typed. Optionally scoped to one `contract`.
+/// Looks up the prefix in the sorted symbol index rather than scanning every
+/// symbol.
+#[tracing::instrument(skip_all)]
+pub async fn suggest_topics(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<ListTopicsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = parse_topics_limit(query.limit.as_deref(), 10, 100)?;
+    let prefix = query.prefix.ok_or_else(|| ApiError::BadRequest {
+        message: "prefix is required".to_string(),
+        param: Some("prefix".to_string()),
+    })?;
+    let data = topics(&state, query.contract.as_deref(), &prefix, limit);
+
+    let mut url = format!("/topics/suggest?prefix={}", urlencoding::encode(&prefix));
+    if let Some(ref contract) = query.contract {
+        url.push_str(&format!("&contract={}", urlencoding::encode(contract)));
+    }
+    Ok(PrettyJson(ListResponse {
+        object: "list",
+        url: base_path::link(&url),
+        next: None,
+        total: None,
+        columns: None,
+        data,
+        warnings: Vec::new(),
+        meta: None,
+    }))
+}
+
+fn parse_topics_limit(limit: Option<&str>, default: usize, max: usize) -> Result<usize, ApiError> {
+    match limit {
+        Some(l) => l
+            .parse::<usize>()
+            .ok()
+            .filter(|l| (1..=max).contains(l))
+            .ok_or_else(|| ApiError::BadRequest {
+                message: format!("limit must be between 1 and {}", max),
+                param: Some("limit".to_string()),
+            }),
+        None => Ok(default),
+    }
+}
+
+/// The `limit` most frequent first-topic symbols starting with `prefix`.
+fn topics(state: &AppState, contract: Option<&str>, prefix: &str, limit: usize) -> Vec<Topic> {
+    let mut counts = state.store.topics_with_prefix(contract, prefix);
+    counts.truncate(limit);
+    counts
+        .into_iter()
+        .map(|(symbol, event_count)| {
+            let mut q = format!("topic0:{}", serde_json::json!({ "symbol": symbol }));
+            if let Some(contract) = contract {
+                q = format!("contract:{} {}", contract, q);
+            }
+            Topic {
+                object: "topic",
+                url: base_path::link(&format!("/events?q={}", urlencoding::encode(&q))),
+                symbol,
+                event_count,
+            }
+        })
+        .collect()
+}
+
+/// GET /assets
+///
+/// Classic assets whose Stellar Asset Contracts have events in the cached
+/// ledgers, with their contract IDs, most active first. Events that only
+/// look like asset contract events, from contracts other than the one the
+/// network derives for the asset, are ignored.
+#[tracing::instrument(skip_all)]
+pub async fn list_assets(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let passphrase = state.config.network_passphrase();
+    let data: Vec<Asset> = state
+        .store
+        .asset_contracts()
+        .into_iter()
+        .filter(|(asset, contract, _)| {
+            sac::contract_id(asset, passphrase).as_deref() == Some(contract.as_str())
+        })
+        .filter_map(|(asset, contract, event_count)| {
+            let (code, issuer) = sac::split_asset(&asset)?;
+            Some(Asset {
+                object: "asset",
+                code: code.to_string(),
+                issuer: issuer.map(str::to_string),
+                url: base_path::link(&format!("/events?q=contract:{}", contract)),
+                asset,
+                contract,
+                event_count,
+            })
+        })
+        .collect();
+    PrettyJson(ListResponse {
+        object: "list",
+        url: base_path::link("/assets"),
+        next: None,
+        total: None,
+        columns: None,
+        data,
+        warnings: Vec::new(),
+        meta: None,
+    })
+}
+
+/// GET /transactions/{hash}/events
+///
+/// Every event emitted by a transaction, in order. The containing ledger is
+/// found through the store's transaction index, so only transactions in
+/// cached ledgers are found.
+#[tracing::instrument(skip_all)]
+pub async fn list_transaction_events(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ApiError::BadRequest {
+            message: "transaction hash must be 64 hex characters".to_string(),
+            param: Some("hash".to_string()),
+        });
+    }
+    let hash = hash.to_ascii_lowercase();
+    let (_, events) = state
+        .store
+        .transaction_events(&hash)
+        .ok_or_else(|| ApiError::NotFound {
+            message: format!("no events found for transaction {} in cached ledgers", hash),
+        })?;
+
+    Ok(PrettyJson(ListResponse {
+        object: "list",
+        url: base_path::link(&format!("/transactions/{}/events", hash)),
+        next: None,
+        total: None,
+        columns: None,
+        data: events.into_iter().map(Event::from).collect(),
+        warnings: Vec::new(),
+        meta: None,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StatsQuery {
+    top: Option<String>,
+}
+
+/// GET /stats
+///
+/// Events per minute, the distribution of events per ledger, and the most
+/// active contracts and topics over the cached window. Served from
+/// aggregates maintained as ledgers are cached and expire.
+#[tracing::instrument(skip_all)]
+pub async fn stats(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<StatsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let top = match query.top {
+        Some(ref t) => t
+            .parse::<usize>()
+            .ok()
+            .filter(|t| (1..=100).contains(t))
+            .ok_or_else(|| ApiError::BadRequest {
+                message: "top must be between 1 and 100".to_string(),
+                param: Some("top".to_string()),
+            })?,
+        None => 10,
+    };
+
+    let activity = state.store.activity_stats(top);
+    let mut top_contracts = state.store.contract_summaries();
+    top_contracts.truncate(top);
+
+    let mut min = 0;
+    let events_per_ledger: Vec<LedgerBucket> = activity
+        .ledger_buckets
+        .iter()
+        .enumerate()
+        .map(|(i, &ledgers)| {
+            let max = crate::db::LEDGER_EVENT_BUCKETS.get(i).copied();
+            let bucket = LedgerBucket { min, max, ledgers };
+            min = max.map_or(min, |m| m + 1);
+            bucket
+        })
+        .collect();
+
+    Ok(PrettyJson(Stats {
+        object: "stats",
+        ledgers: activity.ledger_buckets.iter().sum(),
+        events: activity.per_minute.iter().map(|m| m.1).sum(),
+        events_per_minute: activity
+            .per_minute
+            .into_iter()
+            .map(|(minute, events, ledgers)| MinuteStats {
+                minute: chrono::DateTime::from_timestamp(minute, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_default(),
+                events,
+                ledgers,
+            })
+            .collect(),
+        events_per_ledger,
+        events_by_type: activity.event_types,
+        top_contracts: top_contracts
+            .into_iter()
+            .map(|c| TopContract {
+                contract: c.contract_id,
+                events: c.event_count,
+            })
+            .collect(),
+        top_topics: activity
+            .top_topics
+            .into_iter()
+            .map(|(symbol, events)| TopTopic { symbol, events })
+            .collect(),
+    }))
+}
+
 /// GET /schema
 pub async fn schema() -> impl IntoResponse {
     (
@@ -534,19 +1902,42 @@ pub async fn schema() -> impl IntoResponse {
 
 const SCHEMA_JSON: &str = include_str!("schema.json");
 
-/// GET /events/:id
-#[tracing::instrument(skip_all, fields(id = %id))]
-pub async fn get_event(
-    State(state): State<Arc<AppState>>,
-    axum::extract::Path(id): axum::extract::Path<String>,
+/// GET /errors
+pub async fn list_errors() -> impl IntoResponse {
+    PrettyJson(ListResponse {
+        object: "list",
+        url: base_path::link("/errors"),
+        next: None,
+        total: None,
+        columns: None,
+        data: ErrorCode::ALL
+            .iter()
+            .map(|&code| ErrorCodeInfo::from(code))
+            .collect(),
+        warnings: Vec::new(),
+        meta: None,
+    })
+}
+
+/// GET /errors/:code
+pub async fn get_error(
+    axum::extract::Path(code): axum::extract::Path<String>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let start = std::time::Instant::now();
+    let code = code.parse::<ErrorCode>().map_err(|_| ApiError::NotFound {
+        message: format!("error code not found: {}", code),
+    })?;
+    Ok(PrettyJson(ErrorCodeInfo::from(code)))
+}
+
+/// Find an event by external ID, backfilling its ledger if needed.
+async fn lookup_event(state: &AppState, id: &str) -> Result<EventRow, ApiError> {
+    let not_found = || ApiError::NotFound {
+        message: format!("event not found: {}", id),
+    };
 
     // Decode the external ID to get components.
     let (ledger_seq, phase, tx_index, sub, event_index) =
-        crate::ledger::event_id::decode_event_id(&id).ok_or_else(|| ApiError::NotFound {
-            message: format!("event not found: {}", id),
-        })?;
+        crate::ledger::event_id::decode_event_id(id).ok_or_else(not_found)?;
 
     // Reconstruct EventPhase; return 404 for invalid (phase, sub) combinations.
     let event_phase = match (phase, sub) {
@@ -554,11 +1945,7 @@ pub async fn get_event(
         (1, 0) => crate::ledger::event_id::EventPhase::Operation,
         (1, 1) => crate::ledger::event_id::EventPhase::AfterTx,
         (2, 0) => crate::ledger::event_id::EventPhase::AfterAllTxs,
-        _ => {
-            return Err(ApiError::NotFound {
-                message: format!("event not found: {}", id),
-            })
-        }
+        _ => return Err(not_found()),
     };
 
     // Reconstruct internal ID for store lookup.
@@ -567,23 +1954,108 @@ pub async fn get_event(
 
     // Backfill the ledger on demand. Use direct fetch since the event was
     // requested by ID — don't skip based on the latest-synced watermark.
-    backfill_ledger(&state, ledger_seq).await;
+    backfill_ledger(state, ledger_seq).await;
 
-    let row = state
+    state
         .store
         .get_event(ledger_seq, &internal_id)
         .map_err(|e| ApiError::Internal {
             message: format!("database error: {}", e),
         })?
-        .ok_or_else(|| ApiError::NotFound {
-            message: format!("event not found: {}", id),
-        })?;
+        .ok_or_else(not_found)
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetEventQuery {
+    encoding: Option<String>,
+    expand: Option<String>,
+    time_format: Option<String>,
+}
 
-    let event = Event::from(row);
+/// GET /events/:id
+#[tracing::instrument(skip_all, fields(id = %id))]
+pub async fn get_event(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<GetEventQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let start = std::time::Instant::now();
+    let encoding = parse_encoding(query.encoding.as_deref())?;
+    let expand_transaction = parse_expand(query.expand.as_deref())?;
+    let fields = apply_time_format(EventFields::ALL, query.time_format.as_deref())?;
+
+    let row = lookup_event(&state, &id).await?;
+    let transaction = expand_transaction.then(|| row.transaction.clone());
+    let event = Event {
+        fields,
+        transaction,
+        ..Event::from_row(row, encoding)
+    };
 
     metrics::counter!("api_requests_total", "endpoint" => "get_event").increment(1);
-    metrics::histogram!("api_request_duration_seconds", "endpoint" => "get_event")
-        .record(start.elapsed().as_secs_f64());
+    crate::telemetry::record_latency("get_event", start.elapsed());
 
     Ok(PrettyJson(event))
 }
+
+/// GET /events/:id/raw
+///
+/// Return the XDR `TransactionMeta` of the transaction that emitted the
+/// event. Transaction meta is not kept in the store, so the ledger is re-fetched from
+/// the origin (or the disk cache, if enabled).
+#[tracing::instrument(skip_all, fields(id = %id))]
+pub async fn get_event_raw(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    use stellar_xdr::curr::{Limits, WriteXdr};
+
+    let start = std::time::Instant::now();
+
+    let row = lookup_event(&state, &id).await?;
+    let (_, _, tx_index, _, _) =
+        crate::ledger::event_id::decode_event_id(&id).ok_or_else(|| ApiError::NotFound {
+            message: format!("event not found: {}", id),
+        })?;
+
+    let origin_error = |e: crate::Error| ApiError::Internal {
+        message: format!(
+            "failed to fetch ledger {} from origin: {}",
+            row.ledger_sequence, e
+        ),
+    };
+    let batch = crate::ledger::fetch::fetch_ledger_batch(
+        &state.client,
+        &state.meta_url,
+        &state.config.get(),
+        row.ledger_sequence,
+    )
+    .await
+    .map_err(origin_error)?;
+    let tx_meta = crate::ledger::events::find_tx_meta(&batch, row.ledger_sequence, tx_index)
+        .ok_or_else(|| ApiError::Internal {
+            message: format!(
+                "transaction {} missing from ledger {}",
+                tx_index, row.ledger_sequence
+            ),
+        })?;
+    let transaction_meta_xdr =
+        tx_meta
+            .to_xdr_base64(Limits::none())
+            .map_err(|e| ApiError::Internal {
+                message: format!("failed to encode transaction meta: {}", e),
+            })?;
+
+    metrics::counter!("api_requests_total", "endpoint" => "get_event_raw").increment(1);
+    crate::telemetry::record_latency("get_event_raw", start.elapsed());
+
+    Ok(PrettyJson(RawEvent {
+        object: "event_raw",
+        url: base_path::link(&format!("/events/{}/raw", row.id)),
+        id: row.id,
+        ledger: row.ledger_sequence,
+        tx: row.tx_hash,
+        tx_index,
+        transaction_meta_xdr,
+    }))
+}
@@ -1,15 +1,27 @@
+use std::sync::Arc;
+
 use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use serde::Serialize;
 
-use crate::db::EventRow;
+use super::base_path;
+use super::error::ErrorCode;
+use super::query_parser::QueryParseErrorKind;
+use crate::db::{ContractSummary, EventFilter, EventRow, EventTypeCounts, LedgerSummary};
+use crate::ledger::events::{EventXdr, TransactionSummary};
 
-/// JSON response wrapper that pretty-prints the output.
+/// JSON response wrapper, pretty-printed or compact as negotiated for the
+/// request (see [`super::pretty`]).
 pub struct PrettyJson<T>(pub T);
 
 impl<T: Serialize> IntoResponse for PrettyJson<T> {
     fn into_response(self) -> Response {
-        match serde_json::to_vec_pretty(&self.0) {
+        let bytes = if super::pretty::is_pretty() {
+            serde_json::to_vec_pretty(&self.0)
+        } else {
+            serde_json::to_vec(&self.0)
+        };
+        match bytes {
             Ok(bytes) => ([(header::CONTENT_TYPE, "application/json")], bytes).into_response(),
             Err(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -21,6 +33,81 @@ impl<T: Serialize> IntoResponse for PrettyJson<T> {
     }
 }
 
+/// Media type of newline-delimited JSON responses.
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Response header carrying the `next` cursor of an NDJSON list.
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+/// Response header carrying a warning of an NDJSON list, repeated once per
+/// warning.
+pub const WARNING_HEADER: &str = "x-warning";
+
+/// Response header carrying the compact JSON `meta` of an NDJSON list.
+pub const QUERY_META_HEADER: &str = "x-query-meta";
+
+/// Whether an `Accept` header asks for newline-delimited JSON.
+pub fn accepts_ndjson(headers: &axum::http::HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|media| {
+            media
+                .split(';')
+                .next()
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case(NDJSON_CONTENT_TYPE))
+        })
+}
+
+/// A list rendered as newline-delimited JSON: one compact item per line,
+/// without the list envelope, after a header line of the `columns` when the
+/// list has them. The page is built before the response starts; only its
+/// serialization is streamed. The `next` cursor is sent in the
+/// `X-Next-Cursor` header, each warning in an `X-Warning` header, and
+/// `meta` in `X-Query-Meta`.
+pub struct NdjsonList<T: Serialize>(pub ListResponse<T>);
+
+impl<T: Serialize + Send + 'static> IntoResponse for NdjsonList<T> {
+    fn into_response(self) -> Response {
+        let ListResponse {
+            next,
+            columns,
+            data,
+            warnings,
+            meta,
+            ..
+        } = self.0;
+        let header = columns.map(|columns| serde_json::to_vec(&columns));
+        let items = data.into_iter().map(|item| serde_json::to_vec(&item));
+        let lines = futures::stream::iter(header.into_iter().chain(items).map(|line| {
+            let mut line = line?;
+            line.push(b'\n');
+            Ok::<_, serde_json::Error>(line)
+        }));
+        let mut response = (
+            [(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+            axum::body::Body::from_stream(lines),
+        )
+            .into_response();
+        let headers = response.headers_mut();
+        if let Some(value) = next.and_then(|n| header::HeaderValue::from_str(&n).ok()) {
+            headers.insert(NEXT_CURSOR_HEADER, value);
+        }
+        for warning in warnings {
+            if let Ok(value) = header::HeaderValue::from_str(&warning) {
+                headers.append(WARNING_HEADER, value);
+            }
+        }
+        let meta = meta.and_then(|m| serde_json::to_string(&m).ok());
+        if let Some(value) = meta.and_then(|m| header::HeaderValue::from_str(&m).ok()) {
+            headers.insert(QUERY_META_HEADER, value);
+        }
+        response
+    }
+}
+
 /// Paginated list response envelope.
 #[derive(Debug, Serialize)]
 pub struct ListResponse<T: Serialize> {
@@ -28,32 +115,476 @@ pub struct ListResponse<T: Serialize> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next: Option<String>,
     pub object: &'static str,
+    /// Matches in the cached window, included when requested with
+    /// `include_total=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// Names of the positions in each item, when items are arrays
+    /// (`representation=compact`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<&'static str>>,
     pub data: Vec<T>,
+    /// Non-fatal notices about how the request was interpreted.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Query execution details, included when requested with `debug=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<QueryMeta>,
 }
 
-/// A Stellar contract event, formatted for the API response.
+/// The newest events together with the latest cached ledger.
 #[derive(Debug, Serialize)]
+pub struct LatestEvents {
+    pub object: &'static str,
+    pub url: String,
+    /// The latest cached ledger, or `null` if nothing is cached yet.
+    pub ledger: Option<CachedLedger>,
+    /// Names of the positions in each event, with `representation=compact`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<&'static str>>,
+    /// Cursor for older events, as `before` on `/events`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    /// Cursor for polling newer events, as `after` on `/events`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll: Option<String>,
+    /// Newest first.
+    pub data: Vec<Event>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Execution details for a list query.
+#[derive(Debug, Serialize)]
+pub struct QueryMeta {
+    /// Wall-clock time spent handling the request, in milliseconds.
+    pub elapsed_ms: f64,
+    /// The same time in whole microseconds.
+    pub elapsed_us: u64,
+    /// Number of ledger partitions scanned.
+    pub partitions_scanned: u32,
+    /// Number of events compared against the filters.
+    pub events_examined: u64,
+    /// Number of examined events that matched the filters. Partitions whose
+    /// index rules out every filter are skipped without examining events.
+    pub events_matched: u64,
+    /// Whether any ledgers were fetched from the archive on demand.
+    pub backfilled: bool,
+    /// Number of ledgers fetched from the archive on demand.
+    pub ledgers_backfilled: u32,
+}
+
+/// A Stellar contract event, formatted for the API response.
+#[derive(Debug)]
 pub struct Event {
     pub object: &'static str,
     pub id: String,
     pub url: String,
-    #[serde(rename = "ledger")]
     pub ledger_sequence: u32,
-    #[serde(rename = "at")]
     pub ledger_closed_at: String,
-    #[serde(rename = "tx")]
+    pub ledger_closed_at_unix: i64,
     pub tx_hash: String,
-    #[serde(rename = "type")]
+    /// Index of the emitting operation within the transaction, for events
+    /// emitted during an operation.
+    pub op_index: Option<u32>,
     pub event_type: String,
-    #[serde(rename = "contract")]
     pub contract_id: Option<String>,
     pub topics: serde_json::Value,
     pub data: serde_json::Value,
+    /// Fields to include when serialized. `object` and `id` are always
+    /// included.
+    pub fields: EventFields,
+    /// Summary of the emitting transaction, included when requested with
+    /// `expand=transaction`. The inner value is `None` (rendered as `null`)
+    /// when no summary was recorded at ingest.
+    pub transaction: Option<Option<Arc<TransactionSummary>>>,
+    /// Serialize as a positional array of the [`EventFields::columns`]
+    /// rather than an object (`representation=compact`).
+    pub compact: bool,
+}
+
+/// A selection of [`Event`] fields, by their serialized names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventFields(u16);
+
+impl EventFields {
+    /// Serialized names of the selectable fields, in output order.
+    pub const NAMES: [&'static str; 10] = [
+        "url", "ledger", "at", "at_unix", "tx", "op", "type", "contract", "topics", "data",
+    ];
+
+    /// Every field.
+    pub const ALL: EventFields = EventFields((1 << Self::NAMES.len()) - 1);
+
+    /// Parse a comma-separated list of field names. Returns the first unknown
+    /// name on failure.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut bits = 0;
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match Self::NAMES.iter().position(|&n| n == name) {
+                Some(i) => bits |= 1 << i,
+                None if name == "object" || name == "id" => {}
+                None => return Err(name.to_string()),
+            }
+        }
+        Ok(EventFields(bits))
+    }
+
+    /// Names of the values of an event in the compact representation, in
+    /// order: `id`, the selected fields, and `transaction` if expanded.
+    pub fn columns(self, transaction: bool) -> Vec<&'static str> {
+        let mut columns = vec!["id"];
+        columns.extend(Self::NAMES.into_iter().filter(|name| self.contains(name)));
+        if transaction {
+            columns.push("transaction");
+        }
+        columns
+    }
+
+    /// This selection with `name` removed.
+    pub fn without(self, name: &str) -> Self {
+        match Self::NAMES.iter().position(|&n| n == name) {
+            Some(i) => EventFields(self.0 & !(1 << i)),
+            None => self,
+        }
+    }
+
+    fn contains(self, name: &str) -> bool {
+        Self::NAMES
+            .iter()
+            .position(|&n| n == name)
+            .is_some_and(|i| self.0 & (1 << i) != 0)
+    }
+}
+
+/// The value of one of an [`Event`]'s selectable fields.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum FieldValue<'a> {
+    Str(&'a str),
+    OptionalStr(Option<&'a str>),
+    Ledger(u32),
+    OptionalIndex(Option<u32>),
+    Timestamp(i64),
+    Json(&'a serde_json::Value),
+}
+
+impl Event {
+    fn field(&self, name: &str) -> FieldValue<'_> {
+        match name {
+            "url" => FieldValue::Str(&self.url),
+            "ledger" => FieldValue::Ledger(self.ledger_sequence),
+            "at" => FieldValue::Str(&self.ledger_closed_at),
+            "at_unix" => FieldValue::Timestamp(self.ledger_closed_at_unix),
+            "tx" => FieldValue::Str(&self.tx_hash),
+            "op" => FieldValue::OptionalIndex(self.op_index),
+            "type" => FieldValue::Str(&self.event_type),
+            "contract" => FieldValue::OptionalStr(self.contract_id.as_deref()),
+            "topics" => FieldValue::Json(&self.topics),
+            _ => FieldValue::Json(&self.data),
+        }
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        let transaction = self.transaction.as_ref().map(|t| t.as_deref());
+        if self.compact {
+            let mut seq = serializer.serialize_seq(None)?;
+            seq.serialize_element(&self.id)?;
+            for name in EventFields::NAMES {
+                if self.fields.contains(name) {
+                    seq.serialize_element(&self.field(name))?;
+                }
+            }
+            if let Some(transaction) = transaction {
+                seq.serialize_element(&transaction)?;
+            }
+            return seq.end();
+        }
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("object", self.object)?;
+        map.serialize_entry("id", &self.id)?;
+        for name in EventFields::NAMES {
+            if self.fields.contains(name) {
+                map.serialize_entry(name, &self.field(name))?;
+            }
+        }
+        if let Some(transaction) = transaction {
+            map.serialize_entry("transaction", &transaction)?;
+        }
+        map.end()
+    }
+}
+
+/// The raw transaction meta containing an event, for verifying what the API
+/// decoded.
+#[derive(Debug, Serialize)]
+pub struct RawEvent {
+    pub object: &'static str,
+    pub id: String,
+    pub url: String,
+    pub ledger: u32,
+    pub tx: String,
+    /// Position of the transaction in the ledger's apply order.
+    pub tx_index: u32,
+    /// Base64-encoded XDR `TransactionMeta` of the containing transaction.
+    pub transaction_meta_xdr: String,
+}
+
+/// Activity across the cached ledgers.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub object: &'static str,
+    /// Cached ledgers.
+    pub ledgers: u64,
+    /// Events in the cached ledgers.
+    pub events: u64,
+    /// Events and ledgers per minute of ledger close time, oldest first.
+    pub events_per_minute: Vec<MinuteStats>,
+    /// Number of ledgers by how many events they contain.
+    pub events_per_ledger: Vec<LedgerBucket>,
+    /// Events in the cached ledgers, by type.
+    pub events_by_type: EventTypeCounts,
+    pub top_contracts: Vec<TopContract>,
+    /// Most frequent first-topic symbols.
+    pub top_topics: Vec<TopTopic>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinuteStats {
+    pub minute: String,
+    pub events: u64,
+    pub ledgers: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerBucket {
+    /// Fewest events in a ledger in this bucket.
+    pub min: u64,
+    /// Most events in a ledger in this bucket, or `null` if unbounded.
+    pub max: Option<u64>,
+    pub ledgers: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopContract {
+    pub contract: String,
+    pub events: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopTopic {
+    pub symbol: String,
+    pub events: u64,
+}
+
+/// Number of events matching a query.
+#[derive(Debug, Serialize)]
+pub struct EventCount {
+    pub object: &'static str,
+    pub url: String,
+    /// Matches across all cached ledgers.
+    pub total: usize,
+    /// Matches per ledger, in ledger order. Ledgers without matches are
+    /// omitted.
+    pub ledgers: Vec<LedgerEventCount>,
+}
+
+/// Result of validating a query without executing it.
+#[derive(Debug, Serialize)]
+pub struct QueryValidation {
+    pub object: &'static str,
+    pub valid: bool,
+    /// Why the query is invalid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<QueryValidationError>,
+    /// The normalized query in the string syntax.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    /// The normalized query in the JSON syntax.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json: Option<serde_json::Value>,
+    /// The filters the query expands to; an event matches if it matches any
+    /// of them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<EventFilter>,
+    /// Distinct qualifier keys used, sorted.
+    pub qualifiers: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<QueryCost>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryValidationError {
+    pub kind: QueryParseErrorKind,
+    pub message: String,
+    /// Byte offset in a query string where the error was detected.
+    pub position: usize,
+}
+
+/// Estimated work for `/events` to answer a query.
+#[derive(Debug, Serialize)]
+pub struct QueryCost {
+    /// Filters each examined event is checked against.
+    pub filters: usize,
+    /// Ledger the query is answered from alone, if pinned to one.
+    pub pinned_ledger: Option<u32>,
+    /// Most ledger partitions a page of results scans.
+    pub max_ledgers_scanned: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerEventCount {
+    pub ledger: u32,
+    pub count: usize,
+}
+
+/// A ledger partition held in the store.
+#[derive(Debug, Serialize)]
+pub struct CachedLedger {
+    pub object: &'static str,
+    pub ledger: u32,
+    /// Events query for this ledger's events.
+    pub url: String,
+    pub event_count: usize,
+    /// Ledger close time, or `null` if unknown.
+    pub closed_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_count: Option<u32>,
+    /// `cached`, or `expired` for a partition awaiting cleanup.
+    pub status: &'static str,
+    /// When the partition is dropped from the cache.
+    pub expires_at: String,
+}
+
+impl From<LedgerSummary> for CachedLedger {
+    fn from(summary: LedgerSummary) -> Self {
+        CachedLedger {
+            object: "ledger",
+            ledger: summary.ledger,
+            url: base_path::link(&format!("/events?q=ledger:{}", summary.ledger)),
+            event_count: summary.event_count,
+            closed_at: summary.closed_at,
+            protocol_version: summary.protocol_version,
+            tx_count: summary.tx_count,
+            status: if summary.expired { "expired" } else { "cached" },
+            expires_at: chrono::DateTime::from_timestamp(summary.expires_at, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Activity of a contract across the cached ledgers.
+#[derive(Debug, Serialize)]
+pub struct Contract {
+    pub object: &'static str,
+    pub id: String,
+    /// Events query for this contract's events.
+    pub url: String,
+    pub event_count: u64,
+    /// The same events, by type.
+    pub events_by_type: EventTypeCounts,
+    pub first_seen_ledger: u32,
+    pub last_seen_ledger: u32,
+    pub last_activity_at: String,
+}
+
+/// A first-topic symbol seen in the cached ledgers.
+#[derive(Debug, Serialize)]
+pub struct Topic {
+    pub object: &'static str,
+    pub symbol: String,
+    /// Events query for events with this first topic.
+    pub url: String,
+    pub event_count: u64,
+}
+
+/// A classic asset whose Stellar Asset Contract has events in the cached
+/// ledgers.
+#[derive(Debug, Serialize)]
+pub struct Asset {
+    pub object: &'static str,
+    /// SEP-11 form: `native` or `CODE:ISSUER`.
+    pub asset: String,
+    pub code: String,
+    /// Issuing account, or `null` for the native asset.
+    pub issuer: Option<String>,
+    /// ID of the asset's contract.
+    pub contract: String,
+    /// Events query for the asset contract's events.
+    pub url: String,
+    pub event_count: u64,
+}
+
+impl From<ContractSummary> for Contract {
+    fn from(summary: ContractSummary) -> Self {
+        let url = base_path::link(&format!("/events?q=contract:{}", summary.contract_id));
+        Contract {
+            object: "contract",
+            id: summary.contract_id,
+            url,
+            event_count: summary.event_count,
+            events_by_type: summary.event_types,
+            first_seen_ledger: summary.first_seen_ledger,
+            last_seen_ledger: summary.last_seen_ledger,
+            last_activity_at: summary.last_activity_at,
+        }
+    }
+}
+
+/// How event topics and data are rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// XDR-JSON values.
+    #[default]
+    Json,
+    /// Base64-encoded XDR `ScVal`s.
+    Xdr,
+}
+
+impl Event {
+    /// Build an event with its topics and data rendered in `encoding`.
+    pub fn from_row(row: EventRow, encoding: Encoding) -> Self {
+        let xdr = match encoding {
+            Encoding::Json => None,
+            Encoding::Xdr => Some(event_xdr(&row)),
+        };
+        let mut event = Event::from(row);
+        if let Some(xdr) = xdr {
+            event.topics = serde_json::Value::from(xdr.topics_base64());
+            event.data = serde_json::Value::from(xdr.data_base64());
+        }
+        event
+    }
+}
+
+/// The XDR of an event's topics and data. Topics are held only as XDR-JSON,
+/// so they are re-encoded; the data's raw XDR is used when it was retained,
+/// and re-encoded otherwise, such as for an event imported from an older
+/// snapshot. Values that do not decode as an `ScVal` are rendered as empty
+/// strings.
+fn event_xdr(row: &EventRow) -> EventXdr {
+    EventXdr {
+        topics: EventXdr::encode_topics(&row.topics),
+        data: row
+            .data_xdr
+            .clone()
+            .unwrap_or_else(|| EventXdr::encode_value(&row.data)),
+    }
 }
 
 impl From<EventRow> for Event {
     fn from(row: EventRow) -> Self {
-        let url = format!("/events/{}", row.id);
+        let url = base_path::link(&format!("/events/{}", row.id));
         Event {
             id: row.id,
             url,
@@ -61,10 +592,15 @@ impl From<EventRow> for Event {
             event_type: row.event_type.to_string(),
             ledger_sequence: row.ledger_sequence,
             ledger_closed_at: row.ledger_closed_at,
+            ledger_closed_at_unix: row.ledger_closed_at_unix,
             contract_id: row.contract_id,
             tx_hash: row.tx_hash,
+            op_index: row.op_index,
             topics: row.topics,
             data: row.data,
+            fields: EventFields::ALL,
+            transaction: None,
+            compact: false,
         }
     }
 }
@@ -79,11 +615,49 @@ pub struct ErrorResponse {
 pub struct ErrorBody {
     #[serde(rename = "type")]
     pub error_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub code: Option<String>,
+    pub code: ErrorCode,
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub param: Option<String>,
+    pub doc_url: String,
+    /// ID of the failed request, for matching it to server logs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Reference entry for a single error code, served at `/errors/{code}`.
+#[derive(Debug, Serialize)]
+pub struct ErrorCodeInfo {
+    pub object: &'static str,
+    pub code: ErrorCode,
+    #[serde(rename = "type")]
+    pub error_type: &'static str,
+    pub status: u16,
+    pub description: &'static str,
+    pub url: String,
+}
+
+impl From<ErrorCode> for ErrorCodeInfo {
+    fn from(code: ErrorCode) -> Self {
+        ErrorCodeInfo {
+            object: "error_code",
+            code,
+            error_type: code.error_type(),
+            status: code.status().as_u16(),
+            description: code.description(),
+            url: code.doc_url(),
+        }
+    }
+}
+
+/// An additional network served under `/networks/{name}`, listed at
+/// `/networks`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Network {
+    pub object: &'static str,
+    pub name: String,
+    pub network_passphrase: String,
+    pub url: String,
 }
 
 /// Server status response.
@@ -96,6 +670,59 @@ pub struct StatusResponse {
     pub build: BuildInfo,
 }
 
+/// Readiness probe response.
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    /// `ready` or `not_ready`.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub latest_ledger: Option<u32>,
+    pub network_tip: Option<u32>,
+    pub lag_ledgers: Option<u32>,
+    pub max_lag_ledgers: u32,
+}
+
+/// Progress of the tip-following sync and the history backfill, served at
+/// `/sync`.
+#[derive(Debug, Serialize)]
+pub struct SyncStatus {
+    pub object: &'static str,
+    /// First ledger sync started from.
+    pub start_ledger: Option<u32>,
+    pub last_synced_ledger: Option<u32>,
+    pub network_tip: Option<u32>,
+    pub lag_ledgers: Option<u32>,
+    /// The `--end-ledger` sync stopped at, once reached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ledger_reached: Option<u32>,
+    /// Synced ledgers whose previous ledger hash did not match the header of
+    /// the ledger synced before them.
+    pub hash_chain_breaks: u64,
+    /// The most recent ledger that broke the hash chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_hash_chain_break: Option<u32>,
+    /// Present when a history backfill was started with `--backfill-from`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_backfill: Option<HistoryBackfillStatus>,
+}
+
+/// Progress of the history backfill.
+#[derive(Debug, Serialize)]
+pub struct HistoryBackfillStatus {
+    /// `waiting` (for sync to choose its start), `running`, or `completed`.
+    pub status: &'static str,
+    pub from: u32,
+    pub to: Option<u32>,
+    /// Next ledger to check.
+    pub next: u32,
+    pub remaining: Option<u32>,
+    /// Ledgers checked per second over the most recent batch.
+    pub ledgers_per_second: Option<f64>,
+    /// Estimated seconds until the backfill completes.
+    pub eta_seconds: Option<u64>,
+}
+
 /// Build metadata embedded at compile time.
 #[derive(Debug, Serialize)]
 pub struct BuildInfo {
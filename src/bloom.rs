@@ -0,0 +1,75 @@
+//! A small Bloom filter over 64-bit key hashes.
+//!
+//! Each ledger partition keeps one over the contract IDs and first topics of
+//! its events, so a scan across many cached ledgers can skip the partitions
+//! that cannot hold a match for a selective filter without examining their
+//! events. Lookups may report false positives, never false negatives.
+
+/// Bits per inserted key, for a false positive rate of about 2%.
+const BITS_PER_KEY: usize = 10;
+
+/// Bit positions probed per key.
+const PROBES: u64 = 3;
+
+/// A fixed-size Bloom filter, sized when built from its keys.
+#[derive(Debug, Clone)]
+pub struct Bloom {
+    bits: Vec<u64>,
+}
+
+impl Bloom {
+    /// Build a filter holding `keys`, which are already hashes.
+    pub fn new(keys: &[u64]) -> Self {
+        let words = (keys.len() * BITS_PER_KEY).div_ceil(64).max(1);
+        let mut bloom = Self {
+            bits: vec![0; words],
+        };
+        for &key in keys {
+            for bit in bloom.probes(key) {
+                bloom.bits[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        bloom
+    }
+
+    /// Whether `key` may have been inserted. `false` is definite.
+    pub fn may_contain(&self, key: u64) -> bool {
+        self.probes(key)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Bytes held by the filter's bits.
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * std::mem::size_of::<u64>()
+    }
+
+    /// Bit positions for `key`, by double hashing its two halves.
+    fn probes(&self, key: u64) -> impl Iterator<Item = usize> {
+        let len = (self.bits.len() * 64) as u64;
+        let h1 = key;
+        let h2 = key.rotate_left(32) | 1;
+        (0..PROBES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_may_contain() {
+        let keys: Vec<u64> = (0..100u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect();
+        let bloom = Bloom::new(&keys);
+        assert!(keys.iter().all(|&k| bloom.may_contain(k)));
+
+        let false_positives = (1000..11000u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .filter(|&k| bloom.may_contain(k))
+            .count();
+        assert!(false_positives < 500, "{}", false_positives);
+
+        assert!(!Bloom::new(&[]).may_contain(42));
+    }
+}
@@ -0,0 +1,142 @@
+//! Sync state checkpoint file.
+//!
+//! A small JSON file holding the network passphrase and every sync state
+//! value, including `last_synced_ledger`. The event store rewrites it
+//! atomically as `last_synced_ledger` advances, at most once every
+//! [`SAVE_INTERVAL`], so an instance keeping its cache only in memory still
+//! resumes syncing close to where it left off after a restart, instead of
+//! rediscovering the network tip and leaving a gap.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::EventStore;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Contents {
+    network_passphrase: String,
+    sync_state: BTreeMap<String, String>,
+}
+
+/// A checkpoint file for one network.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    network_passphrase: String,
+    /// When the checkpoint was last saved, if it has been. Also serializes
+    /// saves, which share a temporary file.
+    last_saved: Mutex<Option<Instant>>,
+}
+
+/// Shortest time between saves of a checkpoint as sync advances.
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+impl Checkpoint {
+    /// Open the checkpoint at `path`, returning it with the sync state it
+    /// holds, which is empty if the file does not exist yet. Fails if the
+    /// file is for another network.
+    pub fn open(
+        path: PathBuf,
+        network_passphrase: &str,
+    ) -> Result<(Self, BTreeMap<String, String>), crate::Error> {
+        let sync_state = match std::fs::read(&path) {
+            Ok(data) => {
+                let contents: Contents = serde_json::from_slice(&data)?;
+                if contents.network_passphrase != network_passphrase {
+                    return Err(crate::Error::Internal(format!(
+                        "checkpoint {} is for network '{}', expected '{}'",
+                        path.display(),
+                        contents.network_passphrase,
+                        network_passphrase
+                    )));
+                }
+                contents.sync_state
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let checkpoint = Self {
+            path,
+            network_passphrase: network_passphrase.to_string(),
+            last_saved: Mutex::new(None),
+        };
+        Ok((checkpoint, sync_state))
+    }
+
+    /// Replace the checkpoint with the store's current sync state,
+    /// atomically.
+    pub fn save(&self, store: &EventStore) -> Result<(), crate::Error> {
+        // Read the state under the lock, so that a slower concurrent save
+        // cannot overwrite newer values with older ones.
+        let mut last_saved = self.last_saved.lock().expect("checkpoint lock poisoned");
+        self.write(store)?;
+        *last_saved = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Like [`Self::save`], unless the checkpoint was saved less than
+    /// [`SAVE_INTERVAL`] ago. Returns whether it was saved.
+    pub fn save_if_due(&self, store: &EventStore) -> Result<bool, crate::Error> {
+        let mut last_saved = self.last_saved.lock().expect("checkpoint lock poisoned");
+        if last_saved.is_some_and(|at| at.elapsed() < SAVE_INTERVAL) {
+            return Ok(false);
+        }
+        self.write(store)?;
+        *last_saved = Some(Instant::now());
+        Ok(true)
+    }
+
+    /// Write the sync state to a temporary file, flushed to disk, and rename
+    /// it over the checkpoint, so a crash leaves either the old or the new
+    /// checkpoint.
+    fn write(&self, store: &EventStore) -> Result<(), crate::Error> {
+        let data = serde_json::to_vec(&Contents {
+            network_passphrase: self.network_passphrase.clone(),
+            sync_state: store.sync_state_entries(),
+        })?;
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_open() {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let (checkpoint, state) = Checkpoint::open(path.clone(), "Test Network").unwrap();
+        assert!(state.is_empty());
+        let store = EventStore::new(60);
+        store.set_sync_state("last_synced_ledger", "100").unwrap();
+        checkpoint.save(&store).unwrap();
+
+        let (_, state) = Checkpoint::open(path.clone(), "Test Network").unwrap();
+        assert_eq!(state["last_synced_ledger"], "100");
+        assert!(Checkpoint::open(path.clone(), "Other Network").is_err());
+
+        // Saves as sync advances are held back until the interval passes.
+        store.set_sync_state("last_synced_ledger", "101").unwrap();
+        assert!(!checkpoint.save_if_due(&store).unwrap());
+        let (_, state) = Checkpoint::open(path.clone(), "Test Network").unwrap();
+        assert_eq!(state["last_synced_ledger"], "100");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,215 @@
+//! Typed HTTP client for the events API.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use futures::Stream;
+use serde::Deserialize;
+
+use crate::Error;
+
+/// Maximum number of retries for a rate-limited or unavailable response.
+const MAX_RETRIES: u32 = 5;
+
+/// Initial delay between retries when the server gives no `Retry-After`.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Upper bound on the delay between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Client for a stellar-events-api server.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+/// Parameters for listing events. See the `/events` endpoint documentation.
+#[derive(Debug, Clone, Default)]
+pub struct ListEventsQuery {
+    pub limit: Option<u32>,
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub q: Option<String>,
+    /// `asc` or `desc` (the server default).
+    pub order: Option<String>,
+}
+
+/// A single page of events.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventPage {
+    pub data: Vec<Event>,
+    pub next: Option<String>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// A contract event as returned by the API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub ledger: u32,
+    pub at: String,
+    /// `at` in Unix seconds. Zero from servers that predate the field.
+    #[serde(default)]
+    pub at_unix: i64,
+    pub tx: String,
+    /// Index of the emitting operation, for operation events.
+    #[serde(default)]
+    pub op: Option<u32>,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub contract: Option<String>,
+    pub topics: serde_json::Value,
+    pub data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: String,
+    message: String,
+}
+
+impl Client {
+    /// Create a client for the server at `base_url`, e.g. `http://localhost:3000`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Create a client that sends requests through an existing HTTP client.
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        Client {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fetch a single page of events.
+    pub async fn list_events(&self, query: &ListEventsQuery) -> Result<EventPage, Error> {
+        let mut params = Vec::new();
+        if let Some(limit) = query.limit {
+            params.push(format!("limit={}", limit));
+        }
+        if let Some(ref after) = query.after {
+            params.push(format!("after={}", urlencoding::encode(after)));
+        }
+        if let Some(ref before) = query.before {
+            params.push(format!("before={}", urlencoding::encode(before)));
+        }
+        if let Some(ref q) = query.q {
+            params.push(format!("q={}", urlencoding::encode(q)));
+        }
+        if let Some(ref order) = query.order {
+            params.push(format!("order={}", urlencoding::encode(order)));
+        }
+        let url = format!("{}/events?{}", self.base_url, params.join("&"));
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let resp = self.http.get(&url).send().await?;
+            let status = resp.status();
+
+            let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::SERVICE_UNAVAILABLE;
+            if retryable && attempt < MAX_RETRIES {
+                let delay = retry_after(resp.headers()).unwrap_or(backoff);
+                tracing::debug!(status = %status, delay_ms = delay.as_millis() as u64, "retrying list_events");
+                tokio::time::sleep(delay).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                attempt += 1;
+                continue;
+            }
+
+            let body = resp.bytes().await?;
+            if !status.is_success() {
+                let (code, message) = match serde_json::from_slice::<ErrorResponse>(&body) {
+                    Ok(err) => (err.error.code, err.error.message),
+                    Err(_) => (
+                        "unknown".to_string(),
+                        String::from_utf8_lossy(&body).into_owned(),
+                    ),
+                };
+                return Err(Error::Api {
+                    status: status.as_u16(),
+                    code,
+                    message,
+                });
+            }
+            return Ok(serde_json::from_slice(&body)?);
+        }
+    }
+
+    /// Stream every event matching `query`, following `next` cursors across
+    /// pages. Paginates backward from `before` (or the latest ledger), or
+    /// forward when `after` is set. Events within each page are yielded in
+    /// the order the server returns them. The stream ends when the server
+    /// returns no further cursor, or after the first error.
+    pub fn list_events_stream(
+        &self,
+        query: ListEventsQuery,
+    ) -> impl Stream<Item = Result<Event, Error>> + '_ {
+        struct State {
+            query: ListEventsQuery,
+            buffer: VecDeque<Event>,
+            done: bool,
+        }
+
+        let state = State {
+            query,
+            buffer: VecDeque::new(),
+            done: false,
+        };
+
+        futures::stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some(event) = state.buffer.pop_front() {
+                    return Some((Ok(event), state));
+                }
+                if state.done {
+                    return None;
+                }
+
+                let page = match self.list_events(&state.query).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        state.done = true;
+                        return Some((Err(e), state));
+                    }
+                };
+
+                let forward = state.query.after.is_some();
+                let cursor = if forward {
+                    &mut state.query.after
+                } else {
+                    &mut state.query.before
+                };
+                // Stop on a missing or non-advancing cursor.
+                match page.next {
+                    Some(next) if cursor.as_deref() != Some(next.as_str()) => {
+                        *cursor = Some(next);
+                    }
+                    _ => state.done = true,
+                }
+                state.buffer.extend(page.data);
+            }
+        })
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|secs| Duration::from_secs(secs).min(MAX_BACKOFF))
+}
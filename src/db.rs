@@ -1,34 +1,473 @@
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
 
 use dashmap::DashMap;
+use lru::LruCache;
+use stellar_xdr::curr::{Limits, ReadXdr, ScVal};
 
-use crate::ledger::events::ExtractedEvent;
+use crate::bloom::Bloom;
+use crate::checkpoint::Checkpoint;
+use crate::ledger::events::{EventXdr, ExtractedEvent, LedgerInfo, TransactionSummary};
+use crate::ledger::sac;
+use crate::storage::StorageBackend;
 
 /// In-memory event store, partitioned by ledger sequence.
 ///
 /// Each ledger's events are stored in an immutable partition behind an `Arc`,
 /// enabling lock-free concurrent reads. Expired partitions are simply dropped
-/// (O(1) cleanup vs. SQLite's expensive DELETE + VACUUM).
+/// (O(1) cleanup vs. SQLite's expensive DELETE + VACUUM). With a
+/// [`StorageBackend`], partitions and sync state are also written through to
+/// durable storage and can be restored from it after a restart. With a spill
+/// directory, partitions that go unread are moved out of memory to disk and
+/// read back for each read that needs their events. With compression,
+/// partitions far enough behind the latest ledger are kept as compressed
+/// blocks, decompressed per read. Spilling, compression, and compaction run
+/// from the periodic cleanup rather than on insert.
+/// With compaction, older partitions are merged into multi-ledger segments
+/// instead; the two are alternatives for older ledgers, since compaction
+/// only merges partitions still held uncompressed in memory. Spilled and
+/// compressed partitions keep their filter index in memory, so scans rule
+/// them out without reading their events. With a storage backend and a cap
+/// on cached ledgers, the oldest partitions are left in the backend the same
+/// way, rather than dropped.
 pub struct EventStore {
-    /// Ledger sequence -> immutable partition.
+    /// Ledger sequence -> immutable partition held in memory.
     ledgers: DashMap<u32, Arc<LedgerPartition>>,
+    /// Ledger sequence -> partition spilled to disk or left in the storage
+    /// backend. Spilled partitions stay in the contract, transaction, and
+    /// activity indexes.
+    spilled: DashMap<u32, SpilledPartition>,
+    /// Ledger sequence -> partition compressed in memory. Compressed
+    /// partitions also stay in the indexes.
+    compressed: DashMap<u32, CompressedPartition>,
+    /// Segment number (ledger sequence / [`SEGMENT_LEDGERS`]) -> partitions
+    /// merged by compaction. Merged partitions also stay in the indexes.
+    segments: DashMap<u32, Arc<Segment>>,
+    /// Held while replacing segments, which are rebuilt rather than changed.
+    segment_lock: Mutex<()>,
     /// Highest ledger sequence currently stored.
     latest_ledger: AtomicU32,
+    /// Contract ID -> activity across cached partitions.
+    contracts: DashMap<String, ContractActivity>,
+    /// Transaction hash -> ledger containing its events.
+    transactions: DashMap<String, u32>,
+    /// Activity aggregates over the cached partitions, for `/stats`.
+    activity: Mutex<ActivityAggregates>,
+    /// (close time, ledger sequence) of the cached partitions with a known
+    /// close time, for translating `since`/`until` into ledger bounds.
+    close_times: Mutex<BTreeSet<(i64, u32)>>,
+    /// Sequences of the cached ledgers held by this process, wherever their
+    /// partitions are held, so that the oldest can be evicted without
+    /// scanning every partition.
+    sequences: Mutex<BTreeSet<u32>>,
+    /// Sequences of the cached ledgers left in the storage backend.
+    backend_sequences: Mutex<BTreeSet<u32>>,
     /// Simple key-value store for sync state.
     sync_state: DashMap<String, String>,
     /// Cache TTL in seconds.
     cache_ttl_seconds: i64,
+    /// TTL in seconds of ledgers backfilled for scans, if not the cache TTL.
+    backfill_ttl_seconds: Option<i64>,
+    /// TTL in seconds of ledgers fetched for lookups pinned to them, if not
+    /// the cache TTL.
+    pinned_ttl_seconds: Option<i64>,
+    /// Durable copy of the partitions and sync state, if configured.
+    backend: Option<Box<dyn StorageBackend>>,
+    /// File the sync state is rewritten to on every change, if configured.
+    checkpoint: Option<Checkpoint>,
+    /// Where idle partitions are spilled, if configured.
+    spill: Option<SpillConfig>,
+    /// How many ledgers behind the latest a partition is compressed, if
+    /// compression is enabled.
+    compress_after_ledgers: Option<u32>,
+    /// Most partitions cached at once, if capped.
+    max_cached_ledgers: Option<usize>,
+    /// Contract ID -> seconds after their ledger closes that its events are
+    /// kept, beyond the cache TTL.
+    retention: HashMap<String, i64>,
+    /// Ledgers whose partitions were cut down to retained contracts' events
+    /// when they expired. They are queried as usual but count as uncached,
+    /// so they are fetched again in full when needed.
+    retained_only: DashMap<u32, ()>,
+    /// How many ledgers behind the latest a segment must end before its
+    /// partitions are compacted, if compaction is enabled.
+    compact_after_ledgers: Option<u32>,
+    /// Random identifier of this store instance, embedded in cursor tokens
+    /// so that tokens from other instances are recognised.
+    epoch: u32,
+    /// Generation assigned to the next partition indexed.
+    next_generation: AtomicU64,
+    /// Ledger sequence -> generation of its cached partition, which changes
+    /// whenever the partition is dropped or replaced.
+    generations: DashMap<u32, u64>,
+    /// Whether reading a partition pushes its expiry back to a full TTL
+    /// from the read.
+    sliding_ttl: bool,
+}
+
+/// Ledgers covered by each compacted segment. Segments are aligned to
+/// multiples of this.
+const SEGMENT_LEDGERS: u32 = 1024;
+
+/// File extension of spilled partitions.
+const SPILL_EXTENSION: &str = ".json.zst";
+
+/// zstd level for spilled and compressed partitions.
+const SPILL_COMPRESSION_LEVEL: i32 = 3;
+
+/// Path of a spilled partition's file.
+fn spill_path(dir: &std::path::Path, ledger_seq: u32) -> PathBuf {
+    dir.join(format!("{:010}{}", ledger_seq, SPILL_EXTENSION))
+}
+
+/// Spilling of partitions that have not been read recently.
+struct SpillConfig {
+    dir: PathBuf,
+    /// Seconds since a partition was last read after which it is spilled.
+    idle_seconds: i64,
+}
+
+/// A partition spilled to disk: enough to describe it, and to rule it out
+/// of a scan, without reloading.
+struct SpilledPartition {
+    summary: LedgerSummary,
+    keys: PartitionKeys,
+    target: SpillTarget,
+}
+
+/// Where a spilled partition's events are read back from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SpillTarget {
+    /// Its file in the spill directory.
+    File,
+    /// The storage backend, which saved it when it was cached.
+    Backend,
+}
+
+/// The filter index of a spilled or compressed partition, kept so that
+/// scans and counts can skip it without reading its events.
+struct PartitionKeys {
+    /// Close time in Unix seconds, if known.
+    closed_at: Option<i64>,
+    /// Sorted, deduplicated search tokens of every event in the partition.
+    search_tokens: Vec<String>,
+    /// The contract IDs and first topics of the events.
+    keys: Bloom,
+    /// External IDs of the first and last events, which a scan that skips
+    /// the partition returns as its cursor.
+    first_id: Option<String>,
+    last_id: Option<String>,
+}
+
+impl PartitionKeys {
+    /// Whether any event in the partition could match the filters, by the
+    /// same checks as [`LedgerPartition::may_match`].
+    fn may_match(&self, params: &EventQueryParams) -> bool {
+        filters_may_match(params, self.closed_at, &self.search_tokens, &self.keys)
+    }
+
+    /// Approximate bytes held by the search tokens, Bloom filter, and IDs.
+    fn heap_size(&self) -> usize {
+        strings_size(&self.search_tokens)
+            + self.keys.size_bytes()
+            + self.first_id.as_ref().map_or(0, String::len)
+            + self.last_id.as_ref().map_or(0, String::len)
+    }
+}
+
+/// A partition held as a zstd-compressed block of its JSON export, in the
+/// same form as a spill file, with its filter index alongside so it can be
+/// ruled out of a scan without decompressing.
+struct CompressedPartition {
+    summary: LedgerSummary,
+    keys: PartitionKeys,
+    data: Vec<u8>,
+}
+
+/// Serialize and compress a partition, as written to spill files.
+fn compress_partition(
+    ledger_seq: u32,
+    partition: &LedgerPartition,
+) -> Result<Vec<u8>, crate::Error> {
+    let json = serde_json::to_vec(&partition.export(ledger_seq))?;
+    Ok(zstd::encode_all(json.as_slice(), SPILL_COMPRESSION_LEVEL)?)
+}
+
+/// Partitions of a range of older ledgers, merged by compaction under one
+/// entry, with search tokens and a Bloom filter shared across them so a scan
+/// can rule out the whole range at once.
+struct Segment {
+    /// (ledger sequence, partition), ascending.
+    partitions: Vec<(u32, Arc<LedgerPartition>)>,
+    /// Sorted, deduplicated search tokens of every event in the segment.
+    search_tokens: Vec<String>,
+    /// The contract IDs and first topics of every event in the segment.
+    keys: Bloom,
+}
+
+impl Segment {
+    /// Build a segment from partitions in ledger order.
+    fn new(partitions: Vec<(u32, Arc<LedgerPartition>)>) -> Self {
+        let mut search_tokens: Vec<String> = partitions
+            .iter()
+            .flat_map(|(_, p)| p.search_tokens.iter().cloned())
+            .collect();
+        search_tokens.sort();
+        search_tokens.dedup();
+
+        let mut keys: Vec<u64> = partitions
+            .iter()
+            .flat_map(|(_, p)| p.events.iter())
+            .flat_map(|e| {
+                let contract = e.contract_id.as_deref().map(contract_key);
+                let topic0 = e.topics.get(0).map(topic_hash);
+                contract.into_iter().chain(topic0)
+            })
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        Self {
+            partitions,
+            search_tokens,
+            keys: Bloom::new(&keys),
+        }
+    }
+
+    fn get(&self, ledger_seq: u32) -> Option<&Arc<LedgerPartition>> {
+        let pos = self
+            .partitions
+            .binary_search_by_key(&ledger_seq, |(seq, _)| *seq)
+            .ok()?;
+        Some(&self.partitions[pos].1)
+    }
+
+    /// Whether any event in the segment could match the filters, by the
+    /// same checks as [`LedgerPartition::may_match`] except the time window.
+    fn may_match(&self, params: &EventQueryParams) -> bool {
+        params.filters.is_empty()
+            || params
+                .filters
+                .iter()
+                .any(|f| keys_may_match(f, &self.search_tokens, &self.keys))
+    }
+
+    /// Approximate bytes held by the shared search tokens and Bloom filter.
+    fn index_size(&self) -> usize {
+        strings_size(&self.search_tokens) + self.keys.size_bytes()
+    }
+}
+
+/// Whether any event of a partition with this close time, search tokens,
+/// and contract and first topic keys could match the filters.
+fn filters_may_match(
+    params: &EventQueryParams,
+    closed_at: Option<i64>,
+    search_tokens: &[String],
+    keys: &Bloom,
+) -> bool {
+    params.filters.is_empty()
+        || params.filters.iter().any(|f| {
+            closed_at.is_none_or(|t| f.in_time_window(t)) && keys_may_match(f, search_tokens, keys)
+        })
+}
+
+/// Whether events with these search tokens and contract and first topic
+/// keys could match the `search`, contract, and exact `topic0` constraints
+/// of `filter`.
+fn keys_may_match(filter: &EventFilter, search_tokens: &[String], keys: &Bloom) -> bool {
+    let search = filter
+        .search
+        .as_ref()
+        .is_none_or(|terms| terms.iter().all(|term| contains_term(search_tokens, term)));
+    search
+        && filter
+            .contract_id
+            .as_deref()
+            .is_none_or(|cid| keys.may_contain(contract_key(cid)))
+        && filter
+            .topics
+            .as_ref()
+            .and_then(|t| t.first())
+            .filter(|t| !t.is_null())
+            .is_none_or(|t| keys.may_contain(topic_hash(t)))
+}
+
+/// Approximate heap bytes held by a JSON value, excluding the value itself.
+fn json_heap_size(value: &serde_json::Value) -> usize {
+    use serde_json::Value;
+    match value {
+        Value::String(s) => s.len(),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| std::mem::size_of::<Value>() + json_heap_size(v))
+            .sum(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(k, v)| std::mem::size_of::<(String, Value)>() + k.len() + json_heap_size(v))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Approximate heap bytes held by a list of strings.
+fn strings_size(strings: &[String]) -> usize {
+    strings
+        .iter()
+        .map(|s| std::mem::size_of::<String>() + s.len())
+        .sum()
+}
+
+/// Decompress and parse a block written by [`compress_partition`].
+fn decompress_partition(data: &[u8]) -> Result<PartitionExport, crate::Error> {
+    let json = zstd::decode_all(data)?;
+    Ok(serde_json::from_slice(&json)?)
 }
 
 /// An immutable partition holding all events for a single ledger.
 /// Built once during ingestion, never modified afterward.
 struct LedgerPartition {
+    /// Header details, when the partition was built from fetched ledger meta.
+    info: Option<LedgerInfo>,
     /// Events sorted by ID for cursor-based pagination.
     events: Vec<StoredEvent>,
-    /// Unix timestamp when this partition expires.
-    expires_at: i64,
+    /// Sorted, deduplicated search tokens of every event in the partition,
+    /// used to skip partitions that cannot match a `search` filter.
+    search_tokens: Vec<String>,
+    /// Unix timestamp when this partition expires, pushed back by reads
+    /// when the TTL slides.
+    expires_at: AtomicI64,
+    /// Unix timestamp when this partition was built or last read.
+    last_read_at: AtomicI64,
+    /// [`topic_hash`] of each first topic -> positions in `events` of the
+    /// events with it, ascending, used to jump straight to the candidates
+    /// for filters on an exact `topic0`.
+    topic0_index: HashMap<u64, Vec<u32>>,
+    /// The contract IDs and first topics of the events, used to skip the
+    /// partition for filters on a contract or exact `topic0` it lacks.
+    keys: Bloom,
+}
+
+/// Where a contract's events appear among the cached partitions.
+#[derive(Default)]
+struct ContractActivity {
+    /// Ledger sequence -> (events in that ledger, ledger close time).
+    ledgers: BTreeMap<u32, (u64, String)>,
+    /// Sum of the per-ledger event counts.
+    event_count: u64,
+    /// The same events, by type.
+    event_types: EventTypeCounts,
+    /// First topic symbol -> events, sorted for prefix lookups.
+    topic0: BTreeMap<String, u64>,
+    /// SEP-11 asset named by asset contract events -> events.
+    assets: HashMap<String, u64>,
+}
+
+/// Upper bounds (inclusive) of the events-per-ledger histogram buckets. The
+/// last bucket is unbounded.
+pub const LEDGER_EVENT_BUCKETS: [u64; 4] = [0, 9, 99, 999];
+
+/// Aggregates over the cached partitions, updated as partitions are added
+/// and dropped.
+#[derive(Default)]
+struct ActivityAggregates {
+    /// Minute start (Unix seconds) -> (events, ledgers) closed in it.
+    per_minute: BTreeMap<i64, (u64, u64)>,
+    /// Ledgers per events-per-ledger bucket.
+    ledger_buckets: [u64; LEDGER_EVENT_BUCKETS.len() + 1],
+    /// First topic symbol -> events, sorted for prefix lookups.
+    topic0: BTreeMap<String, u64>,
+    /// Events by type.
+    event_types: EventTypeCounts,
+}
+
+impl ActivityAggregates {
+    fn apply(&mut self, partition: &LedgerPartition, add: bool) {
+        fn adjust(value: &mut u64, by: u64, add: bool) {
+            *value = if add {
+                *value + by
+            } else {
+                value.saturating_sub(by)
+            };
+        }
+
+        let events = partition.events.len() as u64;
+        let bucket = LEDGER_EVENT_BUCKETS
+            .iter()
+            .position(|&max| events <= max)
+            .unwrap_or(LEDGER_EVENT_BUCKETS.len());
+        adjust(&mut self.ledger_buckets[bucket], 1, add);
+
+        if let Some(closed_at) = partition.closed_at() {
+            let minute = closed_at - closed_at.rem_euclid(60);
+            let entry = self.per_minute.entry(minute).or_default();
+            adjust(&mut entry.0, events, add);
+            adjust(&mut entry.1, 1, add);
+            if entry.1 == 0 {
+                self.per_minute.remove(&minute);
+            }
+        }
+
+        let types = EventTypeCounts::of(partition.events.iter());
+        self.event_types.apply(&types, add);
+
+        for event in &partition.events {
+            let Some(symbol) = event.topic0_symbol() else {
+                continue;
+            };
+            if add {
+                *self.topic0.entry(symbol.to_string()).or_default() += 1;
+            } else if let Some(count) = self.topic0.get_mut(symbol) {
+                *count -= 1;
+                if *count == 0 {
+                    self.topic0.remove(symbol);
+                }
+            }
+        }
+    }
+}
+
+/// Contract ID -> events by type, over one partition.
+fn contract_type_counts(partition: &LedgerPartition) -> HashMap<&str, EventTypeCounts> {
+    let mut counts: HashMap<&str, EventTypeCounts> = HashMap::new();
+    for event in &partition.events {
+        if let Some(cid) = event.contract_id.as_deref() {
+            *counts.entry(cid).or_default().get_mut(event.event_type) += 1;
+        }
+    }
+    counts
+}
+
+/// Contract ID -> first topic symbol -> events, over one partition.
+fn contract_topic_counts(partition: &LedgerPartition) -> HashMap<&str, HashMap<&str, u64>> {
+    let mut counts: HashMap<&str, HashMap<&str, u64>> = HashMap::new();
+    for event in &partition.events {
+        if let (Some(cid), Some(symbol)) = (event.contract_id.as_deref(), event.topic0_symbol()) {
+            *counts.entry(cid).or_default().entry(symbol).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Contract ID -> asset named in asset contract events -> events, over one
+/// partition.
+fn contract_asset_counts(partition: &LedgerPartition) -> HashMap<&str, HashMap<String, u64>> {
+    let mut counts: HashMap<&str, HashMap<String, u64>> = HashMap::new();
+    for event in &partition.events {
+        let Some(cid) = event.contract_id.as_deref() else {
+            continue;
+        };
+        let Some(topics) = event.topics.as_array() else {
+            continue;
+        };
+        if let Some(asset) = sac::asset_from_topics(topics) {
+            *counts.entry(cid).or_default().entry(asset).or_default() += 1;
+        }
+    }
+    counts
 }
 
 /// Internal event representation optimised for in-memory filtering.
@@ -37,29 +476,107 @@ struct StoredEvent {
     external_id: String,
     ledger_sequence: u32,
     ledger_closed_at: String,
+    /// Ledger close time in Unix seconds.
+    ledger_closed_at_unix: i64,
     contract_id: Option<String>,
+    /// Index of the emitting operation, for operation events.
+    op_index: Option<u32>,
     /// 0 = contract, 1 = system, 2 = diagnostic
     event_type: u8,
     event_type_str: &'static str,
+    /// The topics as XDR-JSON, which every filter reads. Their XDR is not
+    /// kept, and is re-encoded when a response asks for it.
     topics: serde_json::Value,
-    data: serde_json::Value,
+    /// The data as XDR-JSON, or `None` when it is held only as XDR in
+    /// `data_xdr` and converted when it is needed; see [`StoredEvent::data`].
+    data: Option<serde_json::Value>,
+    /// The data as encoded on the ledger, when extracted from XDR.
+    data_xdr: Option<Vec<u8>>,
     tx_hash: String,
+    transaction: Option<Arc<TransactionSummary>>,
+    /// Lowercased symbol and string values from the topics and data.
+    search_tokens: Vec<String>,
 }
 
 impl StoredEvent {
+    /// Approximate bytes of memory held by the event. Transaction summaries
+    /// are shared between events and not counted.
+    fn approx_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.id.len()
+            + self.external_id.len()
+            + self.ledger_closed_at.len()
+            + self.contract_id.as_ref().map_or(0, String::len)
+            + json_heap_size(&self.topics)
+            + self.data.as_ref().map_or(0, json_heap_size)
+            + self.data_xdr.as_ref().map_or(0, Vec::len)
+            + self.tx_hash.len()
+            + strings_size(&self.search_tokens)
+    }
+
+    /// The data as XDR-JSON, converted from its XDR through
+    /// [`DATA_JSON_CACHE`] if it is not held as XDR-JSON.
+    fn data(&self) -> serde_json::Value {
+        self.data_with(cached_data_json)
+    }
+
+    /// The data as XDR-JSON, converted from its XDR with `convert` if it is
+    /// not held as XDR-JSON.
+    fn data_with(&self, convert: fn(&[u8]) -> serde_json::Value) -> serde_json::Value {
+        match (&self.data, &self.data_xdr) {
+            (Some(data), _) => data.clone(),
+            (None, Some(xdr)) => convert(xdr),
+            (None, None) => serde_json::Value::Null,
+        }
+    }
+
+    /// The amount in the data, read from its XDR if it is not held as
+    /// XDR-JSON.
+    fn amount(&self) -> Option<i128> {
+        match (&self.data, &self.data_xdr) {
+            (Some(data), _) => crate::ledger::amount::from_data(data),
+            (None, Some(xdr)) => ScVal::from_xdr(xdr, Limits::none())
+                .ok()
+                .and_then(|data| crate::ledger::amount::from_data_scval(&data)),
+            (None, None) => None,
+        }
+    }
+
+    /// The symbol of the first topic, if it is one.
+    fn topic0_symbol(&self) -> Option<&str> {
+        self.topics.get(0)?.get("symbol")?.as_str()
+    }
+
     fn to_event_row(&self) -> EventRow {
         EventRow {
             id: self.external_id.clone(),
             ledger_sequence: self.ledger_sequence,
             ledger_closed_at: self.ledger_closed_at.clone(),
+            ledger_closed_at_unix: self.ledger_closed_at_unix,
+            op_index: self.op_index,
             contract_id: self.contract_id.clone(),
             event_type: self.event_type_str,
             topics: self.topics.clone(),
-            data: self.data.clone(),
+            data: self.data(),
+            data_xdr: self.data_xdr.clone(),
             tx_hash: self.tx_hash.clone(),
+            transaction: self.transaction.clone(),
         }
     }
 
+    /// The function named by a diagnostic `fn_call` event (topics `fn_call`,
+    /// contract, function) or `fn_return` event (topics `fn_return`,
+    /// function).
+    fn called_function(&self) -> Option<&str> {
+        let topics = self.topics.as_array()?;
+        let position = match topics.first()?.get("symbol")?.as_str()? {
+            "fn_call" => 2,
+            "fn_return" => 1,
+            _ => return None,
+        };
+        topics.get(position)?.get("symbol")?.as_str()
+    }
+
     /// Check whether this event matches a single filter (all conditions AND'd).
     fn matches_filter(&self, filter: &EventFilter) -> bool {
         if let Some(ledger) = filter.ledger {
@@ -68,12 +585,20 @@ impl StoredEvent {
             }
         }
 
+        if !filter.in_time_window(self.ledger_closed_at_unix) {
+            return false;
+        }
+
         if let Some(ref tx) = filter.tx {
             if self.tx_hash != *tx {
                 return false;
             }
         }
 
+        if filter.op.is_some() && self.op_index != filter.op {
+            return false;
+        }
+
         if let Some(ref cid) = filter.contract_id {
             match &self.contract_id {
                 Some(eid) if eid == cid => {}
@@ -114,6 +639,51 @@ impl StoredEvent {
             }
         }
 
+        if let Some(ref function) = filter.function {
+            if self.event_type != 2 || self.called_function() != Some(function.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(ref comparisons) = filter.amount {
+            match self.amount() {
+                Some(amount) if comparisons.iter().all(|c| c.matches(amount)) => {}
+                _ => return false,
+            }
+        }
+
+        if let Some(ref subset_topics) = filter.subset_topics {
+            let stored = match self.topics.as_array() {
+                Some(v) => v,
+                None => return false,
+            };
+            for (i, topic_val) in subset_topics.iter().enumerate() {
+                if topic_val.is_null() {
+                    continue;
+                }
+                match stored.get(i) {
+                    Some(actual) if scval_contains(actual, topic_val) => {}
+                    _ => return false,
+                }
+            }
+        }
+
+        if let Some(ref topic_types) = filter.topic_types {
+            let stored = match self.topics.as_array() {
+                Some(v) => v,
+                None => return false,
+            };
+            for (i, expected) in topic_types.iter().enumerate() {
+                let Some(expected) = expected else {
+                    continue;
+                };
+                match stored.get(i) {
+                    Some(actual) if scval_type(actual) == Some(expected.as_str()) => {}
+                    _ => return false,
+                }
+            }
+        }
+
         if let Some(ref any_topics) = filter.any_topics {
             let stored = match self.topics.as_array() {
                 Some(v) => v,
@@ -126,208 +696,1269 @@ impl StoredEvent {
             }
         }
 
+        if let Some(ref terms) = filter.search {
+            if !terms
+                .iter()
+                .all(|term| contains_term(&self.search_tokens, term))
+            {
+                return false;
+            }
+        }
+
         true
     }
 }
 
-impl EventStore {
-    /// Create a new in-memory event store.
-    pub fn new(cache_ttl_seconds: i64) -> Self {
-        Self {
-            ledgers: DashMap::new(),
-            latest_ledger: AtomicU32::new(0),
-            sync_state: DashMap::new(),
-            cache_ttl_seconds,
-        }
-    }
+impl From<ExtractedEvent> for StoredEvent {
+    fn from(event: ExtractedEvent) -> Self {
+        let id = crate::ledger::event_id::event_id(
+            event.ledger_sequence,
+            event.phase,
+            event.tx_index,
+            event.event_index,
+        );
+        let (phase, sub) = event.phase.as_phase_sub();
+        let external_id = crate::ledger::event_id::encode_event_id(
+            event.ledger_sequence,
+            phase,
+            event.tx_index,
+            sub,
+            event.event_index,
+        );
+        let (event_type, event_type_str) = match event.event_type {
+            crate::ledger::events::EventType::Contract => (0u8, "contract"),
+            crate::ledger::events::EventType::System => (1u8, "system"),
+            crate::ledger::events::EventType::Diagnostic => (2u8, "diagnostic"),
+        };
+        let ledger_closed_at = chrono::DateTime::from_timestamp(event.ledger_closed_at, 0)
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_default();
+        let topics = serde_json::Value::Array(event.topics_xdr_json);
+        let data = event.data_xdr_json;
+        let data_xdr = event.xdr.map(|xdr| xdr.data);
+        let search_tokens = event_search_tokens(&topics, data.as_ref(), data_xdr.as_deref());
 
-    /// Insert extracted events into the store, grouped by ledger.
-    #[tracing::instrument(skip_all, fields(event_count = events.len()))]
-    pub fn insert_events(&self, events: Vec<ExtractedEvent>) -> Result<(), crate::Error> {
-        // Group events by ledger sequence.
-        let mut by_ledger: HashMap<u32, Vec<ExtractedEvent>> = HashMap::new();
-        for event in events {
-            by_ledger
-                .entry(event.ledger_sequence)
-                .or_default()
-                .push(event);
+        StoredEvent {
+            id,
+            external_id,
+            ledger_sequence: event.ledger_sequence,
+            ledger_closed_at,
+            ledger_closed_at_unix: event.ledger_closed_at,
+            op_index: event.op_index,
+            contract_id: event.contract_id,
+            event_type,
+            event_type_str,
+            topics,
+            data,
+            data_xdr,
+            tx_hash: event.tx_hash,
+            transaction: event.transaction,
+            search_tokens,
         }
+    }
+}
 
-        for (ledger_seq, ledger_events) in by_ledger {
-            // Skip if already cached (idempotent).
-            if self.ledgers.contains_key(&ledger_seq) {
-                continue;
-            }
+impl LedgerPartition {
+    /// Build a partition from a ledger's events.
+    fn new(info: Option<LedgerInfo>, mut stored: Vec<StoredEvent>, expires_at: i64) -> Self {
+        // Sort by ID for cursor-based pagination.
+        stored.sort_by(|a, b| a.id.cmp(&b.id));
 
-            let mut stored: Vec<StoredEvent> = Vec::with_capacity(ledger_events.len());
+        let mut search_tokens: Vec<String> = stored
+            .iter()
+            .flat_map(|e| e.search_tokens.iter().cloned())
+            .collect();
+        search_tokens.sort();
+        search_tokens.dedup();
 
-            for event in ledger_events {
-                let id = crate::ledger::event_id::event_id(
-                    event.ledger_sequence,
-                    event.phase,
-                    event.tx_index,
-                    event.event_index,
-                );
-                let (phase, sub) = event.phase.as_phase_sub();
-                let external_id = crate::ledger::event_id::encode_event_id(
-                    event.ledger_sequence,
-                    phase,
-                    event.tx_index,
-                    sub,
-                    event.event_index,
-                );
-                let (event_type, event_type_str) = match event.event_type {
-                    crate::ledger::events::EventType::Contract => (0u8, "contract"),
-                    crate::ledger::events::EventType::System => (1u8, "system"),
-                    crate::ledger::events::EventType::Diagnostic => (2u8, "diagnostic"),
-                };
-                let ledger_closed_at = chrono::DateTime::from_timestamp(event.ledger_closed_at, 0)
-                    .map(|dt| dt.to_rfc3339())
-                    .unwrap_or_default();
-                let topics = serde_json::Value::Array(event.topics_xdr_json);
-                let data = event.data_xdr_json;
-
-                stored.push(StoredEvent {
-                    id,
-                    external_id,
-                    ledger_sequence: event.ledger_sequence,
-                    ledger_closed_at,
-                    contract_id: event.contract_id,
-                    event_type,
-                    event_type_str,
-                    topics,
-                    data,
-                    tx_hash: event.tx_hash,
-                });
+        let mut topic0_index: HashMap<u64, Vec<u32>> = HashMap::new();
+        for (pos, event) in stored.iter().enumerate() {
+            if let Some(topic0) = event.topics.get(0) {
+                topic0_index
+                    .entry(topic_hash(topic0))
+                    .or_default()
+                    .push(pos as u32);
             }
+        }
 
-            // Sort by ID for cursor-based pagination.
-            stored.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut contract_ids: Vec<&str> = stored
+            .iter()
+            .filter_map(|e| e.contract_id.as_deref())
+            .collect();
+        contract_ids.sort_unstable();
+        contract_ids.dedup();
+        let keys: Vec<u64> = contract_ids
+            .into_iter()
+            .map(contract_key)
+            .chain(topic0_index.keys().copied())
+            .collect();
+        let keys = Bloom::new(&keys);
 
-            let now = chrono::Utc::now().timestamp();
-            let partition = Arc::new(LedgerPartition {
-                events: stored,
-                expires_at: now + self.cache_ttl_seconds,
-            });
+        Self {
+            info,
+            events: stored,
+            search_tokens,
+            expires_at: AtomicI64::new(expires_at),
+            last_read_at: AtomicI64::new(chrono::Utc::now().timestamp()),
+            topic0_index,
+            keys,
+        }
+    }
 
-            let event_count = partition.events.len();
-            self.ledgers.insert(ledger_seq, partition);
+    /// Positions within `range` of the events that could match the filters,
+    /// ascending. When every filter names an exact `topic0`, only the events
+    /// with one of those first topics are candidates; otherwise every
+    /// position is.
+    fn positions(
+        &self,
+        params: &EventQueryParams,
+        range: std::ops::Range<usize>,
+    ) -> Box<dyn DoubleEndedIterator<Item = usize> + '_> {
+        let topic0s: Option<Vec<&serde_json::Value>> = (!params.filters.is_empty())
+            .then(|| {
+                params
+                    .filters
+                    .iter()
+                    .map(|f| f.topics.as_ref()?.first().filter(|t| !t.is_null()))
+                    .collect()
+            })
+            .flatten();
+        let Some(topic0s) = topic0s else {
+            return Box::new(range);
+        };
+        let mut candidates: Vec<usize> = topic0s
+            .into_iter()
+            .filter_map(|topic0| self.topic0_index.get(&topic_hash(topic0)))
+            .flatten()
+            .map(|&pos| pos as usize)
+            .filter(|pos| range.contains(pos))
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        Box::new(candidates.into_iter())
+    }
 
-            metrics::gauge!("store_partitions_total").set(self.ledgers.len() as f64);
-            metrics::counter!("store_events_ingested_total").increment(event_count as u64);
+    /// Portable copy of the partition, which holds `ledger_seq`.
+    fn export(&self, ledger_seq: u32) -> PartitionExport {
+        PartitionExport {
+            ledger: ledger_seq,
+            info: self.info,
+            expires_at: Some(self.expires_at()),
+            events: self
+                .events
+                .iter()
+                .map(|e| ExportedEvent {
+                    id: e.id.clone(),
+                    ledger_closed_at: e.ledger_closed_at.clone(),
+                    op_index: e.op_index,
+                    contract_id: e.contract_id.clone(),
+                    event_type: e.event_type_str.to_string(),
+                    topics: e.topics.clone(),
+                    // Bypass the cache, which a whole partition would flush.
+                    data: e.data_with(data_json),
+                    xdr: e.data_xdr.as_ref().map(|data| EventXdr {
+                        topics: EventXdr::encode_topics(&e.topics),
+                        data: data.clone(),
+                    }),
+                    tx_hash: e.tx_hash.clone(),
+                    transaction: e.transaction.as_deref().cloned(),
+                })
+                .collect(),
+        }
+    }
 
-            tracing::debug!(
-                ledger = ledger_seq,
-                events = event_count,
-                "inserted ledger partition"
-            );
+    /// Approximate bytes of memory held by the partition and its events.
+    fn approx_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .events
+                .iter()
+                .map(StoredEvent::approx_size)
+                .sum::<usize>()
+            + strings_size(&self.search_tokens)
+            + self
+                .topic0_index
+                .values()
+                .map(|positions| {
+                    std::mem::size_of::<(u64, Vec<u32>)>()
+                        + positions.len() * std::mem::size_of::<u32>()
+                })
+                .sum::<usize>()
+            + self.keys.size_bytes()
+    }
 
-            // Update latest ledger tracker.
-            self.latest_ledger.fetch_max(ledger_seq, Ordering::Relaxed);
+    /// Close time in Unix seconds, if known.
+    fn closed_at(&self) -> Option<i64> {
+        match self.info {
+            Some(info) => Some(info.closed_at),
+            None => self.events.first().map(|e| e.ledger_closed_at_unix),
         }
+    }
 
-        Ok(())
+    /// Unix timestamp when this partition expires.
+    fn expires_at(&self) -> i64 {
+        self.expires_at.load(Ordering::Relaxed)
     }
 
-    /// Record that a ledger has been cached (sets TTL).
-    pub fn record_ledger_cached(
-        &self,
-        ledger_sequence: u32,
-        _ttl_seconds: i64,
-    ) -> Result<(), crate::Error> {
-        // In the in-memory store, TTL is set during insert_events.
-        // This method exists for API compatibility. If the ledger was inserted
-        // without events (empty ledger), record it now.
-        if !self.ledgers.contains_key(&ledger_sequence) {
-            let now = chrono::Utc::now().timestamp();
-            let partition = Arc::new(LedgerPartition {
-                events: Vec::new(),
-                expires_at: now + self.cache_ttl_seconds,
-            });
-            self.ledgers.insert(ledger_sequence, partition);
-            self.latest_ledger
-                .fetch_max(ledger_sequence, Ordering::Relaxed);
+    /// Record a read at `now`, and with a sliding TTL of `slide` seconds,
+    /// push the expiry back to `now + slide` unless it has already passed.
+    fn touch(&self, now: i64, slide: Option<i64>) {
+        self.last_read_at.store(now, Ordering::Relaxed);
+        if let Some(ttl) = slide {
+            let _ =
+                self.expires_at
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |expires_at| {
+                        (expires_at > now && expires_at < now + ttl).then_some(now + ttl)
+                    });
         }
-        Ok(())
     }
 
-    /// Check if a ledger is cached and not expired.
-    pub fn is_ledger_cached(&self, ledger_sequence: u32) -> Result<bool, crate::Error> {
-        let now = chrono::Utc::now().timestamp();
-        Ok(self
-            .ledgers
-            .get(&ledger_sequence)
-            .is_some_and(|p| p.expires_at > now))
+    fn summary(&self, ledger: u32, now: i64) -> LedgerSummary {
+        let closed_at = match self.info {
+            Some(info) => {
+                chrono::DateTime::from_timestamp(info.closed_at, 0).map(|dt| dt.to_rfc3339())
+            }
+            None => self.events.first().map(|e| e.ledger_closed_at.clone()),
+        };
+        let expires_at = self.expires_at();
+        LedgerSummary {
+            ledger,
+            event_count: self.events.len(),
+            closed_at,
+            protocol_version: self.info.map(|i| i.protocol_version),
+            tx_count: self.info.map(|i| i.tx_count),
+            expires_at,
+            expired: expires_at <= now,
+        }
     }
 
-    /// Find ledger sequences in the given range that are NOT cached.
-    pub fn find_uncached_ledgers(&self, start: u32, count: u32) -> Result<Vec<u32>, crate::Error> {
-        let now = chrono::Utc::now().timestamp();
-        let end = start + count;
-        Ok((start..end)
-            .filter(|seq| self.ledgers.get(seq).is_none_or(|p| p.expires_at <= now))
-            .collect())
+    /// Whether any event in this partition could match the filters. Only the
+    /// `since`/`until` window, against the close time, `search` terms,
+    /// against the partition token index, and the contract and exact
+    /// `topic0`, against the partition Bloom filter, are checked.
+    fn may_match(&self, params: &EventQueryParams) -> bool {
+        filters_may_match(params, self.closed_at(), &self.search_tokens, &self.keys)
     }
 
-    /// Get sync state value.
-    pub fn get_sync_state(&self, key: &str) -> Result<Option<String>, crate::Error> {
-        Ok(self.sync_state.get(key).map(|v| v.value().clone()))
+    /// Copy of the filter index, for holding the partition out of memory.
+    fn partition_keys(&self) -> PartitionKeys {
+        PartitionKeys {
+            closed_at: self.closed_at(),
+            search_tokens: self.search_tokens.clone(),
+            keys: self.keys.clone(),
+            first_id: self.events.first().map(|e| e.external_id.clone()),
+            last_id: self.events.last().map(|e| e.external_id.clone()),
+        }
     }
+}
 
-    /// Set sync state value.
-    pub fn set_sync_state(&self, key: &str, value: &str) -> Result<(), crate::Error> {
-        self.sync_state.insert(key.to_string(), value.to_string());
-        Ok(())
+/// Whether the XDR-JSON value `pattern` is contained in `value`: objects
+/// match if each of the pattern's fields is contained in the same field of
+/// the value, arrays if each pattern element is contained in some element of
+/// the value (so a map matches by a subset of its entries, in any order), and
+/// anything else only if equal.
+pub fn scval_contains(value: &serde_json::Value, pattern: &serde_json::Value) -> bool {
+    match (value, pattern) {
+        (serde_json::Value::Object(value), serde_json::Value::Object(pattern)) => {
+            pattern.iter().all(|(key, pattern)| {
+                value
+                    .get(key)
+                    .is_some_and(|value| scval_contains(value, pattern))
+            })
+        }
+        (serde_json::Value::Array(values), serde_json::Value::Array(patterns)) => patterns
+            .iter()
+            .all(|pattern| values.iter().any(|value| scval_contains(value, pattern))),
+        _ => value == pattern,
     }
+}
 
-    /// Query events within a single ledger partition.
-    ///
-    /// Always returns results in descending order (newest first).
-    pub fn query_single_ledger(
-        &self,
-        ledger_seq: u32,
-        params: &EventQueryParams,
-    ) -> Result<EventQueryResult, crate::Error> {
-        let partition = match self.ledgers.get(&ledger_seq) {
-            Some(p) => Arc::clone(p.value()),
-            None => {
-                return Ok(EventQueryResult {
-                    data: Vec::new(),
-                    next: None,
-                })
-            }
-        };
+/// Discriminants of XDR-JSON ScVals, as named by [`scval_type`].
+pub const SCVAL_TYPES: &[&str] = &[
+    "bool",
+    "void",
+    "error",
+    "u32",
+    "i32",
+    "u64",
+    "i64",
+    "timepoint",
+    "duration",
+    "u128",
+    "i128",
+    "u256",
+    "i256",
+    "bytes",
+    "string",
+    "symbol",
+    "vec",
+    "map",
+    "address",
+    "contract_instance",
+    "ledger_key_contract_instance",
+    "ledger_key_nonce",
+];
 
-        let events = &partition.events;
-        let limit = params.limit as usize;
+/// The discriminant of an XDR-JSON ScVal: the key of a single-key object
+/// (`{"address": ...}`), or the value itself for variants without a body
+/// (`"void"`).
+pub fn scval_type(value: &serde_json::Value) -> Option<&str> {
+    match value {
+        serde_json::Value::Object(map) if map.len() == 1 => map.keys().next().map(String::as_str),
+        serde_json::Value::String(s) => Some(s),
+        _ => None,
+    }
+}
 
-        if let Some(ref after) = params.after {
-            // `after` cursor: select events with id > after, iterate forward,
-            // then reverse for descending display. The `next` cursor advances
-            // forward so the client can pass it as `after` again.
-            let start = match events.binary_search_by(|e| e.id.as_str().cmp(after.as_str())) {
-                Ok(pos) => pos + 1,
-                Err(pos) => pos,
-            };
+/// Bloom filter key of a contract ID, distinct from any [`topic_hash`] of
+/// the same text.
+fn contract_key(contract_id: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
 
-            let mut results: Vec<EventRow> = Vec::with_capacity(limit.min(events.len()));
-            let mut last_examined_id: Option<&str> = None;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    "contract".hash(&mut hasher);
+    contract_id.hash(&mut hasher);
+    hasher.finish()
+}
 
-            for event in events.iter().skip(start) {
-                if results.len() >= limit {
-                    break;
+/// Hash of an XDR-JSON value that is equal for equal values, whatever the
+/// order of their object fields.
+fn topic_hash(value: &serde_json::Value) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    fn feed(value: &serde_json::Value, hasher: &mut std::collections::hash_map::DefaultHasher) {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut fields: Vec<_> = map.iter().collect();
+                fields.sort_unstable_by_key(|(key, _)| *key);
+                for (key, value) in fields {
+                    key.hash(hasher);
+                    feed(value, hasher);
+                }
+                b'}'.hash(hasher);
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    feed(item, hasher);
+                }
+                b']'.hash(hasher);
+            }
+            other => other.to_string().hash(hasher),
+        }
+    }
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    feed(value, &mut hasher);
+    hasher.finish()
+}
+
+/// Whether any token contains `term` as a substring.
+fn contains_term(tokens: &[String], term: &str) -> bool {
+    tokens.iter().any(|token| token.contains(term))
+}
+
+/// Sorted, deduplicated search tokens of an event's topics and data.
+fn event_search_tokens(
+    topics: &serde_json::Value,
+    data: Option<&serde_json::Value>,
+    data_xdr: Option<&[u8]>,
+) -> Vec<String> {
+    let mut tokens = Vec::new();
+    collect_search_tokens(topics, &mut tokens);
+    match (data, data_xdr) {
+        (Some(data), _) => collect_search_tokens(data, &mut tokens),
+        (None, Some(xdr)) => {
+            if let Ok(data) = ScVal::from_xdr(xdr, Limits::none()) {
+                collect_scval_search_tokens(&data, &mut tokens);
+            }
+        }
+        (None, None) => {}
+    }
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+/// Collect the lowercased `symbol` and `string` values in an XDR-JSON ScVal,
+/// recursing into vectors and maps.
+fn collect_search_tokens(value: &serde_json::Value, tokens: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, val) in map {
+                match (key.as_str(), val) {
+                    ("symbol" | "string", serde_json::Value::String(s)) => {
+                        tokens.push(s.to_lowercase());
+                    }
+                    _ => collect_search_tokens(val, tokens),
                 }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_search_tokens(item, tokens);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collect the lowercased symbol and string values in an `ScVal`, as
+/// [`collect_search_tokens`] does from its XDR-JSON, without converting it.
+fn collect_scval_search_tokens(value: &ScVal, tokens: &mut Vec<String>) {
+    let mut push = |text: serde_json::Result<serde_json::Value>| {
+        if let Ok(serde_json::Value::String(text)) = text {
+            tokens.push(text.to_lowercase());
+        }
+    };
+    match value {
+        ScVal::Symbol(symbol) => push(serde_json::to_value(symbol)),
+        ScVal::String(string) => push(serde_json::to_value(string)),
+        ScVal::Vec(Some(items)) => {
+            for item in items.iter() {
+                collect_scval_search_tokens(item, tokens);
+            }
+        }
+        ScVal::Map(Some(map)) => {
+            for entry in map.iter() {
+                collect_scval_search_tokens(&entry.key, tokens);
+                collect_scval_search_tokens(&entry.val, tokens);
+            }
+        }
+        ScVal::ContractInstance(instance) => {
+            for entry in instance.storage.iter().flat_map(|map| map.iter()) {
+                collect_scval_search_tokens(&entry.key, tokens);
+                collect_scval_search_tokens(&entry.val, tokens);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Convert event data from XDR to XDR-JSON.
+fn data_json(xdr: &[u8]) -> serde_json::Value {
+    metrics::counter!("store_data_conversions_total").increment(1);
+    ScVal::from_xdr(xdr, Limits::none())
+        .ok()
+        .and_then(|data| serde_json::to_value(data).ok())
+        .unwrap_or(serde_json::Value::Null)
+}
+
+/// Most event data values kept converted in [`DATA_JSON_CACHE`].
+const DATA_JSON_CACHE_ENTRIES: usize = 4096;
+
+/// XDR -> XDR-JSON of recently read event data, so that repeated reads of
+/// the same events, and events with identical data, are converted once.
+static DATA_JSON_CACHE: LazyLock<Mutex<LruCache<Vec<u8>, serde_json::Value>>> =
+    LazyLock::new(|| {
+        Mutex::new(LruCache::new(
+            NonZeroUsize::new(DATA_JSON_CACHE_ENTRIES).expect("cache size is non-zero"),
+        ))
+    });
+
+/// [`data_json`], through [`DATA_JSON_CACHE`]. The lock is not held while
+/// converting.
+fn cached_data_json(xdr: &[u8]) -> serde_json::Value {
+    if let Some(data) = DATA_JSON_CACHE
+        .lock()
+        .expect("data cache lock poisoned")
+        .get(xdr)
+    {
+        return data.clone();
+    }
+    let data = data_json(xdr);
+    DATA_JSON_CACHE
+        .lock()
+        .expect("data cache lock poisoned")
+        .put(xdr.to_vec(), data.clone());
+    data
+}
+
+impl EventStore {
+    /// Create a new in-memory event store.
+    pub fn new(cache_ttl_seconds: i64) -> Self {
+        Self {
+            ledgers: DashMap::new(),
+            spilled: DashMap::new(),
+            compressed: DashMap::new(),
+            segments: DashMap::new(),
+            segment_lock: Mutex::new(()),
+            latest_ledger: AtomicU32::new(0),
+            contracts: DashMap::new(),
+            transactions: DashMap::new(),
+            activity: Mutex::new(ActivityAggregates::default()),
+            close_times: Mutex::new(BTreeSet::new()),
+            sequences: Mutex::new(BTreeSet::new()),
+            backend_sequences: Mutex::new(BTreeSet::new()),
+            sync_state: DashMap::new(),
+            cache_ttl_seconds,
+            backfill_ttl_seconds: None,
+            pinned_ttl_seconds: None,
+            backend: None,
+            checkpoint: None,
+            spill: None,
+            compress_after_ledgers: None,
+            max_cached_ledgers: None,
+            retention: HashMap::new(),
+            retained_only: DashMap::new(),
+            compact_after_ledgers: None,
+            sliding_ttl: false,
+            epoch: rand::random(),
+            next_generation: AtomicU64::new(1),
+            generations: DashMap::new(),
+        }
+    }
+
+    /// Write partitions and sync state through to `backend`. Call
+    /// [`restore`](Self::restore) to load what it already holds.
+    pub fn with_backend(mut self, backend: Box<dyn StorageBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Write the sync state to the checkpoint file at `path` as
+    /// `last_synced_ledger` advances, first loading the values it already
+    /// holds over the store's. Fails if the file cannot be read or is for
+    /// another network.
+    pub fn with_checkpoint(
+        mut self,
+        path: PathBuf,
+        network_passphrase: &str,
+    ) -> Result<Self, crate::Error> {
+        let (checkpoint, sync_state) = Checkpoint::open(path, network_passphrase)?;
+        for (key, value) in sync_state {
+            self.set_sync_state(&key, &value)?;
+        }
+        self.checkpoint = Some(checkpoint);
+        Ok(self)
+    }
+
+    /// Spill partitions that have not been read for `idle_seconds` to files
+    /// in `dir` when [`spill_idle`](Self::spill_idle) runs. Files left in
+    /// `dir` by a previous run are removed.
+    pub fn with_spill_dir(mut self, dir: PathBuf, idle_seconds: i64) -> Result<Self, crate::Error> {
+        std::fs::create_dir_all(&dir)?;
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.to_string_lossy().ends_with(SPILL_EXTENSION) {
+                std::fs::remove_file(path)?;
+            }
+        }
+        self.spill = Some(SpillConfig { dir, idle_seconds });
+        Ok(self)
+    }
+
+    /// Compress the partitions of ledgers more than `after_ledgers` behind
+    /// the latest when [`compress_old`](Self::compress_old) runs, keeping
+    /// them as compressed blocks that are decompressed each time they are
+    /// read.
+    /// Compressed partitions are not spilled, and not compacted, so this is
+    /// an alternative to [`with_compaction`](Self::with_compaction) rather
+    /// than an addition to it.
+    pub fn with_compression(mut self, after_ledgers: u32) -> Self {
+        self.compress_after_ledgers = Some(after_ledgers);
+        self
+    }
+
+    /// Keep the events of each rule's contract for its retention period
+    /// after their ledger closes, even once their partition expires.
+    pub fn with_retention(mut self, rules: Vec<RetentionRule>) -> Self {
+        for rule in rules {
+            self.retention.insert(rule.contract_id, rule.seconds);
+        }
+        self
+    }
+
+    /// Merge the in-memory partitions of older ledgers into segments of
+    /// [`SEGMENT_LEDGERS`] consecutive ledgers when [`compact`](Self::compact)
+    /// runs, once a segment's range ends more than `after_ledgers` behind the
+    /// latest ledger.
+    pub fn with_compaction(mut self, after_ledgers: u32) -> Self {
+        self.compact_after_ledgers = Some(after_ledgers);
+        self
+    }
+
+    /// Keep ledgers cached for `source` for `ttl_seconds` instead of the
+    /// cache TTL. For [`CacheSource::Sync`], this replaces the cache TTL
+    /// itself.
+    pub fn with_source_ttl(mut self, source: CacheSource, ttl_seconds: i64) -> Self {
+        match source {
+            CacheSource::Sync => self.cache_ttl_seconds = ttl_seconds,
+            CacheSource::Backfill => self.backfill_ttl_seconds = Some(ttl_seconds),
+            CacheSource::Pinned => self.pinned_ttl_seconds = Some(ttl_seconds),
+        }
+        self
+    }
+
+    /// Refresh a partition's expiry to the cache TTL from now each time it is
+    /// read, so ledgers that keep being queried stay cached while untouched
+    /// ones expire as usual. A partition that has already expired is not
+    /// revived, and refreshed expiries are not written to the backend.
+    pub fn with_sliding_ttl(mut self) -> Self {
+        self.sliding_ttl = true;
+        self
+    }
+
+    /// Cache at most `max` partitions, dropping those of the oldest ledgers
+    /// when an insert exceeds it, whatever their expiry. With a storage
+    /// backend, this bounds the partitions held by this process instead:
+    /// those of the oldest ledgers are left in the backend until they expire,
+    /// and read back from it when a query needs their events.
+    pub fn with_max_cached_ledgers(mut self, max: usize) -> Self {
+        self.max_cached_ledgers = Some(max);
+        self
+    }
+
+    /// Load the unexpired partitions and the sync state saved in the storage
+    /// backend, deleting expired partitions from it. With a cap on cached
+    /// ledgers, only the newest partitions are kept in memory and the rest
+    /// are left in the backend. Returns the number of partitions restored;
+    /// without a backend, nothing is restored.
+    pub fn restore(&self) -> Result<usize, crate::Error> {
+        let Some(backend) = &self.backend else {
+            return Ok(0);
+        };
+        for (key, value) in backend.load_sync_state()? {
+            self.sync_state.insert(key, value);
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let mut restored = 0;
+        let mut expired = Vec::new();
+        backend.load_partitions(&mut |export, expires_at| {
+            if expires_at <= now {
+                expired.push(export.ledger);
+                return Ok(());
+            }
+            let ledger_seq = export.ledger;
+            let (info, stored) = decode_partition(export)?;
+            self.add_partition(ledger_seq, info, stored, expires_at);
+            self.evict_excess();
+            restored += 1;
+            Ok(())
+        })?;
+        backend.delete_partitions(&expired)?;
+        Ok(restored)
+    }
+
+    /// Insert extracted events into the store, grouped by ledger.
+    #[tracing::instrument(skip_all, fields(event_count = events.len()))]
+    pub fn insert_events(&self, events: Vec<ExtractedEvent>) -> Result<(), crate::Error> {
+        // Group events by ledger sequence.
+        let mut by_ledger: HashMap<u32, Vec<ExtractedEvent>> = HashMap::new();
+        for event in events {
+            by_ledger
+                .entry(event.ledger_sequence)
+                .or_default()
+                .push(event);
+        }
+
+        for (ledger_seq, ledger_events) in by_ledger {
+            // Skip if already cached (idempotent).
+            if self.partition_expiry(ledger_seq).is_some() {
+                continue;
+            }
+
+            let stored = ledger_events.into_iter().map(StoredEvent::from).collect();
+            self.insert_partition(ledger_seq, None, stored, self.new_expiry());
+        }
+
+        Ok(())
+    }
+
+    /// Insert a fetched ledger: its header details, if found, and all of its
+    /// events, kept for the TTL of the `source` it was fetched for.
+    /// Ledgers without events are recorded too. No-op if the ledger is
+    /// already cached; an expired partition is replaced.
+    pub fn insert_ledger(
+        &self,
+        ledger_seq: u32,
+        info: Option<LedgerInfo>,
+        events: Vec<ExtractedEvent>,
+        source: CacheSource,
+    ) -> Result<(), crate::Error> {
+        if self.is_ledger_cached(ledger_seq)? {
+            return Ok(());
+        }
+        let stored = events.into_iter().map(StoredEvent::from).collect();
+        let expires_at = chrono::Utc::now().timestamp() + self.ttl_seconds(source);
+        self.insert_partition(ledger_seq, info, stored, expires_at);
+        Ok(())
+    }
+
+    /// Replace the partition for a ledger, whether or not it is cached, with
+    /// one built from freshly extracted events, kept for the TTL of the
+    /// `source` it was fetched for.
+    pub fn replace_ledger(
+        &self,
+        ledger_seq: u32,
+        info: Option<LedgerInfo>,
+        events: Vec<ExtractedEvent>,
+        source: CacheSource,
+    ) {
+        let stored = events.into_iter().map(StoredEvent::from).collect();
+        let expires_at = chrono::Utc::now().timestamp() + self.ttl_seconds(source);
+        self.insert_partition(ledger_seq, info, stored, expires_at);
+    }
+
+    /// How long ledgers cached for `source` are kept, in seconds.
+    pub fn ttl_seconds(&self, source: CacheSource) -> i64 {
+        match source {
+            CacheSource::Sync => None,
+            CacheSource::Backfill => self.backfill_ttl_seconds,
+            CacheSource::Pinned => self.pinned_ttl_seconds,
+        }
+        .unwrap_or(self.cache_ttl_seconds)
+    }
+
+    /// Expiry time of a partition cached now with the cache TTL.
+    fn new_expiry(&self) -> i64 {
+        chrono::Utc::now().timestamp() + self.cache_ttl_seconds
+    }
+
+    /// Build a partition from a ledger's events, add it to the store, and
+    /// save it to the storage backend.
+    fn insert_partition(
+        &self,
+        ledger_seq: u32,
+        info: Option<LedgerInfo>,
+        stored: Vec<StoredEvent>,
+        expires_at: i64,
+    ) {
+        let partition = self.add_partition(ledger_seq, info, stored, expires_at);
+        if let Some(backend) = &self.backend {
+            if let Err(e) = backend.save_partition(&partition.export(ledger_seq), expires_at) {
+                metrics::counter!("store_backend_errors_total").increment(1);
+                tracing::warn!(ledger = ledger_seq, error = %e, "failed to save partition");
+            }
+        }
+        self.evict_excess();
+    }
+
+    /// Build a partition expiring at `expires_at` and add it to the store.
+    fn add_partition(
+        &self,
+        ledger_seq: u32,
+        info: Option<LedgerInfo>,
+        stored: Vec<StoredEvent>,
+        expires_at: i64,
+    ) -> Arc<LedgerPartition> {
+        let partition = Arc::new(LedgerPartition::new(info, stored, expires_at));
+        self.retained_only.remove(&ledger_seq);
+
+        let event_count = partition.events.len();
+        // Unindex a replaced partition before indexing its replacement, as
+        // both are keyed by the same ledger.
+        let replaced = match self.ledgers.insert(ledger_seq, Arc::clone(&partition)) {
+            Some(replaced) => Some(replaced),
+            None => self.take_held_elsewhere(ledger_seq),
+        };
+        if let Some(replaced) = replaced {
+            self.unindex_partition(ledger_seq, &replaced);
+        }
+        self.index_partition(ledger_seq, &partition);
+
+        metrics::gauge!("store_partitions_total").set(self.cached_ledger_count() as f64);
+        metrics::counter!("store_events_ingested_total").increment(event_count as u64);
+
+        tracing::debug!(
+            ledger = ledger_seq,
+            events = event_count,
+            "inserted ledger partition"
+        );
+
+        // Update latest ledger tracker.
+        self.latest_ledger.fetch_max(ledger_seq, Ordering::Relaxed);
+        partition
+    }
+
+    /// Drop the partitions of the oldest ledgers while more than the
+    /// configured maximum are cached, or with a storage backend, leave them
+    /// in the backend while more than the maximum are held by this process.
+    /// Returns the number evicted.
+    fn evict_excess(&self) -> usize {
+        let Some(max) = self.max_cached_ledgers else {
+            return 0;
+        };
+        let sequences = self.sequences.lock().expect("sequence lock poisoned");
+        let excess = sequences.len().saturating_sub(max);
+        if excess == 0 {
+            return 0;
+        }
+        let ledgers: Vec<u32> = sequences.iter().take(excess).copied().collect();
+        drop(sequences);
+        let evicted = if self.backend.is_some() {
+            self.leave_in_backend(&ledgers)
+        } else {
+            self.remove_partitions(&ledgers)
+        };
+        metrics::counter!("store_partitions_evicted_total").increment(evicted as u64);
+        tracing::debug!(evicted, max, "evicted oldest partitions");
+        evicted
+    }
+
+    /// Drop the events of the partitions for `ledgers` from this process,
+    /// keeping them indexed, so that they are read back from the storage
+    /// backend when a query needs them. Returns the number left there.
+    fn leave_in_backend(&self, ledgers: &[u32]) -> usize {
+        let now = chrono::Utc::now().timestamp();
+        let mut held = self.take_from_segments(ledgers);
+        let mut left = Vec::with_capacity(ledgers.len());
+        for &seq in ledgers {
+            if let Some((_, partition)) = self.ledgers.remove(&seq) {
+                held.push((seq, partition));
+            } else if let Some((_, compressed)) = self.compressed.remove(&seq) {
+                metrics::gauge!("store_compressed_bytes").decrement(compressed.data.len() as f64);
+                self.spilled.insert(
+                    seq,
+                    SpilledPartition {
+                        summary: compressed.summary,
+                        keys: compressed.keys,
+                        target: SpillTarget::Backend,
+                    },
+                );
+                left.push(seq);
+            } else if let Some(mut spilled) = self.spilled.get_mut(&seq) {
+                if spilled.target == SpillTarget::File {
+                    spilled.target = SpillTarget::Backend;
+                    drop(spilled);
+                    self.remove_spill_file(seq);
+                }
+                left.push(seq);
+            }
+        }
+        for (seq, partition) in held {
+            self.spilled.insert(
+                seq,
+                SpilledPartition {
+                    summary: partition.summary(seq, now),
+                    keys: partition.partition_keys(),
+                    target: SpillTarget::Backend,
+                },
+            );
+            left.push(seq);
+        }
+
+        let mut sequences = self.sequences.lock().expect("sequence lock poisoned");
+        let mut backend_sequences = self
+            .backend_sequences
+            .lock()
+            .expect("sequence lock poisoned");
+        for seq in &left {
+            sequences.remove(seq);
+            backend_sequences.insert(*seq);
+        }
+        drop((sequences, backend_sequences));
+        metrics::gauge!("store_partitions_compressed").set(self.compressed.len() as f64);
+        metrics::gauge!("store_partitions_spilled").set(self.spilled.len() as f64);
+        left.len()
+    }
+
+    /// Delete dropped partitions from the storage backend.
+    fn delete_saved_partitions(&self, ledgers: &[u32]) {
+        if ledgers.is_empty() {
+            return;
+        }
+        if let Some(backend) = &self.backend {
+            if let Err(e) = backend.delete_partitions(ledgers) {
+                metrics::counter!("store_backend_errors_total").increment(1);
+                tracing::warn!(error = %e, "failed to delete saved partitions");
+            }
+        }
+    }
+
+    /// Add a partition's events to the per-contract summaries, the
+    /// transaction index, and the activity aggregates.
+    fn index_partition(&self, ledger_seq: u32, partition: &LedgerPartition) {
+        self.generations.insert(
+            ledger_seq,
+            self.next_generation.fetch_add(1, Ordering::Relaxed),
+        );
+        self.sequences
+            .lock()
+            .expect("sequence lock poisoned")
+            .insert(ledger_seq);
+        self.activity
+            .lock()
+            .expect("activity lock poisoned")
+            .apply(partition, true);
+        if let Some(closed_at) = partition.closed_at() {
+            self.close_times
+                .lock()
+                .expect("close time lock poisoned")
+                .insert((closed_at, ledger_seq));
+        }
+        let mut counts: HashMap<&str, (u64, &str)> = HashMap::new();
+        for event in &partition.events {
+            if let Some(ref cid) = event.contract_id {
+                counts.entry(cid).or_insert((0, &event.ledger_closed_at)).0 += 1;
+            }
+            self.transactions.insert(event.tx_hash.clone(), ledger_seq);
+        }
+        let topics = contract_topic_counts(partition);
+        let assets = contract_asset_counts(partition);
+        let types = contract_type_counts(partition);
+        for (cid, (count, closed_at)) in counts {
+            let mut activity = self.contracts.entry(cid.to_string()).or_default();
+            activity.event_count += count;
+            if let Some(types) = types.get(cid) {
+                activity.event_types.apply(types, true);
+            }
+            activity
+                .ledgers
+                .insert(ledger_seq, (count, closed_at.to_string()));
+            for (symbol, count) in topics.get(cid).into_iter().flatten() {
+                *activity.topic0.entry(symbol.to_string()).or_default() += count;
+            }
+            for (asset, count) in assets.get(cid).into_iter().flatten() {
+                *activity.assets.entry(asset.clone()).or_default() += count;
+            }
+        }
+    }
+
+    /// Remove a dropped partition's events from the per-contract summaries,
+    /// the transaction index, and the activity aggregates.
+    fn unindex_partition(&self, ledger_seq: u32, partition: &LedgerPartition) {
+        self.generations.remove(&ledger_seq);
+        self.sequences
+            .lock()
+            .expect("sequence lock poisoned")
+            .remove(&ledger_seq);
+        self.backend_sequences
+            .lock()
+            .expect("sequence lock poisoned")
+            .remove(&ledger_seq);
+        self.activity
+            .lock()
+            .expect("activity lock poisoned")
+            .apply(partition, false);
+        if let Some(closed_at) = partition.closed_at() {
+            self.close_times
+                .lock()
+                .expect("close time lock poisoned")
+                .remove(&(closed_at, ledger_seq));
+        }
+        let mut contracts: Vec<&str> = partition
+            .events
+            .iter()
+            .filter_map(|e| e.contract_id.as_deref())
+            .collect();
+        contracts.sort_unstable();
+        contracts.dedup();
+        let topics = contract_topic_counts(partition);
+        let assets = contract_asset_counts(partition);
+        let types = contract_type_counts(partition);
+        for cid in contracts {
+            self.contracts.remove_if_mut(cid, |_, activity| {
+                if let Some((count, _)) = activity.ledgers.remove(&ledger_seq) {
+                    activity.event_count -= count;
+                    if let Some(types) = types.get(cid) {
+                        activity.event_types.apply(types, false);
+                    }
+                }
+                for (symbol, count) in topics.get(cid).into_iter().flatten() {
+                    if let Some(total) = activity.topic0.get_mut(*symbol) {
+                        *total = total.saturating_sub(*count);
+                        if *total == 0 {
+                            activity.topic0.remove(*symbol);
+                        }
+                    }
+                }
+                for (asset, count) in assets.get(cid).into_iter().flatten() {
+                    if let Some(total) = activity.assets.get_mut(asset) {
+                        *total = total.saturating_sub(*count);
+                        if *total == 0 {
+                            activity.assets.remove(asset);
+                        }
+                    }
+                }
+                activity.ledgers.is_empty()
+            });
+        }
+        for event in &partition.events {
+            self.transactions
+                .remove_if(&event.tx_hash, |_, ledger| *ledger == ledger_seq);
+        }
+    }
+
+    /// Every event in a cached ledger, in ID order.
+    pub fn ledger_events(&self, ledger_seq: u32) -> Vec<EventRow> {
+        let Some(partition) = self.partition(ledger_seq) else {
+            return Vec::new();
+        };
+        partition
+            .events
+            .iter()
+            .map(StoredEvent::to_event_row)
+            .collect()
+    }
+
+    /// The cached ledger containing a transaction's events, from the
+    /// transaction index.
+    pub fn transaction_ledger(&self, tx_hash: &str) -> Option<u32> {
+        self.transactions.get(tx_hash).map(|seq| *seq)
+    }
+
+    /// Find the ledger containing a transaction's events and return them, in
+    /// ID order. Returns `None` if no cached ledger has events from the
+    /// transaction.
+    pub fn transaction_events(&self, tx_hash: &str) -> Option<(u32, Vec<EventRow>)> {
+        let ledger_seq = *self.transactions.get(tx_hash)?;
+        let partition = self.partition(ledger_seq)?;
+        let events: Vec<EventRow> = partition
+            .events
+            .iter()
+            .filter(|e| e.tx_hash == tx_hash)
+            .map(StoredEvent::to_event_row)
+            .collect();
+        if events.is_empty() {
+            return None;
+        }
+        Some((ledger_seq, events))
+    }
+
+    /// Snapshot of the activity aggregates, with the `top` most frequent
+    /// first-topic symbols.
+    pub fn activity_stats(&self, top: usize) -> ActivityStats {
+        let mut top_topics = self.topic_counts(None);
+        top_topics.truncate(top);
+
+        let activity = self.activity.lock().expect("activity lock poisoned");
+        ActivityStats {
+            per_minute: activity
+                .per_minute
+                .iter()
+                .map(|(&minute, &(events, ledgers))| (minute, events, ledgers))
+                .collect(),
+            ledger_buckets: activity.ledger_buckets.to_vec(),
+            event_types: activity.event_types,
+            top_topics,
+        }
+    }
+
+    /// Distinct first-topic symbols in the cached ledgers with their event
+    /// counts, most frequent first. With `contract_id`, only that contract's
+    /// events are counted.
+    pub fn topic_counts(&self, contract_id: Option<&str>) -> Vec<(String, u64)> {
+        self.topics_with_prefix(contract_id, "")
+    }
+
+    /// Like [`Self::topic_counts`], limited to symbols starting with `prefix`.
+    /// Only the matching range of the sorted symbol index is visited.
+    pub fn topics_with_prefix(
+        &self,
+        contract_id: Option<&str>,
+        prefix: &str,
+    ) -> Vec<(String, u64)> {
+        let collect = |topic0: &BTreeMap<String, u64>| -> Vec<(String, u64)> {
+            topic0
+                .range::<str, _>((
+                    std::ops::Bound::Included(prefix),
+                    std::ops::Bound::Unbounded,
+                ))
+                .take_while(|(symbol, _)| symbol.starts_with(prefix))
+                .map(|(symbol, count)| (symbol.clone(), *count))
+                .collect()
+        };
+        let mut topics = match contract_id {
+            None => collect(&self.activity.lock().expect("activity lock poisoned").topic0),
+            Some(cid) => self
+                .contracts
+                .get(cid)
+                .map(|activity| collect(&activity.topic0))
+                .unwrap_or_default(),
+        };
+        topics.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        topics
+    }
+
+    /// Every (asset, contract ID) pair named by asset contract events in the
+    /// cached ledgers, with the pair's event count, most active first. The
+    /// contract is not checked to be the asset's contract.
+    pub fn asset_contracts(&self) -> Vec<(String, String, u64)> {
+        let mut pairs: Vec<(String, String, u64)> = self
+            .contracts
+            .iter()
+            .flat_map(|kv| {
+                let cid = kv.key().clone();
+                kv.value()
+                    .assets
+                    .iter()
+                    .map(|(asset, count)| (asset.clone(), cid.clone(), *count))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        pairs.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        pairs
+    }
+
+    /// Summarise the activity of every contract with events in the cached
+    /// ledgers, most active first.
+    pub fn contract_summaries(&self) -> Vec<ContractSummary> {
+        let mut summaries: Vec<ContractSummary> = self
+            .contracts
+            .iter()
+            .filter_map(|kv| {
+                let activity = kv.value();
+                let (&first_seen_ledger, _) = activity.ledgers.first_key_value()?;
+                let (&last_seen_ledger, (_, last_activity_at)) =
+                    activity.ledgers.last_key_value()?;
+                Some(ContractSummary {
+                    contract_id: kv.key().clone(),
+                    event_count: activity.event_count,
+                    event_types: activity.event_types,
+                    first_seen_ledger,
+                    last_seen_ledger,
+                    last_activity_at: last_activity_at.clone(),
+                })
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.event_count
+                .cmp(&a.event_count)
+                .then_with(|| a.contract_id.cmp(&b.contract_id))
+        });
+        summaries
+    }
+
+    /// Record that a ledger has been cached. A ledger inserted without
+    /// events has no partition yet, so an empty one is recorded, kept for
+    /// `ttl_seconds` and saved to the storage backend; a ledger with a
+    /// partition keeps its expiry.
+    pub fn record_ledger_cached(
+        &self,
+        ledger_sequence: u32,
+        ttl_seconds: i64,
+    ) -> Result<(), crate::Error> {
+        if self.partition_expiry(ledger_sequence).is_none() {
+            let expires_at = chrono::Utc::now().timestamp() + ttl_seconds;
+            self.insert_partition(ledger_sequence, None, Vec::new(), expires_at);
+        }
+        Ok(())
+    }
+
+    /// Check if a ledger is cached and not expired.
+    pub fn is_ledger_cached(&self, ledger_sequence: u32) -> Result<bool, crate::Error> {
+        let now = chrono::Utc::now().timestamp();
+        Ok(self.is_complete(ledger_sequence, now))
+    }
+
+    /// Whether a ledger's whole partition is cached and not expired.
+    fn is_complete(&self, ledger_seq: u32, now: i64) -> bool {
+        self.partition_expiry(ledger_seq)
+            .is_some_and(|expires_at| expires_at > now)
+            && !self.retained_only.contains_key(&ledger_seq)
+    }
+
+    /// Find ledger sequences in the given range that are NOT cached.
+    pub fn find_uncached_ledgers(&self, start: u32, count: u32) -> Result<Vec<u32>, crate::Error> {
+        let now = chrono::Utc::now().timestamp();
+        let end = start + count;
+        Ok((start..end)
+            .filter(|&seq| !self.is_complete(seq, now))
+            .collect())
+    }
+
+    /// Get sync state value.
+    pub fn get_sync_state(&self, key: &str) -> Result<Option<String>, crate::Error> {
+        Ok(self.sync_state.get(key).map(|v| v.value().clone()))
+    }
+
+    /// Every sync state value.
+    pub fn sync_state_entries(&self) -> BTreeMap<String, String> {
+        self.sync_state
+            .iter()
+            .map(|kv| (kv.key().clone(), kv.value().clone()))
+            .collect()
+    }
+
+    /// Set sync state value.
+    pub fn set_sync_state(&self, key: &str, value: &str) -> Result<(), crate::Error> {
+        self.sync_state.insert(key.to_string(), value.to_string());
+        if let Some(backend) = &self.backend {
+            backend.save_sync_state(key, value)?;
+        }
+        // Other values are written along with the next save, which keeps
+        // checkpoint writes to at most one per save interval.
+        if let Some(checkpoint) = &self.checkpoint {
+            if key == "last_synced_ledger" {
+                checkpoint.save_if_due(self)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the sync state to the checkpoint file now, if there is one,
+    /// including any values held back since the last save.
+    pub fn save_checkpoint(&self) -> Result<(), crate::Error> {
+        match &self.checkpoint {
+            Some(checkpoint) => checkpoint.save(self),
+            None => Ok(()),
+        }
+    }
+
+    /// Query events within a single ledger partition.
+    ///
+    /// Returns results in `params.order`, newest first by default.
+    pub fn query_single_ledger(
+        &self,
+        ledger_seq: u32,
+        params: &EventQueryParams,
+    ) -> Result<EventQueryResult, crate::Error> {
+        let Some(partition) = self.partition(ledger_seq) else {
+            return Ok(EventQueryResult::default());
+        };
+
+        let events = &partition.events;
+        let limit = params.limit as usize;
+        let mut stats = QueryStats {
+            partitions_scanned: 1,
+            ..Default::default()
+        };
+
+        if params.after.is_some() || params.order == SortOrder::Asc {
+            // `after` cursor or ascending order: select events with id > after,
+            // iterate forward, reversing afterwards for descending order. The `next`
+            // cursor advances forward so the client can pass it as `after` again.
+            let start = match params.after {
+                Some(ref after) => {
+                    match events.binary_search_by(|e| e.id.as_str().cmp(after.as_str())) {
+                        Ok(pos) => pos + 1,
+                        Err(pos) => pos,
+                    }
+                }
+                None => 0,
+            };
+
+            let mut results: Vec<EventRow> = Vec::with_capacity(limit.min(events.len()));
+            let mut last_examined_id: Option<&str> = None;
+
+            let mut exhausted = true;
+            for pos in partition.positions(params, start..events.len()) {
+                if results.len() >= limit {
+                    exhausted = false;
+                    break;
+                }
+                let event = &events[pos];
                 last_examined_id = Some(&event.external_id);
+                stats.events_examined += 1;
                 if !self.event_matches(event, params) {
                     continue;
                 }
+                stats.events_matched += 1;
                 results.push(event.to_event_row());
             }
+            // Every candidate was examined, so nothing else in range can match.
+            if exhausted {
+                last_examined_id = events[start..].last().map(|e| e.external_id.as_str());
+            }
 
-            results.reverse();
+            if params.order == SortOrder::Desc {
+                results.reverse();
+            }
             Ok(EventQueryResult {
                 data: results,
                 next: last_examined_id.map(|id| id.to_owned()),
+                stats,
             })
         } else {
             // No cursor or `before` cursor: iterate backward (already desc).
@@ -344,24 +1975,103 @@ impl EventStore {
             let mut results: Vec<EventRow> = Vec::with_capacity(limit.min(events.len()));
             let mut last_examined_id: Option<&str> = None;
 
-            for event in events[..end].iter().rev() {
+            let mut exhausted = true;
+            for pos in partition.positions(params, 0..end).rev() {
                 if results.len() >= limit {
+                    exhausted = false;
                     break;
                 }
+                let event = &events[pos];
                 last_examined_id = Some(&event.external_id);
+                stats.events_examined += 1;
                 if !self.event_matches(event, params) {
                     continue;
                 }
+                stats.events_matched += 1;
                 results.push(event.to_event_row());
             }
+            // Every candidate was examined, so nothing else in range can match.
+            if exhausted {
+                last_examined_id = events[..end].first().map(|e| e.external_id.as_str());
+            }
 
             Ok(EventQueryResult {
                 data: results,
                 next: last_examined_id.map(|id| id.to_owned()),
+                stats,
             })
         }
     }
 
+    /// A token naming the cached copy of a ledger's partition, to embed in
+    /// pagination cursors so that [`cursor_token_is_current`] can tell when
+    /// the partition has since been dropped or replaced.
+    ///
+    /// [`cursor_token_is_current`]: Self::cursor_token_is_current
+    pub fn cursor_token(&self, ledger_seq: u32) -> Option<String> {
+        self.generations
+            .get(&ledger_seq)
+            .map(|generation| format!("{:08x}{:x}", self.epoch, *generation))
+    }
+
+    /// Whether a token from [`cursor_token`](Self::cursor_token) still names
+    /// the cached copy of the ledger's partition. Tokens issued by another
+    /// store instance cannot be checked, and are taken to be current. `None`
+    /// if the token is malformed.
+    pub fn cursor_token_is_current(&self, ledger_seq: u32, token: &str) -> Option<bool> {
+        let epoch = u32::from_str_radix(token.get(..8)?, 16).ok()?;
+        let generation = u64::from_str_radix(token.get(8..)?, 16).ok()?;
+        Some(
+            epoch != self.epoch
+                || self
+                    .generations
+                    .get(&ledger_seq)
+                    .is_some_and(|current| *current == generation),
+        )
+    }
+
+    /// Count the events in a single ledger partition that match the filters,
+    /// without materialising rows. Cursors and limit are ignored.
+    pub fn count_single_ledger(
+        &self,
+        ledger_seq: u32,
+        params: &EventQueryParams,
+    ) -> Result<usize, crate::Error> {
+        let Some(partition) = self.partition(ledger_seq) else {
+            return Ok(0);
+        };
+        Ok(partition
+            .positions(params, 0..partition.events.len())
+            .filter(|&pos| self.event_matches(&partition.events[pos], params))
+            .count())
+    }
+
+    /// Count the events matching `params.filters` in each cached, unexpired
+    /// ledger, without materialising rows. Ledgers with no matches are
+    /// omitted; the rest are returned in ledger order.
+    pub fn count_by_ledger(&self, params: &EventQueryParams) -> Vec<(u32, usize)> {
+        let now = chrono::Utc::now().timestamp();
+        let mut counts: Vec<(u32, usize)> = self
+            .ledger_sequences()
+            .into_iter()
+            .filter(|&seq| self.partition_expiry(seq).is_some_and(|t| t > now))
+            .filter(|&seq| self.segment_may_match(seq, params))
+            .filter(|&seq| self.held_keys(seq, |k| k.may_match(params)) != Some(false))
+            .filter_map(|seq| Some((seq, self.partition(seq)?)))
+            .filter(|(_, partition)| partition.may_match(params))
+            .map(|(seq, partition)| {
+                let count = partition
+                    .positions(params, 0..partition.events.len())
+                    .filter(|&pos| self.event_matches(&partition.events[pos], params))
+                    .count();
+                (seq, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        counts.sort_unstable();
+        counts
+    }
+
     /// Check whether a single event passes the filter constraints.
     fn event_matches(&self, event: &StoredEvent, params: &EventQueryParams) -> bool {
         if !params.filters.is_empty() && !params.filters.iter().any(|f| event.matches_filter(f)) {
@@ -383,11 +2093,20 @@ impl EventStore {
         params: &EventQueryParams,
         results: &mut Vec<EventRow>,
         remaining: usize,
+        stats: &mut QueryStats,
     ) -> Option<String> {
-        let partition = match self.ledgers.get(&seq) {
-            Some(p) => Arc::clone(p.value()),
-            None => return None,
-        };
+        // A compressed or spilled partition that cannot match is skipped
+        // without reading its events.
+        if cursor.is_none() {
+            if let Some(Some(first_id)) =
+                self.held_keys(seq, |k| (!k.may_match(params)).then(|| k.first_id.clone()))
+            {
+                stats.partitions_scanned += 1;
+                return first_id;
+            }
+        }
+        let partition = self.partition(seq)?;
+        stats.partitions_scanned += 1;
         let events = &partition.events;
         let end = match cursor {
             Some(c) => match events.binary_search_by(|e| e.id.as_str().cmp(c)) {
@@ -397,20 +2116,29 @@ impl EventStore {
             None => events.len(),
         };
 
+        // Nothing here can match: skip to the oldest event in range.
+        if !partition.may_match(params) {
+            return events[..end].first().map(|e| e.external_id.clone());
+        }
+
         let mut added = 0;
         let mut last_examined_id: Option<String> = None;
-        for event in events[..end].iter().rev() {
+        for pos in partition.positions(params, 0..end).rev() {
             if added >= remaining {
-                break;
+                return last_examined_id;
             }
+            let event = &events[pos];
             last_examined_id = Some(event.external_id.clone());
+            stats.events_examined += 1;
             if self.event_matches(event, params) {
+                stats.events_matched += 1;
                 results.push(event.to_event_row());
                 added += 1;
             }
         }
 
-        last_examined_id
+        // Every candidate was examined, so nothing else in range can match.
+        events[..end].first().map(|e| e.external_id.clone())
     }
 
     /// Scan a single ledger partition forward (oldest to newest).
@@ -425,11 +2153,20 @@ impl EventStore {
         params: &EventQueryParams,
         results: &mut Vec<EventRow>,
         remaining: usize,
+        stats: &mut QueryStats,
     ) -> Option<String> {
-        let partition = match self.ledgers.get(&seq) {
-            Some(p) => Arc::clone(p.value()),
-            None => return None,
-        };
+        // A compressed or spilled partition that cannot match is skipped
+        // without reading its events.
+        if cursor.is_none() {
+            if let Some(Some(last_id)) =
+                self.held_keys(seq, |k| (!k.may_match(params)).then(|| k.last_id.clone()))
+            {
+                stats.partitions_scanned += 1;
+                return last_id;
+            }
+        }
+        let partition = self.partition(seq)?;
+        stats.partitions_scanned += 1;
         let events = &partition.events;
         let start = match cursor {
             Some(c) => match events.binary_search_by(|e| e.id.as_str().cmp(c)) {
@@ -439,66 +2176,848 @@ impl EventStore {
             None => 0,
         };
 
+        // Nothing here can match: skip to the newest event in range.
+        if !partition.may_match(params) {
+            return events.get(start..)?.last().map(|e| e.external_id.clone());
+        }
+
         let mut added = 0;
         let mut last_examined_id: Option<String> = None;
-        for event in events.iter().skip(start) {
+        for pos in partition.positions(params, start..events.len()) {
             if added >= remaining {
-                break;
+                return last_examined_id;
             }
+            let event = &events[pos];
             last_examined_id = Some(event.external_id.clone());
+            stats.events_examined += 1;
             if self.event_matches(event, params) {
+                stats.events_matched += 1;
                 results.push(event.to_event_row());
                 added += 1;
             }
         }
 
-        last_examined_id
+        // Every candidate was examined, so nothing else in range can match.
+        events.get(start..)?.last().map(|e| e.external_id.clone())
+    }
+
+    /// Get the highest ledger sequence in the store.
+    pub fn latest_ledger_sequence(&self) -> Result<Option<u32>, crate::Error> {
+        let v = self.latest_ledger.load(Ordering::Relaxed);
+        Ok(if v == 0 { None } else { Some(v) })
+    }
+
+    /// Ledger bounds implied by the `since`/`until` windows of `filters`,
+    /// from the close times of the cached ledgers. A bound is only given when
+    /// every filter has that side of the window and the cached ledgers span
+    /// it, so a scan can stop at the bound without missing uncached matches.
+    pub fn ledger_window(&self, filters: &[EventFilter]) -> (Option<u32>, Option<u32>) {
+        if filters.is_empty() {
+            return (None, None);
+        }
+        let close_times = self.close_times.lock().expect("close time lock poisoned");
+        let lower = filters
+            .iter()
+            .map(|f| {
+                let since = f.since?;
+                // A ledger closed before `since` is cached, so the first one
+                // closed at or after it is the first that can match.
+                close_times.range(..(since, 0)).next()?;
+                match close_times.range((since, 0)..).next() {
+                    Some(&(_, ledger)) => Some(ledger),
+                    None => close_times.last().map(|&(_, ledger)| ledger + 1),
+                }
+            })
+            .collect::<Option<Vec<u32>>>()
+            .and_then(|bounds| bounds.into_iter().min());
+        let upper = filters
+            .iter()
+            .map(|f| {
+                let after = f.until?.checked_add(1)?;
+                // A ledger closed after `until` is cached, so the last one
+                // closed at or before it is the last that can match.
+                close_times.range((after, 0)..).next()?;
+                match close_times.range(..(after, 0)).next_back() {
+                    Some(&(_, ledger)) => Some(ledger),
+                    None => close_times
+                        .first()
+                        .map(|&(_, ledger)| ledger.saturating_sub(1)),
+                }
+            })
+            .collect::<Option<Vec<u32>>>()
+            .and_then(|bounds| bounds.into_iter().max());
+        (lower, upper)
+    }
+
+    /// Get the configured cache TTL in seconds.
+    pub fn cache_ttl_seconds(&self) -> i64 {
+        self.cache_ttl_seconds
+    }
+
+    /// Get the number of ledgers currently cached.
+    pub fn cached_ledger_count(&self) -> usize {
+        self.ledgers.len()
+            + self.spilled.len()
+            + self.compressed.len()
+            + self
+                .segments
+                .iter()
+                .map(|kv| kv.value().partitions.len())
+                .sum::<usize>()
+    }
+
+    /// Summaries of the cached partitions below `before` (all of them if
+    /// unset), newest first, at most `limit`.
+    pub fn ledger_summaries(&self, before: Option<u32>, limit: usize) -> Vec<LedgerSummary> {
+        let before = before.unwrap_or(u32::MAX);
+        let mut ledgers: Vec<u32> = self
+            .ledger_sequences()
+            .into_iter()
+            .filter(|seq| *seq < before)
+            .collect();
+        ledgers.sort_unstable_by_key(|seq| std::cmp::Reverse(*seq));
+        ledgers.truncate(limit);
+
+        ledgers
+            .into_iter()
+            .filter_map(|ledger| self.ledger_summary(ledger))
+            .collect()
+    }
+
+    /// Sizes of the cached partitions, in ledger order, and of the indexes
+    /// over them. Sizes are estimates of the memory held, not exact.
+    pub fn stats(&self) -> StoreStats {
+        let mut partitions: Vec<PartitionStats> = self
+            .ledgers
+            .iter()
+            .map(|kv| PartitionStats {
+                ledger: *kv.key(),
+                storage: PartitionStorage::Memory,
+                event_count: kv.value().events.len(),
+                approx_bytes: kv.value().approx_size(),
+                expires_at: kv.value().expires_at(),
+            })
+            .chain(self.compressed.iter().map(|kv| PartitionStats {
+                ledger: *kv.key(),
+                storage: PartitionStorage::Compressed,
+                event_count: kv.value().summary.event_count,
+                approx_bytes: std::mem::size_of::<CompressedPartition>()
+                    + kv.value().keys.heap_size()
+                    + kv.value().data.len(),
+                expires_at: kv.value().summary.expires_at,
+            }))
+            .chain(self.spilled.iter().map(|kv| PartitionStats {
+                ledger: *kv.key(),
+                storage: match kv.value().target {
+                    SpillTarget::File => PartitionStorage::Spilled,
+                    SpillTarget::Backend => PartitionStorage::Backend,
+                },
+                event_count: kv.value().summary.event_count,
+                approx_bytes: std::mem::size_of::<SpilledPartition>() + kv.value().keys.heap_size(),
+                expires_at: kv.value().summary.expires_at,
+            }))
+            .collect();
+        let mut segment_bytes = 0;
+        for segment in self.segments.iter() {
+            segment_bytes += segment.index_size();
+            partitions.extend(segment.partitions.iter().map(|(seq, p)| PartitionStats {
+                ledger: *seq,
+                storage: PartitionStorage::Segment,
+                event_count: p.events.len(),
+                approx_bytes: p.approx_size(),
+                expires_at: p.expires_at(),
+            }));
+        }
+        partitions.sort_unstable_by_key(|p| p.ledger);
+
+        let contract_bytes: usize = self
+            .contracts
+            .iter()
+            .map(|kv| {
+                let activity = kv.value();
+                std::mem::size_of::<(String, ContractActivity)>()
+                    + kv.key().len()
+                    + activity
+                        .ledgers
+                        .values()
+                        .map(|(_, closed_at)| {
+                            std::mem::size_of::<(u32, (u64, String))>() + closed_at.len()
+                        })
+                        .sum::<usize>()
+                    + activity
+                        .topic0
+                        .keys()
+                        .chain(activity.assets.keys())
+                        .map(|k| std::mem::size_of::<(String, u64)>() + k.len())
+                        .sum::<usize>()
+            })
+            .sum();
+        let transaction_bytes: usize = self
+            .transactions
+            .iter()
+            .map(|kv| std::mem::size_of::<(String, u32)>() + kv.key().len())
+            .sum();
+
+        StoreStats {
+            partitions,
+            index_bytes: contract_bytes + transaction_bytes + segment_bytes,
+        }
+    }
+
+    /// Summary of the partition for `ledger_seq`, including an expired one
+    /// that has not been cleaned up yet.
+    pub fn ledger_summary(&self, ledger_seq: u32) -> Option<LedgerSummary> {
+        let now = chrono::Utc::now().timestamp();
+        if let Some(partition) = self.ledgers.get(&ledger_seq) {
+            return Some(partition.summary(ledger_seq, now));
+        }
+        if let Some(partition) = self.segment_partition(ledger_seq) {
+            return Some(partition.summary(ledger_seq, now));
+        }
+        let summary = match self.compressed.get(&ledger_seq) {
+            Some(compressed) => compressed.summary.clone(),
+            None => self.spilled.get(&ledger_seq)?.summary.clone(),
+        };
+        Some(LedgerSummary {
+            expired: summary.expires_at <= now,
+            ..summary
+        })
+    }
+
+    /// Clean up expired cache entries. Returns the number of ledgers removed.
+    #[tracing::instrument(skip_all)]
+    pub fn cleanup_expired(&self) -> Result<u64, crate::Error> {
+        let now = chrono::Utc::now().timestamp();
+
+        // Collect expired keys first to avoid holding iterators during removal.
+        let mut expired: Vec<u32> = self
+            .ledger_sequences()
+            .into_iter()
+            .filter(|&seq| self.partition_expiry(seq).is_some_and(|t| t <= now))
+            .collect();
+        expired.retain(|&seq| !self.retain_events(seq, now));
+        let removed = self.remove_partitions(&expired) as u64;
+
+        if removed > 0 {
+            metrics::counter!("store_partitions_expired_total").increment(removed);
+            tracing::debug!(
+                removed,
+                remaining = self.cached_ledger_count(),
+                "expired partitions removed"
+            );
+        }
+
+        Ok(removed)
+    }
+
+    /// Replace an expired partition with one holding only the events of
+    /// contracts with a retention rule that still covers them, expiring when
+    /// the last of those rules lapses. Returns `false`, leaving the
+    /// partition to be dropped, if there are no such events.
+    fn retain_events(&self, ledger_seq: u32, now: i64) -> bool {
+        if self.retention.is_empty() {
+            return false;
+        }
+        let Some(partition) = self.partition(ledger_seq) else {
+            return false;
+        };
+        let retained_until = |event: &StoredEvent| -> Option<i64> {
+            let seconds = self.retention.get(event.contract_id.as_deref()?)?;
+            Some(event.ledger_closed_at_unix + seconds).filter(|&t| t > now)
+        };
+        let keep: Vec<Option<i64>> = partition.events.iter().map(retained_until).collect();
+        let Some(expires_at) = keep.iter().flatten().max().copied() else {
+            return false;
+        };
+
+        let mut export = partition.export(ledger_seq);
+        let mut kept = keep.iter();
+        export
+            .events
+            .retain(|_| kept.next().is_some_and(Option::is_some));
+        let retained = export.events.len();
+        match decode_partition(export) {
+            Ok((info, stored)) => {
+                self.insert_partition(ledger_seq, info, stored, expires_at);
+                self.retained_only.insert(ledger_seq, ());
+                tracing::debug!(ledger = ledger_seq, retained, "kept retained events");
+                true
+            }
+            Err(e) => {
+                tracing::warn!(ledger = ledger_seq, error = %e, "failed to retain events");
+                false
+            }
+        }
+    }
+
+    /// Drop the cached partitions for ledgers `start..=end`. Returns the
+    /// number of partitions removed.
+    pub fn purge_range(&self, start: u32, end: u32) -> usize {
+        let purged: Vec<u32> = self
+            .ledger_sequences()
+            .into_iter()
+            .filter(|seq| (start..=end).contains(seq))
+            .collect();
+        self.remove_partitions(&purged)
+    }
+
+    /// Drop the partitions for `ledgers`, wherever they are held. Returns the
+    /// number removed.
+    fn remove_partitions(&self, ledgers: &[u32]) -> usize {
+        let mut removed = 0;
+        for (seq, partition) in self.take_from_segments(ledgers) {
+            self.unindex_partition(seq, &partition);
+            removed += 1;
+        }
+        for &seq in ledgers {
+            self.retained_only.remove(&seq);
+            let partition = match self.ledgers.remove(&seq) {
+                Some((_, partition)) => Some(partition),
+                None => self
+                    .take_compressed(seq)
+                    .or_else(|| self.take_spilled(seq))
+                    .map(Arc::new),
+            };
+            if let Some(partition) = partition {
+                self.unindex_partition(seq, &partition);
+                removed += 1;
+            }
+        }
+        // A partition that could not be read back is gone all the same.
+        let mut sequences = self.sequences.lock().expect("sequence lock poisoned");
+        let mut backend_sequences = self
+            .backend_sequences
+            .lock()
+            .expect("sequence lock poisoned");
+        for seq in ledgers {
+            sequences.remove(seq);
+            backend_sequences.remove(seq);
+        }
+        let new_latest = sequences
+            .last()
+            .max(backend_sequences.last())
+            .copied()
+            .unwrap_or(0);
+        drop((sequences, backend_sequences));
+        self.delete_saved_partitions(ledgers);
+
+        // Update latest_ledger if the current one was removed.
+        if removed > 0 {
+            self.latest_ledger.store(new_latest, Ordering::Relaxed);
+            metrics::gauge!("store_partitions_total").set(self.cached_ledger_count() as f64);
+        }
+        removed
+    }
+
+    /// Copy the cached, unexpired partitions for ledgers `start..=end`, in
+    /// ledger order. Compressed and spilled partitions are copied without
+    /// being reloaded.
+    pub fn export_partitions(&self, start: u32, end: u32) -> Vec<PartitionExport> {
+        let now = chrono::Utc::now().timestamp();
+        let mut ledgers: Vec<u32> = self
+            .ledger_sequences()
+            .into_iter()
+            .filter(|seq| (start..=end).contains(seq))
+            .filter(|&seq| self.partition_expiry(seq).is_some_and(|t| t > now))
+            .collect();
+        ledgers.sort_unstable();
+
+        ledgers
+            .into_iter()
+            .filter_map(|seq| {
+                if let Some(partition) = self.ledgers.get(&seq) {
+                    return Some(partition.export(seq));
+                }
+                if let Some(partition) = self.segment_partition(seq) {
+                    return Some(partition.export(seq));
+                }
+                if let Some(compressed) = self.compressed.get(&seq) {
+                    return decompress_partition(&compressed.data).ok();
+                }
+                let target = self.spilled.get(&seq)?.target;
+                self.read_spilled(seq, target).ok()
+            })
+            .collect()
+    }
+
+    /// Sequences of every cached ledger, wherever it is held, unordered.
+    fn ledger_sequences(&self) -> Vec<u32> {
+        let mut ledgers: Vec<u32> = self
+            .ledgers
+            .iter()
+            .map(|kv| *kv.key())
+            .chain(self.compressed.iter().map(|kv| *kv.key()))
+            .chain(self.spilled.iter().map(|kv| *kv.key()))
+            .collect();
+        for segment in self.segments.iter() {
+            ledgers.extend(segment.partitions.iter().map(|(seq, _)| *seq));
+        }
+        ledgers
+    }
+
+    /// When a cached ledger's partition expires, without decompressing or
+    /// reloading it.
+    fn partition_expiry(&self, ledger_seq: u32) -> Option<i64> {
+        if let Some(partition) = self.ledgers.get(&ledger_seq) {
+            return Some(partition.expires_at());
+        }
+        if let Some(partition) = self.segment_partition(ledger_seq) {
+            return Some(partition.expires_at());
+        }
+        if let Some(compressed) = self.compressed.get(&ledger_seq) {
+            return Some(compressed.summary.expires_at);
+        }
+        self.spilled
+            .get(&ledger_seq)
+            .map(|spilled| spilled.summary.expires_at)
+    }
+
+    /// The partition for a ledger, recording the read. A compressed
+    /// partition is decompressed, and a spilled one read from disk or the
+    /// storage backend, for this read only.
+    fn partition(&self, ledger_seq: u32) -> Option<Arc<LedgerPartition>> {
+        let now = chrono::Utc::now().timestamp();
+        let slide = self.sliding_ttl.then_some(self.cache_ttl_seconds);
+        if let Some(partition) = self.ledgers.get(&ledger_seq) {
+            partition.touch(now, slide);
+            return Some(Arc::clone(partition.value()));
+        }
+        if let Some(partition) = self.segment_partition(ledger_seq) {
+            partition.touch(now, slide);
+            return Some(partition);
+        }
+        if let Some(mut compressed) = self.compressed.get_mut(&ledger_seq) {
+            if let Some(ttl) = slide {
+                let summary = &mut compressed.summary;
+                if summary.expires_at > now && summary.expires_at < now + ttl {
+                    summary.expires_at = now + ttl;
+                }
+            }
+            let expires_at = compressed.summary.expires_at;
+            let decoded = decompress_partition(&compressed.data).and_then(decode_partition);
+            drop(compressed);
+            metrics::counter!("store_partition_decompressions_total").increment(1);
+            return match decoded {
+                Ok((info, stored)) => {
+                    Some(Arc::new(LedgerPartition::new(info, stored, expires_at)))
+                }
+                Err(e) => {
+                    tracing::warn!(ledger = ledger_seq, error = %e, "failed to decompress partition");
+                    None
+                }
+            };
+        }
+        let (expires_at, target) = {
+            let mut spilled = self.spilled.get_mut(&ledger_seq)?;
+            if let Some(ttl) = slide {
+                let summary = &mut spilled.summary;
+                if summary.expires_at > now && summary.expires_at < now + ttl {
+                    summary.expires_at = now + ttl;
+                }
+            }
+            (spilled.summary.expires_at, spilled.target)
+        };
+        match target {
+            SpillTarget::File => metrics::counter!("store_spilled_reads_total").increment(1),
+            SpillTarget::Backend => metrics::counter!("store_backend_reads_total").increment(1),
+        }
+        match self.load_spilled(ledger_seq, target, expires_at) {
+            Ok(partition) => Some(Arc::new(partition)),
+            Err(e) => {
+                tracing::warn!(ledger = ledger_seq, error = %e, "failed to read spilled partition");
+                None
+            }
+        }
+    }
+
+    /// Apply `f` to the filter index of a ledger's partition if it is held
+    /// compressed or spilled, so it can be ruled out without decompressing
+    /// or reading its events.
+    fn held_keys<R>(&self, ledger_seq: u32, f: impl FnOnce(&PartitionKeys) -> R) -> Option<R> {
+        if let Some(compressed) = self.compressed.get(&ledger_seq) {
+            return Some(f(&compressed.keys));
+        }
+        self.spilled
+            .get(&ledger_seq)
+            .map(|spilled| f(&spilled.keys))
+    }
+
+    /// Move partitions that have not been read for the configured idle time
+    /// out of memory to the spill directory. Returns the number spilled;
+    /// without a spill directory, nothing is spilled.
+    pub fn spill_idle(&self) -> Result<usize, crate::Error> {
+        let Some(spill) = &self.spill else {
+            return Ok(0);
+        };
+        let now = chrono::Utc::now().timestamp();
+        let idle: Vec<(u32, Arc<LedgerPartition>)> = self
+            .ledgers
+            .iter()
+            .filter(|kv| {
+                kv.value().expires_at() > now
+                    && kv.value().last_read_at.load(Ordering::Relaxed) + spill.idle_seconds <= now
+            })
+            .map(|kv| (*kv.key(), Arc::clone(kv.value())))
+            .collect();
+
+        let mut spilled = 0;
+        for (seq, partition) in idle {
+            let data = compress_partition(seq, &partition)?;
+            std::fs::write(spill_path(&spill.dir, seq), data)?;
+            self.spilled.insert(
+                seq,
+                SpilledPartition {
+                    summary: partition.summary(seq, now),
+                    keys: partition.partition_keys(),
+                    target: SpillTarget::File,
+                },
+            );
+            // A partition replaced while it was being written stays in memory.
+            if self
+                .ledgers
+                .remove_if(&seq, |_, p| Arc::ptr_eq(p, &partition))
+                .is_some()
+            {
+                spilled += 1;
+            } else {
+                self.spilled.remove(&seq);
+                self.remove_spill_file(seq);
+            }
+        }
+
+        if spilled > 0 {
+            metrics::counter!("store_partitions_spilled_total").increment(spilled as u64);
+            metrics::gauge!("store_partitions_spilled").set(self.spilled.len() as f64);
+        }
+        Ok(spilled)
+    }
+
+    /// Read a spilled partition from its file or the storage backend.
+    fn read_spilled(
+        &self,
+        ledger_seq: u32,
+        target: SpillTarget,
+    ) -> Result<PartitionExport, crate::Error> {
+        match target {
+            SpillTarget::File => {
+                let spill = self
+                    .spill
+                    .as_ref()
+                    .ok_or_else(|| crate::Error::Internal("no spill directory".to_string()))?;
+                decompress_partition(&std::fs::read(spill_path(&spill.dir, ledger_seq))?)
+            }
+            SpillTarget::Backend => self
+                .backend
+                .as_ref()
+                .ok_or_else(|| crate::Error::Internal("no storage backend".to_string()))?
+                .load_partition(ledger_seq)?
+                .ok_or_else(|| crate::Error::Internal("partition not saved".to_string())),
+        }
+    }
+
+    /// Rebuild a spilled partition from its file or the storage backend.
+    fn load_spilled(
+        &self,
+        ledger_seq: u32,
+        target: SpillTarget,
+        expires_at: i64,
+    ) -> Result<LedgerPartition, crate::Error> {
+        let (info, stored) = decode_partition(self.read_spilled(ledger_seq, target)?)?;
+        Ok(LedgerPartition::new(info, stored, expires_at))
+    }
+
+    /// Remove a spilled partition, returning it read back so it can be
+    /// unindexed. An unreadable partition is dropped with a warning.
+    fn take_spilled(&self, ledger_seq: u32) -> Option<LedgerPartition> {
+        let (_, spilled) = self.spilled.remove(&ledger_seq)?;
+        let partition = self.load_spilled(ledger_seq, spilled.target, spilled.summary.expires_at);
+        if spilled.target == SpillTarget::File {
+            self.remove_spill_file(ledger_seq);
+        }
+        metrics::gauge!("store_partitions_spilled").set(self.spilled.len() as f64);
+        match partition {
+            Ok(partition) => Some(partition),
+            Err(e) => {
+                tracing::warn!(ledger = ledger_seq, error = %e, "failed to read spilled partition");
+                None
+            }
+        }
+    }
+
+    /// The partition for a ledger from its compacted segment, if it has been
+    /// compacted.
+    fn segment_partition(&self, ledger_seq: u32) -> Option<Arc<LedgerPartition>> {
+        let segment = self.segments.get(&(ledger_seq / SEGMENT_LEDGERS))?;
+        segment.get(ledger_seq).map(Arc::clone)
+    }
+
+    /// Whether the compacted segment covering a ledger, if any, could hold
+    /// an event matching the filters.
+    fn segment_may_match(&self, ledger_seq: u32, params: &EventQueryParams) -> bool {
+        self.segments
+            .get(&(ledger_seq / SEGMENT_LEDGERS))
+            .is_none_or(|segment| segment.may_match(params))
+    }
+
+    /// Merge the unexpired in-memory partitions of every segment that ends
+    /// more than the configured number of ledgers behind the latest into
+    /// that segment. Returns the number of partitions merged; without
+    /// compaction configured, nothing is.
+    pub fn compact(&self) -> usize {
+        let Some(after) = self.compact_after_ledgers else {
+            return 0;
+        };
+        // Segments numbered below this end at or before the threshold.
+        let below = self
+            .latest_ledger
+            .load(Ordering::Relaxed)
+            .saturating_sub(after)
+            / SEGMENT_LEDGERS;
+        let now = chrono::Utc::now().timestamp();
+        let mut groups: BTreeMap<u32, Vec<(u32, Arc<LedgerPartition>)>> = BTreeMap::new();
+        for kv in self.ledgers.iter() {
+            let id = *kv.key() / SEGMENT_LEDGERS;
+            if id < below && kv.value().expires_at() > now {
+                groups
+                    .entry(id)
+                    .or_default()
+                    .push((*kv.key(), Arc::clone(kv.value())));
+            }
+        }
+
+        let _guard = self.segment_lock.lock().expect("segment lock poisoned");
+        let mut compacted = 0;
+        for (id, moved) in groups {
+            // Partitions being moved come first, so they win over any copy
+            // of the same ledger already in the segment.
+            let mut members = moved.clone();
+            if let Some(existing) = self.segments.get(&id).map(|s| Arc::clone(s.value())) {
+                members.extend(existing.partitions.iter().cloned());
+            }
+            members.sort_by_key(|(seq, _)| *seq);
+            members.dedup_by_key(|(seq, _)| *seq);
+            self.segments.insert(id, Arc::new(Segment::new(members)));
+
+            // A partition replaced while the segment was built stays as is.
+            let stale: Vec<u32> = moved
+                .iter()
+                .filter(|(seq, partition)| {
+                    self.ledgers
+                        .remove_if(seq, |_, p| Arc::ptr_eq(p, partition))
+                        .is_none()
+                })
+                .map(|(seq, _)| *seq)
+                .collect();
+            compacted += moved.len() - stale.len();
+            self.remove_from_segment(id, &stale);
+        }
+
+        if compacted > 0 {
+            metrics::counter!("store_partitions_compacted_total").increment(compacted as u64);
+            metrics::gauge!("store_segments").set(self.segments.len() as f64);
+        }
+        compacted
+    }
+
+    /// Remove the partitions for `ledgers` from their compacted segments,
+    /// returning them so they can be unindexed.
+    fn take_from_segments(&self, ledgers: &[u32]) -> Vec<(u32, Arc<LedgerPartition>)> {
+        let mut by_segment: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for &seq in ledgers {
+            if self.segments.contains_key(&(seq / SEGMENT_LEDGERS)) {
+                by_segment
+                    .entry(seq / SEGMENT_LEDGERS)
+                    .or_default()
+                    .push(seq);
+            }
+        }
+        if by_segment.is_empty() {
+            return Vec::new();
+        }
+
+        let _guard = self.segment_lock.lock().expect("segment lock poisoned");
+        let taken: Vec<_> = by_segment
+            .into_iter()
+            .flat_map(|(id, seqs)| self.remove_from_segment(id, &seqs))
+            .collect();
+        metrics::gauge!("store_segments").set(self.segments.len() as f64);
+        taken
     }
 
-    /// Get the highest ledger sequence in the store.
-    pub fn latest_ledger_sequence(&self) -> Result<Option<u32>, crate::Error> {
-        let v = self.latest_ledger.load(Ordering::Relaxed);
-        Ok(if v == 0 { None } else { Some(v) })
+    /// Rebuild segment `id` without the partitions for `ledgers`, dropping
+    /// it if none remain, and return the removed partitions. The caller
+    /// holds the segment lock.
+    fn remove_from_segment(&self, id: u32, ledgers: &[u32]) -> Vec<(u32, Arc<LedgerPartition>)> {
+        if ledgers.is_empty() {
+            return Vec::new();
+        }
+        let Some(segment) = self.segments.get(&id).map(|s| Arc::clone(s.value())) else {
+            return Vec::new();
+        };
+        let (removed, kept): (Vec<_>, Vec<_>) = segment
+            .partitions
+            .iter()
+            .cloned()
+            .partition(|(seq, _)| ledgers.contains(seq));
+        if removed.is_empty() {
+            return removed;
+        }
+        if kept.is_empty() {
+            self.segments.remove(&id);
+        } else {
+            self.segments.insert(id, Arc::new(Segment::new(kept)));
+        }
+        removed
     }
 
-    /// Get the number of ledgers currently cached.
-    pub fn cached_ledger_count(&self) -> usize {
-        self.ledgers.len()
+    /// Remove a partition held in a segment, compressed, or spilled rather
+    /// than in the main map, returning it so it can be unindexed.
+    fn take_held_elsewhere(&self, ledger_seq: u32) -> Option<Arc<LedgerPartition>> {
+        if let Some((_, partition)) = self.take_from_segments(&[ledger_seq]).pop() {
+            return Some(partition);
+        }
+        self.take_compressed(ledger_seq)
+            .or_else(|| self.take_spilled(ledger_seq))
+            .map(Arc::new)
     }
 
-    /// Clean up expired cache entries. Returns the number of ledgers removed.
-    #[tracing::instrument(skip_all)]
-    pub fn cleanup_expired(&self) -> Result<u64, crate::Error> {
+    /// Compress the unexpired in-memory partitions of ledgers more than the
+    /// configured number behind the latest. Returns the number compressed;
+    /// without compression configured, nothing is.
+    pub fn compress_old(&self) -> usize {
+        let Some(after) = self.compress_after_ledgers else {
+            return 0;
+        };
+        let threshold = self
+            .latest_ledger
+            .load(Ordering::Relaxed)
+            .saturating_sub(after);
         let now = chrono::Utc::now().timestamp();
-        let mut removed = 0u64;
-
-        // Collect expired keys first to avoid holding iterators during removal.
-        let expired: Vec<u32> = self
+        let old: Vec<(u32, Arc<LedgerPartition>)> = self
             .ledgers
             .iter()
-            .filter(|kv| kv.value().expires_at <= now)
-            .map(|kv| *kv.key())
+            .filter(|kv| *kv.key() < threshold && kv.value().expires_at() > now)
+            .map(|kv| (*kv.key(), Arc::clone(kv.value())))
             .collect();
 
-        for seq in expired {
-            self.ledgers.remove(&seq);
-            removed += 1;
+        let mut compressed = 0;
+        for (seq, partition) in old {
+            let data = match compress_partition(seq, &partition) {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::warn!(ledger = seq, error = %e, "failed to compress partition");
+                    continue;
+                }
+            };
+            let data_len = data.len();
+            self.compressed.insert(
+                seq,
+                CompressedPartition {
+                    summary: partition.summary(seq, now),
+                    keys: partition.partition_keys(),
+                    data,
+                },
+            );
+            // A partition replaced while it was being compressed stays as is.
+            if self
+                .ledgers
+                .remove_if(&seq, |_, p| Arc::ptr_eq(p, &partition))
+                .is_some()
+            {
+                compressed += 1;
+                metrics::gauge!("store_compressed_bytes").increment(data_len as f64);
+            } else {
+                self.compressed.remove(&seq);
+            }
         }
 
-        // Update latest_ledger if the current one was removed.
-        if removed > 0 {
-            let new_latest = self.ledgers.iter().map(|kv| *kv.key()).max().unwrap_or(0);
-            self.latest_ledger.store(new_latest, Ordering::Relaxed);
-            metrics::gauge!("store_partitions_total").set(self.ledgers.len() as f64);
-            metrics::counter!("store_partitions_expired_total").increment(removed);
-            tracing::debug!(
-                removed,
-                remaining = self.ledgers.len(),
-                "expired partitions removed"
-            );
+        if compressed > 0 {
+            metrics::counter!("store_partitions_compressed_total").increment(compressed as u64);
+            metrics::gauge!("store_partitions_compressed").set(self.compressed.len() as f64);
         }
+        compressed
+    }
 
-        Ok(removed)
+    /// Remove a compressed partition, returning it decompressed so it can be
+    /// unindexed.
+    fn take_compressed(&self, ledger_seq: u32) -> Option<LedgerPartition> {
+        let (_, compressed) = self.compressed.remove(&ledger_seq)?;
+        metrics::gauge!("store_partitions_compressed").set(self.compressed.len() as f64);
+        metrics::gauge!("store_compressed_bytes").decrement(compressed.data.len() as f64);
+        match decompress_partition(&compressed.data).and_then(decode_partition) {
+            Ok((info, stored)) => Some(LedgerPartition::new(
+                info,
+                stored,
+                compressed.summary.expires_at,
+            )),
+            Err(e) => {
+                tracing::warn!(ledger = ledger_seq, error = %e, "failed to decompress partition");
+                None
+            }
+        }
+    }
+
+    fn remove_spill_file(&self, ledger_seq: u32) {
+        if let Some(spill) = &self.spill {
+            let _ = std::fs::remove_file(spill_path(&spill.dir, ledger_seq));
+        }
+    }
+
+    /// Add a partition exported by another instance, or by this one before
+    /// a restart. It expires when the export says it does, if that is sooner
+    /// than this store's TTL for `source`. Returns `false` without changes
+    /// if the ledger is already cached or the export has expired.
+    pub fn import_partition(
+        &self,
+        export: PartitionExport,
+        source: CacheSource,
+    ) -> Result<bool, crate::Error> {
+        let ttl_expiry = chrono::Utc::now().timestamp() + self.ttl_seconds(source);
+        let expires_at = export.expires_at.map_or(ttl_expiry, |t| t.min(ttl_expiry));
+        if self.is_ledger_cached(export.ledger)? || expires_at <= chrono::Utc::now().timestamp() {
+            return Ok(false);
+        }
+
+        let ledger_seq = export.ledger;
+        let (info, stored) = decode_partition(export)?;
+        self.insert_partition(ledger_seq, info, stored, expires_at);
+        Ok(true)
+    }
+
+    /// Write a cached ledger's partition to a file at `path`, in the
+    /// zstd-compressed JSON form of spill files but without its expiry, so
+    /// it can be imported into another environment with
+    /// [`import_partition_file`](Self::import_partition_file). Returns
+    /// `false` without writing if the ledger is not cached.
+    pub fn export_partition_file(
+        &self,
+        ledger_seq: u32,
+        path: &std::path::Path,
+    ) -> Result<bool, crate::Error> {
+        let Some(mut export) = self.export_partitions(ledger_seq, ledger_seq).pop() else {
+            return Ok(false);
+        };
+        export.expires_at = None;
+        let json = serde_json::to_vec(&export)?;
+        std::fs::write(
+            path,
+            zstd::encode_all(json.as_slice(), SPILL_COMPRESSION_LEVEL)?,
+        )?;
+        Ok(true)
+    }
+
+    /// Add the partition in a file written by
+    /// [`export_partition_file`](Self::export_partition_file), kept for the
+    /// TTL of `source`. Returns the ledger, and `false` without changes if
+    /// it is already cached.
+    pub fn import_partition_file(
+        &self,
+        path: &std::path::Path,
+        source: CacheSource,
+    ) -> Result<(u32, bool), crate::Error> {
+        let export = decompress_partition(&std::fs::read(path)?)?;
+        let ledger_seq = export.ledger;
+        Ok((ledger_seq, self.import_partition(export, source)?))
     }
 
     /// Look up a single event by ledger sequence and internal ID.
@@ -507,9 +3026,8 @@ impl EventStore {
         ledger_seq: u32,
         internal_id: &str,
     ) -> Result<Option<EventRow>, crate::Error> {
-        let partition = match self.ledgers.get(&ledger_seq) {
-            Some(p) => Arc::clone(p.value()),
-            None => return Ok(None),
+        let Some(partition) = self.partition(ledger_seq) else {
+            return Ok(None);
         };
 
         match partition
@@ -542,6 +3060,15 @@ pub struct EventFilter {
     /// non-wildcard position matches exactly.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub topics: Option<Vec<serde_json::Value>>,
+    /// Positional subset topic matching, like `topics` except that each
+    /// non-wildcard value only needs to be contained in the stored topic (see
+    /// [`scval_contains`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subset_topics: Option<Vec<serde_json::Value>>,
+    /// Positional topic type matching. Each element is an ScVal type name
+    /// (see [`scval_type`]) or `null` (wildcard).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic_types: Option<Vec<Option<String>>>,
     /// Non-positional topic matching. Each element is an XDR-JSON ScVal that must
     /// appear in at least one topic position. Multiple values are AND'd (all must match).
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -549,9 +3076,156 @@ pub struct EventFilter {
     /// Filter by ledger sequence.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ledger: Option<u32>,
+    /// Only events in ledgers closed at or after this time (Unix seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<i64>,
+    /// Only events in ledgers closed at or before this time (Unix seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<i64>,
     /// Filter by transaction hash.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tx: Option<String>,
+    /// Filter by the index of the emitting operation within its transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op: Option<u32>,
+    /// Function name of a diagnostic `fn_call` or `fn_return` event.
+    #[serde(rename = "fn", default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// Comparisons the amount in the event data must satisfy (all of them).
+    /// Events without an amount never match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Vec<AmountFilter>>,
+    /// Case-insensitive search terms, each of which must appear within a
+    /// symbol or string value in the event's topics or data. Stored lowercased.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<Vec<String>>,
+}
+
+/// A comparison operator of an [`AmountFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+/// Why a ledger is being cached, which decides how long it is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheSource {
+    /// Synced from the network as it closed.
+    Sync,
+    /// Backfilled while scanning older ledgers for a query, export, or
+    /// backfill job.
+    Backfill,
+    /// Fetched for a query or lookup that names the ledger.
+    Pinned,
+}
+
+/// How long to keep one contract's events beyond the cache TTL, written
+/// `contract:C...=30d`, with a duration in seconds (`s`), minutes (`m`),
+/// hours (`h`), or days (`d`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionRule {
+    pub contract_id: String,
+    /// Seconds after its ledger closes that an event is kept.
+    pub seconds: i64,
+}
+
+impl std::str::FromStr for RetentionRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid retention rule '{}', expected contract:C...=30d", s);
+        let (contract_id, duration) = s
+            .strip_prefix("contract:")
+            .and_then(|rule| rule.split_once('='))
+            .ok_or_else(invalid)?;
+        if stellar_strkey::Contract::from_string(contract_id).is_err() {
+            return Err(format!("invalid contract ID '{}'", contract_id));
+        }
+        let unit = match duration.chars().last() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let count: i64 = duration[..duration.len() - 1]
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            contract_id: contract_id.to_string(),
+            seconds: count.saturating_mul(unit),
+        })
+    }
+}
+
+/// A comparison against the amount in an event's data, written `>1000`,
+/// `<=5`, `=7`, or just `7`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct AmountFilter {
+    pub op: Comparison,
+    pub value: i128,
+}
+
+impl AmountFilter {
+    /// Whether `amount` satisfies the comparison.
+    pub fn matches(&self, amount: i128) -> bool {
+        match self.op {
+            Comparison::Lt => amount < self.value,
+            Comparison::Le => amount <= self.value,
+            Comparison::Eq => amount == self.value,
+            Comparison::Ge => amount >= self.value,
+            Comparison::Gt => amount > self.value,
+        }
+    }
+}
+
+impl std::str::FromStr for AmountFilter {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (op, value) = if let Some(v) = s.strip_prefix(">=") {
+            (Comparison::Ge, v)
+        } else if let Some(v) = s.strip_prefix("<=") {
+            (Comparison::Le, v)
+        } else if let Some(v) = s.strip_prefix('>') {
+            (Comparison::Gt, v)
+        } else if let Some(v) = s.strip_prefix('<') {
+            (Comparison::Lt, v)
+        } else {
+            (Comparison::Eq, s.strip_prefix('=').unwrap_or(s))
+        };
+        let value = value.parse::<i128>().map_err(|_| ())?;
+        Ok(Self { op, value })
+    }
+}
+
+impl std::fmt::Display for AmountFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self.op {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Eq => "",
+            Comparison::Ge => ">=",
+            Comparison::Gt => ">",
+        };
+        write!(f, "{}{}", op, self.value)
+    }
+}
+
+impl EventFilter {
+    /// Whether a ledger closed at `closed_at` (Unix seconds) falls within the
+    /// filter's `since`/`until` window.
+    fn in_time_window(&self, closed_at: i64) -> bool {
+        self.since.is_none_or(|since| closed_at >= since)
+            && self.until.is_none_or(|until| closed_at <= until)
+    }
 }
 
 /// Parameters for querying events.
@@ -562,16 +3236,282 @@ pub struct EventQueryParams {
     pub before: Option<String>,
     /// Structured filters. Each filter is OR'd; conditions within are AND'd.
     pub filters: Vec<EventFilter>,
+    /// Order of the returned events.
+    pub order: SortOrder,
+}
+
+/// Order of events in a query result.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Newest first.
+    #[default]
+    Desc,
+    /// Oldest first. Only valid with an `after` cursor or a pinned ledger.
+    Asc,
+}
+
+/// Activity across the cached ledgers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityStats {
+    /// (minute start in Unix seconds, events, ledgers), in time order.
+    pub per_minute: Vec<(i64, u64, u64)>,
+    /// Ledgers per events-per-ledger bucket; see [`LEDGER_EVENT_BUCKETS`].
+    pub ledger_buckets: Vec<u64>,
+    /// Events by type.
+    pub event_types: EventTypeCounts,
+    /// Most frequent first-topic symbols with their event counts.
+    pub top_topics: Vec<(String, u64)>,
+}
+
+/// Event counts by event type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct EventTypeCounts {
+    pub contract: u64,
+    pub system: u64,
+    pub diagnostic: u64,
+}
+
+impl EventTypeCounts {
+    fn of<'a>(events: impl Iterator<Item = &'a StoredEvent>) -> Self {
+        let mut counts = Self::default();
+        for event in events {
+            *counts.get_mut(event.event_type) += 1;
+        }
+        counts
+    }
+
+    /// The count for a stored event type code.
+    fn get_mut(&mut self, event_type: u8) -> &mut u64 {
+        match event_type {
+            1 => &mut self.system,
+            2 => &mut self.diagnostic,
+            _ => &mut self.contract,
+        }
+    }
+
+    /// Add `other` to these counts, or subtract it if `add` is false.
+    fn apply(&mut self, other: &Self, add: bool) {
+        for (count, by) in [
+            (&mut self.contract, other.contract),
+            (&mut self.system, other.system),
+            (&mut self.diagnostic, other.diagnostic),
+        ] {
+            *count = if add {
+                *count + by
+            } else {
+                count.saturating_sub(by)
+            };
+        }
+    }
+}
+
+/// A cached ledger partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerSummary {
+    pub ledger: u32,
+    pub event_count: usize,
+    /// Ledger close time (RFC 3339). Unknown for ledgers cached without
+    /// header details and without events.
+    pub closed_at: Option<String>,
+    /// Protocol version, when header details were recorded.
+    pub protocol_version: Option<u32>,
+    /// Transactions applied in the ledger, when header details were recorded.
+    pub tx_count: Option<u32>,
+    /// Unix timestamp when the partition expires.
+    pub expires_at: i64,
+    /// Whether the partition has expired but not been cleaned up yet.
+    pub expired: bool,
+}
+
+/// Memory use of the store, from [`EventStore::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreStats {
+    /// Every cached partition, in ledger order.
+    pub partitions: Vec<PartitionStats>,
+    /// Approximate bytes held by the contract and transaction indexes and
+    /// the indexes shared within compacted segments.
+    pub index_bytes: usize,
+}
+
+impl StoreStats {
+    /// Approximate bytes held by the partitions and indexes together.
+    pub fn total_bytes(&self) -> usize {
+        self.partitions
+            .iter()
+            .map(|p| p.approx_bytes)
+            .sum::<usize>()
+            + self.index_bytes
+    }
+}
+
+/// Size of a single cached partition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionStats {
+    pub ledger: u32,
+    pub storage: PartitionStorage,
+    pub event_count: usize,
+    /// Approximate bytes of memory held by the partition.
+    pub approx_bytes: usize,
+    /// Unix timestamp when the partition expires.
+    pub expires_at: i64,
+}
+
+/// Where a cached partition is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionStorage {
+    /// In memory, ready to query.
+    Memory,
+    /// In memory as a compressed block, decompressed per read.
+    Compressed,
+    /// In memory, merged into a multi-ledger segment by compaction.
+    Segment,
+    /// On disk in the spill directory, read back per read.
+    Spilled,
+    /// Only in the storage backend, read back per read.
+    Backend,
+}
+
+impl PartitionStorage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PartitionStorage::Memory => "memory",
+            PartitionStorage::Compressed => "compressed",
+            PartitionStorage::Spilled => "spilled",
+            PartitionStorage::Segment => "segment",
+            PartitionStorage::Backend => "backend",
+        }
+    }
+}
+
+/// Activity of a single contract across the cached ledgers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractSummary {
+    pub contract_id: String,
+    pub event_count: u64,
+    /// The same events, by type.
+    pub event_types: EventTypeCounts,
+    pub first_seen_ledger: u32,
+    pub last_seen_ledger: u32,
+    /// Close time of `last_seen_ledger` (RFC 3339).
+    pub last_activity_at: String,
+}
+
+/// Rebuild a partition's stored events from its export, validating them.
+fn decode_partition(
+    export: PartitionExport,
+) -> Result<(Option<LedgerInfo>, Vec<StoredEvent>), crate::Error> {
+    let mut stored = Vec::with_capacity(export.events.len());
+    let mut transactions: HashMap<String, Arc<TransactionSummary>> = HashMap::new();
+    for event in export.events {
+        let invalid = |reason: &str| {
+            crate::Error::Internal(format!(
+                "invalid event {} in ledger {}: {}",
+                event.id, export.ledger, reason
+            ))
+        };
+        let (ledger_sequence, ..) = crate::ledger::event_id::parse_event_id(&event.id)
+            .ok_or_else(|| invalid("malformed id"))?;
+        if ledger_sequence != export.ledger {
+            return Err(invalid("id belongs to another ledger"));
+        }
+        let external_id = crate::ledger::event_id::to_external_id(&event.id)
+            .ok_or_else(|| invalid("malformed id"))?;
+        let (event_type, event_type_str) = match event.event_type.as_str() {
+            "contract" => (0u8, "contract"),
+            "system" => (1u8, "system"),
+            "diagnostic" => (2u8, "diagnostic"),
+            _ => return Err(invalid("unknown event type")),
+        };
+        let ledger_closed_at_unix = chrono::DateTime::parse_from_rfc3339(&event.ledger_closed_at)
+            .map_err(|_| invalid("malformed close time"))?
+            .timestamp();
+        let search_tokens = event_search_tokens(&event.topics, Some(&event.data), None);
+        let transaction = event.transaction.map(|summary| {
+            Arc::clone(
+                transactions
+                    .entry(event.tx_hash.clone())
+                    .or_insert_with(|| Arc::new(summary)),
+            )
+        });
+        stored.push(StoredEvent {
+            id: event.id,
+            external_id,
+            ledger_sequence,
+            ledger_closed_at: event.ledger_closed_at,
+            ledger_closed_at_unix,
+            op_index: event.op_index,
+            contract_id: event.contract_id,
+            event_type,
+            event_type_str,
+            topics: event.topics,
+            // Events that carry their XDR go back to holding only the data's.
+            data: event.xdr.is_none().then_some(event.data),
+            data_xdr: event.xdr.map(|xdr| xdr.data),
+            tx_hash: event.tx_hash,
+            transaction,
+            search_tokens,
+        });
+    }
+
+    Ok((export.info, stored))
+}
+
+/// Portable copy of one ledger partition, used to warm another instance's
+/// cache without fetching from the archive.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PartitionExport {
+    pub ledger: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<LedgerInfo>,
+    pub events: Vec<ExportedEvent>,
+    /// Unix timestamp at which the partition leaves the exporting cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+/// An event within a [`PartitionExport`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExportedEvent {
+    /// Internal event ID.
+    pub id: String,
+    pub ledger_closed_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op_index: Option<u32>,
+    pub contract_id: Option<String>,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub topics: serde_json::Value,
+    pub data: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xdr: Option<EventXdr>,
+    pub tx_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionSummary>,
 }
 
 /// Result of an event query.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct EventQueryResult {
     pub data: Vec<EventRow>,
     /// Cursor for the next page. Pass as `before` to continue paginating backward,
     /// or as `after` to continue polling forward. When filters are applied, this may
     /// point beyond the last returned event to avoid re-scanning examined ranges.
     pub next: Option<String>,
+    /// Work performed to produce the result.
+    pub stats: QueryStats,
+}
+
+/// Counters describing the work done by a query.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QueryStats {
+    /// Number of ledger partitions scanned.
+    pub partitions_scanned: u32,
+    /// Number of events compared against the filters.
+    pub events_examined: u64,
+    /// Number of examined events that matched the filters.
+    pub events_matched: u64,
+    /// Number of ledgers fetched from the archive to answer the query.
+    pub ledgers_backfilled: u32,
 }
 
 /// A single event row returned from queries.
@@ -580,9 +3520,587 @@ pub struct EventRow {
     pub id: String,
     pub ledger_sequence: u32,
     pub ledger_closed_at: String,
+    /// Ledger close time in Unix seconds.
+    pub ledger_closed_at_unix: i64,
+    /// Index of the emitting operation, for operation events.
+    pub op_index: Option<u32>,
     pub contract_id: Option<String>,
     pub event_type: &'static str,
     pub topics: serde_json::Value,
     pub data: serde_json::Value,
+    /// Raw XDR of the data, when retained at ingest. The topics' XDR is
+    /// re-encoded from `topics` when needed.
+    pub data_xdr: Option<Vec<u8>>,
     pub tx_hash: String,
+    /// Summary of the transaction envelope, when recorded at ingest.
+    pub transaction: Option<Arc<TransactionSummary>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ExtractedEventBuilder;
+    use stellar_xdr::curr::{ScMap, ScMapEntry, ScString, ScSymbol, WriteXdr};
+
+    const TTL: i64 = 24 * 60 * 60;
+
+    /// An event at `index` in `ledger`, to finish building.
+    fn event(ledger: u32, index: u32) -> ExtractedEventBuilder {
+        ExtractedEvent::builder().ledger(ledger).event_index(index)
+    }
+
+    /// Query parameters matching the events of a single contract.
+    fn contract_params(contract: &str) -> EventQueryParams {
+        EventQueryParams {
+            filters: vec![EventFilter {
+                contract_id: Some(contract.to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Seconds until a cached ledger's partition expires.
+    fn expires_in(store: &EventStore, ledger: u32) -> i64 {
+        store.ledger_summary(ledger).unwrap().expires_at - chrono::Utc::now().timestamp()
+    }
+
+    #[test]
+    fn test_restore_after_restart() {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-sqlite-store-{}.db",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let open_store = || {
+            let backend = crate::storage::SqliteBackend::open(&path).unwrap();
+            EventStore::new(TTL).with_backend(Box::new(backend))
+        };
+
+        let store = open_store();
+        store
+            .insert_events(vec![
+                event(1000, 0).build(),
+                event(1000, 1).build(),
+                event(1001, 0).build(),
+                event(1002, 0).build(),
+            ])
+            .unwrap();
+        store.set_sync_state("last_synced_ledger", "1002").unwrap();
+        assert_eq!(store.purge_range(1002, 1002), 1);
+        // A ledger without events is saved too, so it is not fetched again.
+        store.record_ledger_cached(1003, TTL).unwrap();
+        drop(store);
+
+        let store = open_store();
+        assert_eq!(store.restore().unwrap(), 3);
+        assert!(store.is_ledger_cached(1000).unwrap());
+        assert!(store.is_ledger_cached(1001).unwrap());
+        assert!(store.is_ledger_cached(1003).unwrap());
+        assert!(!store.is_ledger_cached(1002).unwrap());
+        assert_eq!(store.latest_ledger_sequence().unwrap(), Some(1003));
+        assert_eq!(
+            store
+                .get_sync_state("last_synced_ledger")
+                .unwrap()
+                .as_deref(),
+            Some("1002")
+        );
+
+        // Without a backend there is nothing to restore.
+        assert_eq!(EventStore::new(TTL).restore().unwrap(), 0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_spill_and_read_back() {
+        let dir =
+            std::env::temp_dir().join(format!("stellar-events-api-spill-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let store = EventStore::new(TTL).with_spill_dir(dir.clone(), 0).unwrap();
+        store
+            .insert_events(vec![
+                event(1000, 0).build(),
+                event(1000, 1).build(),
+                event(1001, 0).build(),
+            ])
+            .unwrap();
+
+        assert_eq!(store.spill_idle().unwrap(), 2);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        // Spilled ledgers are still cached and described without reading
+        // their files.
+        assert_eq!(store.cached_ledger_count(), 2);
+        assert!(store.is_ledger_cached(1000).unwrap());
+        let summaries = store.ledger_summaries(None, 10);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[1].event_count, 2);
+        assert_eq!(store.export_partitions(1000, 1001).len(), 2);
+
+        // Reading a spilled ledger reads its file, for that read only.
+        assert_eq!(store.ledger_events(1000).len(), 2);
+        assert!(store.ledgers.is_empty());
+        assert_eq!(store.spilled.len(), 2);
+
+        // Scans and counts rule out spilled ledgers that cannot match without
+        // reading their files, so they still work with the files moved away.
+        let moved = dir.with_extension("moved");
+        std::fs::rename(&dir, &moved).unwrap();
+        let params = contract_params(&("C".to_string() + &"A".repeat(55)));
+        assert!(store.count_by_ledger(&params).is_empty());
+        let mut results = Vec::new();
+        let mut stats = QueryStats::default();
+        let next = store.scan_ledger_forward(1000, None, &params, &mut results, 10, &mut stats);
+        assert!(results.is_empty());
+        assert!(next.is_some());
+        assert_eq!(stats.partitions_scanned, 1);
+        std::fs::rename(&moved, &dir).unwrap();
+
+        // Purging removes spilled ledgers and their files.
+        assert_eq!(store.purge_range(1001, 1001), 1);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(store.cached_ledger_count(), 1);
+        assert_eq!(store.latest_ledger_sequence().unwrap(), Some(1000));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compress_old_partitions() {
+        let store = EventStore::new(TTL).with_compression(1);
+        for ledger in [1000, 1000, 1001, 1002] {
+            store.insert_events(vec![event(ledger, 0).build()]).unwrap();
+        }
+        assert!(store.compressed.is_empty());
+        assert_eq!(store.compress_old(), 1);
+
+        // Ledger 1000 is more than one ledger behind 1002 and is compressed,
+        // but still cached, described, exported, and read like any other.
+        assert!(store.compressed.contains_key(&1000));
+        assert_eq!(store.cached_ledger_count(), 3);
+        assert!(store.is_ledger_cached(1000).unwrap());
+        let summaries = store.ledger_summaries(None, 10);
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(summaries[2].event_count, 1);
+        assert_eq!(store.export_partitions(1000, 1002).len(), 3);
+        assert_eq!(store.ledger_events(1000).len(), 1);
+        assert_eq!(store.ledger_events(1000).len(), 1);
+
+        // Its filter index is kept alongside, so scans and counts for another
+        // contract skip it, stepping past its events.
+        let params = contract_params(&("C".to_string() + &"A".repeat(55)));
+        assert!(store.count_by_ledger(&params).is_empty());
+        let mut results = Vec::new();
+        let mut stats = QueryStats::default();
+        let next = store.scan_ledger_backward(1000, None, &params, &mut results, 10, &mut stats);
+        assert_eq!(next, Some(store.ledger_events(1000)[0].id.clone()));
+        assert_eq!(stats.partitions_scanned, 1);
+        assert_eq!(stats.events_examined, 0);
+
+        // Replacing and purging compressed ledgers unindexes them.
+        store.insert_events(vec![event(1000, 0).build()]).unwrap();
+        assert_eq!(store.cached_ledger_count(), 3);
+        assert_eq!(store.purge_range(1000, 1001), 2);
+        assert_eq!(store.cached_ledger_count(), 1);
+        assert_eq!(store.latest_ledger_sequence().unwrap(), Some(1002));
+    }
+
+    #[test]
+    fn test_evicts_oldest_ledgers() {
+        let store = EventStore::new(TTL).with_max_cached_ledgers(2);
+        for ledger in [1001, 1000, 1002] {
+            store.insert_events(vec![event(ledger, 0).build()]).unwrap();
+        }
+        assert_eq!(store.cached_ledger_count(), 2);
+        assert!(!store.is_ledger_cached(1000).unwrap());
+        assert!(store.is_ledger_cached(1001).unwrap());
+
+        // An older ledger than every cached one is dropped straight away, and
+        // empty ledgers count against the cap too.
+        store.insert_events(vec![event(999, 0).build()]).unwrap();
+        assert!(!store.is_ledger_cached(999).unwrap());
+        store.record_ledger_cached(1003, 60 * 60).unwrap();
+        assert_eq!(store.cached_ledger_count(), 2);
+        assert!(store.is_ledger_cached(1003).unwrap());
+        assert!(store.is_ledger_cached(1002).unwrap());
+        assert_eq!(store.latest_ledger_sequence().unwrap(), Some(1003));
+        let sequences = store.sequences.lock().unwrap();
+        assert!(sequences.iter().eq(&[1002, 1003]));
+    }
+
+    #[test]
+    fn test_evicts_into_backend() {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-sqlite-evict-{}.db",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let open_store = || {
+            let backend = crate::storage::SqliteBackend::open(&path).unwrap();
+            EventStore::new(TTL)
+                .with_backend(Box::new(backend))
+                .with_max_cached_ledgers(2)
+        };
+        let contract_b = "C".to_string() + &"B".repeat(55);
+
+        let store = open_store();
+        for ledger in [1000, 1001, 1002] {
+            store.insert_events(vec![event(ledger, 0).build()]).unwrap();
+        }
+        store
+            .insert_events(vec![event(1003, 0).contract(&contract_b).build()])
+            .unwrap();
+
+        // The oldest ledgers are left in the backend rather than dropped:
+        // still cached, described, and queried, but not held in memory.
+        assert_eq!(store.ledgers.len(), 2);
+        assert_eq!(store.cached_ledger_count(), 4);
+        assert!(store.is_ledger_cached(1000).unwrap());
+        let storage: Vec<&str> = store
+            .stats()
+            .partitions
+            .iter()
+            .map(|p| p.storage.as_str())
+            .collect();
+        assert_eq!(storage, ["backend", "backend", "memory", "memory"]);
+        assert_eq!(store.ledger_events(1000).len(), 1);
+        assert_eq!(store.export_partitions(1000, 1003).len(), 4);
+        assert!(store.ledgers.get(&1000).is_none());
+
+        // Scans rule them out by their filter index without reading them.
+        let params = contract_params(&contract_b);
+        assert_eq!(store.count_by_ledger(&params).len(), 1);
+
+        // Purging one deletes it from the backend too.
+        assert_eq!(store.purge_range(1001, 1001), 1);
+        assert_eq!(store.cached_ledger_count(), 3);
+        drop(store);
+
+        // A restart keeps only the newest ledgers in memory.
+        let store = open_store();
+        assert_eq!(store.restore().unwrap(), 3);
+        assert_eq!(store.ledgers.len(), 2);
+        assert_eq!(store.cached_ledger_count(), 3);
+        assert!(!store.is_ledger_cached(1001).unwrap());
+        assert_eq!(store.ledger_events(1000).len(), 1);
+        assert_eq!(store.latest_ledger_sequence().unwrap(), Some(1003));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_compact_into_segments() {
+        let store = EventStore::new(TTL).with_compaction(100);
+        let contract_a = "C".to_string() + &"A".repeat(55);
+        let contract_b = "C".to_string() + &"B".repeat(55);
+        let contract_event =
+            |ledger: u32, contract: &str| event(ledger, 0).contract(contract).build();
+        for ledger in 1024..1030 {
+            store
+                .insert_events(vec![contract_event(ledger, &contract_a)])
+                .unwrap();
+        }
+        store
+            .insert_events(vec![contract_event(2048, &contract_b)])
+            .unwrap();
+        store
+            .insert_events(vec![contract_event(5000, &contract_b)])
+            .unwrap();
+
+        // Segments ending more than 100 ledgers behind 5000 are compacted;
+        // the one holding 5000 is not.
+        assert_eq!(store.compact(), 7);
+        assert_eq!(store.compact(), 0);
+        assert_eq!(store.segments.len(), 2);
+        assert_eq!(store.ledgers.len(), 1);
+        let stats = store.stats();
+        assert_eq!(stats.partitions.len(), 8);
+        assert_eq!(
+            stats
+                .partitions
+                .iter()
+                .filter(|p| p.storage == PartitionStorage::Segment)
+                .count(),
+            7
+        );
+
+        // Compacted ledgers are read, counted, and described like any other.
+        assert_eq!(store.cached_ledger_count(), 8);
+        assert_eq!(store.ledger_events(1025).len(), 1);
+        assert_eq!(store.ledger_summaries(None, 10).len(), 8);
+        assert_eq!(
+            store.count_by_ledger(&contract_params(&contract_a)).len(),
+            6
+        );
+        assert_eq!(
+            store.count_by_ledger(&contract_params(&contract_b)),
+            vec![(2048, 1), (5000, 1)]
+        );
+
+        // Replacing, purging, and late arrivals update the segment.
+        store.replace_ledger(
+            1024,
+            None,
+            vec![contract_event(1024, &contract_b)],
+            CacheSource::Sync,
+        );
+        assert_eq!(
+            store.count_by_ledger(&contract_params(&contract_a)).len(),
+            5
+        );
+        assert_eq!(store.purge_range(1025, 1026), 2);
+        store
+            .insert_events(vec![contract_event(1500, &contract_a)])
+            .unwrap();
+        assert_eq!(store.compact(), 2);
+        assert_eq!(store.cached_ledger_count(), 7);
+        assert_eq!(
+            store.count_by_ledger(&contract_params(&contract_a)).len(),
+            4
+        );
+        assert_eq!(store.purge_range(0, 4096), 6);
+        assert_eq!(store.cached_ledger_count(), 1);
+    }
+
+    #[test]
+    fn test_retain_contract_events() {
+        let retained = stellar_strkey::Contract([1; 32]).to_string();
+        let other = stellar_strkey::Contract([2; 32]).to_string();
+        let rule: RetentionRule = format!("contract:{}=30d", retained).parse().unwrap();
+        assert_eq!(rule.seconds, 30 * 24 * 60 * 60);
+        assert!("contract:CAAA=30d".parse::<RetentionRule>().is_err());
+        assert!(format!("contract:{}=30w", retained)
+            .parse::<RetentionRule>()
+            .is_err());
+
+        // Every partition expires immediately.
+        let store = EventStore::new(0).with_retention(vec![rule]);
+        let now = chrono::Utc::now().timestamp();
+        let event_at = |ledger: u32, index: u32, contract: &str, closed_at: i64| {
+            event(ledger, index)
+                .closed_at(closed_at)
+                .contract(contract)
+                .build()
+        };
+        store
+            .insert_events(vec![
+                event_at(100, 0, &retained, now),
+                event_at(100, 1, &other, now),
+                event_at(101, 0, &other, now),
+                // Closed longer ago than the retention period.
+                event_at(102, 0, &retained, now - 31 * 24 * 60 * 60),
+            ])
+            .unwrap();
+
+        assert_eq!(store.cleanup_expired().unwrap(), 2);
+        assert_eq!(store.cached_ledger_count(), 1);
+        let events = store.ledger_events(100);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].contract_id.as_deref(), Some(retained.as_str()));
+
+        // The cut-down ledger is not treated as cached, and is not expired
+        // again until the retention lapses.
+        assert!(store.retained_only.contains_key(&100));
+        assert!(!store.is_ledger_cached(100).unwrap());
+        assert_eq!(store.find_uncached_ledgers(100, 1).unwrap(), vec![100]);
+        assert_eq!(store.cleanup_expired().unwrap(), 0);
+        assert_eq!(store.ledger_events(100).len(), 1);
+    }
+
+    #[test]
+    fn test_insert_uses_source_ttl() {
+        let store = EventStore::new(TTL)
+            .with_source_ttl(CacheSource::Backfill, 2 * 60 * 60)
+            .with_source_ttl(CacheSource::Pinned, 10 * 60);
+        assert_eq!(store.ttl_seconds(CacheSource::Sync), TTL);
+
+        for (ledger, source) in [
+            (1000, CacheSource::Sync),
+            (1001, CacheSource::Backfill),
+            (1002, CacheSource::Pinned),
+        ] {
+            store
+                .insert_ledger(ledger, None, vec![event(ledger, 0).build()], source)
+                .unwrap();
+        }
+
+        assert!((TTL - 5..=TTL).contains(&expires_in(&store, 1000)));
+        assert!((2 * 60 * 60 - 5..=2 * 60 * 60).contains(&expires_in(&store, 1001)));
+        assert!((10 * 60 - 5..=10 * 60).contains(&expires_in(&store, 1002)));
+
+        // Replacements and imports are kept for their source's TTL too.
+        store.replace_ledger(
+            1000,
+            None,
+            vec![event(1000, 0).build()],
+            CacheSource::Pinned,
+        );
+        assert!((10 * 60 - 5..=10 * 60).contains(&expires_in(&store, 1000)));
+        let target = EventStore::new(TTL).with_source_ttl(CacheSource::Backfill, 60 * 60);
+        let export = store.export_partitions(1001, 1001).pop().unwrap();
+        assert!(target
+            .import_partition(export, CacheSource::Backfill)
+            .unwrap());
+        assert!((60 * 60 - 5..=60 * 60).contains(&expires_in(&target, 1001)));
+    }
+
+    #[test]
+    fn test_record_ledger_cached_uses_ttl() {
+        let store = EventStore::new(TTL);
+        store.record_ledger_cached(1000, 60).unwrap();
+        assert!((55..=60).contains(&expires_in(&store, 1000)));
+
+        store.record_ledger_cached(1001, 0).unwrap();
+        assert!(!store.is_ledger_cached(1001).unwrap());
+    }
+
+    /// A store holding ledgers 1000 and 1001, each expiring a minute from
+    /// now.
+    fn expiring_store(sliding: bool) -> EventStore {
+        let store = EventStore::new(TTL);
+        let store = if sliding {
+            store.with_sliding_ttl()
+        } else {
+            store
+        };
+        let expires_at = chrono::Utc::now().timestamp() + 60;
+        for ledger in [1000, 1001] {
+            let stored = vec![StoredEvent::from(event(ledger, 0).build())];
+            store.insert_partition(ledger, None, stored, expires_at);
+        }
+        store
+    }
+
+    #[test]
+    fn test_read_refreshes_expiry() {
+        let store = expiring_store(true);
+        assert_eq!(store.ledger_events(1000).len(), 1);
+
+        // The read ledger now expires a full TTL out; the untouched one does
+        // not.
+        assert!(expires_in(&store, 1000) > TTL - 60);
+        assert!(expires_in(&store, 1001) <= 60);
+    }
+
+    #[test]
+    fn test_fixed_ttl_by_default() {
+        let store = expiring_store(false);
+        assert_eq!(store.ledger_events(1000).len(), 1);
+        assert!(expires_in(&store, 1000) <= 60);
+    }
+
+    #[test]
+    fn test_export_import_partition_file() {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-partition-{}.json.zst",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let source = EventStore::new(60);
+        source
+            .insert_events((0..3).map(|i| event(1000, i).build()).collect())
+            .unwrap();
+        assert!(!source.export_partition_file(1001, &path).unwrap());
+        assert!(source.export_partition_file(1000, &path).unwrap());
+
+        // The import is kept for the target's TTL, not the source's.
+        let target = EventStore::new(TTL);
+        assert_eq!(
+            target
+                .import_partition_file(&path, CacheSource::Sync)
+                .unwrap(),
+            (1000, true)
+        );
+        assert_eq!(
+            target
+                .import_partition_file(&path, CacheSource::Sync)
+                .unwrap(),
+            (1000, false)
+        );
+        assert_eq!(target.ledger_events(1000).len(), 3);
+        assert!(expires_in(&target, 1000) > 60 * 60);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn symbol(s: &str) -> ScVal {
+        ScVal::Symbol(ScSymbol(s.try_into().unwrap()))
+    }
+
+    #[test]
+    fn test_data_converted_on_demand() {
+        let topic = symbol("transfer");
+        let data = ScVal::Map(Some(
+            ScMap::sorted_from(vec![
+                ScMapEntry {
+                    key: symbol("amount"),
+                    val: ScVal::U32(7),
+                },
+                ScMapEntry {
+                    key: symbol("memo"),
+                    val: ScVal::String(ScString("Hello World".try_into().unwrap())),
+                },
+            ])
+            .unwrap(),
+        ));
+
+        let mut event = event(1000, 0).build();
+        event.topics_xdr_json = vec![serde_json::to_value(&topic).unwrap()];
+        event.data_xdr_json = None;
+        event.xdr = Some(EventXdr {
+            topics: vec![topic.to_xdr(Limits::none()).unwrap()],
+            data: data.to_xdr(Limits::none()).unwrap(),
+        });
+        let store = EventStore::new(60);
+        store.insert_events(vec![event]).unwrap();
+
+        let expected = serde_json::to_value(&data).unwrap();
+        let row = &store.ledger_events(1000)[0];
+        assert_eq!(row.data, expected);
+        // The conversion is kept for the next read.
+        let data_xdr = data.to_xdr(Limits::none()).unwrap();
+        assert!(DATA_JSON_CACHE.lock().unwrap().contains(&data_xdr));
+        // Only the data's XDR is held; the topics' is re-encoded from their
+        // XDR-JSON, byte for byte.
+        assert_eq!(row.data_xdr.as_ref(), Some(&data_xdr));
+        assert_eq!(
+            EventXdr::encode_topics(&row.topics),
+            vec![topic.to_xdr(Limits::none()).unwrap()]
+        );
+
+        // Search terms are indexed from the XDR without converting it, and
+        // amounts are read straight from it.
+        let matches = |filter: EventFilter| {
+            let params = EventQueryParams {
+                limit: 10,
+                filters: vec![filter],
+                ..Default::default()
+            };
+            store.query_single_ledger(1000, &params).unwrap().data.len()
+        };
+        let search = |term: &str| EventFilter {
+            search: Some(vec![term.to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(matches(search("hello")), 1);
+        assert_eq!(matches(search("memo")), 1);
+        assert_eq!(matches(search("goodbye")), 0);
+        let amount = |comparison: &str| EventFilter {
+            amount: Some(vec![comparison.parse().unwrap()]),
+            ..Default::default()
+        };
+        assert_eq!(matches(amount(">=7")), 1);
+        assert_eq!(matches(amount(">7")), 0);
+
+        // Exports carry the converted data and the re-encoded XDR.
+        let export = store.export_partitions(1000, 1000);
+        assert_eq!(export[0].events[0].data, expected);
+        let xdr = export[0].events[0].xdr.as_ref().unwrap();
+        assert_eq!(xdr.topics, vec![topic.to_xdr(Limits::none()).unwrap()]);
+        assert_eq!(xdr.data, data_xdr);
+    }
 }
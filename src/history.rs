@@ -0,0 +1,198 @@
+//! Full-history backfill.
+//!
+//! The tip-following sync only caches ledgers from where it started. With
+//! `--backfill-from`, a separate task walks forwards from an older ledger up
+//! to the first ledger sync cached, one batch at a time at a bounded rate,
+//! fetching every ledger not already cached. Its position is kept in the sync
+//! state, so a restart resumes where it left off, and its progress and ETA
+//! are served at `/sync`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::api::routes::{backfill_batch, BACKFILL_BATCH_SIZE};
+use crate::db::{CacheSource, EventStore};
+use crate::sync::SYNC_START_KEY;
+use crate::AppState;
+
+/// Sync state key holding the ledger the history backfill started from.
+pub const HISTORY_BACKFILL_FROM_KEY: &str = "history_backfill_from";
+
+/// Sync state key holding the next ledger the history backfill will check.
+pub const HISTORY_BACKFILL_NEXT_KEY: &str = "history_backfill_next";
+
+/// Sync state key holding the history backfill's most recent rate, in
+/// ledgers per second.
+pub const HISTORY_BACKFILL_RATE_KEY: &str = "history_backfill_rate";
+
+/// How often to check whether sync has chosen its starting ledger.
+const START_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress of the history backfill, read from the sync state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryProgress {
+    pub from: u32,
+    /// Last ledger to backfill, once sync has chosen its starting ledger.
+    pub to: Option<u32>,
+    pub next: u32,
+    /// Ledgers per second over the most recent batch.
+    pub rate: Option<f64>,
+}
+
+impl HistoryProgress {
+    /// Read the progress from the store, if a history backfill was started.
+    pub fn read(store: &EventStore) -> Option<Self> {
+        let read = |key: &str| store.get_sync_state(key).ok().flatten();
+        let from = read(HISTORY_BACKFILL_FROM_KEY)?.parse().ok()?;
+        let next = read(HISTORY_BACKFILL_NEXT_KEY)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(from);
+        let to = read(SYNC_START_KEY)
+            .and_then(|v| v.parse::<u32>().ok())
+            .map(|start| start.saturating_sub(1));
+        let rate = read(HISTORY_BACKFILL_RATE_KEY).and_then(|v| v.parse().ok());
+        Some(Self {
+            from,
+            to,
+            next,
+            rate,
+        })
+    }
+
+    /// Ledgers left to check, once the end is known.
+    pub fn remaining(&self) -> Option<u32> {
+        self.to.map(|to| (to + 1).saturating_sub(self.next))
+    }
+
+    /// Estimated seconds until the backfill completes, at the recent rate.
+    pub fn eta_seconds(&self) -> Option<u64> {
+        let remaining = self.remaining()?;
+        let rate = self.rate.filter(|&rate| rate > 0.0)?;
+        Some((f64::from(remaining) / rate).ceil() as u64)
+    }
+}
+
+/// Backfill every ledger from `from` up to the ledger sync started at,
+/// checking at most `max_ledgers_per_second` ledgers per second.
+pub async fn run_history_backfill(state: Arc<AppState>, from: u32, max_ledgers_per_second: u32) {
+    let store = &state.store;
+    let next = match HistoryProgress::read(store) {
+        Some(progress) if progress.from == from => progress.next,
+        _ => from,
+    };
+    let save = |key: &str, value: String| {
+        if let Err(e) = store.set_sync_state(key, &value) {
+            tracing::warn!(error = %e, key, "failed to save history backfill progress");
+        }
+    };
+    save(HISTORY_BACKFILL_FROM_KEY, from.to_string());
+    save(HISTORY_BACKFILL_NEXT_KEY, next.to_string());
+
+    // Backfill up to the ledger sync started at, once it has chosen one.
+    let end = loop {
+        let start = store
+            .get_sync_state(SYNC_START_KEY)
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse::<u32>().ok());
+        if let Some(start) = start {
+            break start;
+        }
+        tokio::time::sleep(START_POLL_INTERVAL).await;
+    };
+
+    tracing::info!(from, next, end, "starting history backfill");
+    let max_ledgers_per_second = f64::from(max_ledgers_per_second.max(1));
+    let mut next = next;
+    while next < end {
+        let started = Instant::now();
+        let count = BACKFILL_BATCH_SIZE.min(end - next);
+        let uncached = store.find_uncached_ledgers(next, count).unwrap_or_default();
+        let result = backfill_batch(&state, &uncached, CacheSource::Backfill).await;
+        let failed = uncached.len() as u32 - result.fetched;
+        metrics::counter!("history_backfill_fetched_total").increment(u64::from(result.fetched));
+        if failed > 0 {
+            metrics::counter!("history_backfill_failed_total").increment(u64::from(failed));
+            tracing::warn!(
+                start = next,
+                count,
+                failed,
+                "history backfill could not fetch some ledgers"
+            );
+        }
+
+        // Hold the batch to the configured rate.
+        let budget = Duration::from_secs_f64(f64::from(count) / max_ledgers_per_second);
+        if let Some(wait) = budget.checked_sub(started.elapsed()) {
+            tokio::time::sleep(wait).await;
+        }
+
+        next += count;
+        let rate = f64::from(count) / started.elapsed().as_secs_f64();
+        save(HISTORY_BACKFILL_NEXT_KEY, next.to_string());
+        save(HISTORY_BACKFILL_RATE_KEY, format!("{:.2}", rate));
+        metrics::gauge!("history_backfill_remaining_ledgers").set(f64::from(end - next));
+    }
+    tracing::info!(from, end, "history backfill completed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::path::StoreConfig;
+
+    fn state() -> Arc<AppState> {
+        Arc::new(AppState {
+            store: EventStore::new(24 * 60 * 60),
+            config: StoreConfig::default().into(),
+            meta_url: String::new(),
+            client: reqwest::Client::new(),
+            peer: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_backfill_runs_up_to_sync_start() {
+        let state = state();
+        for ledger in 100..110 {
+            state
+                .store
+                .insert_ledger(ledger, None, Vec::new(), CacheSource::Sync)
+                .unwrap();
+        }
+        state.store.set_sync_state(SYNC_START_KEY, "110").unwrap();
+
+        run_history_backfill(Arc::clone(&state), 100, 1000).await;
+
+        let progress = HistoryProgress::read(&state.store).unwrap();
+        assert_eq!(progress.from, 100);
+        assert_eq!(progress.to, Some(109));
+        assert_eq!(progress.next, 110);
+        assert_eq!(progress.remaining(), Some(0));
+        assert_eq!(progress.eta_seconds(), Some(0));
+    }
+
+    #[test]
+    fn test_progress_eta() {
+        let store = EventStore::new(24 * 60 * 60);
+        assert!(HistoryProgress::read(&store).is_none());
+
+        store
+            .set_sync_state(HISTORY_BACKFILL_FROM_KEY, "1000")
+            .unwrap();
+        let progress = HistoryProgress::read(&store).unwrap();
+        assert_eq!(progress.next, 1000);
+        assert_eq!(progress.remaining(), None);
+
+        store.set_sync_state(SYNC_START_KEY, "2000").unwrap();
+        store
+            .set_sync_state(HISTORY_BACKFILL_NEXT_KEY, "1500")
+            .unwrap();
+        store
+            .set_sync_state(HISTORY_BACKFILL_RATE_KEY, "10.00")
+            .unwrap();
+        let progress = HistoryProgress::read(&store).unwrap();
+        assert_eq!(progress.remaining(), Some(500));
+        assert_eq!(progress.eta_seconds(), Some(50));
+    }
+}
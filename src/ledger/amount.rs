@@ -0,0 +1,226 @@
+//! Amounts carried in the data of token-shaped events.
+//!
+//! Token events such as `transfer`, `mint`, and `burn` carry their amount as
+//! the event data, either directly as an integer ScVal or, for events with
+//! extra fields (e.g. a muxed destination ID), as the `amount` entry of a map.
+
+use stellar_xdr::curr::ScVal;
+
+/// The amount in an event's XDR-JSON data, if it has one. Amounts outside the
+/// range of an `i128` (large `u128` and 256-bit values) saturate.
+pub fn from_data(data: &serde_json::Value) -> Option<i128> {
+    if let Some(entries) = data.get("map").and_then(|m| m.as_array()) {
+        let entry = entries.iter().find(|e| {
+            e.get("key")
+                .and_then(|k| k.get("symbol"))
+                .and_then(|s| s.as_str())
+                == Some("amount")
+        })?;
+        return from_scval(entry.get("val")?);
+    }
+    from_scval(data)
+}
+
+/// The amount in an event's data, as [`from_data`] reads it from the data's
+/// XDR-JSON, without converting it.
+pub fn from_data_scval(data: &ScVal) -> Option<i128> {
+    if let ScVal::Map(Some(map)) = data {
+        let entry = map.iter().find(|entry| match &entry.key {
+            ScVal::Symbol(symbol) => symbol.as_vec().as_slice() == b"amount",
+            _ => false,
+        })?;
+        return integer_scval(&entry.val);
+    }
+    integer_scval(data)
+}
+
+/// The value of an integer ScVal, saturating as [`from_scval`] does.
+fn integer_scval(value: &ScVal) -> Option<i128> {
+    match value {
+        ScVal::U32(n) => Some((*n).into()),
+        ScVal::I32(n) => Some((*n).into()),
+        ScVal::U64(n) => Some((*n).into()),
+        ScVal::I64(n) => Some((*n).into()),
+        ScVal::Timepoint(n) => Some(n.0.into()),
+        ScVal::Duration(n) => Some(n.0.into()),
+        ScVal::I128(n) => Some((i128::from(n.hi) << 64) | i128::from(n.lo)),
+        ScVal::U128(n) => {
+            let value = (u128::from(n.hi) << 64) | u128::from(n.lo);
+            Some(i128::try_from(value).unwrap_or(i128::MAX))
+        }
+        ScVal::U256(n) => Some(if n.hi_hi == 0 && n.hi_lo == 0 {
+            let value = (u128::from(n.lo_hi) << 64) | u128::from(n.lo_lo);
+            i128::try_from(value).unwrap_or(i128::MAX)
+        } else {
+            i128::MAX
+        }),
+        ScVal::I256(n) => {
+            let low = (i128::from(n.lo_hi as i64) << 64) | i128::from(n.lo_lo);
+            // The value fits in an i128 when the high half only extends the
+            // sign of the low half.
+            let extends_sign = if low < 0 {
+                n.hi_hi == -1 && n.hi_lo == u64::MAX
+            } else {
+                n.hi_hi == 0 && n.hi_lo == 0
+            };
+            Some(match (extends_sign, n.hi_hi < 0) {
+                (true, _) => low,
+                (false, true) => i128::MIN,
+                (false, false) => i128::MAX,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The value of an integer XDR-JSON ScVal.
+fn from_scval(value: &serde_json::Value) -> Option<i128> {
+    let (kind, inner) = value.as_object()?.iter().next()?;
+    match kind.as_str() {
+        "u32" | "i32" | "u64" | "i64" | "timepoint" | "duration" => integer(inner),
+        "i128" | "u128" | "i256" | "u256" => match inner.get("hi").zip(inner.get("lo")) {
+            Some((hi, lo)) => parts(kind, hi, lo),
+            None => integer(inner),
+        },
+        _ => None,
+    }
+}
+
+/// A JSON number, or a decimal string as 64-bit and wider integers are
+/// rendered.
+fn integer(value: &serde_json::Value) -> Option<i128> {
+    if let Some(n) = value.as_i64() {
+        return Some(n.into());
+    }
+    if let Some(n) = value.as_u64() {
+        return Some(n.into());
+    }
+    let s = value.as_str()?;
+    if let Ok(n) = s.parse::<i128>() {
+        return Some(n);
+    }
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(if s.starts_with('-') {
+        i128::MIN
+    } else {
+        i128::MAX
+    })
+}
+
+/// A 128-bit value given as `hi` and `lo` 64-bit halves, where `hi` is signed
+/// for `i128`.
+fn parts(kind: &str, hi: &serde_json::Value, lo: &serde_json::Value) -> Option<i128> {
+    let lo = u128::from(lo.as_u64()?);
+    match kind {
+        "i128" => Some((i128::from(hi.as_i64()?) << 64) | lo as i128),
+        "u128" => {
+            let value = (u128::from(hi.as_u64()?) << 64) | lo;
+            Some(i128::try_from(value).unwrap_or(i128::MAX))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_data() {
+        assert_eq!(from_data(&json!({"i128": "1000000"})), Some(1_000_000));
+        assert_eq!(from_data(&json!({"i128": "-5"})), Some(-5));
+        assert_eq!(from_data(&json!({"u64": "42"})), Some(42));
+        assert_eq!(from_data(&json!({"u32": 7})), Some(7));
+        assert_eq!(
+            from_data(&json!({"u256": "1000000000000000000000000000000000000000000"})),
+            Some(i128::MAX)
+        );
+        assert_eq!(from_data(&json!({"symbol": "transfer"})), None);
+        assert_eq!(from_data(&json!("void")), None);
+
+        let muxed = json!({"map": [
+            {"key": {"symbol": "amount"}, "val": {"i128": "250"}},
+            {"key": {"symbol": "to_muxed_id"}, "val": {"u64": "9"}}
+        ]});
+        assert_eq!(from_data(&muxed), Some(250));
+    }
+
+    #[test]
+    fn test_from_data_scval_matches_from_data() {
+        use stellar_xdr::curr::{
+            Int128Parts, Int256Parts, ScMap, ScMapEntry, ScSymbol, UInt256Parts,
+        };
+
+        let symbol = |s: &str| ScVal::Symbol(ScSymbol(s.try_into().unwrap()));
+        let values = [
+            ScVal::I128(Int128Parts { hi: 0, lo: 1000 }),
+            ScVal::I128(Int128Parts {
+                hi: -1,
+                lo: u64::MAX,
+            }),
+            ScVal::U64(42),
+            ScVal::I32(-7),
+            ScVal::U256(UInt256Parts {
+                hi_hi: 0,
+                hi_lo: 1,
+                lo_hi: 0,
+                lo_lo: 0,
+            }),
+            ScVal::I256(Int256Parts {
+                hi_hi: -1,
+                hi_lo: u64::MAX,
+                lo_hi: u64::MAX,
+                lo_lo: u64::MAX - 4,
+            }),
+            ScVal::I256(Int256Parts {
+                hi_hi: -2,
+                hi_lo: 0,
+                lo_hi: 0,
+                lo_lo: 0,
+            }),
+            symbol("transfer"),
+            ScVal::Void,
+            ScVal::Map(Some(
+                ScMap::sorted_from(vec![
+                    ScMapEntry {
+                        key: symbol("amount"),
+                        val: ScVal::I128(Int128Parts { hi: 0, lo: 250 }),
+                    },
+                    ScMapEntry {
+                        key: symbol("to_muxed_id"),
+                        val: ScVal::U64(9),
+                    },
+                ])
+                .unwrap(),
+            )),
+        ];
+        for value in values {
+            let json = serde_json::to_value(&value).unwrap();
+            assert_eq!(from_data_scval(&value), from_data(&json), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_from_data_hi_lo() {
+        assert_eq!(
+            from_data(&json!({"i128": {"hi": 0, "lo": 1000000}})),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            from_data(&json!({"i128": {"hi": 1, "lo": 0}})),
+            Some(1 << 64)
+        );
+        assert_eq!(
+            from_data(&json!({"i128": {"hi": -1, "lo": u64::MAX}})),
+            Some(-1)
+        );
+        assert_eq!(
+            from_data(&json!({"u128": {"hi": u64::MAX, "lo": u64::MAX}})),
+            Some(i128::MAX)
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Optional on-disk cache of compressed ledger batch objects.
+//!
+//! Batches in the metadata store never change once written, so a batch
+//! fetched once can be served from disk for any later backfill of the same
+//! range, even after its in-memory partition has expired. Entries are keyed
+//! by object path and evicted least-recently-used first once the cache grows
+//! past its size limit.
+//!
+//! Each entry also records the `ETag` and `Last-Modified` validators it was
+//! served with. When a revalidation interval is configured, entries older than
+//! it are revalidated with a conditional request instead of being trusted
+//! indefinitely, for origins whose objects may be rewritten.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// File extension of cached objects.
+const EXTENSION: &str = "zst";
+
+/// File extension of the metadata stored beside each object.
+const META_EXTENSION: &str = "meta";
+
+/// HTTP cache validators of a fetched object.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Whether a conditional request can be made with these validators.
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EntryMeta {
+    #[serde(flatten)]
+    validators: Validators,
+    /// Unix time the object was last fetched or revalidated.
+    validated_at: i64,
+}
+
+/// An object read from the cache.
+#[derive(Debug)]
+pub struct CachedObject {
+    pub data: Vec<u8>,
+    pub validators: Validators,
+    /// Whether the entry is older than the revalidation interval and should be
+    /// checked against the origin before use.
+    pub stale: bool,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    /// File name -> (size in bytes, last-used tick).
+    entries: HashMap<String, (u64, u64)>,
+    total_bytes: u64,
+    tick: u64,
+}
+
+/// A size-bounded directory of cached objects.
+#[derive(Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    revalidate_after: Option<Duration>,
+    state: Mutex<LruState>,
+}
+
+impl DiskCache {
+    /// Open (creating if needed) a cache in `dir` holding at most
+    /// `max_bytes`. Files left by a previous run are kept, oldest-modified
+    /// first in eviction order.
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        let mut existing = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let meta = entry.metadata()?;
+            let modified = meta.modified().unwrap_or(std::time::UNIX_EPOCH);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            existing.push((modified, name, meta.len()));
+        }
+        existing.sort();
+
+        let cache = DiskCache {
+            dir,
+            max_bytes,
+            revalidate_after: None,
+            state: Mutex::new(LruState::default()),
+        };
+        {
+            let mut state = cache.state.lock().expect("disk cache lock poisoned");
+            for (_, name, size) in existing {
+                state.tick += 1;
+                let tick = state.tick;
+                state.entries.insert(name, (size, tick));
+                state.total_bytes += size;
+            }
+            cache.evict(&mut state);
+        }
+        Ok(cache)
+    }
+
+    /// Mark entries older than `interval` as stale, so they are revalidated
+    /// against the origin before use.
+    pub fn with_revalidation(mut self, interval: Duration) -> Self {
+        self.revalidate_after = Some(interval);
+        self
+    }
+
+    /// Read the object cached under `key`.
+    pub fn get(&self, key: &str) -> Option<CachedObject> {
+        let name = file_name(key);
+        {
+            let mut state = self.state.lock().expect("disk cache lock poisoned");
+            state.tick += 1;
+            let tick = state.tick;
+            state.entries.get_mut(&name)?.1 = tick;
+        }
+        let data = match std::fs::read(self.dir.join(&name)) {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::warn!(error = %e, key, "failed to read cached object");
+                self.forget(&name);
+                return None;
+            }
+        };
+        let meta = self.read_meta(&name);
+        let stale = self.revalidate_after.is_some_and(|interval| {
+            chrono::Utc::now().timestamp() - meta.validated_at >= interval.as_secs() as i64
+        });
+        Some(CachedObject {
+            data,
+            validators: meta.validators,
+            stale,
+        })
+    }
+
+    /// Record that the entry under `key` was confirmed unchanged by the
+    /// origin.
+    pub fn touch(&self, key: &str, validators: &Validators) {
+        let name = file_name(key);
+        if let Err(e) = self.write_meta(&name, validators) {
+            tracing::warn!(error = %e, key, "failed to update cached object metadata");
+        }
+    }
+
+    /// Cache `data` under `key` with the validators it was served with,
+    /// evicting older entries to stay within the size limit. Objects larger
+    /// than the limit are not cached.
+    pub fn put(&self, key: &str, data: &[u8], validators: &Validators) {
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return;
+        }
+        let name = file_name(key);
+        if let Err(e) =
+            write_atomic(&self.dir, &name, data).and_then(|()| self.write_meta(&name, validators))
+        {
+            tracing::warn!(error = %e, key, "failed to write cached object");
+            return;
+        }
+
+        let mut state = self.state.lock().expect("disk cache lock poisoned");
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((old_size, _)) = state.entries.insert(name, (size, tick)) {
+            state.total_bytes -= old_size;
+        }
+        state.total_bytes += size;
+        self.evict(&mut state);
+    }
+
+    /// Total size of cached objects, in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        self.state
+            .lock()
+            .expect("disk cache lock poisoned")
+            .total_bytes
+    }
+
+    fn read_meta(&self, name: &str) -> EntryMeta {
+        std::fs::read(self.dir.join(meta_name(name)))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_meta(&self, name: &str, validators: &Validators) -> std::io::Result<()> {
+        let meta = EntryMeta {
+            validators: validators.clone(),
+            validated_at: chrono::Utc::now().timestamp(),
+        };
+        let bytes = serde_json::to_vec(&meta).map_err(std::io::Error::other)?;
+        write_atomic(&self.dir, &meta_name(name), &bytes)
+    }
+
+    fn forget(&self, name: &str) {
+        let mut state = self.state.lock().expect("disk cache lock poisoned");
+        if let Some((size, _)) = state.entries.remove(name) {
+            state.total_bytes -= size;
+        }
+    }
+
+    fn evict(&self, state: &mut LruState) {
+        while state.total_bytes > self.max_bytes {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, tick))| *tick)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            if let Some((size, _)) = state.entries.remove(&oldest) {
+                state.total_bytes -= size;
+            }
+            if let Err(e) = std::fs::remove_file(self.dir.join(&oldest)) {
+                tracing::debug!(error = %e, file = %oldest, "failed to remove evicted object");
+            }
+            let _ = std::fs::remove_file(self.dir.join(meta_name(&oldest)));
+            metrics::counter!("ledger_disk_cache_evictions_total").increment(1);
+        }
+        metrics::gauge!("ledger_disk_cache_bytes").set(state.total_bytes as f64);
+    }
+}
+
+/// Object paths contain `/`, so files are named by a hash of the path.
+fn file_name(key: &str) -> String {
+    format!("{}.{}", hex::encode(Sha256::digest(key)), EXTENSION)
+}
+
+fn meta_name(name: &str) -> String {
+    format!("{}.{}", name, META_EXTENSION)
+}
+
+/// Write via a temporary file so readers never see a partial object.
+fn write_atomic(dir: &Path, name: &str, data: &[u8]) -> std::io::Result<()> {
+    let tmp = dir.join(format!("{}.tmp", name));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, dir.join(name))
+}
+
+static CACHE: OnceLock<DiskCache> = OnceLock::new();
+
+/// Install the process-wide cache used by ledger fetches. Only the first call
+/// has an effect.
+pub fn init(cache: DiskCache) {
+    let _ = CACHE.set(cache);
+}
+
+/// The process-wide cache, if one was installed.
+pub fn global() -> Option<&'static DiskCache> {
+    CACHE.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "stellar-events-api-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_get_put_and_lru_eviction() {
+        let dir = temp_dir("disk-cache-lru");
+        let cache = DiskCache::open(&dir, 10).unwrap();
+        assert!(cache.get("a/1").is_none());
+
+        cache.put("a/1", b"1111", &Validators::default());
+        cache.put("a/2", b"2222", &Validators::default());
+        assert_eq!(cache.get("a/1").unwrap().data, b"1111");
+
+        // Exceeds the limit: the least recently used entry (a/2) goes.
+        cache.put("a/3", b"3333", &Validators::default());
+        assert_eq!(cache.size_bytes(), 8);
+        assert!(cache.get("a/2").is_none());
+        assert!(cache.get("a/1").is_some());
+        assert!(cache.get("a/3").is_some());
+
+        // Too large to cache at all.
+        cache.put("a/4", &[0; 11], &Validators::default());
+        assert!(cache.get("a/4").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reopen_keeps_entries() {
+        let dir = temp_dir("disk-cache-reopen");
+        let validators = Validators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+        };
+        DiskCache::open(&dir, 100)
+            .unwrap()
+            .put("a/1", b"data", &validators);
+
+        let cache = DiskCache::open(&dir, 100).unwrap();
+        assert_eq!(cache.size_bytes(), 4);
+        let cached = cache.get("a/1").unwrap();
+        assert_eq!(cached.data, b"data");
+        assert_eq!(cached.validators, validators);
+        assert!(!cached.stale);
+
+        // With a zero revalidation interval every entry is stale.
+        let cache = DiskCache::open(&dir, 100)
+            .unwrap()
+            .with_revalidation(Duration::ZERO);
+        assert!(cache.get("a/1").unwrap().stale);
+
+        // Reopening with a smaller limit evicts down to it.
+        let cache = DiskCache::open(&dir, 2).unwrap();
+        assert_eq!(cache.size_bytes(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
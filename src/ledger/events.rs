@@ -1,6 +1,14 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha2::{Digest, Sha256};
 use stellar_xdr::curr::{
-    ContractEvent, ContractEventType, LedgerCloseMeta, LedgerCloseMetaBatch, TransactionMeta,
-    TransactionMetaV3, TransactionMetaV4,
+    ContractEvent, ContractEventType, FeeBumpTransactionInnerTx, GeneralizedTransactionSet,
+    LedgerCloseMeta, LedgerCloseMetaBatch, LedgerHeader, Limits, MuxedAccount, ScVal,
+    TransactionEnvelope, TransactionMeta, TransactionMetaV3, TransactionMetaV4, TransactionPhase,
+    TxSetComponent, WriteXdr,
 };
 
 pub use super::event_id::EventPhase;
@@ -13,11 +21,126 @@ pub struct ExtractedEvent {
     pub phase: EventPhase,
     pub tx_index: u32,
     pub event_index: u32,
+    /// Index of the emitting operation within its transaction, for events
+    /// emitted during an operation.
+    pub op_index: Option<u32>,
     pub tx_hash: String,
     pub contract_id: Option<String>,
     pub event_type: EventType,
     pub topics_xdr_json: Vec<serde_json::Value>,
-    pub data_xdr_json: serde_json::Value,
+    /// The data as XDR-JSON, or `None` when it is left as XDR in `xdr` and
+    /// converted only when a response or filter needs it.
+    pub data_xdr_json: Option<serde_json::Value>,
+    /// The topics and data as encoded on the ledger, when extracted from XDR.
+    pub xdr: Option<EventXdr>,
+    /// Summary of the emitting transaction's envelope, when it was found in
+    /// the ledger's transaction set. Shared by the transaction's events.
+    pub transaction: Option<Arc<TransactionSummary>>,
+}
+
+/// Summary of a transaction envelope and the fee it was charged.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TransactionSummary {
+    /// Source account (`G...` or `M...`). For a fee bump, the inner
+    /// transaction's source.
+    pub source_account: String,
+    /// Account that paid the fee of a fee bump transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_account: Option<String>,
+    pub operation_count: u32,
+    /// Maximum fee bid, in stroops.
+    pub max_fee: i64,
+    /// Fee charged, in stroops.
+    pub fee_charged: i64,
+}
+
+/// XDR `ScVal`s of an event's topics and data, serialized as base64.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(into = "Base64EventXdr", try_from = "Base64EventXdr")]
+pub struct EventXdr {
+    pub topics: Vec<Vec<u8>>,
+    pub data: Vec<u8>,
+}
+
+impl EventXdr {
+    /// The topics, base64-encoded.
+    pub fn topics_base64(&self) -> Vec<String> {
+        self.topics
+            .iter()
+            .map(|topic| BASE64.encode(topic))
+            .collect()
+    }
+
+    /// The data, base64-encoded.
+    pub fn data_base64(&self) -> String {
+        BASE64.encode(&self.data)
+    }
+
+    /// Encode an array of XDR-JSON topics, as held by the store, back to
+    /// XDR.
+    pub fn encode_topics(topics: &serde_json::Value) -> Vec<Vec<u8>> {
+        match topics {
+            serde_json::Value::Array(topics) => topics.iter().map(Self::encode_value).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Encode an XDR-JSON `ScVal` back to XDR. A value that does not decode
+    /// as an `ScVal` is encoded as empty bytes.
+    pub fn encode_value(value: &serde_json::Value) -> Vec<u8> {
+        serde_json::from_value::<ScVal>(value.clone())
+            .ok()
+            .and_then(|v| v.to_xdr(Limits::none()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// [`EventXdr`] as it is serialized.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Base64EventXdr {
+    topics: Vec<String>,
+    data: String,
+}
+
+impl From<EventXdr> for Base64EventXdr {
+    fn from(xdr: EventXdr) -> Self {
+        Self {
+            topics: xdr.topics_base64(),
+            data: xdr.data_base64(),
+        }
+    }
+}
+
+impl TryFrom<Base64EventXdr> for EventXdr {
+    type Error = base64::DecodeError;
+
+    fn try_from(xdr: Base64EventXdr) -> Result<Self, Self::Error> {
+        Ok(Self {
+            topics: xdr
+                .topics
+                .iter()
+                .map(|topic| BASE64.decode(topic))
+                .collect::<Result<_, _>>()?,
+            data: BASE64.decode(&xdr.data)?,
+        })
+    }
+}
+
+/// Header details of a ledger, kept alongside its events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LedgerInfo {
+    pub sequence: u32,
+    /// Close time, in Unix seconds.
+    pub closed_at: i64,
+    pub protocol_version: u32,
+    /// Number of transactions applied in the ledger.
+    pub tx_count: u32,
+    /// Hash of the ledger header, recomputed from the header itself.
+    #[serde(default, with = "hex::serde")]
+    pub hash: [u8; 32],
+    /// Hash of the previous ledger's header, as recorded in this header.
+    #[serde(default, with = "hex::serde")]
+    pub previous_hash: [u8; 32],
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -77,6 +200,56 @@ fn ledger_sequence_num(meta: &LedgerCloseMeta) -> u32 {
     }
 }
 
+/// Extract the protocol version from a LedgerCloseMeta.
+fn ledger_protocol_version(meta: &LedgerCloseMeta) -> u32 {
+    match meta {
+        LedgerCloseMeta::V0(v0) => v0.ledger_header.header.ledger_version,
+        LedgerCloseMeta::V1(v1) => v1.ledger_header.header.ledger_version,
+        LedgerCloseMeta::V2(v2) => v2.ledger_header.header.ledger_version,
+    }
+}
+
+/// Number of transactions applied in a LedgerCloseMeta.
+fn ledger_tx_count(meta: &LedgerCloseMeta) -> usize {
+    match meta {
+        LedgerCloseMeta::V0(v0) => v0.tx_processing.len(),
+        LedgerCloseMeta::V1(v1) => v1.tx_processing.len(),
+        LedgerCloseMeta::V2(v2) => v2.tx_processing.len(),
+    }
+}
+
+/// The header of a LedgerCloseMeta.
+fn ledger_header(meta: &LedgerCloseMeta) -> &LedgerHeader {
+    match meta {
+        LedgerCloseMeta::V0(v0) => &v0.ledger_header.header,
+        LedgerCloseMeta::V1(v1) => &v1.ledger_header.header,
+        LedgerCloseMeta::V2(v2) => &v2.ledger_header.header,
+    }
+}
+
+/// Extract the header details of ledger `seq` from a batch.
+pub fn extract_ledger_info(batch: &LedgerCloseMetaBatch, seq: u32) -> Option<LedgerInfo> {
+    let meta = batch
+        .ledger_close_metas
+        .iter()
+        .find(|m| ledger_sequence_num(m) == seq)?;
+    let header = ledger_header(meta);
+    // Hash the header rather than trusting the hash stored beside it, so a
+    // corrupt header shows up as a break in the chain of previous hashes.
+    let hash = header
+        .to_xdr(Limits::none())
+        .map(|xdr| Sha256::digest(xdr).into())
+        .unwrap_or_default();
+    Some(LedgerInfo {
+        sequence: seq,
+        closed_at: ledger_close_time(meta),
+        protocol_version: ledger_protocol_version(meta),
+        tx_count: ledger_tx_count(meta) as u32,
+        hash,
+        previous_hash: header.previous_ledger_hash.0,
+    })
+}
+
 /// Extract contract events from a single ContractEvent XDR.
 fn extract_contract_event(
     event: &ContractEvent,
@@ -85,7 +258,8 @@ fn extract_contract_event(
     Option<String>,
     EventType,
     Vec<serde_json::Value>,
-    serde_json::Value,
+    Option<serde_json::Value>,
+    Option<EventXdr>,
 ) {
     let contract_id = event
         .contract_id
@@ -93,19 +267,35 @@ fn extract_contract_event(
         .map(|id| id_cache.get_or_insert(&id.0));
     let event_type = EventType::from(event.type_);
 
-    let (topics, data) = match &event.body {
+    let (topics, data, xdr) = match &event.body {
         stellar_xdr::curr::ContractEventBody::V0(v0) => {
             let topics: Vec<serde_json::Value> = v0
                 .topics
                 .iter()
                 .map(|t| serde_json::to_value(t).unwrap_or(serde_json::Value::Null))
                 .collect();
-            let data = serde_json::to_value(&v0.data).unwrap_or(serde_json::Value::Null);
-            (topics, data)
+            let xdr = v0
+                .topics
+                .iter()
+                .map(|t| t.to_xdr(Limits::none()))
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|topics| {
+                    Ok(EventXdr {
+                        topics,
+                        data: v0.data.to_xdr(Limits::none())?,
+                    })
+                })
+                .ok();
+            // The data is converted on demand from its XDR, unless that
+            // could not be encoded.
+            let data = xdr
+                .is_none()
+                .then(|| serde_json::to_value(&v0.data).unwrap_or(serde_json::Value::Null));
+            (topics, data, xdr)
         }
     };
 
-    (contract_id, event_type, topics, data)
+    (contract_id, event_type, topics, data, xdr)
 }
 
 /// Convert a contract ID hash to Stellar strkey format (C...).
@@ -135,7 +325,8 @@ fn extract_events_from_tx_meta(
 }
 
 /// Extract events from TransactionMetaV3 (Protocol 20-21).
-/// V3 has no stages or per-operation events; all events are operation-level.
+/// V3 has no stages or per-operation events; all events are operation-level,
+/// from the single operation of a Soroban transaction.
 fn extract_events_from_v3(
     v3: &TransactionMetaV3,
     seq: u32,
@@ -154,6 +345,7 @@ fn extract_events_from_v3(
                 EventPhase::Operation,
                 tx_idx,
                 evt_idx as u32,
+                Some(0),
                 tx_hash,
                 events,
                 id_cache,
@@ -177,7 +369,7 @@ fn extract_events_from_v4(
 ) {
     // Operation-level events, flattened across all operations
     let mut op_evt_idx: u32 = 0;
-    for op_meta in v4.operations.iter() {
+    for (op_idx, op_meta) in v4.operations.iter().enumerate() {
         for contract_event in op_meta.events.iter() {
             push_event(
                 contract_event,
@@ -186,6 +378,7 @@ fn extract_events_from_v4(
                 EventPhase::Operation,
                 tx_idx,
                 op_evt_idx,
+                Some(op_idx as u32),
                 tx_hash,
                 events,
                 id_cache,
@@ -208,6 +401,7 @@ fn extract_events_from_v4(
             phase,
             tx_idx,
             evt_idx as u32,
+            None,
             tx_hash,
             events,
             id_cache,
@@ -224,11 +418,13 @@ fn push_event(
     phase: EventPhase,
     tx_idx: u32,
     evt_idx: u32,
+    op_idx: Option<u32>,
     tx_hash: &str,
     events: &mut Vec<ExtractedEvent>,
     id_cache: &mut ContractIdCache,
 ) {
-    let (contract_id, event_type, topics, data) = extract_contract_event(contract_event, id_cache);
+    let (contract_id, event_type, topics, data, xdr) =
+        extract_contract_event(contract_event, id_cache);
 
     events.push(ExtractedEvent {
         ledger_sequence: seq,
@@ -236,11 +432,14 @@ fn push_event(
         phase,
         tx_index: tx_idx,
         event_index: evt_idx,
+        op_index: op_idx,
         tx_hash: tx_hash.to_string(),
         contract_id,
         event_type,
         topics_xdr_json: topics,
         data_xdr_json: data,
+        xdr,
+        transaction: None,
     });
 }
 
@@ -265,18 +464,29 @@ impl ContractIdCache {
     }
 }
 
+/// Find the `TransactionMeta` of the transaction at `tx_index` in ledger
+/// `seq`, using the same transaction ordering as [`extract_events`].
+pub fn find_tx_meta(
+    batch: &LedgerCloseMetaBatch,
+    seq: u32,
+    tx_index: u32,
+) -> Option<&TransactionMeta> {
+    let ledger_meta = batch
+        .ledger_close_metas
+        .iter()
+        .find(|m| ledger_sequence_num(m) == seq)?;
+    let index = tx_index as usize;
+    match ledger_meta {
+        LedgerCloseMeta::V0(v0) => v0.tx_processing.get(index).map(|t| &t.tx_apply_processing),
+        LedgerCloseMeta::V1(v1) => v1.tx_processing.get(index).map(|t| &t.tx_apply_processing),
+        LedgerCloseMeta::V2(v2) => v2.tx_processing.get(index).map(|t| &t.tx_apply_processing),
+    }
+}
+
 /// Extract all events from a LedgerCloseMetaBatch.
 pub fn extract_events(batch: &LedgerCloseMetaBatch) -> Vec<ExtractedEvent> {
     // Pre-allocate based on number of transactions (heuristic: ~5 events per tx).
-    let tx_count: usize = batch
-        .ledger_close_metas
-        .iter()
-        .map(|m| match m {
-            LedgerCloseMeta::V0(v0) => v0.tx_processing.len(),
-            LedgerCloseMeta::V1(v1) => v1.tx_processing.len(),
-            LedgerCloseMeta::V2(v2) => v2.tx_processing.len(),
-        })
-        .sum();
+    let tx_count: usize = batch.ledger_close_metas.iter().map(ledger_tx_count).sum();
     let mut events = Vec::with_capacity(tx_count * 5);
     let mut id_cache = ContractIdCache::new();
 
@@ -333,6 +543,120 @@ pub fn extract_events(batch: &LedgerCloseMetaBatch) -> Vec<ExtractedEvent> {
     events
 }
 
+/// Every transaction envelope in a ledger's transaction set, in set order.
+fn ledger_envelopes(meta: &LedgerCloseMeta) -> Vec<&TransactionEnvelope> {
+    let phases = match meta {
+        LedgerCloseMeta::V0(v0) => return v0.tx_set.txs.iter().collect(),
+        LedgerCloseMeta::V1(v1) => &v1.tx_set,
+        LedgerCloseMeta::V2(v2) => &v2.tx_set,
+    };
+    let GeneralizedTransactionSet::V1(set) = phases;
+    let mut envelopes = Vec::new();
+    for phase in set.phases.iter() {
+        match phase {
+            TransactionPhase::V0(components) => {
+                for TxSetComponent::TxsetCompTxsMaybeDiscountedFee(component) in components.iter() {
+                    envelopes.extend(component.txs.iter());
+                }
+            }
+            TransactionPhase::V1(parallel) => {
+                for stage in parallel.execution_stages.iter() {
+                    for cluster in stage.0.iter() {
+                        envelopes.extend(cluster.0.iter());
+                    }
+                }
+            }
+        }
+    }
+    envelopes
+}
+
+/// Hex transaction hash -> fee charged, for every transaction in a ledger.
+fn ledger_fees_charged(meta: &LedgerCloseMeta) -> HashMap<String, i64> {
+    let results: Vec<_> = match meta {
+        LedgerCloseMeta::V0(v0) => v0.tx_processing.iter().map(|t| &t.result).collect(),
+        LedgerCloseMeta::V1(v1) => v1.tx_processing.iter().map(|t| &t.result).collect(),
+        LedgerCloseMeta::V2(v2) => v2.tx_processing.iter().map(|t| &t.result).collect(),
+    };
+    results
+        .into_iter()
+        .map(|r| (hex::encode(r.transaction_hash.0), r.result.fee_charged))
+        .collect()
+}
+
+fn muxed_strkey(account: &MuxedAccount) -> String {
+    match account {
+        MuxedAccount::Ed25519(key) => stellar_strkey::ed25519::PublicKey(key.0).to_string(),
+        MuxedAccount::MuxedEd25519(muxed) => stellar_strkey::ed25519::MuxedAccount {
+            ed25519: muxed.ed25519.0,
+            id: muxed.id,
+        }
+        .to_string(),
+    }
+}
+
+fn summarize_envelope(envelope: &TransactionEnvelope, fee_charged: i64) -> TransactionSummary {
+    let (source_account, fee_account, operation_count, max_fee) = match envelope {
+        TransactionEnvelope::TxV0(e) => (
+            stellar_strkey::ed25519::PublicKey(e.tx.source_account_ed25519.0).to_string(),
+            None,
+            e.tx.operations.len(),
+            i64::from(e.tx.fee),
+        ),
+        TransactionEnvelope::Tx(e) => (
+            muxed_strkey(&e.tx.source_account),
+            None,
+            e.tx.operations.len(),
+            i64::from(e.tx.fee),
+        ),
+        TransactionEnvelope::TxFeeBump(e) => {
+            let FeeBumpTransactionInnerTx::Tx(ref inner) = e.tx.inner_tx;
+            (
+                muxed_strkey(&inner.tx.source_account),
+                Some(muxed_strkey(&e.tx.fee_source)),
+                inner.tx.operations.len(),
+                e.tx.fee,
+            )
+        }
+    };
+    TransactionSummary {
+        source_account,
+        fee_account,
+        operation_count: operation_count as u32,
+        max_fee,
+        fee_charged,
+    }
+}
+
+/// Attach a summary of each event's transaction envelope to the events
+/// extracted from `batch`. Envelopes are matched to events by transaction
+/// hash, which depends on the network, so `network_passphrase` must be the
+/// one the ledgers were closed under.
+pub fn attach_transactions(
+    batch: &LedgerCloseMetaBatch,
+    network_passphrase: &str,
+    events: &mut [ExtractedEvent],
+) {
+    let network_id: [u8; 32] = Sha256::digest(network_passphrase).into();
+    let mut summaries: HashMap<String, Arc<TransactionSummary>> = HashMap::new();
+    for meta in batch.ledger_close_metas.iter() {
+        let fees = ledger_fees_charged(meta);
+        for envelope in ledger_envelopes(meta) {
+            let Ok(hash) = envelope.hash(network_id) else {
+                continue;
+            };
+            let hash = hex::encode(hash);
+            if let Some(&fee_charged) = fees.get(&hash) {
+                let summary = summarize_envelope(envelope, fee_charged);
+                summaries.insert(hash, Arc::new(summary));
+            }
+        }
+    }
+    for event in events {
+        event.transaction = summaries.get(&event.tx_hash).cloned();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,19 +1,86 @@
 use std::io::Cursor;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use stellar_xdr::curr::{LedgerCloseMetaBatch, Limits, ReadXdr};
+use stellar_xdr::curr::{LedgerCloseMeta, LedgerCloseMetaBatch, Limits, ReadXdr};
+use tokio::io::AsyncReadExt;
 
+use super::disk_cache::{self, DiskCache, Validators};
 use super::path::StoreConfig;
+use super::sigv4;
+use crate::redact::redact_url;
 use crate::Error;
 
+/// How ledger fetches are retried after transient origin failures.
+///
+/// Only timeouts and `5xx` responses are retried. Each retry waits a random
+/// delay of up to `initial_backoff * 2^(attempt - 1)`, capped at
+/// `max_backoff` ("full jitter"), so replicas retrying after the same blip do
+/// not hit the origin in lockstep.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts per fetch, including the first (at least 1).
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Upper bound of the delay before retry number `retry` (starting at 1).
+    fn backoff_cap(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        let cap = self.backoff_cap(retry).as_millis() as u64;
+        Duration::from_millis(rand::random_range(0..=cap))
+    }
+}
+
+static RETRY_POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+
+/// Install the process-wide retry policy for ledger fetches. Only the first
+/// call has an effect.
+pub fn init_retry_policy(policy: RetryPolicy) {
+    let _ = RETRY_POLICY.set(policy);
+}
+
+fn retry_policy() -> RetryPolicy {
+    RETRY_POLICY.get().copied().unwrap_or_default()
+}
+
 /// Fetches the store configuration from the remote endpoint.
 pub async fn fetch_config(client: &reqwest::Client, meta_url: &str) -> Result<StoreConfig, Error> {
     let url = format!("{}/.config.json", meta_url);
-    tracing::info!(url = %url, "fetching store config");
-    let resp = client.get(&url).send().await?;
-    if !resp.status().is_success() {
-        return Err(Error::ConfigNotFound(url));
-    }
-    let bytes = resp.bytes().await?;
+    tracing::info!(url = %redact_url(&url), "fetching store config");
+    let bytes = match local_path(&url) {
+        Some(path) => match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::ConfigNotFound(url));
+            }
+            Err(e) => return Err(e.into()),
+        },
+        None => {
+            let resp = send(client, client.get(&url)).await?;
+            if !resp.status().is_success() {
+                return Err(Error::ConfigNotFound(redact_url(&url)));
+            }
+            resp.bytes().await?.to_vec()
+        }
+    };
     let config: StoreConfig = serde_json::from_slice(&bytes)?;
     tracing::info!(
         ledgers_per_batch = config.ledgers_per_batch,
@@ -23,6 +90,34 @@ pub async fn fetch_config(client: &reqwest::Client, meta_url: &str) -> Result<St
     Ok(config)
 }
 
+/// Re-fetches the store configuration, sending `etag` from a previous fetch
+/// as `If-None-Match`. Returns `None` if the origin answers `304 Not
+/// Modified`, and otherwise the configuration with its new `ETag`.
+pub async fn refetch_config(
+    client: &reqwest::Client,
+    meta_url: &str,
+    etag: Option<&str>,
+) -> Result<Option<(StoreConfig, Option<String>)>, Error> {
+    let url = format!("{}/.config.json", meta_url);
+    if local_path(&url).is_some() {
+        return Ok(Some((fetch_config(client, meta_url).await?, None)));
+    }
+    let mut request = client.get(&url);
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let resp = send(client, request).await?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(Error::ConfigNotFound(redact_url(&url)));
+    }
+    let etag = header_string(&resp, reqwest::header::ETAG);
+    let config = serde_json::from_slice(&resp.bytes().await?)?;
+    Ok(Some((config, etag)))
+}
+
 /// Fetches and parses a ledger close meta batch for the given ledger sequence.
 /// Returns the decompressed XDR bytes.
 pub async fn fetch_ledger_raw(
@@ -31,19 +126,383 @@ pub async fn fetch_ledger_raw(
     config: &StoreConfig,
     ledger_sequence: u32,
 ) -> Result<Vec<u8>, Error> {
+    fetch_ledger_raw_cached(
+        client,
+        meta_url,
+        config,
+        ledger_sequence,
+        disk_cache::global(),
+    )
+    .await
+}
+
+/// Like [`fetch_ledger_raw`], reading through `disk_cache` instead of the
+/// process-wide cache. Stale cache entries are revalidated with a conditional
+/// request, and kept when the origin answers `304 Not Modified`.
+pub async fn fetch_ledger_raw_cached(
+    client: &reqwest::Client,
+    meta_url: &str,
+    config: &StoreConfig,
+    ledger_sequence: u32,
+    disk_cache: Option<&DiskCache>,
+) -> Result<Vec<u8>, Error> {
+    let compressed =
+        match open_ledger(client, meta_url, config, ledger_sequence, disk_cache).await? {
+            Compressed::Bytes(compressed) => compressed,
+            Compressed::File(file) => {
+                let mut compressed = Vec::new();
+                tokio::fs::File::from_std(file)
+                    .read_to_end(&mut compressed)
+                    .await?;
+                compressed
+            }
+            Compressed::Response(resp, store) => {
+                let compressed = resp.bytes().await?.to_vec();
+                if let Some(store) = store {
+                    store.put(&compressed);
+                }
+                compressed
+            }
+        };
+    decompress(&compressed)
+}
+
+/// Fetches and parses a ledger close meta batch for the given ledger
+/// sequence, through the process-wide disk cache. The response body is
+/// decompressed and parsed as it streams in, so neither the whole compressed
+/// nor the whole decompressed batch is held in memory (unless it is being
+/// written to the disk cache).
+pub async fn fetch_ledger_batch(
+    client: &reqwest::Client,
+    meta_url: &str,
+    config: &StoreConfig,
+    ledger_sequence: u32,
+) -> Result<LedgerCloseMetaBatch, Error> {
+    fetch_ledger_batch_cached(
+        client,
+        meta_url,
+        config,
+        ledger_sequence,
+        disk_cache::global(),
+    )
+    .await
+}
+
+/// Like [`fetch_ledger_batch`], reading through `disk_cache` instead of the
+/// process-wide cache.
+pub async fn fetch_ledger_batch_cached(
+    client: &reqwest::Client,
+    meta_url: &str,
+    config: &StoreConfig,
+    ledger_sequence: u32,
+    disk_cache: Option<&DiskCache>,
+) -> Result<LedgerCloseMetaBatch, Error> {
+    match open_ledger(client, meta_url, config, ledger_sequence, disk_cache).await? {
+        Compressed::Bytes(compressed) => parse_compressed(&compressed[..]),
+        Compressed::File(file) => tokio::task::spawn_blocking(move || parse_compressed(file))
+            .await
+            .map_err(|e| Error::Internal(format!("ledger parse task failed: {}", e)))?,
+        Compressed::Response(resp, store) => parse_response(resp, store).await,
+    }
+}
+
+/// A ledger batch's compressed bytes, wherever they are.
+enum Compressed<'a> {
+    /// Already read, from the disk cache.
+    Bytes(Vec<u8>),
+    /// In an opened local file, for a `file://` meta URL.
+    File(std::fs::File),
+    /// In an origin response body still to be read, to be written to the
+    /// disk cache once read if a store is given.
+    Response(reqwest::Response, Option<CacheStore<'a>>),
+}
+
+/// Where to write a fetched batch in the disk cache.
+struct CacheStore<'a> {
+    cache: &'a DiskCache,
+    path: String,
+    validators: Validators,
+    /// Whether this replaces a stale entry.
+    revalidated: bool,
+}
+
+impl CacheStore<'_> {
+    fn put(&self, compressed: &[u8]) {
+        if self.revalidated {
+            metrics::counter!("ledger_disk_cache_revalidations_total", "result" => "modified")
+                .increment(1);
+        } else {
+            metrics::counter!("ledger_disk_cache_misses_total").increment(1);
+        }
+        self.cache.put(&self.path, compressed, &self.validators);
+    }
+}
+
+/// Locate a ledger batch: in a local directory, in `disk_cache` if it holds a
+/// fresh (or revalidated) copy, or else in a response from the origin.
+async fn open_ledger<'a>(
+    client: &reqwest::Client,
+    meta_url: &str,
+    config: &StoreConfig,
+    ledger_sequence: u32,
+    disk_cache: Option<&'a DiskCache>,
+) -> Result<Compressed<'a>, Error> {
     let path = config.path_for_ledger(ledger_sequence);
+    if let Some(dir) = local_path(meta_url) {
+        let file = dir.join(&path);
+        tracing::debug!(path = %file.display(), ledger = ledger_sequence, "reading ledger");
+        return match tokio::fs::File::open(&file).await {
+            Ok(file) => Ok(Compressed::File(file.into_std().await)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(Error::LedgerNotFound(ledger_sequence))
+            }
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    let cached = disk_cache.and_then(|cache| cache.get(&path));
+    if let Some(ref cached) = cached {
+        if !cached.stale {
+            metrics::counter!("ledger_disk_cache_hits_total").increment(1);
+            tracing::debug!(path = %path, ledger = ledger_sequence, "ledger read from disk cache");
+            return Ok(Compressed::Bytes(cached.data.clone()));
+        }
+    }
+
     let url = format!("{}/{}", meta_url, path);
-    tracing::debug!(url = %url, ledger = ledger_sequence, "fetching ledger");
+    tracing::debug!(url = %redact_url(&url), ledger = ledger_sequence, "fetching ledger");
 
-    let resp = client.get(&url).send().await?;
+    let mut request = client.get(&url);
+    if let Some(ref cached) = cached {
+        if let Some(ref etag) = cached.validators.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = cached.validators.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let resp = send_with_retry(client, request, ledger_sequence, retry_policy()).await?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let (Some(cache), Some(cached)) = (disk_cache, cached.as_ref()) {
+            metrics::counter!("ledger_disk_cache_revalidations_total", "result" => "not_modified")
+                .increment(1);
+            cache.touch(&path, &cached.validators);
+            return Ok(Compressed::Bytes(cached.data.clone()));
+        }
+    }
     if !resp.status().is_success() {
         return Err(Error::LedgerNotFound(ledger_sequence));
     }
 
-    let compressed = resp.bytes().await?;
-    let decompressed = zstd::bulk::decompress(&compressed, 10 * 1024 * 1024)?;
+    let store = disk_cache.map(|cache| CacheStore {
+        cache,
+        validators: Validators {
+            etag: header_string(&resp, reqwest::header::ETAG),
+            last_modified: header_string(&resp, reqwest::header::LAST_MODIFIED),
+        },
+        path,
+        revalidated: cached.is_some(),
+    });
+    Ok(Compressed::Response(resp, store))
+}
+
+/// Parse a batch from a response body as it arrives: chunks are handed to a
+/// blocking task that decompresses and parses them, holding only a few
+/// chunks at a time.
+async fn parse_response(
+    mut resp: reqwest::Response,
+    store: Option<CacheStore<'_>>,
+) -> Result<LedgerCloseMetaBatch, Error> {
+    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    let parse = tokio::task::spawn_blocking(move || parse_compressed(ChunkReader::new(rx)));
+
+    let mut compressed = store.as_ref().map(|_| Vec::new());
+    let streamed = async {
+        while let Some(chunk) = resp.chunk().await? {
+            if let Some(compressed) = compressed.as_mut() {
+                compressed.extend_from_slice(&chunk);
+            }
+            if tx.send(chunk).await.is_err() {
+                // The parser stopped early, and will report why.
+                break;
+            }
+        }
+        Ok::<_, Error>(())
+    }
+    .await;
+    drop(tx);
+
+    let parsed = parse
+        .await
+        .map_err(|e| Error::Internal(format!("ledger parse task failed: {}", e)))?;
+    streamed?;
+    let batch = parsed?;
+    if let (Some(store), Some(compressed)) = (store, compressed) {
+        store.put(&compressed);
+    }
+    Ok(batch)
+}
+
+/// A blocking reader over body chunks received from a channel.
+struct ChunkReader {
+    rx: tokio::sync::mpsc::Receiver<bytes::Bytes>,
+    chunk: bytes::Bytes,
+}
+
+impl ChunkReader {
+    fn new(rx: tokio::sync::mpsc::Receiver<bytes::Bytes>) -> Self {
+        Self {
+            rx,
+            chunk: bytes::Bytes::new(),
+        }
+    }
+}
+
+impl std::io::Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk.split_to(n));
+        Ok(n)
+    }
+}
+
+/// Decompress and parse a batch from a zstd stream, without holding the
+/// decompressed bytes.
+fn parse_compressed(compressed: impl std::io::Read) -> Result<LedgerCloseMetaBatch, Error> {
+    let decoder = zstd::stream::read::Decoder::new(compressed)?;
+    let mut limited = stellar_xdr::curr::Limited::new(decoder, Limits::none());
+    Ok(LedgerCloseMetaBatch::read_xdr(&mut limited)?)
+}
+
+/// The local file a `file://` URL names, for meta stores kept in a local
+/// directory with the same layout as the bucket.
+fn local_path(url: &str) -> Option<&std::path::Path> {
+    url.strip_prefix("file://").map(std::path::Path::new)
+}
+
+/// Send a request to the origin, signing it if a SigV4 signer is installed.
+async fn send(
+    client: &reqwest::Client,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, Error> {
+    let mut request = request.build()?;
+    if let Some(signer) = sigv4::global() {
+        signer.sign(&mut request).await?;
+    }
+    Ok(client.execute(request).await?)
+}
+
+/// Send a ledger request, retrying timeouts and server errors according to
+/// `policy`. Every attempt is counted in `ledger_fetch_attempts_total` by
+/// outcome.
+async fn send_with_retry(
+    client: &reqwest::Client,
+    request: reqwest::RequestBuilder,
+    ledger_sequence: u32,
+    policy: RetryPolicy,
+) -> Result<reqwest::Response, Error> {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let Some(builder) = request.try_clone() else {
+            return send(client, request).await;
+        };
+        let result = send(client, builder).await;
+        let (outcome, retryable) = match &result {
+            Ok(resp) if resp.status().is_server_error() => ("server_error", true),
+            Ok(resp) if resp.status().is_success() => ("success", false),
+            Ok(resp) if resp.status() == reqwest::StatusCode::NOT_MODIFIED => {
+                ("not_modified", false)
+            }
+            Ok(resp) if resp.status() == reqwest::StatusCode::NOT_FOUND => ("not_found", false),
+            Ok(_) => ("client_error", false),
+            Err(Error::Http(e)) if e.is_timeout() => ("timeout", true),
+            Err(_) => ("error", false),
+        };
+        metrics::counter!("ledger_fetch_attempts_total", "outcome" => outcome).increment(1);
 
-    Ok(decompressed)
+        if !retryable || attempt >= max_attempts {
+            return result;
+        }
+        let delay = policy.backoff(attempt);
+        tracing::debug!(
+            ledger = ledger_sequence,
+            attempt,
+            outcome,
+            delay_ms = delay.as_millis() as u64,
+            "retrying ledger fetch"
+        );
+        metrics::counter!("ledger_fetch_retries_total").increment(1);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn decompress(compressed: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(zstd::bulk::decompress(compressed, 10 * 1024 * 1024)?)
+}
+
+fn header_string(resp: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Fetches a single ledger from a Stellar RPC server's `getLedgers` method,
+/// as a batch holding only that ledger. Fails with
+/// [`Error::LedgerNotFound`] if the server does not have the ledger, either
+/// because it is not closed yet or is older than the server's retention.
+pub async fn fetch_ledger_rpc(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    ledger_sequence: u32,
+) -> Result<LedgerCloseMetaBatch, Error> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "getLedgers",
+        "params": {
+            "startLedger": ledger_sequence,
+            "pagination": { "limit": 1 },
+        },
+    });
+    let resp = client
+        .post(rpc_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        return Err(Error::Internal(format!(
+            "RPC getLedgers returned {}",
+            resp.status()
+        )));
+    }
+    let body: serde_json::Value = serde_json::from_slice(&resp.bytes().await?)?;
+    let ledger = body
+        .pointer("/result/ledgers/0")
+        .filter(|ledger| {
+            ledger.get("sequence").and_then(|v| v.as_u64()) == Some(u64::from(ledger_sequence))
+        })
+        .ok_or(Error::LedgerNotFound(ledger_sequence))?;
+    let meta = ledger
+        .get("metadataXdr")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::Internal("RPC ledger has no metadataXdr".to_string()))?;
+    let meta = LedgerCloseMeta::from_xdr_base64(meta, Limits::none())?;
+    Ok(LedgerCloseMetaBatch {
+        start_sequence: ledger_sequence,
+        end_sequence: ledger_sequence,
+        ledger_close_metas: vec![meta].try_into()?,
+    })
 }
 
 /// Parse decompressed XDR bytes into a LedgerCloseMetaBatch.
@@ -58,6 +517,22 @@ pub fn parse_ledger_batch(data: &[u8]) -> Result<LedgerCloseMetaBatch, Error> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        assert_eq!(policy.backoff_cap(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_cap(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff_cap(8), Duration::from_secs(1));
+        assert_eq!(policy.backoff_cap(100), Duration::from_secs(1));
+        for retry in 1..10 {
+            assert!(policy.backoff(retry) <= policy.backoff_cap(retry));
+        }
+    }
+
     #[test]
     fn test_parse_empty_batch_fails() {
         let result = parse_ledger_batch(&[]);
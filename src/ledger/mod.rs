@@ -1,4 +1,8 @@
+pub mod amount;
+pub mod disk_cache;
 pub mod event_id;
 pub mod events;
 pub mod fetch;
 pub mod path;
+pub mod sac;
+pub mod sigv4;
@@ -1,7 +1,9 @@
+use std::sync::{Arc, RwLock};
+
 use serde::Deserialize;
 
 /// Configuration from the SEP-54 `.config.json` file.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StoreConfig {
     pub network_passphrase: String,
@@ -46,6 +48,77 @@ impl StoreConfig {
     }
 }
 
+/// A [`StoreConfig`] that a refresh can replace while it is in use, when the
+/// bucket layout changes. The network passphrase is fixed for its lifetime.
+#[derive(Debug)]
+pub struct SharedStoreConfig {
+    network_passphrase: String,
+    current: RwLock<Arc<StoreConfig>>,
+    /// The network named by the most recent replacement config, when it was
+    /// another network's.
+    foreign_network: RwLock<Option<String>>,
+}
+
+impl SharedStoreConfig {
+    pub fn new(config: StoreConfig) -> Self {
+        Self {
+            network_passphrase: config.network_passphrase.clone(),
+            current: RwLock::new(Arc::new(config)),
+            foreign_network: RwLock::new(None),
+        }
+    }
+
+    /// The network passphrase the store was first configured with.
+    pub fn network_passphrase(&self) -> &str {
+        &self.network_passphrase
+    }
+
+    /// The current configuration.
+    pub fn get(&self) -> Arc<StoreConfig> {
+        Arc::clone(&self.current.read().expect("store config lock poisoned"))
+    }
+
+    /// The network passphrase of the most recent replacement config, if it
+    /// was for another network than this one.
+    pub fn foreign_network(&self) -> Option<String> {
+        self.foreign_network
+            .read()
+            .expect("store config lock poisoned")
+            .clone()
+    }
+
+    /// Replace the configuration, returning whether it changed. Fails,
+    /// keeping the current configuration and recording the other network in
+    /// [`Self::foreign_network`], if `config` is for another network.
+    pub fn replace(&self, config: StoreConfig) -> Result<bool, crate::Error> {
+        let mut foreign_network = self
+            .foreign_network
+            .write()
+            .expect("store config lock poisoned");
+        if config.network_passphrase != self.network_passphrase {
+            let error = crate::Error::Internal(format!(
+                "store config is for network '{}', expected '{}'",
+                config.network_passphrase, self.network_passphrase
+            ));
+            *foreign_network = Some(config.network_passphrase);
+            return Err(error);
+        }
+        *foreign_network = None;
+        let mut current = self.current.write().expect("store config lock poisoned");
+        if **current == config {
+            return Ok(false);
+        }
+        *current = Arc::new(config);
+        Ok(true)
+    }
+}
+
+impl From<StoreConfig> for SharedStoreConfig {
+    fn from(config: StoreConfig) -> Self {
+        Self::new(config)
+    }
+}
+
 /// Default configuration matching the pubnet S3 bucket layout.
 impl Default for StoreConfig {
     fn default() -> Self {
@@ -63,6 +136,33 @@ impl Default for StoreConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_shared_config_replace() {
+        let shared = SharedStoreConfig::new(StoreConfig::default());
+        assert!(!shared.replace(StoreConfig::default()).unwrap());
+
+        let relaid = StoreConfig {
+            ledgers_per_batch: 64,
+            ..Default::default()
+        };
+        assert!(shared.replace(relaid.clone()).unwrap());
+        assert_eq!(*shared.get(), relaid);
+
+        let other_network = StoreConfig {
+            network_passphrase: "Test SDF Network ; September 2015".to_string(),
+            ..Default::default()
+        };
+        assert!(shared.replace(other_network.clone()).is_err());
+        assert_eq!(*shared.get(), relaid);
+        assert_eq!(
+            shared.foreign_network(),
+            Some(other_network.network_passphrase)
+        );
+
+        shared.replace(relaid).unwrap();
+        assert_eq!(shared.foreign_network(), None);
+    }
+
     #[test]
     fn test_path_single_ledger_batch() {
         let config = StoreConfig {
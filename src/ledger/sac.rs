@@ -0,0 +1,135 @@
+//! Recognition of Stellar Asset Contract (SAC) events.
+//!
+//! The built-in token contract for a classic asset tags each of its events
+//! with the asset, in SEP-11 form (`native` or `CODE:ISSUER`), as the last
+//! topic. Any contract can emit a look-alike event, so an event is only
+//! attributed to an asset once its contract ID has been checked against the
+//! ID the network derives for that asset's SAC (see [`contract_id`]).
+
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{
+    AccountId, AlphaNum12, AlphaNum4, Asset, AssetCode12, AssetCode4, ContractIdPreimage, Hash,
+    HashIdPreimage, HashIdPreimageContractId, Limits, PublicKey, Uint256, WriteXdr,
+};
+
+/// First-topic symbols of the events emitted by the asset contract.
+const SAC_EVENTS: &[&str] = &[
+    "transfer",
+    "mint",
+    "burn",
+    "clawback",
+    "approve",
+    "set_admin",
+    "set_authorized",
+];
+
+/// The SEP-11 asset named by an event's XDR-JSON topics, if they have the
+/// shape of an asset contract event.
+pub fn asset_from_topics(topics: &[serde_json::Value]) -> Option<String> {
+    if topics.len() < 2 {
+        return None;
+    }
+    let name = topics[0].get("symbol")?.as_str()?;
+    if !SAC_EVENTS.contains(&name) {
+        return None;
+    }
+    let asset = topics.last()?.get("string")?.as_str()?;
+    parse_asset(asset).map(|_| asset.to_string())
+}
+
+/// Split a SEP-11 asset into its code and issuer. The native asset has no
+/// issuer.
+pub fn split_asset(asset: &str) -> Option<(&str, Option<&str>)> {
+    if asset == "native" {
+        return Some(("XLM", None));
+    }
+    let (code, issuer) = asset.split_once(':')?;
+    Some((code, Some(issuer)))
+}
+
+fn parse_asset(asset: &str) -> Option<Asset> {
+    let (code, issuer) = split_asset(asset)?;
+    let Some(issuer) = issuer else {
+        return Some(Asset::Native);
+    };
+    if code.is_empty() || code.len() > 12 || !code.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let issuer = stellar_strkey::ed25519::PublicKey::from_string(issuer).ok()?;
+    let issuer = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(issuer.0)));
+    if code.len() <= 4 {
+        let mut bytes = [0u8; 4];
+        bytes[..code.len()].copy_from_slice(code.as_bytes());
+        Some(Asset::CreditAlphanum4(AlphaNum4 {
+            asset_code: AssetCode4(bytes),
+            issuer,
+        }))
+    } else {
+        let mut bytes = [0u8; 12];
+        bytes[..code.len()].copy_from_slice(code.as_bytes());
+        Some(Asset::CreditAlphanum12(AlphaNum12 {
+            asset_code: AssetCode12(bytes),
+            issuer,
+        }))
+    }
+}
+
+/// The contract ID (`C...`) of the asset contract for a SEP-11 asset on the
+/// network with `network_passphrase`.
+pub fn contract_id(asset: &str, network_passphrase: &str) -> Option<String> {
+    let preimage = HashIdPreimage::ContractId(HashIdPreimageContractId {
+        network_id: Hash(Sha256::digest(network_passphrase).into()),
+        contract_id_preimage: ContractIdPreimage::Asset(parse_asset(asset)?),
+    });
+    let xdr = preimage.to_xdr(Limits::none()).ok()?;
+    Some(stellar_strkey::Contract(Sha256::digest(xdr).into()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBNET: &str = "Public Global Stellar Network ; September 2015";
+    const USDC: &str = "USDC:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN";
+
+    #[test]
+    fn test_contract_id() {
+        assert_eq!(
+            contract_id("native", PUBNET).unwrap(),
+            "CAS3J7GYLGXMF6TDJBBYYSE3HQ6BBSMLNUQ34T6TZMYMW2EVH34XOWMA"
+        );
+        assert_eq!(
+            contract_id(USDC, PUBNET).unwrap(),
+            "CCW67TSZV3SSS2HXMBQ5JFGCKJNXKZM7UQUWUZPUTHXSTZLEO7SJMI75"
+        );
+        assert!(contract_id("USDC:notanaccount", PUBNET).is_none());
+        assert!(contract_id(
+            "TOOLONGASSETCODE:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN",
+            PUBNET
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_asset_from_topics() {
+        let topics = vec![
+            serde_json::json!({"symbol": "transfer"}),
+            serde_json::json!({"address": "GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN"}),
+            serde_json::json!({"string": USDC}),
+        ];
+        assert_eq!(asset_from_topics(&topics).as_deref(), Some(USDC));
+
+        let native = vec![
+            serde_json::json!({"symbol": "mint"}),
+            serde_json::json!({"string": "native"}),
+        ];
+        assert_eq!(asset_from_topics(&native).as_deref(), Some("native"));
+
+        let other = vec![
+            serde_json::json!({"symbol": "swap"}),
+            serde_json::json!({"string": USDC}),
+        ];
+        assert_eq!(asset_from_topics(&other), None);
+        assert_eq!(asset_from_topics(&topics[..1]), None);
+    }
+}
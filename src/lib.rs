@@ -1,10 +1,21 @@
 pub mod api;
+pub mod bloom;
+pub mod checkpoint;
+pub mod client;
 pub mod db;
+pub mod history;
 pub mod ledger;
+pub mod redact;
+pub mod server;
+pub mod snapshot;
+pub mod storage;
 pub mod sync;
+pub mod telemetry;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 
 use db::EventStore;
-use ledger::path::StoreConfig;
+use ledger::path::SharedStoreConfig;
 
 /// Shared application state.
 ///
@@ -14,16 +25,20 @@ use ledger::path::StoreConfig;
 /// writes only briefly lock a single shard.
 pub struct AppState {
     pub store: EventStore,
-    pub config: StoreConfig,
+    /// The meta store's configuration, refreshed periodically.
+    pub config: SharedStoreConfig,
     pub meta_url: String,
     pub client: reqwest::Client,
+    /// Peer instance whose cached ledgers are imported before falling back
+    /// to `meta_url` during backfill.
+    pub peer: Option<crate::snapshot::Peer>,
 }
 
 /// Application-wide error type.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("HTTP request failed: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(reqwest::Error),
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -34,6 +49,12 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("storage error: {0}")]
+    Storage(#[from] rusqlite::Error),
+
+    #[error("storage error: {0}")]
+    Redb(#[from] redb::Error),
+
     #[error("ledger {0} not found")]
     LedgerNotFound(u32),
 
@@ -42,4 +63,21 @@ pub enum Error {
 
     #[error("internal error: {0}")]
     Internal(String),
+
+    /// An error from a ledger fetch shared by several concurrent callers.
+    #[error(transparent)]
+    Shared(std::sync::Arc<Error>),
+
+    #[error("API error ({status} {code}): {message}")]
+    Api {
+        status: u16,
+        code: String,
+        message: String,
+    },
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(redact::redact_reqwest_error(e))
+    }
 }
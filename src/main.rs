@@ -1,16 +1,99 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use metrics_exporter_prometheus::Matcher;
 use stellar_events_api::api;
-use stellar_events_api::db::EventStore;
-use stellar_events_api::ledger::fetch::fetch_config;
-use stellar_events_api::sync::run_sync;
+use stellar_events_api::api::access::{ip_filter, IpAccessControl};
+use stellar_events_api::api::base_path;
+use stellar_events_api::api::readiness::{self, ReadinessPolicy};
+use stellar_events_api::db::{CacheSource, EventStore, RetentionRule};
+use stellar_events_api::history;
+use stellar_events_api::ledger::disk_cache::{self, DiskCache};
+use stellar_events_api::ledger::fetch::{self, fetch_config, RetryPolicy};
+use stellar_events_api::ledger::sigv4::{self, Signer};
+use stellar_events_api::redact::{self, RedactionPolicy};
+use stellar_events_api::server::{self, ServerOptions};
+use stellar_events_api::snapshot;
+use stellar_events_api::storage::{RedbBackend, SqliteBackend, StorageBackend};
+use stellar_events_api::sync;
+use stellar_events_api::sync::{run_sync, SyncOptions, TipSource, DEFAULT_HORIZON_URL};
+use stellar_events_api::telemetry;
 use stellar_events_api::AppState;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
 const DEFAULT_META_URL: &str =
     "https://aws-public-blockchain.s3.us-east-2.amazonaws.com/v1.1/stellar/ledgers/pubnet";
 
+/// Where the event store keeps its cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum StoreKind {
+    /// In memory only; the cache is lost on restart
+    Memory,
+    /// In memory, written through to a SQLite database at --db-path
+    Sqlite,
+    /// In memory, written through to an embedded redb key-value database at
+    /// --db-path, with events keyed individually and indexed by contract and
+    /// first topic
+    Redb,
+}
+
+/// One-off commands run against the configured store instead of serving.
+#[derive(Subcommand)]
+enum Command {
+    /// Write a ledger's partition to a file, fetching the ledger from
+    /// --meta-url first if it is not cached
+    ExportPartition {
+        /// Ledger sequence to export
+        ledger: u32,
+        /// File to write the partition to
+        path: std::path::PathBuf,
+    },
+    /// Add partitions from files written by export-partition to the
+    /// database given by --store, which must be sqlite or redb
+    ImportPartition {
+        /// Files to import
+        #[arg(required = true)]
+        paths: Vec<std::path::PathBuf>,
+    },
+}
+
+/// A `NAME=VALUE` pair naming a URL or setting for an additional network.
+#[derive(Debug, Clone)]
+struct NetworkSpec {
+    name: String,
+    value: String,
+}
+
+impl std::str::FromStr for NetworkSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE: {}", s))?;
+        if name.is_empty()
+            || !name
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            return Err(format!(
+                "network name must be lowercase letters, digits, and '-': {}",
+                name
+            ));
+        }
+        if value.is_empty() {
+            return Err(format!("missing value for network '{}'", name));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
 #[derive(Parser)]
 #[command(
     name = "stellar-events-api",
@@ -22,93 +105,1019 @@ struct Cli {
     #[arg(long, default_value = "3000", env = "PORT")]
     port: u16,
 
-    /// Bind address
-    #[arg(long, default_value = "0.0.0.0", env = "BIND_ADDRESS")]
-    bind: String,
+    /// Bind address, either an IP (combined with --port) or an IP:port pair.
+    /// Repeat (or comma-separate) to listen on several addresses
+    #[arg(
+        long,
+        default_value = "0.0.0.0",
+        env = "BIND_ADDRESS",
+        value_delimiter = ','
+    )]
+    bind: Vec<String>,
+
+    /// Separate bind address(es) for the metrics endpoint. When set, /metrics
+    /// is served only on these listeners instead of the public ones
+    #[arg(long, env = "METRICS_BIND_ADDRESS", value_delimiter = ',')]
+    metrics_bind: Vec<String>,
+
+    /// OTLP/HTTP endpoint to export traces to (e.g.
+    /// http://localhost:4318/v1/traces). Also gives the request-latency
+    /// histograms buckets with trace-ID exemplars, served on /metrics to
+    /// scrapers that accept OpenMetrics
+    #[arg(long, env = "OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
+    /// Separate bind address(es) for the admin endpoints. When set, /admin
+    /// is served only on these listeners instead of the public ones
+    #[arg(
+        long,
+        env = "ADMIN_BIND_ADDRESS",
+        value_delimiter = ',',
+        requires = "admin_secret"
+    )]
+    admin_bind: Vec<String>,
+
+    /// Path prefix to serve the API under, e.g. /stellar-events. Links in
+    /// responses include the prefix
+    #[arg(long, default_value = "", env = "BASE_PATH")]
+    base_path: String,
+
+    /// Keep HTTP/1 connections alive between requests
+    #[arg(
+        long,
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        env = "HTTP1_KEEP_ALIVE"
+    )]
+    http1_keep_alive: bool,
+
+    /// Seconds to wait for a client to send HTTP/1 request headers before
+    /// closing the connection
+    #[arg(long, default_value = "30", env = "HTTP1_HEADER_READ_TIMEOUT_SECS")]
+    http1_header_read_timeout_secs: u64,
+
+    /// Seconds between HTTP/2 keep-alive pings (unset disables pings)
+    #[arg(long, env = "HTTP2_KEEP_ALIVE_INTERVAL_SECS")]
+    http2_keep_alive_interval_secs: Option<u64>,
+
+    /// Seconds to wait for an HTTP/2 keep-alive ping acknowledgement
+    #[arg(long, default_value = "20", env = "HTTP2_KEEP_ALIVE_TIMEOUT_SECS")]
+    http2_keep_alive_timeout_secs: u64,
+
+    /// Maximum concurrent streams per HTTP/2 connection
+    #[arg(long, default_value = "200", env = "HTTP2_MAX_CONCURRENT_STREAMS")]
+    http2_max_concurrent_streams: u32,
 
-    /// Base URL for the ledger metadata store
+    /// Maximum open connections per listener (unset for no limit)
+    #[arg(long, env = "MAX_CONNECTIONS")]
+    max_connections: Option<usize>,
+
+    /// File of CIDR allow/deny rules for client IPs. Reloaded on SIGHUP
+    #[arg(long, env = "IP_ACCESS_FILE")]
+    ip_access_file: Option<std::path::PathBuf>,
+
+    /// Take the client IP from the last X-Forwarded-For entry when applying
+    /// IP rules (only enable behind a trusted reverse proxy)
+    #[arg(long, env = "TRUST_FORWARDED_FOR")]
+    trust_forwarded_for: bool,
+
+    /// Shared secret for signing admin requests. Admin endpoints are
+    /// disabled when unset
+    #[arg(long, env = "ADMIN_SECRET", hide_env_values = true)]
+    admin_secret: Option<String>,
+
+    /// Base URL of a peer instance to copy cached ledgers from at startup,
+    /// instead of fetching them from the archive. The peer must have admin
+    /// endpoints enabled with the same --admin-secret
+    #[arg(long, env = "WARM_FROM", requires = "admin_secret")]
+    warm_from: Option<String>,
+
+    /// Base URL of a peer instance to ask for missing ledgers before fetching
+    /// them from --meta-url during backfill. The peer must have admin
+    /// endpoints enabled with the same --admin-secret
+    #[arg(long, env = "PEER_URL", requires = "admin_secret")]
+    peer_url: Option<String>,
+
+    /// Replace entire request query strings in logs, not just sensitive
+    /// parameters such as API keys and signatures
+    #[arg(long, env = "REDACT_QUERY_STRINGS")]
+    redact_query_strings: bool,
+
+    /// Base URL for the ledger metadata store, or a file:// URL of a local
+    /// directory with the same layout
     #[arg(long, default_value = DEFAULT_META_URL, env = "META_URL")]
     meta_url: String,
 
+    /// Network passphrase the metadata store must be for. Startup fails if
+    /// its .config.json names another network or cannot be fetched
+    #[arg(long, env = "NETWORK_PASSPHRASE")]
+    network_passphrase: Option<String>,
+
+    /// Seconds between re-fetches of the metadata store's .config.json, to
+    /// pick up a changed bucket layout (0 reads it only at startup)
+    #[arg(long, default_value = "3600", env = "CONFIG_REFRESH_SECS")]
+    config_refresh_secs: u64,
+
+    /// Sign requests to --meta-url with AWS SigV4, for private S3 buckets.
+    /// Credentials are read from AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY
+    /// (and AWS_SESSION_TOKEN), or from the EC2 instance profile
+    #[arg(long, env = "META_SIGV4")]
+    meta_sigv4: bool,
+
+    /// Attempts per ledger fetch, including the first. Timeouts and 5xx
+    /// responses from --meta-url are retried with jittered exponential backoff
+    #[arg(long, default_value = "3", env = "FETCH_MAX_ATTEMPTS")]
+    fetch_max_attempts: u32,
+
+    /// Upper bound of the delay before the first fetch retry, in
+    /// milliseconds (doubles on each further retry)
+    #[arg(long, default_value = "200", env = "FETCH_RETRY_INITIAL_BACKOFF_MS")]
+    fetch_retry_initial_backoff_ms: u64,
+
+    /// Maximum delay between fetch retries, in milliseconds
+    #[arg(long, default_value = "5000", env = "FETCH_RETRY_MAX_BACKOFF_MS")]
+    fetch_retry_max_backoff_ms: u64,
+
+    /// AWS region of the bucket at --meta-url, used when signing requests
+    #[arg(long, default_value = "us-east-1", env = "AWS_REGION")]
+    aws_region: String,
+
+    /// Horizon server used to discover the latest network ledger
+    #[arg(long, default_value = DEFAULT_HORIZON_URL, env = "HORIZON_URL")]
+    horizon_url: String,
+
+    /// Stellar RPC server used to discover the latest network ledger (takes
+    /// precedence over --horizon-url), and to sync ledgers the meta store
+    /// does not have yet
+    #[arg(long, env = "RPC_URL")]
+    rpc_url: Option<String>,
+
+    /// Additional network to serve under /networks/{name}, as NAME=META_URL,
+    /// with its own in-memory cache and sync. Repeat (or comma-separate) to
+    /// serve several
+    #[arg(long = "network", env = "NETWORKS", value_delimiter = ',')]
+    networks: Vec<NetworkSpec>,
+
+    /// Horizon server used to discover an additional network's latest
+    /// ledger, as NAME=URL (defaults to SDF's Horizon for pubnet, testnet,
+    /// and futurenet)
+    #[arg(
+        long = "network-horizon-url",
+        env = "NETWORK_HORIZON_URLS",
+        value_delimiter = ','
+    )]
+    network_horizon_urls: Vec<NetworkSpec>,
+
+    /// Stellar RPC server for an additional network, as NAME=URL, used like
+    /// --rpc-url (takes precedence over --network-horizon-url)
+    #[arg(
+        long = "network-rpc-url",
+        env = "NETWORK_RPC_URLS",
+        value_delimiter = ','
+    )]
+    network_rpc_urls: Vec<NetworkSpec>,
+
+    /// Network passphrase an additional network's metadata store must be
+    /// for, as NAME=PASSPHRASE. Startup fails if its .config.json names
+    /// another network, and the network stops reporting ready if a refreshed
+    /// config does
+    #[arg(
+        long = "network-passphrase-for",
+        env = "NETWORK_PASSPHRASES",
+        value_delimiter = ','
+    )]
+    network_passphrases: Vec<NetworkSpec>,
+
     /// Ledger sequence to start syncing from (if not resuming)
     #[arg(long, env = "START_LEDGER")]
     start_ledger: Option<u32>,
 
+    /// Number of ledgers behind the discovered network tip to start syncing
+    /// from (ignored when resuming or when --start-ledger is set)
+    #[arg(
+        long,
+        default_value = "10",
+        env = "START_OFFSET",
+        conflicts_with = "start_ledger"
+    )]
+    start_offset: u32,
+
+    /// Ledger sequence to stop syncing at; the server keeps serving cached
+    /// data once it is reached
+    #[arg(long, env = "END_LEDGER")]
+    end_ledger: Option<u32>,
+
+    /// Exit once sync reaches --end-ledger instead of continuing to serve,
+    /// for one-off extractions of a fixed range into --store or
+    /// --snapshot-path
+    #[arg(long, env = "EXIT_AT_END_LEDGER", requires = "end_ledger")]
+    exit_at_end_ledger: bool,
+
+    /// Ledger sequence to backfill history from, up to where sync started,
+    /// in a background task separate from sync (unset disables the history
+    /// backfill)
+    #[arg(long, env = "BACKFILL_FROM")]
+    backfill_from: Option<u32>,
+
+    /// Maximum ledgers per second checked by the history backfill
+    #[arg(long, default_value = "20", env = "BACKFILL_RATE")]
+    backfill_rate: u32,
+
     /// Number of ledgers to fetch concurrently during sync
     #[arg(long, default_value = "10", env = "PARALLEL_FETCHES")]
     parallel_fetches: u32,
 
+    /// Lag behind the network tip, in ledgers, above which sync switches to
+    /// catch-up mode (unset disables catch-up mode)
+    #[arg(long, env = "CATCHUP_THRESHOLD")]
+    catchup_threshold: Option<u32>,
+
+    /// Most ledgers to fetch concurrently while catching up; catch-up starts
+    /// at --parallel-fetches and doubles after each successful batch up to
+    /// this cap, halving after fetch errors
+    #[arg(long, default_value = "50", env = "CATCHUP_PARALLEL_FETCHES")]
+    catchup_parallel_fetches: u32,
+
+    /// Contract IDs to export per-contract ingestion metrics for
+    /// (comma-separated)
+    #[arg(long, env = "METRICS_CONTRACTS", value_delimiter = ',')]
+    metrics_contracts: Vec<String>,
+
+    /// Directory for caching compressed ledger batches on disk, so expired
+    /// ledgers can be backfilled again without refetching them (unset
+    /// disables the disk cache)
+    #[arg(long, env = "DISK_CACHE_DIR")]
+    disk_cache_dir: Option<std::path::PathBuf>,
+
+    /// Maximum size of the disk cache, in megabytes
+    #[arg(long, default_value = "1024", env = "DISK_CACHE_MAX_MB")]
+    disk_cache_max_mb: u64,
+
+    /// Seconds after which disk-cached batches are revalidated against the
+    /// origin with a conditional request (unset trusts them indefinitely)
+    #[arg(long, env = "DISK_CACHE_REVALIDATE_SECS")]
+    disk_cache_revalidate_secs: Option<u64>,
+
+    /// Largest lag behind the network tip, in ledgers, at which
+    /// /health/ready still reports the instance as ready
+    #[arg(long, default_value = "10", env = "READY_MAX_LAG")]
+    ready_max_lag: u32,
+
     /// How long to keep cached ledger data, in days
     #[arg(long, default_value = "1", env = "CACHE_TTL_DAYS")]
     cache_ttl_days: u32,
+
+    /// How long to keep ledgers backfilled while scanning older ledgers for
+    /// a query, export, or backfill job, in seconds (unset uses
+    /// --cache-ttl-days)
+    #[arg(long, env = "BACKFILL_TTL_SECS")]
+    backfill_ttl_secs: Option<u32>,
+
+    /// How long to keep ledgers fetched for a query or lookup that names
+    /// them, in seconds (unset uses --cache-ttl-days)
+    #[arg(long, env = "PINNED_TTL_SECS")]
+    pinned_ttl_secs: Option<u32>,
+
+    /// Refresh a cached ledger's expiry to a full TTL each time a query
+    /// reads it, so ledgers in active use stay cached while untouched ones
+    /// expire
+    #[arg(long, env = "SLIDING_TTL")]
+    sliding_ttl: bool,
+
+    /// Keep a contract's events for longer than the cache TTL, measured from
+    /// their ledger's close time, e.g. `contract:CAAA...=30d` (units s, m,
+    /// h, or d). Repeat (or comma-separate) for several contracts
+    #[arg(long, env = "RETAIN", value_delimiter = ',')]
+    retain: Vec<RetentionRule>,
+
+    /// Most ledgers to cache at once; inserting beyond it drops the oldest
+    /// cached ledgers regardless of TTL (unset leaves the cache bounded only
+    /// by TTL). With --store sqlite or redb, the most held in memory: the
+    /// oldest are left in the database until they expire and read from there
+    #[arg(long, env = "MAX_CACHED_LEDGERS")]
+    max_cached_ledgers: Option<usize>,
+
+    /// Storage for the event cache: `memory`, or `sqlite` or `redb` to also
+    /// keep it in a database that is restored from on restart
+    #[arg(long, value_enum, default_value = "memory", env = "STORE")]
+    store: StoreKind,
+
+    /// Path of the database used with --store sqlite or redb
+    #[arg(long, default_value = "events.db", env = "DB_PATH")]
+    db_path: std::path::PathBuf,
+
+    /// Directory to spill cached ledgers to when they go unread, freeing
+    /// memory until they are read again (unset keeps every cached ledger in
+    /// memory)
+    #[arg(long, env = "SPILL_DIR")]
+    spill_dir: Option<std::path::PathBuf>,
+
+    /// Seconds a cached ledger goes unread before it is spilled to
+    /// --spill-dir
+    #[arg(long, default_value = "3600", env = "SPILL_AFTER_SECS")]
+    spill_after_secs: u64,
+
+    /// Compress cached ledgers more than this many ledgers behind the latest
+    /// into zstd blocks during the hourly cleanup, decompressed on each read
+    /// (unset keeps them uncompressed); an alternative to
+    /// --compact-after-ledgers
+    #[arg(
+        long,
+        env = "COMPRESS_AFTER_LEDGERS",
+        conflicts_with = "compact_after_ledgers"
+    )]
+    compress_after_ledgers: Option<u32>,
+
+    /// Merge cached ledgers into segments of 1024 consecutive ledgers during
+    /// the hourly cleanup, once a segment ends more than this many ledgers
+    /// behind the latest (unset disables compaction)
+    #[arg(long, env = "COMPACT_AFTER_LEDGERS")]
+    compact_after_ledgers: Option<u32>,
+
+    /// File to record the sync position in whenever it advances, and to
+    /// resume from on startup (unset relies on --store or --snapshot-path)
+    #[arg(long, env = "CHECKPOINT_PATH")]
+    checkpoint_path: Option<std::path::PathBuf>,
+
+    /// File to periodically snapshot the cache and sync state to, and to
+    /// reload them from on startup (unset disables snapshots)
+    #[arg(long, env = "SNAPSHOT_PATH")]
+    snapshot_path: Option<std::path::PathBuf>,
+
+    /// Seconds between snapshots to --snapshot-path
+    #[arg(long, default_value = "300", env = "SNAPSHOT_INTERVAL_SECS")]
+    snapshot_interval_secs: u64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    let cli = Cli::parse();
+
+    let (tracer_provider, otlp_layer) = match cli.otlp_endpoint.as_deref() {
+        Some(endpoint) => {
+            let (provider, layer) = telemetry::otlp_layer(endpoint)?;
+            (Some(provider), Some(layer))
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
         )
+        .with(tracing_subscriber::fmt::layer())
+        .with(otlp_layer)
         .init();
+    if let Some(endpoint) = &cli.otlp_endpoint {
+        telemetry::enable_exemplars();
+        tracing::info!(endpoint = %endpoint, "exporting traces over OTLP");
+    }
 
-    let cli = Cli::parse();
+    redact::init(RedactionPolicy {
+        redact_query_strings: cli.redact_query_strings,
+    });
+
+    base_path::init(base_path::normalize(&cli.base_path)?);
+
+    if let Some(ref dir) = cli.disk_cache_dir {
+        let mut cache = DiskCache::open(dir, cli.disk_cache_max_mb * 1024 * 1024)?;
+        if let Some(secs) = cli.disk_cache_revalidate_secs {
+            cache = cache.with_revalidation(Duration::from_secs(secs));
+        }
+        tracing::info!(
+            dir = %dir.display(),
+            size_bytes = cache.size_bytes(),
+            "opened ledger disk cache"
+        );
+        disk_cache::init(cache);
+    }
+
+    readiness::init(ReadinessPolicy {
+        max_lag_ledgers: cli.ready_max_lag,
+    });
+
+    fetch::init_retry_policy(RetryPolicy {
+        max_attempts: cli.fetch_max_attempts.max(1),
+        initial_backoff: Duration::from_millis(cli.fetch_retry_initial_backoff_ms),
+        max_backoff: Duration::from_millis(cli.fetch_retry_max_backoff_ms),
+    });
+
+    if cli.meta_sigv4 {
+        let signer = Signer::from_env(&cli.aws_region)?;
+        tracing::info!(
+            region = %cli.aws_region,
+            instance_profile = signer.uses_instance_profile(),
+            "signing metadata store requests with SigV4"
+        );
+        sigv4::init(signer);
+    }
 
     // Fetch store configuration
     let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
+        .timeout(Duration::from_secs(30))
         .build()?;
 
     let store_config = match fetch_config(&client, &cli.meta_url).await {
         Ok(config) => config,
+        Err(e) if cli.network_passphrase.is_some() => {
+            return Err(format!(
+                "cannot check --network-passphrase, failed to fetch store config: {}",
+                e
+            )
+            .into());
+        }
         Err(e) => {
             tracing::warn!(error = %e, "failed to fetch store config, using defaults");
             stellar_events_api::ledger::path::StoreConfig::default()
         }
     };
+    if let Some(expected) = &cli.network_passphrase {
+        if store_config.network_passphrase != *expected {
+            return Err(format!(
+                "metadata store {} is for network '{}', but --network-passphrase is '{}'",
+                redact::redact_url(&cli.meta_url),
+                store_config.network_passphrase,
+                expected
+            )
+            .into());
+        }
+    }
 
     let cache_ttl_seconds = cli.cache_ttl_days as i64 * 24 * 60 * 60;
-    let store = EventStore::new(cache_ttl_seconds);
+    let mut store = with_cache_options(EventStore::new(cache_ttl_seconds), &cli);
 
     // Install Prometheus metrics exporter.
-    let metrics_handle = metrics_exporter_prometheus::PrometheusBuilder::new()
+    let mut metrics_builder = metrics_exporter_prometheus::PrometheusBuilder::new();
+    if telemetry::exemplars_enabled() {
+        metrics_builder = metrics_builder.set_buckets_for_metric(
+            Matcher::Full(telemetry::LATENCY_METRIC.to_string()),
+            telemetry::LATENCY_BUCKETS,
+        )?;
+    }
+    let metrics_handle = metrics_builder
         .install_recorder()
         .expect("failed to install Prometheus recorder");
 
-    tracing::info!("initialised in-memory event store");
+    let networks = if cli.command.is_some() {
+        Vec::new()
+    } else {
+        open_networks(&client, &cli, cache_ttl_seconds).await?
+    };
+
+    store = with_storage_options(store, &cli, None, &store_config.network_passphrase)?;
+
+    tracing::info!(store = ?cli.store, "initialised event store");
 
     let state = Arc::new(AppState {
         store,
-        config: store_config.clone(),
+        config: store_config.clone().into(),
         meta_url: cli.meta_url.clone(),
         client: client.clone(),
+        peer: cli
+            .peer_url
+            .zip(cli.admin_secret.clone())
+            .map(|(url, secret)| snapshot::Peer {
+                url,
+                auth: api::admin::AdminAuth::new(secret),
+            }),
     });
 
+    if let Some(command) = cli.command {
+        return run_command(command, &state, cli.store).await;
+    }
+
+    if let Some(ref path) = cli.snapshot_path {
+        load_snapshot(&state, path);
+        spawn_snapshotter(
+            Arc::clone(&state),
+            path.clone(),
+            Duration::from_secs(cli.snapshot_interval_secs.max(1)),
+        );
+        for network in &networks {
+            let path = network_path(path, &network.name);
+            load_snapshot(&network.state, &path);
+            spawn_snapshotter(
+                Arc::clone(&network.state),
+                path,
+                Duration::from_secs(cli.snapshot_interval_secs.max(1)),
+            );
+        }
+    }
+
+    if let (Some(peer), Some(secret)) = (&cli.warm_from, &cli.admin_secret) {
+        let peer_source = snapshot::Peer {
+            url: peer.clone(),
+            auth: api::admin::AdminAuth::new(secret.clone()),
+        };
+        match snapshot::warm_from_peer(
+            &client,
+            &state.store,
+            &store_config.network_passphrase,
+            &peer_source,
+        )
+        .await
+        {
+            Ok(summary) => tracing::info!(
+                peer = %redact::redact_url(peer),
+                imported = summary.imported,
+                skipped = summary.skipped,
+                "warmed cache from peer"
+            ),
+            Err(e) => tracing::warn!(
+                peer = %redact::redact_url(peer),
+                error = %e,
+                "failed to warm cache from peer"
+            ),
+        }
+    }
+
     // Start background sync
     let sync_state = Arc::clone(&state);
     let sync_url = cli.meta_url.clone();
-    let sync_config = store_config.clone();
-    let start_ledger = cli.start_ledger;
-    let parallel_fetches = cli.parallel_fetches;
-    tokio::spawn(async move {
-        run_sync(
-            client,
-            sync_url,
-            sync_config,
-            sync_state,
-            start_ledger,
-            parallel_fetches,
-        )
-        .await;
+    let sync_options = SyncOptions {
+        start_ledger: cli.start_ledger,
+        start_offset: cli.start_offset,
+        end_ledger: cli.end_ledger,
+        parallel_fetches: cli.parallel_fetches,
+        catchup_threshold: cli.catchup_threshold,
+        catchup_parallel_fetches: cli.catchup_parallel_fetches,
+        tip_source: match cli.rpc_url {
+            Some(url) => TipSource::Rpc(url),
+            None => TipSource::Horizon(cli.horizon_url),
+        },
+        metrics_contracts: cli.metrics_contracts,
+    };
+    for network in &networks {
+        let options = SyncOptions {
+            start_ledger: None,
+            start_offset: cli.start_offset,
+            end_ledger: None,
+            parallel_fetches: cli.parallel_fetches,
+            catchup_threshold: cli.catchup_threshold,
+            catchup_parallel_fetches: cli.catchup_parallel_fetches,
+            tip_source: network.tip_source.clone(),
+            metrics_contracts: Vec::new(),
+        };
+        tokio::spawn(run_sync(
+            client.clone(),
+            network.state.meta_url.clone(),
+            Arc::clone(&network.state),
+            options,
+        ));
+    }
+    if cli.config_refresh_secs > 0 {
+        let interval = Duration::from_secs(cli.config_refresh_secs);
+        for state in std::iter::once(&state).chain(networks.iter().map(|n| &n.state)) {
+            tokio::spawn(sync::run_config_refresh(
+                client.clone(),
+                state.meta_url.clone(),
+                Arc::clone(state),
+                interval,
+            ));
+        }
+    }
+    if let Some(from) = cli.backfill_from {
+        tokio::spawn(history::run_history_backfill(
+            Arc::clone(&state),
+            from,
+            cli.backfill_rate,
+        ));
+    }
+    let mut sync_task = tokio::spawn(async move {
+        run_sync(client, sync_url, sync_state, sync_options).await;
     });
 
-    // Build and start HTTP server
-    let app = api::router(state, Some(metrics_handle));
-    let addr: SocketAddr = format!("{}:{}", cli.bind, cli.port).parse()?;
-    tracing::info!(address = %addr, "starting server");
+    // Build and start HTTP servers, one per bind address
+    let server_options = ServerOptions {
+        http1_keep_alive: cli.http1_keep_alive,
+        http1_header_read_timeout: Duration::from_secs(cli.http1_header_read_timeout_secs),
+        http2_keep_alive_interval: cli.http2_keep_alive_interval_secs.map(Duration::from_secs),
+        http2_keep_alive_timeout: Duration::from_secs(cli.http2_keep_alive_timeout_secs),
+        http2_max_concurrent_streams: cli.http2_max_concurrent_streams,
+        max_connections: cli.max_connections,
+    };
+    let mut servers = tokio::task::JoinSet::new();
+    let mut app = if cli.metrics_bind.is_empty() {
+        api::router(Arc::clone(&state), Some(metrics_handle))
+    } else {
+        let metrics_app = api::metrics_router(metrics_handle);
+        for bind in &cli.metrics_bind {
+            let addr = parse_bind_address(bind, cli.port)?;
+            tracing::info!(address = %addr, "starting metrics server");
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            servers.spawn(server::serve(
+                listener,
+                metrics_app.clone(),
+                server_options.clone(),
+            ));
+        }
+        api::router(Arc::clone(&state), None)
+    };
+
+    let mut admin_app = None;
+    if let Some(secret) = cli.admin_secret {
+        let admin = api::admin::router(Arc::clone(&state), api::admin::AdminAuth::new(secret));
+        if cli.admin_bind.is_empty() {
+            app = app.merge(admin);
+        } else {
+            admin_app = Some(admin);
+        }
+    }
+
+    if !networks.is_empty() {
+        app = app.merge(api::networks_router(
+            networks
+                .into_iter()
+                .map(|network| (network.name, network.state))
+                .collect(),
+        ));
+    }
+
+    if let Some(ref path) = cli.ip_access_file {
+        let access = Arc::new(IpAccessControl::load(path, cli.trust_forwarded_for)?);
+        tracing::info!(path = %path.display(), "loaded IP access rules");
+        spawn_access_reloader(Arc::clone(&access));
+        admin_app = admin_app.map(|admin| {
+            admin.layer(axum::middleware::from_fn_with_state(
+                Arc::clone(&access),
+                ip_filter,
+            ))
+        });
+        app = app.layer(axum::middleware::from_fn_with_state(access, ip_filter));
+    }
+
+    if !base_path::get().is_empty() {
+        tracing::info!(base_path = base_path::get(), "serving API under base path");
+        app = axum::Router::new().nest(base_path::get(), app);
+        admin_app = admin_app.map(|admin| axum::Router::new().nest(base_path::get(), admin));
+    }
+
+    if let Some(admin) = admin_app {
+        for bind in &cli.admin_bind {
+            let addr = parse_bind_address(bind, cli.port)?;
+            tracing::info!(address = %addr, "starting admin server");
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            servers.spawn(server::serve(
+                listener,
+                admin.clone(),
+                server_options.clone(),
+            ));
+        }
+    }
+
+    for bind in &cli.bind {
+        let addr = parse_bind_address(bind, cli.port)?;
+        tracing::info!(address = %addr, "starting server");
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        servers.spawn(server::serve(listener, app.clone(), server_options.clone()));
+    }
+
+    loop {
+        tokio::select! {
+            result = servers.join_next() => match result {
+                Some(result) => result?,
+                None => break,
+            },
+            result = &mut sync_task, if cli.exit_at_end_ledger => {
+                result?;
+                state.store.save_checkpoint()?;
+                if let Some(path) = cli.snapshot_path {
+                    let count = tokio::task::spawn_blocking(move || {
+                        snapshot::save(&state.store, state.config.network_passphrase(), &path)
+                    })
+                    .await??;
+                    tracing::info!(partitions = count, "saved final cache snapshot");
+                }
+                tracing::info!("reached end ledger, exiting");
+                break;
+            }
+        }
+    }
+
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!(error = %e, "failed to flush traces");
+        }
+    }
+    Ok(())
+}
+
+/// Attach the disk tiers, retention rules, backend, and checkpoint shared by
+/// the stores of every network served. An additional network's files are
+/// kept apart from the main network's by suffixing their paths with its
+/// name.
+fn with_storage_options(
+    mut store: EventStore,
+    cli: &Cli,
+    network: Option<&str>,
+    network_passphrase: &str,
+) -> Result<EventStore, Box<dyn std::error::Error>> {
+    let path_for = |path: &std::path::Path| match network {
+        Some(name) => network_path(path, name),
+        None => path.to_path_buf(),
+    };
+
+    if let Some(ref dir) = cli.spill_dir {
+        let dir = path_for(dir);
+        tracing::info!(
+            network,
+            dir = %dir.display(),
+            after_secs = cli.spill_after_secs,
+            "spilling idle ledgers to disk"
+        );
+        store = store.with_spill_dir(dir, cli.spill_after_secs as i64)?;
+    }
+
+    if !cli.retain.is_empty() {
+        tracing::info!(
+            network,
+            contracts = cli.retain.len(),
+            "retaining contract events beyond the TTL"
+        );
+        store = store.with_retention(cli.retain.clone());
+    }
+
+    let db_path = path_for(&cli.db_path);
+    let backend: Option<Box<dyn StorageBackend>> = match cli.store {
+        StoreKind::Memory => None,
+        StoreKind::Sqlite => Some(Box::new(SqliteBackend::open(&db_path)?)),
+        StoreKind::Redb => Some(Box::new(RedbBackend::open(&db_path)?)),
+    };
+    if let Some(backend) = backend {
+        store = store.with_backend(backend);
+        let restored = store.restore()?;
+        tracing::info!(
+            network,
+            store = ?cli.store,
+            path = %db_path.display(),
+            restored,
+            "restored event store from database"
+        );
+    }
+
+    if let Some(ref path) = cli.checkpoint_path {
+        let path = path_for(path);
+        store = store.with_checkpoint(path.clone(), network_passphrase)?;
+        tracing::info!(
+            network,
+            path = %path.display(),
+            last_synced_ledger = ?store.get_sync_state("last_synced_ledger")?,
+            "checkpointing sync state"
+        );
+    }
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    Ok(store)
+}
+
+/// The path of an additional network's copy of the file or directory at
+/// `path`: `events.db` becomes `events.db.testnet` for `testnet`.
+fn network_path(path: &std::path::Path, network: &str) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(network);
+    name.into()
+}
+
+/// Load the cache snapshot at `path` into `state`'s store, if it exists.
+fn load_snapshot(state: &AppState, path: &std::path::Path) {
+    if !path.exists() {
+        return;
+    }
+    match snapshot::load(&state.store, state.config.network_passphrase(), path) {
+        Ok(summary) => tracing::info!(
+            path = %path.display(),
+            imported = summary.imported,
+            skipped = summary.skipped,
+            "loaded cache snapshot"
+        ),
+        Err(e) => tracing::warn!(
+            path = %path.display(),
+            error = %e,
+            "failed to load cache snapshot"
+        ),
+    }
+}
+
+/// Apply the cache options shared by the stores of every network served.
+fn with_cache_options(mut store: EventStore, cli: &Cli) -> EventStore {
+    if let Some(secs) = cli.backfill_ttl_secs {
+        store = store.with_source_ttl(CacheSource::Backfill, secs as i64);
+    }
+    if let Some(secs) = cli.pinned_ttl_secs {
+        store = store.with_source_ttl(CacheSource::Pinned, secs as i64);
+    }
+
+    if cli.sliding_ttl {
+        store = store.with_sliding_ttl();
+    }
+
+    if let Some(max) = cli.max_cached_ledgers {
+        store = store.with_max_cached_ledgers(max);
+    }
+
+    if let Some(after_ledgers) = cli.compact_after_ledgers {
+        tracing::info!(after_ledgers, "compacting older cached ledgers");
+        store = store.with_compaction(after_ledgers);
+    }
+
+    if let Some(after_ledgers) = cli.compress_after_ledgers {
+        tracing::info!(after_ledgers, "compressing older cached ledgers");
+        store = store.with_compression(after_ledgers);
+    }
+
+    store
+}
+
+/// An additional network, served under `/networks/{name}`.
+struct Network {
+    name: String,
+    state: Arc<AppState>,
+    tip_source: TipSource,
+}
+
+/// Open the additional networks given by --network, each with its own store
+/// configured like the main network's, and its own tip source.
+async fn open_networks(
+    client: &reqwest::Client,
+    cli: &Cli,
+    cache_ttl_seconds: i64,
+) -> Result<Vec<Network>, Box<dyn std::error::Error>> {
+    let mut networks: Vec<Network> = Vec::new();
+    for spec in &cli.networks {
+        if networks.iter().any(|network| network.name == spec.name) {
+            return Err(format!("network '{}' given more than once", spec.name).into());
+        }
+        let config = fetch_config(client, &spec.value)
+            .await
+            .map_err(|e| format!("failed to fetch config for network '{}': {}", spec.name, e))?;
+        if let Some(expected) = find_network_spec(&cli.network_passphrases, &spec.name) {
+            if config.network_passphrase != expected {
+                return Err(format!(
+                    "metadata store {} for network '{}' is for network '{}', but --network-passphrase-for is '{}'",
+                    redact::redact_url(&spec.value),
+                    spec.name,
+                    config.network_passphrase,
+                    expected
+                )
+                .into());
+            }
+        }
+        let tip_source = if let Some(rpc) = find_network_spec(&cli.network_rpc_urls, &spec.name) {
+            TipSource::Rpc(rpc.to_string())
+        } else if let Some(horizon) = find_network_spec(&cli.network_horizon_urls, &spec.name) {
+            TipSource::Horizon(horizon.to_string())
+        } else {
+            let horizon = sync::default_horizon_url(&config.network_passphrase).ok_or_else(|| {
+                format!(
+                    "no default Horizon server for network '{}', set --network-horizon-url or --network-rpc-url",
+                    spec.name
+                )
+            })?;
+            TipSource::Horizon(horizon.to_string())
+        };
+        tracing::info!(
+            network = %spec.name,
+            meta_url = %spec.value,
+            passphrase = %config.network_passphrase,
+            "serving additional network"
+        );
+        networks.push(Network {
+            name: spec.name.clone(),
+            state: Arc::new(AppState {
+                store: with_storage_options(
+                    with_cache_options(EventStore::new(cache_ttl_seconds), cli),
+                    cli,
+                    Some(&spec.name),
+                    &config.network_passphrase,
+                )?,
+                config: config.into(),
+                meta_url: spec.value.clone(),
+                client: client.clone(),
+                peer: None,
+            }),
+            tip_source,
+        });
+    }
+    let per_network = [
+        ("--network-horizon-url", &cli.network_horizon_urls),
+        ("--network-rpc-url", &cli.network_rpc_urls),
+        ("--network-passphrase-for", &cli.network_passphrases),
+    ];
+    for (flag, specs) in per_network {
+        for spec in specs {
+            if !networks.iter().any(|network| network.name == spec.name) {
+                return Err(format!("{} for unknown network '{}'", flag, spec.name).into());
+            }
+        }
+    }
+    Ok(networks)
+}
+
+/// The value given for the network `name` in a per-network option.
+fn find_network_spec<'a>(specs: &'a [NetworkSpec], name: &str) -> Option<&'a str> {
+    specs
+        .iter()
+        .find(|spec| spec.name == name)
+        .map(|spec| spec.value.as_str())
+}
 
+/// Run a one-off command against the store, then exit.
+async fn run_command(
+    command: Command,
+    state: &AppState,
+    store: StoreKind,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::ExportPartition { ledger, path } => {
+            if !state.store.is_ledger_cached(ledger)? {
+                let (info, events) = sync::fetch_and_extract(
+                    &state.client,
+                    &state.meta_url,
+                    &state.config.get(),
+                    ledger,
+                )
+                .await?;
+                state
+                    .store
+                    .insert_ledger(ledger, info, events, CacheSource::Pinned)?;
+            }
+            state.store.export_partition_file(ledger, &path)?;
+            tracing::info!(ledger, path = %path.display(), "exported partition");
+        }
+        Command::ImportPartition { paths } => {
+            if store == StoreKind::Memory {
+                return Err("import-partition needs --store sqlite or redb".into());
+            }
+            for path in paths {
+                let (ledger, imported) = state
+                    .store
+                    .import_partition_file(&path, CacheSource::Sync)?;
+                tracing::info!(ledger, imported, path = %path.display(), "imported partition");
+            }
+        }
+    }
     Ok(())
 }
+
+/// Save a snapshot of the cache to `path` every `interval`.
+fn spawn_snapshotter(state: Arc<AppState>, path: std::path::PathBuf, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let state = Arc::clone(&state);
+            let path = path.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                snapshot::save(&state.store, state.config.network_passphrase(), &path)
+            })
+            .await;
+            match result {
+                Ok(Ok(count)) => {
+                    metrics::counter!("snapshot_saves_total").increment(1);
+                    tracing::debug!(partitions = count, "saved cache snapshot");
+                }
+                Ok(Err(e)) => {
+                    metrics::counter!("snapshot_errors_total").increment(1);
+                    tracing::warn!(error = %e, "failed to save cache snapshot");
+                }
+                Err(e) => tracing::warn!(error = %e, "snapshot task failed"),
+            }
+        }
+    });
+}
+
+/// Reload the IP access rules whenever the process receives SIGHUP.
+#[cfg(unix)]
+fn spawn_access_reloader(access: Arc<IpAccessControl>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to install SIGHUP handler; IP rules will not reload");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match access.reload() {
+                Ok(()) => tracing::info!("reloaded IP access rules"),
+                Err(e) => tracing::warn!(error = %e, "failed to reload IP access rules"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_access_reloader(_access: Arc<IpAccessControl>) {}
+
+/// Resolve a `--bind` value, which is either a bare IP (combined with the
+/// default port) or a full socket address such as `[::]:3000`.
+fn parse_bind_address(bind: &str, port: u16) -> Result<SocketAddr, std::net::AddrParseError> {
+    bind.parse::<SocketAddr>().or_else(|_| {
+        bind.parse::<std::net::IpAddr>()
+            .map(|ip| SocketAddr::new(ip, port))
+    })
+}
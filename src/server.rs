@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+
+/// Delay before retrying after a failed `accept`, e.g. when the process is
+/// out of file descriptors.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Tuning options for the HTTP server.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Whether HTTP/1 connections are kept alive between requests.
+    pub http1_keep_alive: bool,
+    /// How long to wait for a client to send request headers on HTTP/1
+    /// connections before closing them.
+    pub http1_header_read_timeout: Duration,
+    /// Interval between HTTP/2 keep-alive pings. `None` disables pings.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// How long to wait for an HTTP/2 keep-alive ping acknowledgement.
+    pub http2_keep_alive_timeout: Duration,
+    /// Maximum number of concurrent streams per HTTP/2 connection.
+    pub http2_max_concurrent_streams: u32,
+    /// Maximum number of open connections per listener. Further connections
+    /// wait in the accept backlog until a slot frees up.
+    pub max_connections: Option<usize>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            http1_keep_alive: true,
+            http1_header_read_timeout: Duration::from_secs(30),
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: Duration::from_secs(20),
+            http2_max_concurrent_streams: 200,
+            max_connections: None,
+        }
+    }
+}
+
+/// Serve the router on the listener, speaking HTTP/1 or HTTP/2 depending on
+/// what each client negotiates.
+pub async fn serve(listener: TcpListener, app: Router, options: ServerOptions) {
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(options.http1_keep_alive)
+        .header_read_timeout(options.http1_header_read_timeout);
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(options.http2_keep_alive_interval)
+        .keep_alive_timeout(options.http2_keep_alive_timeout)
+        .max_concurrent_streams(options.http2_max_concurrent_streams);
+
+    let limiter = options
+        .max_connections
+        .map(|max| Arc::new(Semaphore::new(max)));
+
+    loop {
+        let permit = match &limiter {
+            Some(limiter) => Some(
+                Arc::clone(limiter)
+                    .acquire_owned()
+                    .await
+                    .expect("connection limiter is never closed"),
+            ),
+            None => None,
+        };
+
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to accept connection");
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+
+        let builder = builder.clone();
+        // Expose the peer address to handlers and middleware.
+        let service = TowerToHyperService::new(tower::util::MapRequest::new(
+            app.clone(),
+            move |mut req: axum::http::Request<hyper::body::Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(remote_addr));
+                req
+            },
+        ));
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = builder
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::trace!(remote = %remote_addr, error = %e, "failed to serve connection");
+            }
+        });
+    }
+}
@@ -0,0 +1,353 @@
+//! Portable snapshots of cached ledger partitions.
+//!
+//! A snapshot lets a new replica warm its cache from a running peer instead
+//! of fetching every ledger from the archive, and lets an instance saving
+//! snapshots to disk with [`save`] restart with its cache intact. It is a
+//! zstd-compressed stream of JSON lines: a header naming the format and
+//! network, followed by one [`PartitionExport`] per ledger. Snapshots saved
+//! to disk also carry the sync state in their header.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::admin::AdminAuth;
+use crate::db::{CacheSource, EventStore, PartitionExport};
+
+/// Format identifier written to the snapshot header.
+pub const FORMAT: &str = "stellar-events-api/partitions";
+
+/// Current snapshot format version.
+pub const VERSION: u32 = 1;
+
+/// Media type of a snapshot.
+pub const CONTENT_TYPE: &str = "application/zstd";
+
+/// Path of the export endpoint on a peer, relative to its base URL.
+pub const EXPORT_PATH: &str = "/admin/partitions/export";
+
+/// A peer instance to copy cached partitions from.
+#[derive(Debug, Clone)]
+pub struct Peer {
+    /// Base URL of the peer.
+    pub url: String,
+    /// Signs export requests. The peer must share the admin secret.
+    pub auth: AdminAuth,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    network_passphrase: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sync_state: BTreeMap<String, String>,
+}
+
+/// Outcome of importing a snapshot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    /// Partitions added to the store.
+    pub imported: usize,
+    /// Partitions skipped because the ledger was already cached.
+    pub skipped: usize,
+}
+
+/// Write the cached partitions for ledgers `start..=end` as a snapshot.
+/// Returns the compressed snapshot and the number of partitions it holds.
+pub fn export(
+    store: &EventStore,
+    network_passphrase: &str,
+    start: u32,
+    end: u32,
+) -> Result<(Vec<u8>, usize), crate::Error> {
+    encode(store, network_passphrase, BTreeMap::new(), start, end)
+}
+
+/// Save every cached partition and the sync state as a snapshot at `path`,
+/// replacing any earlier snapshot there atomically. Returns the number of
+/// partitions saved.
+pub fn save(
+    store: &EventStore,
+    network_passphrase: &str,
+    path: &Path,
+) -> Result<usize, crate::Error> {
+    let (data, count) = encode(
+        store,
+        network_passphrase,
+        store.sync_state_entries(),
+        0,
+        u32::MAX,
+    )?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(count)
+}
+
+/// Load a snapshot saved with [`save`] into the store, restoring the sync
+/// state it holds for keys the store has no value for.
+pub fn load(
+    store: &EventStore,
+    network_passphrase: &str,
+    path: &Path,
+) -> Result<ImportSummary, crate::Error> {
+    let data = std::fs::read(path)?;
+    let (header, summary) = decode(store, network_passphrase, &data, CacheSource::Sync)?;
+    for (key, value) in header.sync_state {
+        if store.get_sync_state(&key)?.is_none() {
+            store.set_sync_state(&key, &value)?;
+        }
+    }
+    Ok(summary)
+}
+
+fn encode(
+    store: &EventStore,
+    network_passphrase: &str,
+    sync_state: BTreeMap<String, String>,
+    start: u32,
+    end: u32,
+) -> Result<(Vec<u8>, usize), crate::Error> {
+    let partitions = store.export_partitions(start, end);
+    let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+
+    let header = Header {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        network_passphrase: network_passphrase.to_string(),
+        sync_state,
+    };
+    serde_json::to_writer(&mut encoder, &header)?;
+    encoder.write_all(b"\n")?;
+    for partition in &partitions {
+        serde_json::to_writer(&mut encoder, partition)?;
+        encoder.write_all(b"\n")?;
+    }
+
+    Ok((encoder.finish()?, partitions.len()))
+}
+
+/// Import a snapshot into the store, keeping its partitions for at most the
+/// TTL of `source`. The snapshot must come from an instance serving the same
+/// network. Ledgers that are already cached are left as-is.
+pub fn import(
+    store: &EventStore,
+    network_passphrase: &str,
+    data: &[u8],
+    source: CacheSource,
+) -> Result<ImportSummary, crate::Error> {
+    decode(store, network_passphrase, data, source).map(|(_, summary)| summary)
+}
+
+fn decode(
+    store: &EventStore,
+    network_passphrase: &str,
+    data: &[u8],
+    source: CacheSource,
+) -> Result<(Header, ImportSummary), crate::Error> {
+    let mut lines = BufReader::new(zstd::Decoder::new(data)?).lines();
+
+    let header: Header = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(crate::Error::Internal("snapshot is empty".to_string())),
+    };
+    if header.format != FORMAT || header.version != VERSION {
+        return Err(crate::Error::Internal(format!(
+            "unsupported snapshot format {} version {}",
+            header.format, header.version
+        )));
+    }
+    if header.network_passphrase != network_passphrase {
+        return Err(crate::Error::Internal(format!(
+            "snapshot is for network '{}', expected '{}'",
+            header.network_passphrase, network_passphrase
+        )));
+    }
+
+    let mut summary = ImportSummary::default();
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let partition: PartitionExport = serde_json::from_str(&line)?;
+        if store.import_partition(partition, source)? {
+            summary.imported += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    metrics::counter!("store_partitions_imported_total").increment(summary.imported as u64);
+    Ok((header, summary))
+}
+
+/// Time allowed for downloading a full snapshot at startup.
+const WARM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Download every cached partition from `peer` and import it. The peer must
+/// have its admin endpoints enabled.
+pub async fn warm_from_peer(
+    client: &reqwest::Client,
+    store: &EventStore,
+    network_passphrase: &str,
+    peer: &Peer,
+) -> Result<ImportSummary, crate::Error> {
+    import_from_peer(
+        client,
+        store,
+        network_passphrase,
+        peer,
+        0..=u32::MAX,
+        WARM_TIMEOUT,
+        CacheSource::Sync,
+    )
+    .await
+}
+
+/// Import whichever of `ledgers` `peer` has cached, keeping them for at most
+/// the TTL of `source`.
+pub async fn import_from_peer(
+    client: &reqwest::Client,
+    store: &EventStore,
+    network_passphrase: &str,
+    peer: &Peer,
+    ledgers: std::ops::RangeInclusive<u32>,
+    timeout: Duration,
+    source: CacheSource,
+) -> Result<ImportSummary, crate::Error> {
+    let base = peer.url.trim_end_matches('/');
+    let path = format!(
+        "{}?start={}&end={}",
+        EXPORT_PATH,
+        ledgers.start(),
+        ledgers.end()
+    );
+    // The peer verifies the path it was sent, including any base path.
+    let signed_path = match reqwest::Url::parse(base) {
+        Ok(url) => format!("{}{}", url.path().trim_end_matches('/'), path),
+        Err(_) => path.clone(),
+    };
+    let request = client.get(format!("{}{}", base, path));
+    let response = peer
+        .auth
+        .sign_get(request, &signed_path)
+        .timeout(timeout)
+        .send()
+        .await?
+        .error_for_status()?;
+    let data = response.bytes().await?;
+    import(store, network_passphrase, &data, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::events::{EventPhase, EventType, ExtractedEvent};
+
+    const NETWORK: &str = "Test SDF Network ; September 2015";
+
+    fn events(ledger: u32) -> Vec<ExtractedEvent> {
+        (0..3)
+            .map(|i| ExtractedEvent {
+                ledger_sequence: ledger,
+                ledger_closed_at: 1_700_000_000,
+                phase: EventPhase::Operation,
+                tx_index: i,
+                event_index: 0,
+                op_index: Some(0),
+                tx_hash: format!("{:064x}", i),
+                contract_id: None,
+                event_type: EventType::Contract,
+                topics_xdr_json: vec![serde_json::json!({"symbol": "transfer"})],
+                data_xdr_json: Some(serde_json::json!({"i128": "100"})),
+                xdr: None,
+                transaction: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let source = EventStore::new(3600);
+        source.insert_events(events(100)).unwrap();
+        source.insert_events(events(101)).unwrap();
+        source.record_ledger_cached(102, 3600).unwrap();
+        source.insert_events(events(200)).unwrap();
+
+        let (data, count) = export(&source, NETWORK, 100, 150).unwrap();
+        assert_eq!(count, 3);
+
+        let target = EventStore::new(3600);
+        target.insert_events(events(101)).unwrap();
+        let summary = import(&target, NETWORK, &data, CacheSource::Sync).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 2,
+                skipped: 1
+            }
+        );
+        assert!(target.is_ledger_cached(100).unwrap());
+        assert!(target.is_ledger_cached(102).unwrap());
+        assert!(!target.is_ledger_cached(200).unwrap());
+        assert_eq!(
+            target.export_partitions(100, 100)[0].events.len(),
+            source.export_partitions(100, 100)[0].events.len()
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-snapshot-{}.zst",
+            std::process::id()
+        ));
+        let source = EventStore::new(3600);
+        source.insert_events(events(100)).unwrap();
+        source.insert_events(events(101)).unwrap();
+        source.set_sync_state("last_synced_ledger", "101").unwrap();
+        assert_eq!(save(&source, NETWORK, &path).unwrap(), 2);
+
+        let target = EventStore::new(7200);
+        let summary = load(&target, NETWORK, &path).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert_eq!(
+            target
+                .get_sync_state("last_synced_ledger")
+                .unwrap()
+                .as_deref(),
+            Some("101")
+        );
+        // Partitions keep their expiry rather than taking a fresh TTL.
+        assert_eq!(
+            target.ledger_summary(100).unwrap().expires_at,
+            source.ledger_summary(100).unwrap().expires_at
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_import_rejects_other_network() {
+        let source = EventStore::new(3600);
+        source.insert_events(events(100)).unwrap();
+        let (data, _) = export(&source, NETWORK, 0, u32::MAX).unwrap();
+
+        let target = EventStore::new(3600);
+        assert!(import(
+            &target,
+            "Public Global Stellar Network ; September 2015",
+            &data,
+            CacheSource::Sync
+        )
+        .is_err());
+        assert!(import(&target, NETWORK, b"not a snapshot", CacheSource::Sync).is_err());
+        assert_eq!(target.cached_ledger_count(), 0);
+    }
+}
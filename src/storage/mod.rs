@@ -0,0 +1,47 @@
+//! Durable storage behind the in-memory event store.
+//!
+//! A [`StorageBackend`] receives every partition the
+//! [`EventStore`](crate::db::EventStore) caches or drops and every sync
+//! state change, and hands them back at startup, so an instance restarted
+//! with the same backend resumes with its cache intact instead of
+//! backfilling it again. With a cap on the ledgers held in memory, the
+//! oldest partitions are left only in the backend: the store keeps their
+//! summaries and filter indexes, and reads their events back from the
+//! backend for the queries that need them.
+
+pub mod redb;
+pub mod sqlite;
+
+use crate::db::PartitionExport;
+
+pub use self::redb::RedbBackend;
+pub use sqlite::SqliteBackend;
+
+/// A durable copy of the event store's partitions and sync state.
+pub trait StorageBackend: Send + Sync {
+    /// Save a partition, replacing any saved copy of the same ledger.
+    /// `expires_at` is the Unix timestamp at which it leaves the cache.
+    fn save_partition(
+        &self,
+        partition: &PartitionExport,
+        expires_at: i64,
+    ) -> Result<(), crate::Error>;
+
+    /// Delete the saved partitions of the given ledgers.
+    fn delete_partitions(&self, ledgers: &[u32]) -> Result<(), crate::Error>;
+
+    /// The saved partition of a ledger, if there is one.
+    fn load_partition(&self, ledger: u32) -> Result<Option<PartitionExport>, crate::Error>;
+
+    /// Visit every saved partition with its expiry time, in ledger order.
+    fn load_partitions(
+        &self,
+        visit: &mut dyn FnMut(PartitionExport, i64) -> Result<(), crate::Error>,
+    ) -> Result<(), crate::Error>;
+
+    /// Save a sync state value.
+    fn save_sync_state(&self, key: &str, value: &str) -> Result<(), crate::Error>;
+
+    /// All saved sync state values.
+    fn load_sync_state(&self) -> Result<Vec<(String, String)>, crate::Error>;
+}
@@ -0,0 +1,268 @@
+//! Embedded key-value storage backend, using redb.
+//!
+//! Unlike the SQLite backend, which stores each partition as one blob, events
+//! are stored individually, keyed by internal event ID. Internal IDs zero-pad
+//! the ledger sequence, so a ledger's events are one contiguous key range,
+//! read back with a single range scan. There are no secondary indexes: the
+//! event store keeps each partition's filter index in memory and only reads
+//! back the ledgers a query cannot rule out. Every write is a durable,
+//! crash-safe transaction.
+
+use std::path::Path;
+
+use redb::{
+    Database, ReadOnlyTable, ReadableDatabase, ReadableTable, TableDefinition, WriteTransaction,
+};
+
+use super::StorageBackend;
+use crate::db::PartitionExport;
+
+/// Ledger sequence -> partition expiry (Unix seconds).
+const PARTITIONS: TableDefinition<u32, i64> = TableDefinition::new("partitions");
+/// Ledger sequence -> JSON ledger header details, for partitions that have
+/// them.
+const LEDGER_INFO: TableDefinition<u32, &[u8]> = TableDefinition::new("ledger_info");
+/// Internal event ID -> JSON [`ExportedEvent`].
+const EVENTS: TableDefinition<&str, &[u8]> = TableDefinition::new("events");
+/// Sync state key -> value.
+const SYNC_STATE: TableDefinition<&str, &str> = TableDefinition::new("sync_state");
+
+/// Convert redb's per-operation errors, which all fold into [`redb::Error`].
+macro_rules! from_redb_error {
+    ($($error:ty),*) => {
+        $(impl From<$error> for crate::Error {
+            fn from(e: $error) -> Self {
+                crate::Error::Redb(e.into())
+            }
+        })*
+    };
+}
+
+from_redb_error!(
+    redb::DatabaseError,
+    redb::TransactionError,
+    redb::TableError,
+    redb::StorageError,
+    redb::CommitError
+);
+
+/// A [`StorageBackend`] in a redb database file.
+pub struct RedbBackend {
+    db: Database,
+}
+
+impl RedbBackend {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> Result<Self, crate::Error> {
+        let db = Database::create(path)?;
+        let txn = db.begin_write()?;
+        create_tables(&txn)?;
+        txn.commit()?;
+        Ok(Self { db })
+    }
+}
+
+impl StorageBackend for RedbBackend {
+    fn save_partition(
+        &self,
+        partition: &PartitionExport,
+        expires_at: i64,
+    ) -> Result<(), crate::Error> {
+        let txn = self.db.begin_write()?;
+        remove_partition(&txn, partition.ledger)?;
+        {
+            let mut partitions = txn.open_table(PARTITIONS)?;
+            partitions.insert(partition.ledger, expires_at)?;
+            if let Some(info) = &partition.info {
+                let mut ledger_info = txn.open_table(LEDGER_INFO)?;
+                ledger_info.insert(partition.ledger, serde_json::to_vec(info)?.as_slice())?;
+            }
+            let mut events = txn.open_table(EVENTS)?;
+            for event in &partition.events {
+                events.insert(event.id.as_str(), serde_json::to_vec(event)?.as_slice())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn delete_partitions(&self, ledgers: &[u32]) -> Result<(), crate::Error> {
+        let txn = self.db.begin_write()?;
+        for ledger in ledgers {
+            remove_partition(&txn, *ledger)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn load_partition(&self, ledger: u32) -> Result<Option<PartitionExport>, crate::Error> {
+        let txn = self.db.begin_read()?;
+        let Some(expires_at) = txn.open_table(PARTITIONS)?.get(ledger)? else {
+            return Ok(None);
+        };
+        let ledger_info = txn.open_table(LEDGER_INFO)?;
+        let events = txn.open_table(EVENTS)?;
+        read_partition(&ledger_info, &events, ledger, expires_at.value()).map(Some)
+    }
+
+    fn load_partitions(
+        &self,
+        visit: &mut dyn FnMut(PartitionExport, i64) -> Result<(), crate::Error>,
+    ) -> Result<(), crate::Error> {
+        let txn = self.db.begin_read()?;
+        let partitions = txn.open_table(PARTITIONS)?;
+        let ledger_info = txn.open_table(LEDGER_INFO)?;
+        let events = txn.open_table(EVENTS)?;
+        for entry in partitions.iter()? {
+            let (ledger, expires_at) = entry?;
+            let partition =
+                read_partition(&ledger_info, &events, ledger.value(), expires_at.value())?;
+            visit(partition, expires_at.value())?;
+        }
+        Ok(())
+    }
+
+    fn save_sync_state(&self, key: &str, value: &str) -> Result<(), crate::Error> {
+        let txn = self.db.begin_write()?;
+        txn.open_table(SYNC_STATE)?.insert(key, value)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn load_sync_state(&self) -> Result<Vec<(String, String)>, crate::Error> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(SYNC_STATE)?;
+        let mut state = Vec::new();
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            state.push((key.value().to_string(), value.value().to_string()));
+        }
+        Ok(state)
+    }
+}
+
+fn create_tables(txn: &WriteTransaction) -> Result<(), crate::Error> {
+    txn.open_table(PARTITIONS)?;
+    txn.open_table(LEDGER_INFO)?;
+    txn.open_table(EVENTS)?;
+    txn.open_table(SYNC_STATE)?;
+    Ok(())
+}
+
+/// Read a saved ledger's header details and events.
+fn read_partition(
+    ledger_info: &ReadOnlyTable<u32, &[u8]>,
+    events: &ReadOnlyTable<&str, &[u8]>,
+    ledger: u32,
+    expires_at: i64,
+) -> Result<PartitionExport, crate::Error> {
+    let info = match ledger_info.get(ledger)? {
+        Some(info) => Some(serde_json::from_slice(info.value())?),
+        None => None,
+    };
+    let (start, end) = id_range(ledger);
+    let mut exported = Vec::new();
+    for entry in events.range(start.as_str()..end.as_str())? {
+        let (_, event) = entry?;
+        exported.push(serde_json::from_slice(event.value())?);
+    }
+    Ok(PartitionExport {
+        ledger,
+        info,
+        events: exported,
+        expires_at: Some(expires_at),
+    })
+}
+
+/// Remove a ledger's partition and events, if saved.
+fn remove_partition(txn: &WriteTransaction, ledger: u32) -> Result<(), crate::Error> {
+    txn.open_table(PARTITIONS)?.remove(ledger)?;
+    txn.open_table(LEDGER_INFO)?.remove(ledger)?;
+    let (start, end) = id_range(ledger);
+    txn.open_table(EVENTS)?
+        .retain_in(start.as_str()..end.as_str(), |_, _| false)?;
+    Ok(())
+}
+
+/// The range of internal event IDs in a ledger.
+fn id_range(ledger: u32) -> (String, String) {
+    (
+        format!("evt_{:010}", ledger),
+        format!("evt_{:010}", u64::from(ledger) + 1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ExportedEvent;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-{}-{}.redb",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn partition(ledger: u32, contract_id: &str) -> PartitionExport {
+        PartitionExport {
+            ledger,
+            info: None,
+            expires_at: None,
+            events: vec![ExportedEvent {
+                id: crate::ledger::event_id::event_id(
+                    ledger,
+                    crate::ledger::event_id::EventPhase::Operation,
+                    0,
+                    0,
+                ),
+                ledger_closed_at: "2024-01-01T00:00:00+00:00".to_string(),
+                op_index: Some(0),
+                contract_id: Some(contract_id.to_string()),
+                event_type: "contract".to_string(),
+                topics: json!([{"symbol": "transfer"}]),
+                data: json!({"i128": "1"}),
+                xdr: None,
+                tx_hash: "00".repeat(32),
+                transaction: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("redb-round-trip");
+        let backend = RedbBackend::open(&path).unwrap();
+        backend.save_partition(&partition(2, "CB"), 200).unwrap();
+        backend.save_partition(&partition(1, "CA"), 100).unwrap();
+        backend.save_partition(&partition(3, "CA"), 300).unwrap();
+        backend.save_partition(&partition(1, "CA"), 150).unwrap();
+        backend.delete_partitions(&[3]).unwrap();
+        backend.save_sync_state("last_synced_ledger", "2").unwrap();
+        drop(backend);
+
+        let backend = RedbBackend::open(&path).unwrap();
+        let mut loaded = Vec::new();
+        backend
+            .load_partitions(&mut |p, expires_at| {
+                loaded.push((p.ledger, p.events.len(), expires_at));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(loaded, vec![(1, 1, 150), (2, 1, 200)]);
+        assert_eq!(
+            backend.load_sync_state().unwrap(),
+            vec![("last_synced_ledger".to_string(), "2".to_string())]
+        );
+
+        assert_eq!(backend.load_partition(2).unwrap().unwrap().events.len(), 1);
+        assert!(backend.load_partition(3).unwrap().is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
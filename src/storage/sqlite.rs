@@ -0,0 +1,189 @@
+//! SQLite storage backend.
+//!
+//! Partitions are stored one row per ledger as zstd-compressed JSON of their
+//! [`PartitionExport`], the same form snapshots use, alongside their expiry
+//! time. The database runs in WAL mode so the write-through from ingestion
+//! does not block on readers, with `synchronous = NORMAL`: a power loss may
+//! drop the last few writes, which are backfilled again like any other
+//! missing ledger.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::StorageBackend;
+use crate::db::PartitionExport;
+
+/// zstd level for stored partitions.
+const COMPRESSION_LEVEL: i32 = 3;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS partitions (
+    ledger INTEGER PRIMARY KEY,
+    expires_at INTEGER NOT NULL,
+    data BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS sync_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// A [`StorageBackend`] in a SQLite database file.
+pub struct SqliteBackend {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &Path) -> Result<Self, crate::Error> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().expect("sqlite connection lock poisoned")
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    fn save_partition(
+        &self,
+        partition: &PartitionExport,
+        expires_at: i64,
+    ) -> Result<(), crate::Error> {
+        let json = serde_json::to_vec(partition)?;
+        let data = zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL)?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO partitions (ledger, expires_at, data) VALUES (?1, ?2, ?3)",
+            params![partition.ledger, expires_at, data],
+        )?;
+        Ok(())
+    }
+
+    fn delete_partitions(&self, ledgers: &[u32]) -> Result<(), crate::Error> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM partitions WHERE ledger = ?1")?;
+            for ledger in ledgers {
+                stmt.execute([ledger])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_partition(&self, ledger: u32) -> Result<Option<PartitionExport>, crate::Error> {
+        let data: Option<Vec<u8>> = self
+            .conn()
+            .query_row(
+                "SELECT data FROM partitions WHERE ledger = ?1",
+                [ledger],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match data {
+            Some(data) => Ok(Some(decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn load_partitions(
+        &self,
+        visit: &mut dyn FnMut(PartitionExport, i64) -> Result<(), crate::Error>,
+    ) -> Result<(), crate::Error> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT expires_at, data FROM partitions ORDER BY ledger")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let expires_at: i64 = row.get(0)?;
+            let data: Vec<u8> = row.get(1)?;
+            visit(decode(&data)?, expires_at)?;
+        }
+        Ok(())
+    }
+
+    fn save_sync_state(&self, key: &str, value: &str) -> Result<(), crate::Error> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO sync_state (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    fn load_sync_state(&self) -> Result<Vec<(String, String)>, crate::Error> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT key, value FROM sync_state")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+/// Decode a stored partition.
+fn decode(data: &[u8]) -> Result<PartitionExport, crate::Error> {
+    let json = zstd::decode_all(data)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "stellar-events-api-{}-{}.db",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn partition(ledger: u32) -> PartitionExport {
+        PartitionExport {
+            ledger,
+            info: None,
+            events: Vec::new(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("sqlite-round-trip");
+        let backend = SqliteBackend::open(&path).unwrap();
+        backend.save_partition(&partition(2), 200).unwrap();
+        backend.save_partition(&partition(1), 100).unwrap();
+        backend.save_partition(&partition(3), 300).unwrap();
+        backend.save_partition(&partition(1), 150).unwrap();
+        backend.delete_partitions(&[3]).unwrap();
+        backend.save_sync_state("last_synced_ledger", "2").unwrap();
+        drop(backend);
+
+        let backend = SqliteBackend::open(&path).unwrap();
+        let mut loaded = Vec::new();
+        backend
+            .load_partitions(&mut |p, expires_at| {
+                loaded.push((p.ledger, expires_at));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(loaded, vec![(1, 150), (2, 200)]);
+        assert_eq!(
+            backend.load_sync_state().unwrap(),
+            vec![("last_synced_ledger".to_string(), "2".to_string())]
+        );
+        assert_eq!(backend.load_partition(2).unwrap().unwrap().ledger, 2);
+        assert!(backend.load_partition(3).unwrap().is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
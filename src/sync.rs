@@ -1,26 +1,177 @@
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Duration;
 
-use crate::ledger::events::{extract_events, ExtractedEvent};
-use crate::ledger::fetch::{fetch_ledger_raw, parse_ledger_batch};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use hex::FromHex;
+
+use crate::db::CacheSource;
+use crate::ledger::events::{
+    attach_transactions, extract_events, extract_ledger_info, ExtractedEvent, LedgerInfo,
+};
+use crate::ledger::fetch::{fetch_ledger_batch, fetch_ledger_rpc, refetch_config};
 use crate::ledger::path::StoreConfig;
+use crate::redact::redact_url;
 use crate::AppState;
 
+/// Default Horizon endpoint used to discover the network tip.
+pub const DEFAULT_HORIZON_URL: &str = "https://horizon.stellar.org";
+
+/// SDF's Horizon server for a well-known network, by passphrase.
+pub fn default_horizon_url(network_passphrase: &str) -> Option<&'static str> {
+    match network_passphrase {
+        "Public Global Stellar Network ; September 2015" => Some(DEFAULT_HORIZON_URL),
+        "Test SDF Network ; September 2015" => Some("https://horizon-testnet.stellar.org"),
+        "Test SDF Future Network ; October 2022" => Some("https://horizon-futurenet.stellar.org"),
+        _ => None,
+    }
+}
+
 /// How often to poll for new ledgers.
 const POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 /// How often to run the cleanup task.
 const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
 
+/// How often to re-discover the network tip while catch-up is enabled.
+const TIP_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often to log a progress summary while catching up.
+const CATCHUP_PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sync state key holding the most recently known network tip.
+pub const NETWORK_TIP_KEY: &str = "network_tip";
+
+/// Sync state key holding the first ledger sync started from, kept across
+/// restarts. The history backfill fills in the ledgers before it.
+pub const SYNC_START_KEY: &str = "sync_start_ledger";
+
+/// Sync state key set, to the end ledger, once sync has reached it.
+pub const END_LEDGER_REACHED_KEY: &str = "end_ledger_reached";
+
+/// Sync state key holding the header hash of the last synced ledger, hex
+/// encoded, for checking the hash chain across restarts. Empty when the
+/// hash is unknown.
+pub const LAST_SYNCED_HASH_KEY: &str = "last_synced_ledger_hash";
+
+/// Sync state key counting the ledgers whose previous ledger hash did not
+/// match the header of the ledger synced before them.
+pub const HASH_CHAIN_BREAKS_KEY: &str = "hash_chain_breaks";
+
+/// Sync state key holding the most recent ledger that broke the hash chain.
+pub const HASH_CHAIN_BREAK_LEDGER_KEY: &str = "hash_chain_break_ledger";
+
+/// Options controlling where the background sync starts and stops.
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// Ledger sequence to start syncing from (if not resuming).
+    pub start_ledger: Option<u32>,
+    /// Number of ledgers behind the discovered network tip to start from
+    /// when neither `start_ledger` nor a previously synced ledger is known.
+    pub start_offset: u32,
+    /// Last ledger sequence to sync. Once reached, sync stops and the
+    /// process keeps serving the cached data.
+    pub end_ledger: Option<u32>,
+    /// Number of ledgers to fetch concurrently.
+    pub parallel_fetches: u32,
+    /// Lag behind the network tip, in ledgers, above which sync switches to
+    /// catch-up mode. `None` disables catch-up mode.
+    pub catchup_threshold: Option<u32>,
+    /// Most ledgers to fetch concurrently while catching up. Catch-up starts
+    /// at `parallel_fetches`, doubles after each batch fetched in full up to
+    /// this cap, and halves after a fetch error.
+    pub catchup_parallel_fetches: u32,
+    /// Where to discover the latest network ledger from.
+    pub tip_source: TipSource,
+    /// Contract IDs whose ingested events are counted individually in the
+    /// `sync_contract_events_total` metric. Limited to
+    /// [`MAX_METRICS_CONTRACTS`] entries to bound label cardinality.
+    pub metrics_contracts: Vec<String>,
+}
+
+/// Maximum number of contracts tracked by per-contract ingestion metrics.
+pub const MAX_METRICS_CONTRACTS: usize = 100;
+
+/// Source used to discover the latest ledger on the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TipSource {
+    /// A Horizon server, read from `history_latest_ledger` on its root resource.
+    Horizon(String),
+    /// A Stellar RPC server, read via the `getLatestLedger` method.
+    Rpc(String),
+}
+
+impl std::fmt::Display for TipSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TipSource::Horizon(url) => write!(f, "horizon ({})", redact_url(url)),
+            TipSource::Rpc(url) => write!(f, "rpc ({})", redact_url(url)),
+        }
+    }
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            start_ledger: None,
+            start_offset: 10,
+            end_ledger: None,
+            parallel_fetches: 10,
+            catchup_threshold: None,
+            catchup_parallel_fetches: 50,
+            tip_source: TipSource::Horizon(DEFAULT_HORIZON_URL.to_string()),
+            metrics_contracts: Vec::new(),
+        }
+    }
+}
+
+/// Sync regime, chosen from the distance to the network tip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncPhase {
+    /// Far behind the tip: fetch aggressively and log progress sparingly.
+    CatchUp,
+    /// Near the tip: fetch the steady-state batch and poll gently.
+    Following,
+}
+
 /// Background sync task that proactively fetches new ledgers.
 pub async fn run_sync(
     client: reqwest::Client,
     meta_url: String,
-    store_config: StoreConfig,
     state: Arc<AppState>,
-    start_ledger: Option<u32>,
-    parallel_fetches: u32,
+    options: SyncOptions,
 ) {
+    let SyncOptions {
+        start_ledger,
+        start_offset,
+        end_ledger,
+        parallel_fetches,
+        catchup_threshold,
+        catchup_parallel_fetches,
+        tip_source,
+        mut metrics_contracts,
+    } = options;
+
+    if metrics_contracts.len() > MAX_METRICS_CONTRACTS {
+        tracing::warn!(
+            count = metrics_contracts.len(),
+            max = MAX_METRICS_CONTRACTS,
+            "too many metrics contracts, ignoring the excess"
+        );
+        metrics_contracts.truncate(MAX_METRICS_CONTRACTS);
+    }
+    // Register every tracked contract up front so a contract that never
+    // emits still exports a zero series to alert on.
+    for contract in &metrics_contracts {
+        metrics::counter!("sync_contract_events_total", "contract" => contract.clone()).absolute(0);
+    }
+    let metrics_contracts: HashSet<String> = metrics_contracts.into_iter().collect();
+
+    let mut network_tip: Option<u32> = None;
+    // Header hash of the last synced ledger, when known, which the next
+    // ledger's previous ledger hash must match.
+    let mut previous_hash: Option<[u8; 32]> = None;
+
     // Determine starting point
     let mut current_ledger = match start_ledger {
         Some(seq) => seq,
@@ -33,14 +184,28 @@ pub async fn run_sync(
                 .flatten()
                 .and_then(|v| v.parse::<u32>().ok());
             match last {
-                Some(seq) => seq + 1,
+                Some(seq) => {
+                    previous_hash = state
+                        .store
+                        .get_sync_state(LAST_SYNCED_HASH_KEY)
+                        .ok()
+                        .flatten()
+                        .and_then(|v| <[u8; 32]>::from_hex(v).ok());
+                    seq + 1
+                }
                 None => {
-                    // Try to discover the latest ledger from Horizon
-                    match discover_latest_ledger(&client).await {
+                    // Try to discover the latest ledger from the network
+                    match discover_latest_ledger(&client, &tip_source).await {
                         Some(seq) => {
-                            tracing::info!(ledger = seq, "discovered latest ledger from horizon");
-                            // Start a few ledgers back to have some initial data
-                            seq.saturating_sub(10)
+                            tracing::info!(
+                                ledger = seq,
+                                offset = start_offset,
+                                source = %tip_source,
+                                "discovered latest ledger"
+                            );
+                            record_network_tip(&state, &mut network_tip, seq);
+                            // Start behind the tip to have some initial data
+                            seq.saturating_sub(start_offset)
                         }
                         None => {
                             tracing::warn!(
@@ -56,6 +221,17 @@ pub async fn run_sync(
     };
 
     tracing::info!(start = current_ledger, "starting ledger sync");
+    if state
+        .store
+        .get_sync_state(SYNC_START_KEY)
+        .ok()
+        .flatten()
+        .is_none()
+    {
+        let _ = state
+            .store
+            .set_sync_state(SYNC_START_KEY, &current_ledger.to_string());
+    }
 
     // Spawn cleanup task
     let cleanup_state = Arc::clone(&state);
@@ -71,14 +247,58 @@ pub async fn run_sync(
                 }
                 _ => {}
             }
+            match cleanup_state.store.spill_idle() {
+                Ok(count) if count > 0 => {
+                    tracing::info!(count, "spilled idle ledgers to disk");
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "error spilling idle ledgers");
+                }
+                _ => {}
+            }
+            let compacted = cleanup_state.store.compact();
+            if compacted > 0 {
+                tracing::info!(count = compacted, "compacted older ledgers into segments");
+            }
+            let compressed = cleanup_state.store.compress_old();
+            if compressed > 0 {
+                tracing::info!(count = compressed, "compressed older ledgers");
+            }
         }
     });
 
+    // Ledgers the meta store does not have yet are read from RPC instead,
+    // when it is the tip source.
+    let rpc_fallback = match &tip_source {
+        TipSource::Rpc(url) => Some(url.as_str()),
+        TipSource::Horizon(_) => None,
+    };
     let mut consecutive_failures = 0u32;
+    let mut phase = SyncPhase::Following;
+    // Parallelism while catching up, raised from `parallel_fetches` towards
+    // `catchup_parallel_fetches` as batches succeed, and cut back on errors.
+    let mut catchup_batch_size = parallel_fetches;
+    let catchup_max_batch_size = catchup_parallel_fetches.max(parallel_fetches);
+    let mut last_tip_check: Option<std::time::Instant> = None;
+    let mut last_progress_log = std::time::Instant::now();
 
     loop {
+        if let Some(end) = end_ledger.filter(|&end| current_ledger > end) {
+            let _ = state
+                .store
+                .set_sync_state(END_LEDGER_REACHED_KEY, &end.to_string());
+            tracing::info!(
+                end,
+                "reached end ledger, sync stopped; serving cached data only"
+            );
+            return;
+        }
+
         // Skip any cached ledgers
         loop {
+            if end_ledger.is_some_and(|end| current_ledger > end) {
+                break;
+            }
             let cached = state
                 .store
                 .is_ledger_cached(current_ledger)
@@ -86,19 +306,63 @@ pub async fn run_sync(
             if cached {
                 current_ledger += 1;
                 consecutive_failures = 0;
+                // The next ledger synced does not follow on from the last
+                // one sync fetched, so its hash cannot be checked.
+                previous_hash = None;
             } else {
                 break;
             }
         }
 
-        // Build batch of ledger sequences to fetch
-        let batch_sequences: Vec<u32> =
-            (current_ledger..current_ledger + parallel_fetches).collect();
+        if end_ledger.is_some_and(|end| current_ledger > end) {
+            continue;
+        }
+
+        // Pick the sync regime from the current lag behind the network tip.
+        if let Some(threshold) = catchup_threshold {
+            if last_tip_check.is_none_or(|t| t.elapsed() >= TIP_REFRESH_INTERVAL) {
+                if let Some(tip) = discover_latest_ledger(&client, &tip_source).await {
+                    record_network_tip(&state, &mut network_tip, tip);
+                }
+                last_tip_check = Some(std::time::Instant::now());
+            }
+            let lag = network_tip.map_or(0, |tip| tip.saturating_sub(current_ledger));
+            metrics::gauge!("sync_lag_ledgers").set(f64::from(lag));
+            let next_phase = if lag > threshold {
+                SyncPhase::CatchUp
+            } else {
+                SyncPhase::Following
+            };
+            if next_phase != phase {
+                tracing::info!(lag, phase = ?next_phase, "switching sync phase");
+                metrics::gauge!("sync_catching_up").set(if next_phase == SyncPhase::CatchUp {
+                    1.0
+                } else {
+                    0.0
+                });
+                phase = next_phase;
+                catchup_batch_size = parallel_fetches;
+            }
+        }
+
+        let batch_size = match phase {
+            SyncPhase::CatchUp => catchup_batch_size,
+            SyncPhase::Following => parallel_fetches,
+        };
+        metrics::gauge!("sync_parallel_fetches").set(f64::from(batch_size));
 
-        // Launch all fetches concurrently
+        // Build batch of ledger sequences to fetch, never past the end ledger
+        let batch_end = match end_ledger {
+            Some(end) => (current_ledger + batch_size).min(end + 1),
+            None => current_ledger + batch_size,
+        };
+        let batch_sequences: Vec<u32> = (current_ledger..batch_end).collect();
+
+        // Launch all fetches concurrently, with the latest store config
+        let store_config = state.config.get();
         let futures: Vec<_> = batch_sequences
             .iter()
-            .map(|&seq| fetch_and_extract(&client, &meta_url, &store_config, seq))
+            .map(|&seq| fetch_with_fallback(&client, &meta_url, rpc_fallback, &store_config, seq))
             .collect();
         let results = futures::future::join_all(futures).await;
 
@@ -110,14 +374,27 @@ pub async fn run_sync(
         for (i, result) in results.into_iter().enumerate() {
             let seq = batch_sequences[i];
             match result {
-                Ok(events) => {
+                Ok((info, events)) => {
                     let event_count = events.len();
+                    if let Some(info) = &info {
+                        check_hash_chain(&state, previous_hash, info);
+                    }
+                    if !metrics_contracts.is_empty() {
+                        record_contract_metrics(&events, &metrics_contracts);
+                    }
                     let db_result = (|| -> Result<(), crate::Error> {
-                        state.store.insert_events(events)?;
-                        state.store.record_ledger_cached(seq, 0)?;
+                        state
+                            .store
+                            .insert_ledger(seq, info, events, CacheSource::Sync)?;
                         state
                             .store
                             .set_sync_state("last_synced_ledger", &seq.to_string())?;
+                        // Without header details the hash is unknown, so
+                        // clear it rather than leave an older ledger's.
+                        let hash = info.map(|info| hex::encode(info.hash));
+                        state
+                            .store
+                            .set_sync_state(LAST_SYNCED_HASH_KEY, &hash.unwrap_or_default())?;
                         Ok(())
                     })();
 
@@ -127,12 +404,15 @@ pub async fn run_sync(
                         break;
                     }
 
+                    previous_hash = info.map(|info| info.hash);
                     advanced += 1;
                     total_events += event_count;
                     consecutive_failures = 0;
                 }
                 Err(crate::Error::LedgerNotFound(_)) => {
                     tracing::debug!(ledger = seq, "ledger not yet available, waiting");
+                    // The archive has nothing newer, so we are at the tip.
+                    record_network_tip(&state, &mut network_tip, seq.saturating_sub(1));
                     should_sleep = Some(SleepReason::NotFound);
                     break;
                 }
@@ -157,19 +437,47 @@ pub async fn run_sync(
             metrics::counter!("sync_ledgers_total").increment(advanced as u64);
             metrics::counter!("sync_events_total").increment(total_events as u64);
             metrics::gauge!("sync_latest_ledger").set(f64::from(end));
-            tracing::info!(
-                ledgers = format!("{}..{}", start, end),
-                events = total_events,
-                "synced ledgers"
-            );
+            match phase {
+                SyncPhase::Following => {
+                    tracing::info!(
+                        ledgers = format!("{}..{}", start, end),
+                        events = total_events,
+                        "synced ledgers"
+                    );
+                }
+                SyncPhase::CatchUp => {
+                    tracing::debug!(
+                        ledgers = format!("{}..{}", start, end),
+                        events = total_events,
+                        "synced ledgers"
+                    );
+                    if last_progress_log.elapsed() >= CATCHUP_PROGRESS_INTERVAL {
+                        tracing::info!(
+                            ledger = end,
+                            tip = network_tip,
+                            lag = network_tip.map(|tip| tip.saturating_sub(end)),
+                            "catching up"
+                        );
+                        last_progress_log = std::time::Instant::now();
+                    }
+                }
+            }
             current_ledger += advanced;
 
             // Periodically update query planner statistics (no-op for in-memory store)
-            if current_ledger % 1000 < parallel_fetches {
+            if current_ledger % 1000 < batch_size {
                 let _ = state.store.analyze();
             }
         }
 
+        if phase == SyncPhase::CatchUp {
+            catchup_batch_size = match should_sleep {
+                None => (catchup_batch_size * 2).min(catchup_max_batch_size),
+                Some(SleepReason::Error) => (catchup_batch_size / 2).max(parallel_fetches),
+                Some(SleepReason::NotFound) => catchup_batch_size,
+            };
+        }
+
         match should_sleep {
             Some(SleepReason::NotFound) => {
                 tokio::time::sleep(POLL_INTERVAL).await;
@@ -185,44 +493,278 @@ pub async fn run_sync(
     }
 }
 
+/// Check that `info` follows on from the ledger synced before it, whose
+/// header hashed to `previous_hash`, flagging a break in the hash chain.
+/// A break means the origin served a corrupt ledger, or ledgers from
+/// different histories; it is recorded rather than stopping sync.
+fn check_hash_chain(state: &AppState, previous_hash: Option<[u8; 32]>, info: &LedgerInfo) {
+    let Some(previous_hash) = previous_hash else {
+        return;
+    };
+    if info.previous_hash == previous_hash {
+        return;
+    }
+    metrics::counter!("sync_hash_chain_breaks_total").increment(1);
+    tracing::warn!(
+        ledger = info.sequence,
+        expected = %hex::encode(previous_hash),
+        actual = %hex::encode(info.previous_hash),
+        "ledger does not follow on from the previous ledger's hash"
+    );
+    let breaks = state
+        .store
+        .get_sync_state(HASH_CHAIN_BREAKS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    let _ = state
+        .store
+        .set_sync_state(HASH_CHAIN_BREAKS_KEY, &(breaks + 1).to_string());
+    let _ = state
+        .store
+        .set_sync_state(HASH_CHAIN_BREAK_LEDGER_KEY, &info.sequence.to_string());
+}
+
+/// Remember the network tip, and publish it for readiness checks.
+fn record_network_tip(state: &AppState, network_tip: &mut Option<u32>, tip: u32) {
+    *network_tip = Some(tip);
+    let _ = state
+        .store
+        .set_sync_state(NETWORK_TIP_KEY, &tip.to_string());
+}
+
 enum SleepReason {
     NotFound,
     Error,
 }
 
-/// Fetch a ledger, decompress, parse, and extract events (no DB access).
-#[tracing::instrument(skip(client, meta_url, store_config))]
+/// Count ingested events per tracked contract.
+fn record_contract_metrics(events: &[ExtractedEvent], contracts: &HashSet<String>) {
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    for event in events {
+        if let Some(ref cid) = event.contract_id {
+            if contracts.contains(cid) {
+                *counts.entry(cid).or_default() += 1;
+            }
+        }
+    }
+    for (contract, count) in counts {
+        metrics::counter!("sync_contract_events_total", "contract" => contract.to_string())
+            .increment(count);
+    }
+}
+
+/// Re-fetch the meta store's `.config.json` every `interval`, revalidating
+/// with its `ETag`, and swap it into `state` when the bucket layout changes.
+/// A config for another network is logged and ignored, and marks the
+/// instance not ready until the store's config names this network again.
+pub async fn run_config_refresh(
+    client: reqwest::Client,
+    meta_url: String,
+    state: Arc<AppState>,
+    interval: Duration,
+) {
+    let mut etag: Option<String> = None;
+    loop {
+        tokio::time::sleep(interval).await;
+        let (config, new_etag) = match refetch_config(&client, &meta_url, etag.as_deref()).await {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => {
+                tracing::debug!("store config not modified");
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to refresh store config");
+                continue;
+            }
+        };
+        match state.config.replace(config) {
+            Ok(changed) => {
+                etag = new_etag;
+                if changed {
+                    metrics::counter!("store_config_changes_total").increment(1);
+                    let config = state.config.get();
+                    tracing::warn!(
+                        ledgers_per_batch = config.ledgers_per_batch,
+                        batches_per_partition = config.batches_per_partition,
+                        "store config changed, using the new layout"
+                    );
+                }
+            }
+            Err(e) => {
+                metrics::counter!("store_config_network_mismatches_total").increment(1);
+                tracing::error!(error = %e, "ignoring refreshed store config");
+            }
+        }
+    }
+}
+
+/// A ledger fetch that concurrent callers for the same ledger await together.
+type SharedFetch = Shared<
+    BoxFuture<'static, Result<(Option<LedgerInfo>, Vec<ExtractedEvent>), Arc<crate::Error>>>,
+>;
+
+/// Ledger fetches in progress, by meta URL and ledger sequence.
+static IN_FLIGHT: LazyLock<Mutex<HashMap<(String, u32), SharedFetch>>> =
+    LazyLock::new(Default::default);
+
+/// Removes a fetch from [`IN_FLIGHT`] when the caller that started it
+/// finishes or is dropped, so later callers start a fresh fetch.
+struct InFlightGuard {
+    key: (String, u32),
+    fetch: SharedFetch,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut in_flight = IN_FLIGHT.lock().expect("in-flight fetch lock poisoned");
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|fetch| fetch.ptr_eq(&self.fetch))
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+/// Fetch a ledger, decompress, parse, and extract its header details and
+/// events (no DB access). Concurrent calls for the same ledger, from sync,
+/// backfill, or queries, share one fetch from the origin.
 pub async fn fetch_and_extract(
     client: &reqwest::Client,
     meta_url: &str,
     store_config: &StoreConfig,
     ledger_sequence: u32,
-) -> Result<Vec<ExtractedEvent>, crate::Error> {
-    let raw = fetch_ledger_raw(client, meta_url, store_config, ledger_sequence).await?;
-    let batch = parse_ledger_batch(&raw)?;
-    let events = extract_events(&batch);
+) -> Result<(Option<LedgerInfo>, Vec<ExtractedEvent>), crate::Error> {
+    let key = (meta_url.to_string(), ledger_sequence);
+    let (fetch, _guard) = {
+        let mut in_flight = IN_FLIGHT.lock().expect("in-flight fetch lock poisoned");
+        match in_flight.get(&key) {
+            Some(fetch) => {
+                metrics::counter!("ledger_fetches_coalesced_total").increment(1);
+                (fetch.clone(), None)
+            }
+            None => {
+                let (client, meta_url, store_config) =
+                    (client.clone(), meta_url.to_string(), store_config.clone());
+                let fetch = async move {
+                    fetch_and_extract_uncoalesced(
+                        &client,
+                        &meta_url,
+                        &store_config,
+                        ledger_sequence,
+                    )
+                    .await
+                    .map_err(Arc::new)
+                }
+                .boxed()
+                .shared();
+                in_flight.insert(key.clone(), fetch.clone());
+                let guard = InFlightGuard {
+                    key,
+                    fetch: fetch.clone(),
+                };
+                (fetch, Some(guard))
+            }
+        }
+    };
+    fetch.await.map_err(|e| match *e {
+        crate::Error::LedgerNotFound(seq) => crate::Error::LedgerNotFound(seq),
+        _ => crate::Error::Shared(e),
+    })
+}
+
+#[tracing::instrument(skip(client, meta_url, store_config))]
+async fn fetch_and_extract_uncoalesced(
+    client: &reqwest::Client,
+    meta_url: &str,
+    store_config: &StoreConfig,
+    ledger_sequence: u32,
+) -> Result<(Option<LedgerInfo>, Vec<ExtractedEvent>), crate::Error> {
+    let batch = fetch_ledger_batch(client, meta_url, store_config, ledger_sequence).await?;
+    let info = extract_ledger_info(&batch, ledger_sequence);
+    let mut events = extract_events(&batch);
+    attach_transactions(&batch, &store_config.network_passphrase, &mut events);
     tracing::trace!(
         ledger = ledger_sequence,
         events = events.len(),
         "extracted events"
     );
-    Ok(events)
+    Ok((info, events))
+}
+
+/// Like [`fetch_and_extract`], reading the ledger from a Stellar RPC server's
+/// `getLedgers` method instead of the meta store.
+#[tracing::instrument(skip(client, rpc_url, store_config))]
+pub async fn fetch_and_extract_rpc(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    store_config: &StoreConfig,
+    ledger_sequence: u32,
+) -> Result<(Option<LedgerInfo>, Vec<ExtractedEvent>), crate::Error> {
+    let batch = fetch_ledger_rpc(client, rpc_url, ledger_sequence).await?;
+    let info = extract_ledger_info(&batch, ledger_sequence);
+    let mut events = extract_events(&batch);
+    attach_transactions(&batch, &store_config.network_passphrase, &mut events);
+    Ok((info, events))
+}
+
+/// Fetch a ledger from the meta store, falling back to `rpc_url`, if given,
+/// when the store does not have it (yet).
+async fn fetch_with_fallback(
+    client: &reqwest::Client,
+    meta_url: &str,
+    rpc_url: Option<&str>,
+    store_config: &StoreConfig,
+    ledger_sequence: u32,
+) -> Result<(Option<LedgerInfo>, Vec<ExtractedEvent>), crate::Error> {
+    let result = fetch_and_extract(client, meta_url, store_config, ledger_sequence).await;
+    let Some(rpc_url) = rpc_url else {
+        return result;
+    };
+    match result {
+        Err(crate::Error::LedgerNotFound(_)) => {
+            let result =
+                fetch_and_extract_rpc(client, rpc_url, store_config, ledger_sequence).await;
+            if result.is_ok() {
+                metrics::counter!("sync_rpc_fallback_ledgers_total").increment(1);
+                tracing::debug!(ledger = ledger_sequence, "fetched ledger from RPC");
+            }
+            result
+        }
+        result => result,
+    }
 }
 
-/// Try to discover the latest ledger sequence from Horizon.
-async fn discover_latest_ledger(client: &reqwest::Client) -> Option<u32> {
-    let resp = client
-        .get("https://horizon.stellar.org/")
-        .send()
-        .await
-        .ok()?;
+/// Try to discover the latest ledger sequence on the network.
+pub async fn discover_latest_ledger(client: &reqwest::Client, source: &TipSource) -> Option<u32> {
+    let resp = match source {
+        TipSource::Horizon(url) => client.get(url).send().await.ok()?,
+        TipSource::Rpc(url) => client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "getLatestLedger",
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .ok()?,
+    };
 
     if !resp.status().is_success() {
         return None;
     }
 
     let body: serde_json::Value = serde_json::from_slice(&resp.bytes().await.ok()?).ok()?;
-    body.get("history_latest_ledger")
-        .and_then(|v| v.as_u64())
-        .map(|v| v as u32)
+    let latest = match source {
+        TipSource::Horizon(_) => body.get("history_latest_ledger"),
+        TipSource::Rpc(_) => body.get("result").and_then(|r| r.get("sequence")),
+    };
+    latest.and_then(|v| v.as_u64()).map(|v| v as u32)
 }
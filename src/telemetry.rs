@@ -0,0 +1,280 @@
+//! Trace export over OTLP, and exemplars linking request latencies to the
+//! traces that produced them.
+//!
+//! With an OTLP endpoint configured, spans are exported to it, and each
+//! request-latency observation made inside a sampled trace is kept as the
+//! exemplar of its histogram bucket, replacing the bucket's previous one.
+//! Prometheus only reads exemplars from the OpenMetrics format, so
+//! [`openmetrics`] rewrites the exporter's text output into it with the
+//! exemplars attached to the latency buckets.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Name of the request-latency histogram.
+pub const LATENCY_METRIC: &str = "api_request_duration_seconds";
+
+/// Upper bounds, in seconds, of the request-latency histogram's buckets
+/// when exemplars are recorded.
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Content type of metrics rendered by [`openmetrics`].
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Service name reported with exported spans.
+const SERVICE_NAME: &str = "stellar-events-api";
+
+/// A latency observed inside a sampled trace.
+#[derive(Debug, Clone, PartialEq)]
+struct Exemplar {
+    trace_id: String,
+    seconds: f64,
+    /// Unix time of the observation, in seconds.
+    timestamp: f64,
+}
+
+/// Endpoint -> latest exemplar of each latency bucket, the last for `+Inf`.
+type Exemplars = HashMap<&'static str, Vec<Option<Exemplar>>>;
+
+static EXEMPLARS: OnceLock<Mutex<Exemplars>> = OnceLock::new();
+
+/// Build a tracing layer exporting spans to the OTLP/HTTP traces endpoint
+/// at `endpoint` (e.g. `http://localhost:4318/v1/traces`). The returned
+/// provider flushes spans still queued when shut down.
+pub fn otlp_layer<S>(
+    endpoint: &str,
+) -> Result<(SdkTracerProvider, OpenTelemetryLayer<S, Tracer>), crate::Error>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| crate::Error::Internal(format!("failed to build OTLP exporter: {e}")))?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(SERVICE_NAME)
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    Ok((provider, layer))
+}
+
+/// Start keeping exemplars of request latencies. Only the first call has an
+/// effect.
+pub fn enable_exemplars() {
+    let _ = EXEMPLARS.set(Mutex::default());
+}
+
+/// Whether exemplars are being kept.
+pub fn exemplars_enabled() -> bool {
+    EXEMPLARS.get().is_some()
+}
+
+/// Record how long a request to `endpoint` took, keeping it as its bucket's
+/// exemplar if exemplars are enabled and the request is being traced.
+pub fn record_latency(endpoint: &'static str, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    metrics::histogram!(LATENCY_METRIC, "endpoint" => endpoint).record(seconds);
+
+    let Some(exemplars) = EXEMPLARS.get() else {
+        return;
+    };
+    let Some(trace_id) = current_trace_id() else {
+        return;
+    };
+    let bucket = LATENCY_BUCKETS
+        .iter()
+        .position(|&le| seconds <= le)
+        .unwrap_or(LATENCY_BUCKETS.len());
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    let mut exemplars = exemplars.lock().expect("exemplar lock poisoned");
+    let buckets = exemplars
+        .entry(endpoint)
+        .or_insert_with(|| vec![None; LATENCY_BUCKETS.len() + 1]);
+    buckets[bucket] = Some(Exemplar {
+        trace_id,
+        seconds,
+        timestamp,
+    });
+}
+
+/// Trace ID of the current span, if it belongs to a sampled trace.
+fn current_trace_id() -> Option<String> {
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    (span_context.is_valid() && span_context.is_sampled())
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// Rewrite metrics in the Prometheus text format as OpenMetrics, attaching
+/// the kept exemplars to the request-latency buckets. Counter families are
+/// named without their `_total` suffix, as OpenMetrics requires, and
+/// counters without one are described as `unknown`.
+pub fn openmetrics(text: &str) -> String {
+    let counters: HashSet<&str> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("# TYPE ")?.strip_suffix(" counter"))
+        .collect();
+    let exemplars = EXEMPLARS
+        .get()
+        .map(|e| e.lock().expect("exemplar lock poisoned").clone())
+        .unwrap_or_default();
+
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines().filter(|line| !line.is_empty()) {
+        if let Some((directive, rest)) = line.strip_prefix("# ").and_then(|l| l.split_once(' ')) {
+            let (name, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+            if counters.contains(name) {
+                match name.strip_suffix("_total") {
+                    Some(family) => {
+                        let _ = writeln!(out, "# {directive} {family} {tail}");
+                        continue;
+                    }
+                    None if directive == "TYPE" => {
+                        let _ = writeln!(out, "# TYPE {name} unknown");
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+        }
+        out.push_str(line);
+        let exemplar = line
+            .strip_prefix(LATENCY_METRIC)
+            .and_then(|l| l.strip_prefix("_bucket{"))
+            .and_then(|l| bucket_exemplar(&exemplars, l));
+        if let Some(exemplar) = exemplar {
+            let _ = write!(
+                out,
+                " # {{trace_id=\"{}\"}} {} {}",
+                exemplar.trace_id, exemplar.seconds, exemplar.timestamp
+            );
+        }
+        out.push('\n');
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+/// The exemplar kept for a latency bucket, given the rest of its sample
+/// line after the opening brace of its labels.
+fn bucket_exemplar<'a>(exemplars: &'a Exemplars, line: &str) -> Option<&'a Exemplar> {
+    let labels = format!(",{}", line.split_once('}')?.0);
+    let endpoint = label(&labels, "endpoint")?;
+    let le = label(&labels, "le")?;
+    let bucket = if le == "+Inf" {
+        LATENCY_BUCKETS.len()
+    } else {
+        let le: f64 = le.parse().ok()?;
+        LATENCY_BUCKETS.iter().position(|&bound| bound == le)?
+    };
+    exemplars.get(endpoint)?.get(bucket)?.as_ref()
+}
+
+/// The value of label `name` in `labels`, given with a leading comma.
+fn label<'a>(labels: &'a str, name: &str) -> Option<&'a str> {
+    let start = labels.find(&format!(",{name}=\""))? + name.len() + 3;
+    let len = labels[start..].find('"')?;
+    Some(&labels[start..start + len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_exemplar_of_traced_request() {
+        enable_exemplars();
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let trace_id = tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            let _entered = span.enter();
+            record_latency("traced", Duration::from_millis(30));
+            current_trace_id().unwrap()
+        });
+        // Requests outside a trace leave the exemplar alone.
+        record_latency("traced", Duration::from_millis(40));
+
+        let text = "\
+# TYPE api_request_duration_seconds histogram
+api_request_duration_seconds_bucket{endpoint=\"traced\",le=\"0.025\"} 0
+api_request_duration_seconds_bucket{endpoint=\"traced\",le=\"0.05\"} 2
+";
+        let rendered = openmetrics(text);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert!(!lines[1].contains('#'));
+        let exemplar = format!("}} 2 # {{trace_id=\"{trace_id}\"}} 0.03 ");
+        assert!(lines[2].contains(&exemplar), "{}", lines[2]);
+    }
+
+    #[test]
+    fn test_openmetrics() {
+        let exemplar = Exemplar {
+            trace_id: "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+            seconds: 0.042,
+            timestamp: 1700000000.5,
+        };
+        let mut buckets = vec![None; LATENCY_BUCKETS.len() + 1];
+        buckets[3] = Some(exemplar.clone());
+        let exemplars: Exemplars = HashMap::from([("events", buckets)]);
+        assert_eq!(
+            bucket_exemplar(&exemplars, r#"endpoint="events",le="0.05"} 3"#),
+            Some(&exemplar)
+        );
+        assert_eq!(
+            bucket_exemplar(&exemplars, r#"endpoint="events",le="0.1"} 3"#),
+            None
+        );
+        assert_eq!(
+            bucket_exemplar(&exemplars, r#"endpoint="get_event",le="0.05"} 3"#),
+            None
+        );
+
+        let text = "\
+# HELP api_requests_total Requests served
+# TYPE api_requests_total counter
+api_requests_total{endpoint=\"events\"} 3
+
+# TYPE sync_errors counter
+sync_errors 1
+
+# TYPE store_partitions_total gauge
+store_partitions_total 10
+";
+        assert_eq!(
+            openmetrics(text),
+            "\
+# HELP api_requests Requests served
+# TYPE api_requests counter
+api_requests_total{endpoint=\"events\"} 3
+# TYPE sync_errors unknown
+sync_errors 1
+# TYPE store_partitions_total gauge
+store_partitions_total 10
+# EOF
+"
+        );
+    }
+}
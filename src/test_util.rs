@@ -0,0 +1,337 @@
+//! Fixtures for integration tests, enabled by the `test-util` feature.
+//!
+//! Downstream crates embedding this one can seed an `EventStore` with
+//! [`ExtractedEvent::builder`], or build ledger data and serve it from a mock
+//! metadata store without writing XDR fixtures by hand:
+//!
+//! ```no_run
+//! # async fn example() {
+//! use stellar_events_api::test_util::{build_test_ledger_compressed, start_mock_s3};
+//!
+//! let ledger = build_test_ledger_compressed(1000, 2, 3);
+//! let meta_url = start_mock_s3(ledger).await;
+//! # }
+//! ```
+
+use std::io::Cursor;
+
+use sha2::Digest;
+
+use stellar_xdr::curr::*;
+
+use crate::ledger::events::{EventPhase, EventType, ExtractedEvent};
+
+impl ExtractedEvent {
+    /// Start building an event for seeding an `EventStore` in tests.
+    ///
+    /// ```
+    /// use stellar_events_api::ledger::events::ExtractedEvent;
+    ///
+    /// let event = ExtractedEvent::builder()
+    ///     .ledger(100)
+    ///     .tx_index(2)
+    ///     .contract("CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA")
+    ///     .symbol_topic("transfer")
+    ///     .data(serde_json::json!({"i128": "100"}))
+    ///     .build();
+    /// assert_eq!(event.ledger_sequence, 100);
+    /// assert_eq!(event.topics_xdr_json, vec![serde_json::json!({"symbol": "transfer"})]);
+    /// ```
+    pub fn builder() -> ExtractedEventBuilder {
+        ExtractedEventBuilder::default()
+    }
+}
+
+/// Builder for [`ExtractedEvent`]. Unset fields default to a contract event
+/// in ledger 1 with no contract, no topics, and `void` data.
+#[derive(Debug, Clone)]
+pub struct ExtractedEventBuilder {
+    event: ExtractedEvent,
+}
+
+impl Default for ExtractedEventBuilder {
+    fn default() -> Self {
+        ExtractedEventBuilder {
+            event: ExtractedEvent {
+                ledger_sequence: 1,
+                ledger_closed_at: 1_700_000_000,
+                phase: EventPhase::Operation,
+                tx_index: 0,
+                event_index: 0,
+                op_index: Some(0),
+                tx_hash: "0".repeat(64),
+                contract_id: None,
+                event_type: EventType::Contract,
+                topics_xdr_json: Vec::new(),
+                data_xdr_json: Some(serde_json::json!("void")),
+                xdr: None,
+                transaction: None,
+            },
+        }
+    }
+}
+
+impl ExtractedEventBuilder {
+    pub fn ledger(mut self, ledger_sequence: u32) -> Self {
+        self.event.ledger_sequence = ledger_sequence;
+        self
+    }
+
+    /// Ledger close time, in Unix seconds.
+    pub fn closed_at(mut self, ledger_closed_at: i64) -> Self {
+        self.event.ledger_closed_at = ledger_closed_at;
+        self
+    }
+
+    pub fn phase(mut self, phase: EventPhase) -> Self {
+        self.event.phase = phase;
+        self
+    }
+
+    pub fn tx_index(mut self, tx_index: u32) -> Self {
+        self.event.tx_index = tx_index;
+        self
+    }
+
+    pub fn event_index(mut self, event_index: u32) -> Self {
+        self.event.event_index = event_index;
+        self
+    }
+
+    pub fn op_index(mut self, op_index: Option<u32>) -> Self {
+        self.event.op_index = op_index;
+        self
+    }
+
+    /// Hex-encoded transaction hash.
+    pub fn tx_hash(mut self, tx_hash: impl Into<String>) -> Self {
+        self.event.tx_hash = tx_hash.into();
+        self
+    }
+
+    /// Contract strkey (`C...`).
+    pub fn contract(mut self, contract_id: impl Into<String>) -> Self {
+        self.event.contract_id = Some(contract_id.into());
+        self
+    }
+
+    pub fn event_type(mut self, event_type: EventType) -> Self {
+        self.event.event_type = event_type;
+        self
+    }
+
+    /// Append a topic, as XDR-JSON.
+    pub fn topic(mut self, topic: serde_json::Value) -> Self {
+        self.event.topics_xdr_json.push(topic);
+        self
+    }
+
+    /// Append a `symbol` topic.
+    pub fn symbol_topic(self, symbol: &str) -> Self {
+        self.topic(serde_json::json!({ "symbol": symbol }))
+    }
+
+    /// Event data, as XDR-JSON.
+    pub fn data(mut self, data: serde_json::Value) -> Self {
+        self.event.data_xdr_json = Some(data);
+        self
+    }
+
+    pub fn build(self) -> ExtractedEvent {
+        self.event
+    }
+}
+
+/// Build a contract event with a `transfer` symbol topic and an `i128` amount.
+/// `contract_byte` becomes the first byte of the contract ID.
+pub fn build_contract_event(contract_byte: u8, event_idx: u32) -> ContractEvent {
+    let mut hash = [0u8; 32];
+    hash[0] = contract_byte;
+
+    ContractEvent {
+        ext: ExtensionPoint::V0,
+        contract_id: Some(ContractId(Hash(hash))),
+        type_: ContractEventType::Contract,
+        body: ContractEventBody::V0(ContractEventV0 {
+            topics: vec![ScVal::Symbol("transfer".try_into().unwrap())]
+                .try_into()
+                .unwrap(),
+            data: ScVal::I128(Int128Parts {
+                hi: 0,
+                lo: (event_idx as u64 + 1) * 1000,
+            }),
+        }),
+    }
+}
+
+/// Build an envelope for a transaction with one operation, sourced from the
+/// account whose key bytes are all `source_byte + 1`.
+fn build_test_envelope(source_byte: u8) -> TransactionEnvelope {
+    TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx: Transaction {
+            source_account: MuxedAccount::Ed25519(Uint256([source_byte.wrapping_add(1); 32])),
+            fee: 200,
+            seq_num: SequenceNumber(1),
+            cond: Preconditions::None,
+            memo: Memo::None,
+            operations: vec![Operation {
+                source_account: None,
+                body: OperationBody::BumpSequence(BumpSequenceOp {
+                    bump_to: SequenceNumber(0),
+                }),
+            }]
+            .try_into()
+            .unwrap(),
+            ext: TransactionExt::V0,
+        },
+        signatures: VecM::default(),
+    })
+}
+
+/// Build a zstd-compressed XDR `LedgerCloseMetaBatch` for one ledger, in the
+/// format served by the ledger metadata store. The ledger has `num_txs`
+/// transactions, each emitting `events_per_tx` contract events.
+pub fn build_test_ledger_compressed(
+    ledger_seq: u32,
+    num_txs: usize,
+    events_per_tx: usize,
+) -> Vec<u8> {
+    build_chained_test_ledger_compressed(ledger_seq, num_txs, events_per_tx, [0; 32])
+}
+
+/// Like [`build_test_ledger_compressed`], with the header recording
+/// `previous_ledger_hash` as the hash of the ledger before it.
+pub fn build_chained_test_ledger_compressed(
+    ledger_seq: u32,
+    num_txs: usize,
+    events_per_tx: usize,
+    previous_ledger_hash: [u8; 32],
+) -> Vec<u8> {
+    let network_id: [u8; 32] =
+        sha2::Sha256::digest(crate::ledger::path::StoreConfig::default().network_passphrase).into();
+    let mut tx_metas = Vec::new();
+    let mut envelopes = Vec::new();
+
+    for tx_idx in 0..num_txs {
+        let mut events = Vec::new();
+        for evt_idx in 0..events_per_tx {
+            events.push(build_contract_event(tx_idx as u8, evt_idx as u32));
+        }
+
+        let envelope = build_test_envelope(tx_idx as u8);
+        let tx_hash = envelope
+            .hash(network_id)
+            .expect("failed to hash test envelope");
+        envelopes.push(envelope);
+
+        let trm = TransactionResultMeta {
+            result: TransactionResultPair {
+                transaction_hash: Hash(tx_hash),
+                result: TransactionResult {
+                    fee_charged: 100,
+                    result: TransactionResultResult::TxSuccess(VecM::default()),
+                    ext: TransactionResultExt::V0,
+                },
+            },
+            fee_processing: LedgerEntryChanges(VecM::default()),
+            tx_apply_processing: TransactionMeta::V3(TransactionMetaV3 {
+                ext: ExtensionPoint::V0,
+                tx_changes_before: LedgerEntryChanges(VecM::default()),
+                operations: VecM::default(),
+                tx_changes_after: LedgerEntryChanges(VecM::default()),
+                soroban_meta: Some(SorobanTransactionMeta {
+                    ext: SorobanTransactionMetaExt::V0,
+                    events: events.try_into().unwrap(),
+                    return_value: ScVal::Void,
+                    diagnostic_events: VecM::default(),
+                }),
+            }),
+        };
+
+        tx_metas.push(trm);
+    }
+
+    let header = LedgerHeader {
+        ledger_version: 21,
+        previous_ledger_hash: Hash(previous_ledger_hash),
+        scp_value: StellarValue {
+            tx_set_hash: Hash([0; 32]),
+            close_time: TimePoint(1700000000),
+            upgrades: VecM::default(),
+            ext: StellarValueExt::Basic,
+        },
+        tx_set_result_hash: Hash([0; 32]),
+        bucket_list_hash: Hash([0; 32]),
+        ledger_seq,
+        total_coins: 0,
+        fee_pool: 0,
+        inflation_seq: 0,
+        id_pool: 0,
+        base_fee: 100,
+        base_reserve: 5000000,
+        max_tx_set_size: 100,
+        skip_list: [Hash([0; 32]), Hash([0; 32]), Hash([0; 32]), Hash([0; 32])],
+        ext: LedgerHeaderExt::V0,
+    };
+
+    let meta = LedgerCloseMeta::V1(LedgerCloseMetaV1 {
+        ext: LedgerCloseMetaExt::V0,
+        ledger_header: LedgerHeaderHistoryEntry {
+            hash: Hash([0; 32]),
+            header,
+            ext: LedgerHeaderHistoryEntryExt::V0,
+        },
+        tx_set: GeneralizedTransactionSet::V1(TransactionSetV1 {
+            previous_ledger_hash: Hash([0; 32]),
+            phases: vec![TransactionPhase::V0(
+                vec![TxSetComponent::TxsetCompTxsMaybeDiscountedFee(
+                    TxSetComponentTxsMaybeDiscountedFee {
+                        base_fee: None,
+                        txs: envelopes.try_into().unwrap(),
+                    },
+                )]
+                .try_into()
+                .unwrap(),
+            )]
+            .try_into()
+            .unwrap(),
+        }),
+        tx_processing: tx_metas.try_into().unwrap(),
+        upgrades_processing: VecM::default(),
+        scp_info: VecM::default(),
+        total_byte_size_of_live_soroban_state: 0,
+        evicted_keys: VecM::default(),
+        unused: VecM::default(),
+    });
+
+    let batch = LedgerCloseMetaBatch {
+        start_sequence: ledger_seq,
+        end_sequence: ledger_seq,
+        ledger_close_metas: vec![meta].try_into().unwrap(),
+    };
+
+    let xdr_bytes = batch.to_xdr(Limits::none()).unwrap();
+    zstd::encode_all(Cursor::new(&xdr_bytes), 3).unwrap()
+}
+
+/// Start a mock ledger metadata store that serves `compressed_data` for any
+/// path, and return its base URL for use as `meta_url`.
+pub async fn start_mock_s3(compressed_data: Vec<u8>) -> String {
+    let data = axum::body::Bytes::from(compressed_data);
+
+    let app = axum::Router::new().fallback(move || {
+        let d = data.clone();
+        async move { d }
+    });
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let url = format!("http://{}", addr);
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    url
+}
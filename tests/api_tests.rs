@@ -2,10 +2,11 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use stellar_events_api::api;
-use stellar_events_api::db::EventStore;
+use stellar_events_api::db::{CacheSource, EventStore};
 use stellar_events_api::ledger::event_id::EventPhase;
 use stellar_events_api::ledger::events::{EventType, ExtractedEvent};
 use stellar_events_api::ledger::path::StoreConfig;
+use stellar_events_api::ledger::sac;
 use stellar_events_api::AppState;
 
 /// Helper: start a test server and return its base URL.
@@ -19,9 +20,10 @@ async fn start_test_server(events: Vec<ExtractedEvent>) -> String {
 
     let state = Arc::new(AppState {
         store,
-        config: StoreConfig::default(),
+        config: StoreConfig::default().into(),
         meta_url: String::new(),
         client: reqwest::Client::new(),
+        peer: None,
     });
 
     let app = api::router(state, None);
@@ -51,6 +53,7 @@ fn make_test_events(count: usize, ledger: u32) -> Vec<ExtractedEvent> {
             phase: EventPhase::Operation,
             tx_index: i as u32,
             event_index: 0,
+            op_index: Some(0),
             tx_hash: format!("{:064x}", i),
             contract_id: Some(format!(
                 "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA{}",
@@ -58,7 +61,9 @@ fn make_test_events(count: usize, ledger: u32) -> Vec<ExtractedEvent> {
             )),
             event_type: EventType::Contract,
             topics_xdr_json: vec![serde_json::json!({"symbol": "transfer"})],
-            data_xdr_json: serde_json::json!({"amount": i * 100}),
+            data_xdr_json: Some(serde_json::json!({"amount": i * 100})),
+            xdr: None,
+            transaction: None,
         })
         .collect()
 }
@@ -74,6 +79,7 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
             phase: EventPhase::Operation,
             tx_index: 0,
             event_index: 0,
+            op_index: Some(0),
             tx_hash: "a".repeat(64),
             contract_id: Some(
                 "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
@@ -84,7 +90,9 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
                 serde_json::json!({"address": "GABC"}),
                 serde_json::json!({"address": "GDEF"}),
             ],
-            data_xdr_json: serde_json::json!({"i128": {"hi": 0, "lo": 100}}),
+            data_xdr_json: Some(serde_json::json!({"i128": {"hi": 0, "lo": 100}})),
+            xdr: None,
+            transaction: None,
         },
         // Event 1: system event (no contract)
         ExtractedEvent {
@@ -93,11 +101,14 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
             phase: EventPhase::Operation,
             tx_index: 0,
             event_index: 1,
+            op_index: Some(0),
             tx_hash: "a".repeat(64),
             contract_id: None,
             event_type: EventType::System,
             topics_xdr_json: vec![serde_json::json!({"symbol": "core_metrics"})],
-            data_xdr_json: serde_json::json!({}),
+            data_xdr_json: Some(serde_json::json!({})),
+            xdr: None,
+            transaction: None,
         },
         // Event 2: transfer on contract CB
         ExtractedEvent {
@@ -106,6 +117,7 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
             phase: EventPhase::Operation,
             tx_index: 1,
             event_index: 0,
+            op_index: Some(0),
             tx_hash: "b".repeat(64),
             contract_id: Some(
                 "CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB".to_string(),
@@ -116,7 +128,9 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
                 serde_json::json!({"address": "GCCC"}),
                 serde_json::json!({"address": "GDDD"}),
             ],
-            data_xdr_json: serde_json::json!({"i128": {"hi": 0, "lo": 200}}),
+            data_xdr_json: Some(serde_json::json!({"i128": {"hi": 0, "lo": 200}})),
+            xdr: None,
+            transaction: None,
         },
         // Event 3: mint on contract CA
         ExtractedEvent {
@@ -125,6 +139,7 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
             phase: EventPhase::Operation,
             tx_index: 2,
             event_index: 0,
+            op_index: Some(0),
             tx_hash: "c".repeat(64),
             contract_id: Some(
                 "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
@@ -134,7 +149,9 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
                 serde_json::json!({"symbol": "mint"}),
                 serde_json::json!({"address": "GABC"}),
             ],
-            data_xdr_json: serde_json::json!({"i128": {"hi": 0, "lo": 500}}),
+            data_xdr_json: Some(serde_json::json!({"i128": {"hi": 0, "lo": 500}})),
+            xdr: None,
+            transaction: None,
         },
         // Event 4: diagnostic on contract CA
         ExtractedEvent {
@@ -143,13 +160,16 @@ fn make_multi_type_events() -> Vec<ExtractedEvent> {
             phase: EventPhase::Operation,
             tx_index: 2,
             event_index: 1,
+            op_index: Some(0),
             tx_hash: "c".repeat(64),
             contract_id: Some(
                 "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
             ),
             event_type: EventType::Diagnostic,
             topics_xdr_json: vec![serde_json::json!({"symbol": "diag"})],
-            data_xdr_json: serde_json::json!({}),
+            data_xdr_json: Some(serde_json::json!({})),
+            xdr: None,
+            transaction: None,
         },
     ]
 }
@@ -356,26 +376,42 @@ async fn test_filter_by_tx_hash() {
 }
 
 #[tokio::test]
-async fn test_tx_without_ledger_returns_error() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_tx_without_ledger() {
+    let mut events = make_test_events(3, 1000);
+    events.extend(
+        make_test_events(1, 1001)
+            .into_iter()
+            .map(|e| ExtractedEvent {
+                tx_hash: "b".repeat(64),
+                ..e
+            }),
+    );
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // Indexed: pinned to the transaction's ledger.
+    let tx = format!("{:064x}", 1);
     let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(&format!("tx:{}", "a".repeat(64)))
-        ))
+        .get(format!("{}/events?q=tx:{}", base_url, tx))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["tx"], tx);
+    assert_eq!(data[0]["ledger"], 1000);
 
+    // Unknown: a bounded scan of recent ledgers finds nothing.
+    let resp = client
+        .get(format!("{}/events?q=tx:{}", base_url, "a".repeat(64)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert!(body["error"]["message"]
-        .as_str()
-        .unwrap()
-        .contains("ledger is required when tx is provided"));
+    assert_eq!(body["data"].as_array().unwrap().len(), 0);
 }
 
 // --- Validation and errors ---
@@ -726,340 +762,721 @@ async fn test_event_fields_complete() {
 }
 
 #[tokio::test]
-async fn test_status_endpoint() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_relative_ledger_query() {
+    let mut events = make_test_events(2, 1000);
+    events.extend(make_test_events(3, 1001));
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    for (q, ledger, count) in [("ledger:latest", 1001, 3), ("ledger:latest-1", 1000, 2)] {
+        let resp = client
+            .get(format!("{}/events?q={}", base_url, q))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        let data = body["data"].as_array().unwrap();
+        assert_eq!(data.len(), count, "{}", q);
+        assert!(data.iter().all(|e| e["ledger"] == ledger), "{}", q);
+    }
+
     let resp = client
-        .get(format!("{}/health", base_url))
+        .get(format!("{}/events?q=ledger:latest-2000", base_url))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
-
+    assert_eq!(resp.status(), 400);
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert_eq!(body["status"], "ok");
-    assert!(body["network_passphrase"].is_string());
-    assert!(body["cached_ledgers"].is_number());
+    assert_eq!(body["error"]["param"], "q");
 }
 
-// --- q= filter tests ---
-
 #[tokio::test]
-async fn test_q_filter_by_type() {
-    let events = make_multi_type_events();
+async fn test_ledger_list_query() {
+    let mut events = make_test_events(2, 1000);
+    events.extend(make_test_events(3, 1001));
+    events.extend(make_test_events(2, 1002));
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
+    let get = |url: String| {
+        let client = client.clone();
+        async move {
+            let resp = client.get(url).send().await.unwrap();
+            assert_eq!(resp.status(), 200);
+            resp.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+
+    let body = get(format!(
+        "{}/events?q=ledger:1000,1002&include_total=true",
+        base_url
+    ))
+    .await;
+    let ledgers: Vec<_> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["ledger"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ledgers, vec![1002, 1002, 1000, 1000]);
+    assert_eq!(body["total"], 4);
+    assert!(body.get("warnings").is_none());
+
+    // Pages continue across the listed ledgers, in both directions.
+    let first = get(format!("{}/events?q=ledger:1000,1002&limit=3", base_url)).await;
+    assert_eq!(first["data"].as_array().unwrap().len(), 3);
+    let next = first["next"].as_str().unwrap();
+    let second = get(format!(
+        "{}/events?q=ledger:1000,1002&limit=3&before={}",
+        base_url, next
+    ))
+    .await;
+    let second = second["data"].as_array().unwrap();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0]["ledger"], 1000);
+
+    let asc = get(format!(
+        "{}/events?q=ledger:1000,1002&order=asc&limit=3",
+        base_url
+    ))
+    .await;
+    let ledgers: Vec<_> = asc["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["ledger"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ledgers, vec![1000, 1000, 1002]);
 
-    let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param("ledger:100 type:system")
-        ))
-        .send()
-        .await
-        .unwrap();
-    assert_eq!(resp.status(), 200);
-    let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["type"], "system");
+    let count = get(format!("{}/events/count?q=ledger:1000,1001", base_url)).await;
+    assert_eq!(count["total"], 5);
 }
 
 #[tokio::test]
-async fn test_q_filter_by_contract() {
-    let events = make_multi_type_events();
+async fn test_time_window_query() {
+    let mut events = Vec::new();
+    for (ledger, offset) in [(1000, 0), (1001, 5), (1002, 10)] {
+        events.extend(
+            make_test_events(2, ledger)
+                .into_iter()
+                .map(|e| ExtractedEvent {
+                    ledger_closed_at: 1700000000 + offset,
+                    ..e
+                }),
+        );
+    }
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    for (q, ledgers) in [
+        ("since:1700000005", vec![1002, 1001]),
+        ("until:2023-11-14T22:13:25Z", vec![1001, 1000]),
+        ("since:1700000005 until:1700000005", vec![1001]),
+    ] {
+        let resp = client
+            .get(format!("{}/events?q={}", base_url, q))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        let mut seen: Vec<u64> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["ledger"].as_u64().unwrap())
+            .collect();
+        seen.dedup();
+        assert_eq!(seen, ledgers, "{}", q);
+    }
+
     let resp = client
         .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(
-                "ledger:100 contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
-            )
+            "{}/events?q=since:1700000010%20until:1700000000",
+            base_url
         ))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
-    let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Events 0, 3, 4 are on contract CA
-    assert_eq!(data.len(), 3);
-    for evt in data {
-        assert_eq!(
-            evt["contract"],
-            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
-        );
-    }
+    assert_eq!(resp.status(), 400);
 }
 
 #[tokio::test]
-async fn test_q_filter_by_topic() {
-    let events = make_multi_type_events();
+async fn test_amount_query() {
+    let events = make_test_events(4, 1000)
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| ExtractedEvent {
+            data_xdr_json: Some(serde_json::json!({"i128": (i as u64 * 1_000_000).to_string()})),
+            ..e
+        })
+        .collect();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
     let resp = client
         .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(r#"ledger:100 topic0:{"symbol":"transfer"}"#)
+            "{}/events?q=ledger:1000%20amount:%3E1000000",
+            base_url
         ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Events 0 and 2 have {"symbol":"transfer"} at position 0
-    assert_eq!(data.len(), 2);
+    let amounts: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["data"]["i128"].as_str().unwrap())
+        .collect();
+    assert_eq!(amounts, ["3000000", "2000000"]);
 }
 
 #[tokio::test]
-async fn test_q_filter_topic_with_wildcard() {
-    let events = make_multi_type_events();
+async fn test_subset_topic_query() {
+    let pool = |name: &str| {
+        serde_json::json!({"map": [
+            {"key": {"symbol": "fee"}, "val": {"u32": 30}},
+            {"key": {"symbol": "pool"}, "val": {"symbol": name}}
+        ]})
+    };
+    let events = make_test_events(3, 1000)
+        .into_iter()
+        .zip(["a", "b", "a"])
+        .map(|(e, name)| ExtractedEvent {
+            topics_xdr_json: vec![serde_json::json!({"symbol": "swap"}), pool(name)],
+            ..e
+        })
+        .collect();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // topic0 = transfer, topic1 = wildcard (gap), topic2 = GDEF
-    let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(r#"ledger:100 topic0:{"symbol":"transfer"} topic2:{"address":"GDEF"}"#)
-        ))
-        .send()
-        .await
-        .unwrap();
-    assert_eq!(resp.status(), 200);
-    let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Only event 0 matches (transfer, GABC, GDEF)
-    assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["topics"][2]["address"], "GDEF");
+    let count = |q: &'static str| {
+        let client = client.clone();
+        let base_url = base_url.clone();
+        async move {
+            let resp = client
+                .get(format!("{}/events?q={}", base_url, q_param(q)))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            let body: serde_json::Value = resp.json().await.unwrap();
+            body["data"].as_array().unwrap().len()
+        }
+    };
+
+    let by_pool = r#"ledger:1000 topic1~:{"map":[{"key":{"symbol":"pool"},"val":{"symbol":"a"}}]}"#;
+    assert_eq!(count(by_pool).await, 2);
+    let by_fee = r#"ledger:1000 topic1~:{"map":[{"val":{"u32":30}}]}"#;
+    assert_eq!(count(by_fee).await, 3);
+    // Exact matching still requires the whole value.
+    let exact = r#"ledger:1000 topic1:{"map":[{"key":{"symbol":"pool"},"val":{"symbol":"a"}}]}"#;
+    assert_eq!(count(exact).await, 0);
 }
 
 #[tokio::test]
-async fn test_q_filter_or_logic() {
-    let events = make_multi_type_events();
+async fn test_topic_type_query() {
+    let second = [
+        serde_json::json!({"address": "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF"}),
+        serde_json::json!({"i128": "100"}),
+        serde_json::json!("void"),
+    ];
+    let events = make_test_events(3, 1000)
+        .into_iter()
+        .zip(second)
+        .map(|(e, topic)| ExtractedEvent {
+            topics_xdr_json: vec![serde_json::json!({"symbol": "transfer"}), topic],
+            ..e
+        })
+        .collect();
+    let base_url = start_test_server(events).await;
+
+    for (q, expected) in [
+        ("ledger:1000 topic1.type:address", 1),
+        ("ledger:1000 topic1.type:void", 1),
+        ("ledger:1000 topic0.type:symbol", 3),
+        ("ledger:1000 topic0.type:symbol topic1.type:i128", 1),
+        ("ledger:1000 topic2.type:address", 0),
+    ] {
+        let body: serde_json::Value = reqwest::get(format!("{}/events?q={}", base_url, q_param(q)))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), expected, "{}", q);
+    }
+}
+
+#[tokio::test]
+async fn test_topic0_index_query() {
+    let events = make_test_events(10, 1000)
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| ExtractedEvent {
+            topics_xdr_json: vec![match i % 2 {
+                0 => serde_json::json!({"symbol": "transfer"}),
+                _ => serde_json::json!({"i128": {"hi": 0, "lo": 5}}),
+            }],
+            ..e
+        })
+        .collect();
+    let base_url = start_test_server(events).await;
+
+    // Only the events with the topic are examined, in whatever field order
+    // the topic is written, and pages still chain until exhausted.
+    let q = q_param(r#"ledger:1000 topic0:{"i128":{"lo":5,"hi":0}}"#);
+    let mut ids = Vec::new();
+    let mut before: Option<String> = None;
+    loop {
+        let mut url = format!("{}/events?debug=true&limit=2&q={}", base_url, q);
+        if let Some(ref cursor) = before {
+            url.push_str(&format!("&before={}", cursor));
+        }
+        let body: serde_json::Value = reqwest::get(url).await.unwrap().json().await.unwrap();
+        let data = body["data"].as_array().unwrap();
+        assert!(body["meta"]["events_examined"].as_u64().unwrap() <= 2);
+        ids.extend(data.iter().map(|e| e["id"].as_str().unwrap().to_string()));
+        match body["next"].as_str() {
+            Some(next) if !data.is_empty() => before = Some(next.to_string()),
+            _ => break,
+        }
+    }
+    assert_eq!(ids.len(), 5);
+    ids.dedup();
+    assert_eq!(ids.len(), 5);
+}
+
+#[tokio::test]
+async fn test_partition_bloom_skips_ledgers() {
+    let rare = "CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB";
+    let mut events: Vec<ExtractedEvent> = (1000..1010)
+        .flat_map(|ledger| make_test_events(3, ledger))
+        .collect();
+    events[15].contract_id = Some(rare.to_string());
+    let base_url = start_test_server(events).await;
+
+    for q in [
+        format!("contract:{}", rare),
+        format!(r#"contract:{} topic0:{{"symbol":"transfer"}}"#, rare),
+    ] {
+        let body: serde_json::Value =
+            reqwest::get(format!("{}/events?debug=true&q={}", base_url, q_param(&q)))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 1, "{}", q);
+        // Only the ledger holding the contract's event is examined.
+        assert_eq!(body["meta"]["events_examined"], 3, "{}", q);
+    }
+}
+
+#[tokio::test]
+async fn test_token_transfer_query() {
+    let symbols = ["transfer", "mint", "approve", "burn"];
+    let events = make_test_events(4, 1000)
+        .into_iter()
+        .zip(symbols)
+        .map(|(e, symbol)| ExtractedEvent {
+            topics_xdr_json: vec![serde_json::json!({ "symbol": symbol })],
+            ..e
+        })
+        .collect();
+    let base_url = start_test_server(events).await;
+
+    let body: serde_json::Value = reqwest::get(format!(
+        "{}/events?q={}",
+        base_url,
+        q_param("ledger:1000 token_transfer:true")
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    let mut found: Vec<_> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["topics"][0]["symbol"].as_str().unwrap().to_string())
+        .collect();
+    found.sort();
+    assert_eq!(found, vec!["burn", "mint", "transfer"]);
+}
+
+#[tokio::test]
+async fn test_fn_query() {
+    let contract = serde_json::json!({"bytes": "00".repeat(32)});
+    let topics = [
+        vec![
+            serde_json::json!({"symbol": "fn_call"}),
+            contract.clone(),
+            serde_json::json!({"symbol": "swap"}),
+        ],
+        vec![
+            serde_json::json!({"symbol": "fn_return"}),
+            serde_json::json!({"symbol": "swap"}),
+        ],
+        vec![
+            serde_json::json!({"symbol": "fn_call"}),
+            contract,
+            serde_json::json!({"symbol": "deposit"}),
+        ],
+    ];
+    let mut events: Vec<ExtractedEvent> = make_test_events(3, 1000)
+        .into_iter()
+        .zip(topics)
+        .map(|(e, topics)| ExtractedEvent {
+            event_type: EventType::Diagnostic,
+            topics_xdr_json: topics,
+            ..e
+        })
+        .collect();
+    // A contract event whose topics merely look like a call is not matched.
+    events.extend(
+        make_test_events(1, 1000)
+            .into_iter()
+            .map(|e| ExtractedEvent {
+                tx_index: 9,
+                topics_xdr_json: vec![
+                    serde_json::json!({"symbol": "fn_return"}),
+                    serde_json::json!({"symbol": "swap"}),
+                ],
+                ..e
+            }),
+    );
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // Two OR groups: CA with transfer OR CB with transfer
-    let q = r#"(ledger:100 contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA topic0:{"symbol":"transfer"}) OR (ledger:100 contract:CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB topic0:{"symbol":"transfer"})"#;
     let resp = client
-        .get(format!("{}/events?q={}", base_url, q_param(q)))
+        .get(format!("{}/events?q=ledger:1000%20fn:swap", base_url))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Event 0 (CA transfer) and event 2 (CB transfer)
     assert_eq!(data.len(), 2);
+    assert!(data.iter().all(|e| e["type"] == "diagnostic"));
 }
 
 #[tokio::test]
-async fn test_q_filter_and_logic() {
-    let events = make_multi_type_events();
+async fn test_op_query() {
+    let events = make_test_events(3, 1000)
+        .into_iter()
+        .enumerate()
+        .map(|(i, e)| ExtractedEvent {
+            tx_index: 0,
+            event_index: i as u32,
+            op_index: Some(i as u32 % 2),
+            tx_hash: "c".repeat(64),
+            ..e
+        })
+        .collect();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // Single AND group: contract CA AND type contract AND topic[0] = mint
-    let q = r#"ledger:100 contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA type:contract topic0:{"symbol":"mint"}"#;
     let resp = client
-        .get(format!("{}/events?q={}", base_url, q_param(q)))
+        .get(format!("{}/events?q=ledger:1000%20op:1", base_url))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Only event 3 matches
     assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["ledger"], 100);
+    assert_eq!(data[0]["op"], 1);
+
+    let resp = client
+        .get(format!("{}/events?q=ledger:1000%20op:0", base_url))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
 }
 
 #[tokio::test]
-async fn test_q_filter_combined_with_pagination() {
-    let events = make_multi_type_events();
-    let base_url = start_test_server(events).await;
+async fn test_validate_query() {
+    let base_url = start_test_server(make_test_events(3, 100)).await;
     let client = reqwest::Client::new();
+    let validate = |q: serde_json::Value| {
+        let client = client.clone();
+        let url = format!("{}/query/validate", base_url);
+        async move {
+            let resp = client
+                .post(url)
+                .json(&serde_json::json!({ "q": q }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            resp.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+
+    let body = validate(serde_json::json!(
+        r#"type:contract topic0:{"symbol":"transfer"}"#
+    ))
+    .await;
+    assert_eq!(body["object"], "query_validation");
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["qualifiers"], serde_json::json!(["topic0", "type"]));
+    assert_eq!(body["filters"].as_array().unwrap().len(), 1);
+    assert_eq!(body["cost"]["filters"], 1);
+    assert_eq!(body["cost"]["pinned_ledger"], serde_json::Value::Null);
+
+    // Both syntaxes normalize to the same filters.
+    let json = validate(body["json"].clone()).await;
+    assert_eq!(json["filters"], body["filters"]);
+    let q = validate(body["q"].clone()).await;
+    assert_eq!(q["filters"], body["filters"]);
+
+    let body = validate(serde_json::json!(
+        "ledger:100 (type:contract OR type:system)"
+    ))
+    .await;
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["filters"].as_array().unwrap().len(), 2);
+    assert_eq!(body["qualifiers"], serde_json::json!(["ledger", "type"]));
+    assert_eq!(body["cost"]["pinned_ledger"], 100);
+    assert_eq!(body["cost"]["max_ledgers_scanned"], 1);
+
+    let body = validate(serde_json::json!("type:contract AND (")).await;
+    assert_eq!(body["valid"], false);
+    assert!(body["error"]["message"].as_str().is_some());
+    assert!(body["error"]["kind"].as_str().is_some());
+    assert!(body.get("filters").is_none());
+}
 
-    let qp = q_param("ledger:100 type:contract");
+#[tokio::test]
+async fn test_time_format() {
+    let base_url = start_test_server(make_test_events(1, 1000)).await;
+    let client = reqwest::Client::new();
+    let first_event = |query: &'static str| {
+        let client = client.clone();
+        let base_url = base_url.clone();
+        async move {
+            let resp = client
+                .get(format!("{}/events?limit=1{}", base_url, query))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            let body: serde_json::Value = resp.json().await.unwrap();
+            body["data"][0].clone()
+        }
+    };
 
-    // First page: limit=1
+    let event = first_event("").await;
+    assert_eq!(event["at"], "2023-11-14T22:13:20+00:00");
+    assert_eq!(event["at_unix"], 1700000000);
+
+    let event = first_event("&time_format=unix").await;
+    assert!(event.get("at").is_none());
+    assert_eq!(event["at_unix"], 1700000000);
+
+    let event = first_event("&time_format=rfc3339").await;
+    assert!(event["at"].is_string());
+    assert!(event.get("at_unix").is_none());
+
+    let event = first_event("&fields=ledger,at_unix").await;
+    assert_eq!(event["at_unix"], 1700000000);
+    assert!(event.get("at").is_none());
+
+    let id = event["id"].as_str().unwrap().to_string();
     let resp = client
-        .get(format!("{}/events?limit=1&q={}", base_url, qp))
+        .get(format!("{}/events/{}?time_format=unix", base_url, id))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
-    let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 1);
-    let first_id = data[0]["id"].as_str().unwrap().to_string();
+    let event: serde_json::Value = resp.json().await.unwrap();
+    assert!(event.get("at").is_none());
+    assert_eq!(event["at_unix"], 1700000000);
 
-    // Second page using before cursor
-    let next_cursor = body["next"].as_str().unwrap();
     let resp = client
-        .get(format!(
-            "{}/events?limit=1&before={}&q={}",
-            base_url, next_cursor, qp
-        ))
+        .get(format!("{}/events?time_format=epoch", base_url))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.status(), 400);
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 1);
-    // Must be a different event
-    assert_ne!(data[0]["id"].as_str().unwrap(), first_id);
+    assert_eq!(body["error"]["param"], "time_format");
 }
 
 #[tokio::test]
-async fn test_q_filter_combined_with_ledger() {
-    let events = make_multi_type_events();
-    let base_url = start_test_server(events).await;
+async fn test_compact_representation() {
+    let base_url = start_test_server(make_test_events(3, 1000)).await;
     let client = reqwest::Client::new();
 
     let resp = client
         .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(r#"ledger:100 topic0:{"symbol":"transfer"}"#)
+            "{}/events?representation=compact&fields=ledger,tx,topics",
+            base_url
         ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Events 0 and 2 have {"symbol":"transfer"} topic
-    assert_eq!(data.len(), 2);
-}
-
-#[tokio::test]
-async fn test_q_filter_paren_group() {
-    let events = make_multi_type_events();
-    let base_url = start_test_server(events).await;
-    let client = reqwest::Client::new();
+    assert_eq!(
+        body["columns"],
+        serde_json::json!(["id", "ledger", "tx", "topics"])
+    );
+    let rows = body["data"].as_array().unwrap();
+    assert_eq!(rows.len(), 3);
+    for row in rows {
+        let row = row.as_array().unwrap();
+        assert_eq!(row.len(), 4);
+        assert!(row[0].as_str().unwrap().starts_with("evt_"));
+        assert_eq!(row[1], 1000);
+        assert_eq!(row[3], serde_json::json!([{"symbol": "transfer"}]));
+    }
 
-    let q = r#"ledger:100 (contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA OR contract:CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB) topic0:{"symbol":"transfer"}"#;
+    // The default representation is unchanged and has no columns.
     let resp = client
-        .get(format!("{}/events?q={}", base_url, q_param(q)))
+        .get(format!("{}/events", base_url))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Events 0 and 2 (transfer on CA or CB)
-    assert_eq!(data.len(), 2);
-}
-
-#[tokio::test]
-async fn test_q_filter_invalid_syntax() {
-    let base_url = start_test_server(vec![]).await;
-    let client = reqwest::Client::new();
+    assert!(body.get("columns").is_none());
+    assert_eq!(body["data"][0]["object"], "event");
 
+    // NDJSON sends the columns as the first line.
     let resp = client
-        .get(format!("{}/events?q={}", base_url, q_param("foo:bar")))
+        .get(format!(
+            "{}/events?representation=compact&time_format=unix",
+            base_url
+        ))
+        .header("accept", "application/x-ndjson")
+        .send()
+        .await
+        .unwrap();
+    let body = resp.text().await.unwrap();
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 4);
+    let columns = lines[0].as_array().unwrap();
+    assert!(columns.contains(&serde_json::json!("at_unix")));
+    assert!(!columns.contains(&serde_json::json!("at")));
+    assert_eq!(lines[1].as_array().unwrap().len(), columns.len());
+
+    let resp = client
+        .get(format!("{}/events?representation=table", base_url))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 400);
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert_eq!(body["error"]["type"], "invalid_request_error");
-    assert_eq!(body["error"]["code"], "invalid_parameter");
-    assert_eq!(body["error"]["param"], "q");
-    assert!(body["error"]["message"]
-        .as_str()
-        .unwrap()
-        .contains("unknown key"));
+    assert_eq!(body["error"]["param"], "representation");
 }
 
 #[tokio::test]
-async fn test_q_filter_post_json() {
-    let events = make_multi_type_events();
-    let base_url = start_test_server(events).await;
+async fn test_pretty_negotiation() {
+    let base_url = start_test_server(make_test_events(1, 1000)).await;
     let client = reqwest::Client::new();
+    let body = |query: &'static str, accept: &'static str| {
+        let client = client.clone();
+        let base_url = base_url.clone();
+        async move {
+            let resp = client
+                .get(format!("{}/events{}", base_url, query))
+                .header("accept", accept)
+                .send()
+                .await
+                .unwrap();
+            (resp.status(), resp.text().await.unwrap())
+        }
+    };
 
-    let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": "ledger:100 type:system"
-        }))
-        .send()
-        .await
-        .unwrap();
-    assert_eq!(resp.status(), 200);
-    let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["type"], "system");
+    let (status, compact) = body("", "application/json").await;
+    assert_eq!(status, 200);
+    assert!(
+        !compact.contains('\n'),
+        "programmatic clients get compact JSON"
+    );
+
+    let (_, pretty) = body("", "text/html,*/*;q=0.8").await;
+    assert!(pretty.contains("\n  \"url\""), "browsers get pretty JSON");
+    let (_, pretty) = body("?pretty=true", "application/json").await;
+    assert!(pretty.contains('\n'));
+    let (_, compact_html) = body("?pretty=false", "text/html").await;
+    assert!(!compact_html.contains('\n'));
+
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&compact).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+    );
+
+    let (status, _) = body("?pretty=maybe", "application/json").await;
+    assert_eq!(status, 400);
 }
 
 #[tokio::test]
-async fn test_no_q_returns_all() {
-    let events = make_multi_type_events();
-    let base_url = start_test_server(events).await;
+async fn test_request_id() {
+    let base_url = start_test_server(vec![]).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .get(format!("{}/events?q={}", base_url, q_param("ledger:100")))
+        .get(format!("{}/health", base_url))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
-    let body: serde_json::Value = resp.json().await.unwrap();
-    assert_eq!(body["data"].as_array().unwrap().len(), 5);
-}
-
-#[tokio::test]
-async fn test_q_filter_url_encoded() {
-    let events = make_multi_type_events();
-    let base_url = start_test_server(events).await;
-    let client = reqwest::Client::new();
+    let generated = resp.headers()["x-request-id"].to_str().unwrap().to_string();
+    assert!(generated.starts_with("req_"));
 
-    // Manually URL-encode: ledger:100 type:contract topic0:{"symbol":"transfer"}
     let resp = client
-        .get(format!(
-            "{}/events?q=ledger%3A100%20type%3Acontract%20topic0%3A%7B%22symbol%22%3A%22transfer%22%7D",
-            base_url
-        ))
+        .get(format!("{}/events?limit=0", base_url))
+        .header("x-request-id", "trace-abc-123")
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.headers()["x-request-id"], "trace-abc-123");
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Events 0 and 2 are contract type with transfer topic
-    assert_eq!(data.len(), 2);
+    assert_eq!(body["error"]["request_id"], "trace-abc-123");
+
+    // Unusable IDs are replaced rather than echoed.
+    let resp = client
+        .get(format!("{}/health", base_url))
+        .header("x-request-id", "a".repeat(500))
+        .send()
+        .await
+        .unwrap();
+    let id = resp.headers()["x-request-id"].to_str().unwrap();
+    assert!(id.starts_with("req_"));
+    assert_ne!(id, generated);
 }
 
 #[tokio::test]
-async fn test_q_filter_empty_value() {
+async fn test_status_endpoint() {
     let base_url = start_test_server(vec![]).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .get(format!("{}/events?q=", base_url))
+        .get(format!("{}/health", base_url))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 200);
+
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert_eq!(body["error"]["param"], "q");
+    assert_eq!(body["status"], "ok");
+    assert!(body["network_passphrase"].is_string());
+    assert!(body["cached_ledgers"].is_number());
 }
 
+// --- q= filter tests ---
+
 #[tokio::test]
-async fn test_q_filter_diagnostic() {
+async fn test_q_filter_by_type() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
@@ -1068,7 +1485,7 @@ async fn test_q_filter_diagnostic() {
         .get(format!(
             "{}/events?q={}",
             base_url,
-            q_param("ledger:100 type:diagnostic")
+            q_param("ledger:100 type:system")
         ))
         .send()
         .await
@@ -1077,21 +1494,22 @@ async fn test_q_filter_diagnostic() {
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
     assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["type"], "diagnostic");
+    assert_eq!(data[0]["type"], "system");
 }
 
 #[tokio::test]
-async fn test_q_filter_topic_gap() {
+async fn test_q_filter_by_contract() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // topic0=transfer, topic1=wildcard, topic2=GDDD -> event 2
     let resp = client
         .get(format!(
             "{}/events?q={}",
             base_url,
-            q_param(r#"ledger:100 topic0:{"symbol":"transfer"} topic2:{"address":"GDDD"}"#)
+            q_param(
+                "ledger:100 contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+            )
         ))
         .send()
         .await
@@ -1099,12 +1517,18 @@ async fn test_q_filter_topic_gap() {
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["topics"][2]["address"], "GDDD");
+    // Events 0, 3, 4 are on contract CA
+    assert_eq!(data.len(), 3);
+    for evt in data {
+        assert_eq!(
+            evt["contract"],
+            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        );
+    }
 }
 
 #[tokio::test]
-async fn test_q_filter_three_way_or() {
+async fn test_q_filter_by_topic() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
@@ -1113,7 +1537,7 @@ async fn test_q_filter_three_way_or() {
         .get(format!(
             "{}/events?q={}",
             base_url,
-            q_param("ledger:100 (type:contract OR type:system OR type:diagnostic)")
+            q_param(r#"ledger:100 topic0:{"symbol":"transfer"}"#)
         ))
         .send()
         .await
@@ -1121,42 +1545,42 @@ async fn test_q_filter_three_way_or() {
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // All 5 events match one of the three types
-    assert_eq!(data.len(), 5);
+    // Events 0 and 2 have {"symbol":"transfer"} at position 0
+    assert_eq!(data.len(), 2);
 }
 
 #[tokio::test]
-async fn test_q_filter_parse_error_format() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_q_filter_topic_with_wildcard() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // topic0 = transfer, topic1 = wildcard (gap), topic2 = GDEF
     let resp = client
         .get(format!(
             "{}/events?q={}",
             base_url,
-            q_param("(type:contract")
+            q_param(r#"ledger:100 topic0:{"symbol":"transfer"} topic2:{"address":"GDEF"}"#)
         ))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert!(body.get("error").is_some());
-    let error = &body["error"];
-    assert_eq!(error["type"], "invalid_request_error");
-    assert_eq!(error["param"], "q");
-    assert!(error.get("message").is_some());
-    assert!(error.get("code").is_some());
+    let data = body["data"].as_array().unwrap();
+    // Only event 0 matches (transfer, GABC, GDEF)
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["topics"][2]["address"], "GDEF");
 }
 
 #[tokio::test]
-async fn test_q_filter_no_match() {
+async fn test_q_filter_or_logic() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // CB has no mint event
-    let q = r#"ledger:100 contract:CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB topic0:{"symbol":"mint"}"#;
+    // Two OR groups: CA with transfer OR CB with transfer
+    let q = r#"(ledger:100 contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA topic0:{"symbol":"transfer"}) OR (ledger:100 contract:CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB topic0:{"symbol":"transfer"})"#;
     let resp = client
         .get(format!("{}/events?q={}", base_url, q_param(q)))
         .send()
@@ -1164,47 +1588,58 @@ async fn test_q_filter_no_match() {
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert_eq!(body["data"].as_array().unwrap().len(), 0);
+    let data = body["data"].as_array().unwrap();
+    // Event 0 (CA transfer) and event 2 (CB transfer)
+    assert_eq!(data.len(), 2);
 }
 
-// --- topic (any position) integration tests ---
-
 #[tokio::test]
-async fn test_q_filter_topic_any_position() {
+async fn test_q_filter_and_logic() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // topic:{"address":"GABC"} should match events that have GABC in any topic position.
-    // Event 0 has GABC at topic1, Event 3 has GABC at topic1.
+    // Single AND group: contract CA AND type contract AND topic[0] = mint
+    let q = r#"ledger:100 contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA type:contract topic0:{"symbol":"mint"}"#;
     let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(r#"ledger:100 topic:{"address":"GABC"}"#)
-        ))
+        .get(format!("{}/events?q={}", base_url, q_param(q)))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 2);
+    // Only event 3 matches
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["ledger"], 100);
 }
 
 #[tokio::test]
-async fn test_q_filter_topic_any_with_type() {
+async fn test_q_filter_combined_with_pagination() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // topic:{"symbol":"transfer"} matches events 0 and 2 (both have transfer at topic0).
-    // Adding type:contract should still match both (both are contract type).
+    let qp = q_param("ledger:100 type:contract");
+
+    // First page: limit=1
+    let resp = client
+        .get(format!("{}/events?limit=1&q={}", base_url, qp))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    let first_id = data[0]["id"].as_str().unwrap().to_string();
+
+    // Second page using before cursor
+    let next_cursor = body["next"].as_str().unwrap();
     let resp = client
         .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(r#"ledger:100 type:contract topic:{"symbol":"transfer"}"#)
+            "{}/events?limit=1&before={}&q={}",
+            base_url, next_cursor, qp
         ))
         .send()
         .await
@@ -1212,23 +1647,22 @@ async fn test_q_filter_topic_any_with_type() {
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 2);
+    assert_eq!(data.len(), 1);
+    // Must be a different event
+    assert_ne!(data[0]["id"].as_str().unwrap(), first_id);
 }
 
 #[tokio::test]
-async fn test_q_filter_topic_any_multiple_and() {
+async fn test_q_filter_combined_with_ledger() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // topic:{"symbol":"transfer"} AND topic:{"address":"GABC"} — both must be present.
-    // Event 0 has [transfer, GABC, GDEF] — matches.
-    // Event 2 has [transfer, GCCC, GDDD] — no GABC, doesn't match.
     let resp = client
         .get(format!(
             "{}/events?q={}",
             base_url,
-            q_param(r#"ledger:100 topic:{"symbol":"transfer"} topic:{"address":"GABC"}"#)
+            q_param(r#"ledger:100 topic0:{"symbol":"transfer"}"#)
         ))
         .send()
         .await
@@ -1236,283 +1670,296 @@ async fn test_q_filter_topic_any_multiple_and() {
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 1);
+    // Events 0 and 2 have {"symbol":"transfer"} topic
+    assert_eq!(data.len(), 2);
 }
 
 #[tokio::test]
-async fn test_q_filter_topic_any_no_match() {
+async fn test_q_filter_paren_group() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    let q = r#"ledger:100 (contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA OR contract:CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB) topic0:{"symbol":"transfer"}"#;
     let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(r#"ledger:100 topic:{"symbol":"nonexistent"}"#)
-        ))
+        .get(format!("{}/events?q={}", base_url, q_param(q)))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert_eq!(body["data"].as_array().unwrap().len(), 0);
+    let data = body["data"].as_array().unwrap();
+    // Events 0 and 2 (transfer on CA or CB)
+    assert_eq!(data.len(), 2);
 }
 
-// --- ledger: and tx: query parser tests ---
-
 #[tokio::test]
-async fn test_q_ledger_invalid_value() {
+async fn test_q_filter_invalid_syntax() {
     let base_url = start_test_server(vec![]).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .get(format!("{}/events?q={}", base_url, q_param("ledger:abc")))
+        .get(format!("{}/events?q={}", base_url, q_param("foo:bar")))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 400);
     let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert_eq!(body["error"]["code"], "invalid_parameter");
+    assert_eq!(body["error"]["param"], "q");
     assert!(body["error"]["message"]
         .as_str()
         .unwrap()
-        .contains("invalid value"));
+        .contains("unknown key"));
 }
 
 #[tokio::test]
-async fn test_q_ledger_with_tx() {
+async fn test_q_filter_post_json() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    let tx_hash = "a".repeat(64);
     let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param(&format!("ledger:100 tx:{}", tx_hash))
-        ))
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": "ledger:100 type:system"
+        }))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Events 0 and 1 share tx_hash "aaa..."
-    assert_eq!(data.len(), 2);
-    for evt in data {
-        assert_eq!(evt["tx"], tx_hash);
-    }
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["type"], "system");
 }
 
-// --- POST JSON query tests ---
-
 #[tokio::test]
-async fn test_post_json_query_single() {
+async fn test_no_q_returns_all() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"and": [{"ledger": 100}, {"type": "contract"}]}
-        }))
+        .get(format!("{}/events?q={}", base_url, q_param("ledger:100")))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    // Events 0, 2, 3 are contract type on ledger 100
-    assert_eq!(data.len(), 3);
-    for evt in data {
-        assert_eq!(evt["type"], "contract");
-    }
+    assert_eq!(body["data"].as_array().unwrap().len(), 5);
 }
 
 #[tokio::test]
-async fn test_post_json_query_and() {
+async fn test_q_filter_url_encoded() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // Manually URL-encode: ledger:100 type:contract topic0:{"symbol":"transfer"}
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"and": [
-                {"ledger": 100},
-                {"type": "contract"},
-                {"contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"}
-            ]}
-        }))
+        .get(format!(
+            "{}/events?q=ledger%3A100%20type%3Acontract%20topic0%3A%7B%22symbol%22%3A%22transfer%22%7D",
+            base_url
+        ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Events 0 and 3 are contract type on contract CA
+    // Events 0 and 2 are contract type with transfer topic
     assert_eq!(data.len(), 2);
-    for evt in data {
-        assert_eq!(evt["type"], "contract");
-        assert_eq!(
-            evt["contract"],
-            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
-        );
-    }
 }
 
 #[tokio::test]
-async fn test_post_json_query_or() {
+async fn test_q_filter_empty_value() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/events?q=", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["param"], "q");
+}
+
+#[tokio::test]
+async fn test_q_filter_diagnostic() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"and": [
-                {"ledger": 100},
-                {"or": [{"type": "contract"}, {"type": "system"}]}
-            ]}
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param("ledger:100 type:diagnostic")
+        ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Events 0, 1, 2, 3 are contract or system (event 4 is diagnostic)
-    assert_eq!(data.len(), 4);
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["type"], "diagnostic");
 }
 
 #[tokio::test]
-async fn test_post_json_query_nested() {
+async fn test_q_filter_topic_gap() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // topic0=transfer, topic1=wildcard, topic2=GDDD -> event 2
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"and": [
-                {"ledger": 100},
-                {"or": [{"type": "contract"}, {"type": "system"}]},
-                {"contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"}
-            ]}
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param(r#"ledger:100 topic0:{"symbol":"transfer"} topic2:{"address":"GDDD"}"#)
+        ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Only contract-type events on contract CA: events 0 and 3
-    // (system event 1 has no contract so doesn't match contract filter)
-    assert_eq!(data.len(), 2);
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["topics"][2]["address"], "GDDD");
 }
 
 #[tokio::test]
-async fn test_post_json_query_invalid_type() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_q_filter_three_way_or() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"type": "bogus"}
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param("ledger:100 (type:contract OR type:system OR type:diagnostic)")
+        ))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    // All 5 events match one of the three types
+    assert_eq!(data.len(), 5);
 }
 
 #[tokio::test]
-async fn test_post_json_query_tx_requires_ledger() {
+async fn test_q_filter_parse_error_format() {
     let base_url = start_test_server(vec![]).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"tx": "abc"}
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param("(type:contract")
+        ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body.get("error").is_some());
+    let error = &body["error"];
+    assert_eq!(error["type"], "invalid_request_error");
+    assert_eq!(error["param"], "q");
+    assert!(error.get("message").is_some());
+    assert!(error.get("code").is_some());
 }
 
 #[tokio::test]
-async fn test_post_json_query_unknown_key() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_q_filter_no_match() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // CB has no mint event
+    let q = r#"ledger:100 contract:CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB topic0:{"symbol":"mint"}"#;
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"foo": "bar"}
-        }))
+        .get(format!("{}/events?q={}", base_url, q_param(q)))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 0);
 }
 
+// --- topic (any position) integration tests ---
+
 #[tokio::test]
-async fn test_post_json_query_multi_key() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_q_filter_topic_any_position() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // topic:{"address":"GABC"} should match events that have GABC in any topic position.
+    // Event 0 has GABC at topic1, Event 3 has GABC at topic1.
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"type": "contract", "contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"}
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param(r#"ledger:100 topic:{"address":"GABC"}"#)
+        ))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
 }
 
 #[tokio::test]
-async fn test_post_json_query_topic() {
+async fn test_q_filter_topic_any_with_type() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
+    // topic:{"symbol":"transfer"} matches events 0 and 2 (both have transfer at topic0).
+    // Adding type:contract should still match both (both are contract type).
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": {"and": [
-                {"ledger": 100},
-                {"topic0": {"symbol": "transfer"}}
-            ]}
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param(r#"ledger:100 type:contract topic:{"symbol":"transfer"}"#)
+        ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    // Events 0 and 2 have transfer at topic0
     assert_eq!(data.len(), 2);
 }
 
 #[tokio::test]
-async fn test_post_json_query_string_still_works() {
+async fn test_q_filter_topic_any_multiple_and() {
     let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // String q in POST should still work
+    // topic:{"symbol":"transfer"} AND topic:{"address":"GABC"} — both must be present.
+    // Event 0 has [transfer, GABC, GDEF] — matches.
+    // Event 2 has [transfer, GCCC, GDDD] — no GABC, doesn't match.
     let resp = client
-        .post(format!("{}/events", base_url))
-        .json(&serde_json::json!({
-            "q": "ledger:100 type:system"
-        }))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param(r#"ledger:100 topic:{"symbol":"transfer"} topic:{"address":"GABC"}"#)
+        ))
         .send()
         .await
         .unwrap();
@@ -1520,214 +1967,2513 @@ async fn test_post_json_query_string_still_works() {
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
     assert_eq!(data.len(), 1);
-    assert_eq!(data[0]["type"], "system");
 }
 
-// --- Schema endpoint ---
-
 #[tokio::test]
-async fn test_schema_endpoint() {
-    let base_url = start_test_server(vec![]).await;
+async fn test_q_filter_topic_any_no_match() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
     let resp = client
-        .get(format!("{}/schema", base_url))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param(r#"ledger:100 topic:{"symbol":"nonexistent"}"#)
+        ))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
-    assert_eq!(
-        resp.headers()
-            .get("content-type")
-            .unwrap()
-            .to_str()
-            .unwrap(),
-        "application/schema+json"
-    );
-
     let body: serde_json::Value = resp.json().await.unwrap();
-    assert!(body.get("$schema").is_some());
-    assert!(body.get("$defs").is_some());
-    assert!(body["$defs"].get("QueryExpr").is_some());
+    assert_eq!(body["data"].as_array().unwrap().len(), 0);
 }
 
-// --- Cross-ledger progressive search tests ---
-
-/// Create test events spread across three ledgers (100, 101, 102), 2 events each.
-fn make_cross_ledger_events() -> Vec<ExtractedEvent> {
-    let mut events = Vec::new();
-    for ledger in [100u32, 101, 102] {
-        for i in 0..2u32 {
-            events.push(ExtractedEvent {
-                ledger_sequence: ledger,
-                ledger_closed_at: 1_700_000_000 + i64::from(ledger) * 100,
-                phase: EventPhase::Operation,
-                tx_index: i,
-                event_index: 0,
-                tx_hash: format!("{:03}_{:061x}", ledger, i),
-                contract_id: Some(if ledger <= 101 {
-                    "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string()
-                } else {
-                    "CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB".to_string()
-                }),
-                event_type: EventType::Contract,
-                topics_xdr_json: vec![serde_json::json!({"symbol": "transfer"})],
-                data_xdr_json: serde_json::json!({"amount": ledger * 10 + i}),
-            });
-        }
-    }
-    events
-}
+// --- ledger: and tx: query parser tests ---
 
 #[tokio::test]
-async fn test_cross_ledger_backward_no_cursor() {
-    let events = make_cross_ledger_events();
-    let base_url = start_test_server(events).await;
+async fn test_q_ledger_invalid_value() {
+    let base_url = start_test_server(vec![]).await;
     let client = reqwest::Client::new();
 
-    // No cursor, limit=3 — should get newest 3 events (desc order).
     let resp = client
-        .get(format!("{}/events?limit=3", base_url))
+        .get(format!("{}/events?q={}", base_url, q_param("ledger:abc")))
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.status(), 400);
     let body: serde_json::Value = resp.json().await.unwrap();
-    let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 3);
-    // Newest first: 2 from ledger 102, then 1 from ledger 101.
-    assert_eq!(data[0]["ledger"], 102);
-    assert_eq!(data[1]["ledger"], 102);
-    assert_eq!(data[2]["ledger"], 101);
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("invalid value"));
 }
 
 #[tokio::test]
-async fn test_cross_ledger_backward_pagination() {
-    let events = make_cross_ledger_events();
+async fn test_q_ledger_with_tx() {
+    let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // Page 1: newest 2
+    let tx_hash = "a".repeat(64);
     let resp = client
-        .get(format!("{}/events?limit=2", base_url))
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param(&format!("ledger:100 tx:{}", tx_hash))
+        ))
         .send()
         .await
         .unwrap();
+    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
+    // Events 0 and 1 share tx_hash "aaa..."
     assert_eq!(data.len(), 2);
-    assert_eq!(data[0]["ledger"], 102);
-    assert_eq!(data[1]["ledger"], 102);
-    let next = body["next"].as_str().unwrap().to_string();
+    for evt in data {
+        assert_eq!(evt["tx"], tx_hash);
+    }
+}
+
+// --- POST JSON query tests ---
+
+#[tokio::test]
+async fn test_post_json_query_single() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
 
-    // Page 2
     let resp = client
-        .get(format!("{}/events?limit=2&before={}", base_url, next))
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"and": [{"ledger": 100}, {"type": "contract"}]}
+        }))
         .send()
         .await
         .unwrap();
+    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 2);
-    assert_eq!(data[0]["ledger"], 101);
-    assert_eq!(data[1]["ledger"], 101);
-    let next = body["next"].as_str().unwrap().to_string();
+    // Events 0, 2, 3 are contract type on ledger 100
+    assert_eq!(data.len(), 3);
+    for evt in data {
+        assert_eq!(evt["type"], "contract");
+    }
+}
+
+#[tokio::test]
+async fn test_post_json_query_and() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
 
-    // Page 3
     let resp = client
-        .get(format!("{}/events?limit=2&before={}", base_url, next))
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"and": [
+                {"ledger": 100},
+                {"type": "contract"},
+                {"contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"}
+            ]}
+        }))
         .send()
         .await
         .unwrap();
+    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
+    // Events 0 and 3 are contract type on contract CA
     assert_eq!(data.len(), 2);
-    assert_eq!(data[0]["ledger"], 100);
-    assert_eq!(data[1]["ledger"], 100);
+    for evt in data {
+        assert_eq!(evt["type"], "contract");
+        assert_eq!(
+            evt["contract"],
+            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        );
+    }
 }
 
 #[tokio::test]
-async fn test_cross_ledger_forward() {
-    let events = make_cross_ledger_events();
+async fn test_get_json_query() {
+    let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
+    let query = serde_json::json!({"and": [{"ledger": 100}, {"type": "contract"}]});
+    let get = |params: String| client.get(format!("{}/events?{}", base_url, params)).send();
+
+    let resp = get(format!("query={}", q_param(&query.to_string())))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+
+    let post: serde_json::Value = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({ "q": query }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(!body["data"].as_array().unwrap().is_empty());
+    assert_eq!(body["data"], post["data"]);
 
-    // Get all events to find the oldest ID.
     let resp = client
-        .get(format!("{}/events?limit=10", base_url))
+        .get(format!(
+            "{}/events/count?query={}",
+            base_url,
+            q_param(&query.to_string())
+        ))
+        .send()
+        .await
+        .unwrap();
+    let count: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(count["total"], body["data"].as_array().unwrap().len());
+
+    for params in [
+        format!("query={}&q=type:contract", q_param(&query.to_string())),
+        "query=%7Bnot%20json".to_string(),
+        "query=%22type%3Acontract%22".to_string(),
+    ] {
+        let resp = get(params.clone()).await.unwrap();
+        assert_eq!(resp.status(), 400, "{}", params);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["param"], "query", "{}", params);
+    }
+}
+
+#[tokio::test]
+async fn test_post_json_query_or() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"and": [
+                {"ledger": 100},
+                {"or": [{"type": "contract"}, {"type": "system"}]}
+            ]}
+        }))
         .send()
         .await
         .unwrap();
+    assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 6);
-    let oldest_id = data.last().unwrap()["id"].as_str().unwrap().to_string();
+    // Events 0, 1, 2, 3 are contract or system (event 4 is diagnostic)
+    assert_eq!(data.len(), 4);
+}
+
+#[tokio::test]
+async fn test_post_json_query_nested() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
 
-    // Query with after=oldest, limit=3.
     let resp = client
-        .get(format!("{}/events?after={}&limit=3", base_url, oldest_id))
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"and": [
+                {"ledger": 100},
+                {"or": [{"type": "contract"}, {"type": "system"}]},
+                {"contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"}
+            ]}
+        }))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 3);
-    // Results in descending order.
-    let ledgers: Vec<u64> = data.iter().map(|e| e["ledger"].as_u64().unwrap()).collect();
-    assert!(ledgers[0] >= ledgers[1]);
-    assert!(ledgers[1] >= ledgers[2]);
+    // Only contract-type events on contract CA: events 0 and 3
+    // (system event 1 has no contract so doesn't match contract filter)
+    assert_eq!(data.len(), 2);
 }
 
 #[tokio::test]
-async fn test_cross_ledger_with_filter() {
-    let events = make_cross_ledger_events();
+async fn test_post_json_query_invalid_type() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"type": "bogus"}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_post_json_query_tx_without_ledger() {
+    let base_url = start_test_server(make_test_events(2, 1000)).await;
+    let client = reqwest::Client::new();
+
+    let tx = format!("{:064x}", 0);
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"tx": tx}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"][0]["tx"], tx);
+}
+
+#[tokio::test]
+async fn test_post_json_query_unknown_key() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"foo": "bar"}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_post_json_query_multi_key() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"type": "contract", "contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_post_json_query_topic() {
+    let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // Filter by contract CA — only ledgers 100 and 101 have contract CA.
     let resp = client
-        .get(format!(
-            "{}/events?q={}",
-            base_url,
-            q_param("contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA")
-        ))
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": {"and": [
+                {"ledger": 100},
+                {"topic0": {"symbol": "transfer"}}
+            ]}
+        }))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 4);
-    for evt in data {
-        assert_eq!(
-            evt["contract"],
-            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
-        );
-    }
+    // Events 0 and 2 have transfer at topic0
+    assert_eq!(data.len(), 2);
 }
 
 #[tokio::test]
-async fn test_cross_ledger_all_events() {
-    let events = make_cross_ledger_events();
+async fn test_post_json_query_string_still_works() {
+    let events = make_multi_type_events();
     let base_url = start_test_server(events).await;
     let client = reqwest::Client::new();
 
-    // Should return all 6 events across 3 ledgers.
+    // String q in POST should still work
     let resp = client
-        .get(format!("{}/events?limit=10", base_url))
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": "ledger:100 type:system"
+        }))
         .send()
         .await
         .unwrap();
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
     let data = body["data"].as_array().unwrap();
-    assert_eq!(data.len(), 6);
-    // Descending order: ledger 102, 102, 101, 101, 100, 100.
-    assert_eq!(data[0]["ledger"], 102);
-    assert_eq!(data[1]["ledger"], 102);
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["type"], "system");
+}
+
+// --- Schema endpoint ---
+
+#[tokio::test]
+async fn test_schema_endpoint() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/schema", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()
+            .get("content-type")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        "application/schema+json"
+    );
+
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body.get("$schema").is_some());
+    assert!(body.get("$defs").is_some());
+    assert!(body["$defs"].get("QueryExpr").is_some());
+}
+
+// --- Cross-ledger progressive search tests ---
+
+/// Create test events spread across three ledgers (100, 101, 102), 2 events each.
+fn make_cross_ledger_events() -> Vec<ExtractedEvent> {
+    let mut events = Vec::new();
+    for ledger in [100u32, 101, 102] {
+        for i in 0..2u32 {
+            events.push(ExtractedEvent {
+                ledger_sequence: ledger,
+                ledger_closed_at: 1_700_000_000 + i64::from(ledger) * 100,
+                phase: EventPhase::Operation,
+                tx_index: i,
+                event_index: 0,
+                op_index: Some(0),
+                tx_hash: format!("{:03}_{:061x}", ledger, i),
+                contract_id: Some(if ledger <= 101 {
+                    "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string()
+                } else {
+                    "CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB".to_string()
+                }),
+                event_type: EventType::Contract,
+                topics_xdr_json: vec![serde_json::json!({"symbol": "transfer"})],
+                data_xdr_json: Some(serde_json::json!({"amount": ledger * 10 + i})),
+                xdr: None,
+                transaction: None,
+            });
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_cross_ledger_backward_no_cursor() {
+    let events = make_cross_ledger_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // No cursor, limit=3 — should get newest 3 events (desc order).
+    let resp = client
+        .get(format!("{}/events?limit=3", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 3);
+    // Newest first: 2 from ledger 102, then 1 from ledger 101.
+    assert_eq!(data[0]["ledger"], 102);
+    assert_eq!(data[1]["ledger"], 102);
+    assert_eq!(data[2]["ledger"], 101);
+}
+
+#[tokio::test]
+async fn test_cross_ledger_backward_pagination() {
+    let events = make_cross_ledger_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // Page 1: newest 2
+    let resp = client
+        .get(format!("{}/events?limit=2", base_url))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["ledger"], 102);
+    assert_eq!(data[1]["ledger"], 102);
+    let next = body["next"].as_str().unwrap().to_string();
+
+    // Page 2
+    let resp = client
+        .get(format!("{}/events?limit=2&before={}", base_url, next))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["ledger"], 101);
+    assert_eq!(data[1]["ledger"], 101);
+    let next = body["next"].as_str().unwrap().to_string();
+
+    // Page 3
+    let resp = client
+        .get(format!("{}/events?limit=2&before={}", base_url, next))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["ledger"], 100);
+    assert_eq!(data[1]["ledger"], 100);
+}
+
+#[tokio::test]
+async fn test_cross_ledger_forward() {
+    let events = make_cross_ledger_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // Get all events to find the oldest ID.
+    let resp = client
+        .get(format!("{}/events?limit=10", base_url))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 6);
+    let oldest_id = data.last().unwrap()["id"].as_str().unwrap().to_string();
+
+    // Query with after=oldest, limit=3.
+    let resp = client
+        .get(format!("{}/events?after={}&limit=3", base_url, oldest_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 3);
+    // Results in descending order.
+    let ledgers: Vec<u64> = data.iter().map(|e| e["ledger"].as_u64().unwrap()).collect();
+    assert!(ledgers[0] >= ledgers[1]);
+    assert!(ledgers[1] >= ledgers[2]);
+}
+
+#[tokio::test]
+async fn test_order_asc_polling() {
+    let events = make_cross_ledger_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let get = |query: String| {
+        let client = client.clone();
+        let base_url = base_url.clone();
+        async move {
+            let resp = client
+                .get(format!("{}/events?{}", base_url, query))
+                .send()
+                .await
+                .unwrap();
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap();
+            (status, body)
+        }
+    };
+
+    // Pinned to a ledger, asc needs no cursor.
+    let (status, body) = get(format!("order=asc&q={}", q_param("ledger:100"))).await;
+    assert_eq!(status, 200);
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["tx"], format!("100_{:061x}", 0));
+    assert_eq!(data[1]["tx"], format!("100_{:061x}", 1));
+    let first = data[0]["id"].as_str().unwrap().to_string();
+
+    // Following `next` walks forward through every later event, oldest first.
+    let mut cursor = first;
+    let mut seen = Vec::new();
+    loop {
+        let (status, body) = get(format!("order=asc&limit=2&after={}", cursor)).await;
+        assert_eq!(status, 200);
+        let data = body["data"].as_array().unwrap();
+        let next = body["next"].as_str().unwrap().to_string();
+        if data.is_empty() {
+            // At the tip the cursor is echoed back rather than dropped.
+            assert_eq!(next, cursor);
+            break;
+        }
+        for evt in data {
+            seen.push(evt["tx"].as_str().unwrap().to_string());
+        }
+        assert_ne!(next, cursor);
+        cursor = next;
+    }
+    // Transaction hashes are prefixed with the ledger, so they sort in event order.
+    assert_eq!(seen.len(), 5);
+    assert!(seen.windows(2).all(|w| w[0] < w[1]));
+
+    // Invalid combinations.
+    let (status, body) = get(format!("order=asc&before={}", cursor)).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["param"], "order");
+    let (status, _) = get("order=asc".to_string()).await;
+    assert_eq!(status, 400);
+    let (status, body) = get("order=sideways".to_string()).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["param"], "order");
+}
+
+#[tokio::test]
+async fn test_cross_ledger_with_filter() {
+    let events = make_cross_ledger_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // Filter by contract CA — only ledgers 100 and 101 have contract CA.
+    let resp = client
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param("contract:CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA")
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 4);
+    for evt in data {
+        assert_eq!(
+            evt["contract"],
+            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_cross_ledger_all_events() {
+    let events = make_cross_ledger_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // Should return all 6 events across 3 ledgers.
+    let resp = client
+        .get(format!("{}/events?limit=10", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 6);
+    // Descending order: ledger 102, 102, 101, 101, 100, 100.
+    assert_eq!(data[0]["ledger"], 102);
+    assert_eq!(data[1]["ledger"], 102);
     assert_eq!(data[2]["ledger"], 101);
     assert_eq!(data[3]["ledger"], 101);
     assert_eq!(data[4]["ledger"], 100);
     assert_eq!(data[5]["ledger"], 100);
 }
+
+// --- Network passphrase ---
+
+#[tokio::test]
+async fn test_network_passphrase_header() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/events", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()
+            .get("x-stellar-network-passphrase")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        StoreConfig::default().network_passphrase
+    );
+}
+
+#[tokio::test]
+async fn test_network_param_validated() {
+    let events = make_test_events(2, 1000);
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!(
+            "{}/events?network={}",
+            base_url,
+            q_param("Public Global Stellar Network ; September 2015")
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = client
+        .get(format!(
+            "{}/events?network={}",
+            base_url,
+            q_param("Test SDF Network ; September 2015")
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "network_mismatch");
+    assert_eq!(body["error"]["param"], "network");
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({"network": "Test SDF Network ; September 2015"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+// --- Server tuning ---
+
+#[tokio::test]
+async fn test_tuned_server_serves_requests() {
+    let state = Arc::new(AppState {
+        store: EventStore::new(24 * 60 * 60),
+        config: StoreConfig::default().into(),
+        meta_url: String::new(),
+        client: reqwest::Client::new(),
+        peer: None,
+    });
+    let app = api::router(state, None);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind");
+    let addr = listener.local_addr().expect("failed to get addr");
+    let options = stellar_events_api::server::ServerOptions {
+        http1_keep_alive: false,
+        max_connections: Some(1),
+        ..Default::default()
+    };
+    tokio::spawn(stellar_events_api::server::serve(listener, app, options));
+
+    // With a single connection slot and no keep-alive, sequential requests
+    // must each get a fresh connection.
+    let client = reqwest::Client::new();
+    for _ in 0..3 {
+        let resp = client
+            .get(format!("http://{}/health", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+}
+
+// --- HEAD /events ---
+
+#[tokio::test]
+async fn test_head_events_total_count_for_pinned_ledger() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .head(format!(
+            "{}/events?limit=1&q={}",
+            base_url,
+            q_param("ledger:100 type:contract")
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+    assert_eq!(resp.headers().get("x-total-count").unwrap(), "3");
+    assert!(resp.bytes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_head_events_without_pinned_ledger() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .head(format!("{}/events", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("x-total-count").is_none());
+
+    let resp = client
+        .head(format!("{}/events?limit=0", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+// --- Error catalog ---
+
+#[tokio::test]
+async fn test_error_response_includes_doc_url() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/events?limit=0", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "invalid_parameter");
+    let doc_url = body["error"]["doc_url"].as_str().unwrap().to_string();
+    assert_eq!(doc_url, "/errors/invalid_parameter");
+
+    let resp = client
+        .get(format!("{}{}", base_url, doc_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "error_code");
+    assert_eq!(body["code"], "invalid_parameter");
+    assert_eq!(body["status"], 400);
+}
+
+#[tokio::test]
+async fn test_error_catalog_lists_codes() {
+    let base_url = start_test_server(vec![]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/errors", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "list");
+    let codes: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["code"].as_str().unwrap())
+        .collect();
+    assert!(codes.contains(&"invalid_parameter"));
+    assert!(codes.contains(&"network_mismatch"));
+    assert!(codes.contains(&"access_denied"));
+    assert!(codes.contains(&"resource_missing"));
+    assert!(codes.contains(&"internal_error"));
+
+    let resp = client
+        .get(format!("{}/errors/not_a_code", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "resource_missing");
+}
+
+// --- Warnings ---
+
+#[tokio::test]
+async fn test_warnings_for_pinned_ledgers() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // No warnings for a plain query: the field is omitted.
+    let resp = client
+        .get(format!("{}/events?q={}", base_url, q_param("ledger:100")))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body.get("warnings").is_none());
+
+    let resp = client
+        .get(format!("{}/events?q={}", base_url, q_param("ledger:200")))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let warnings = body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].as_str().unwrap().contains("not been ingested"));
+
+    // NDJSON has no envelope, so warnings are sent as headers.
+    let resp = client
+        .get(format!("{}/events?q={}", base_url, q_param("ledger:200")))
+        .header("accept", "application/x-ndjson")
+        .send()
+        .await
+        .unwrap();
+    let warnings: Vec<_> = resp.headers().get_all("x-warning").iter().collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].to_str().unwrap().contains("not been ingested"));
+
+    let resp = client
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param("ledger:100 OR ledger:99 OR type:system")
+        ))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let warnings = body["warnings"].as_array().unwrap();
+    assert!(warnings[0]
+        .as_str()
+        .unwrap()
+        .contains("only ledger 100 is searched"));
+
+    // Every filter pinned: all the ledgers are searched.
+    let resp = client
+        .get(format!(
+            "{}/events?q={}",
+            base_url,
+            q_param("ledger:100 OR ledger:99")
+        ))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body.get("warnings").is_none());
+}
+
+#[tokio::test]
+async fn test_debug_meta() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    // Omitted unless requested.
+    let resp = client
+        .get(format!("{}/events?q={}", base_url, q_param("ledger:100")))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body.get("meta").is_none());
+
+    let resp = client
+        .get(format!(
+            "{}/events?debug=true&q={}",
+            base_url,
+            q_param("ledger:100 type:contract")
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let meta = &body["meta"];
+    assert_eq!(meta["partitions_scanned"], 1);
+    assert!(
+        meta["events_examined"].as_u64().unwrap() >= body["data"].as_array().unwrap().len() as u64
+    );
+    assert_eq!(meta["backfilled"], false);
+    assert_eq!(meta["ledgers_backfilled"], 0);
+    assert!(meta["elapsed_ms"].as_f64().unwrap() >= 0.0);
+    assert!(meta["elapsed_us"].as_u64().is_some());
+    assert_eq!(
+        meta["events_matched"].as_u64().unwrap(),
+        body["data"].as_array().unwrap().len() as u64
+    );
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({ "debug": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["meta"]["partitions_scanned"].as_u64().unwrap() >= 1);
+
+    let resp = client
+        .get(format!("{}/events?debug=yes", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["param"], "debug");
+}
+
+#[tokio::test]
+async fn test_client_list_events_stream() {
+    use futures::{StreamExt, TryStreamExt};
+    use stellar_events_api::client::{Client, ListEventsQuery};
+
+    let events = make_multi_type_events();
+    let total = events.len();
+    let base_url = start_test_server(events).await;
+    let client = Client::new(base_url);
+
+    let page = client
+        .list_events(&ListEventsQuery {
+            limit: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.data.len(), 2);
+    assert!(page.next.is_some());
+
+    let streamed: Vec<_> = client
+        .list_events_stream(ListEventsQuery {
+            limit: Some(2),
+            ..Default::default()
+        })
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(streamed.len(), total);
+    let mut ids: Vec<_> = streamed.iter().map(|e| e.id.clone()).collect();
+    ids.dedup();
+    assert_eq!(ids.len(), total);
+
+    // Errors surface as a typed API error and end the stream.
+    let results: Vec<_> = client
+        .list_events_stream(ListEventsQuery {
+            limit: Some(500),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(results.len(), 1);
+    match &results[0] {
+        Err(stellar_events_api::Error::Api { status, code, .. }) => {
+            assert_eq!(*status, 400);
+            assert_eq!(code, "invalid_parameter");
+        }
+        other => panic!("expected API error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_ip_access_rules_reload() {
+    use stellar_events_api::api::access::{ip_filter, IpAccessControl};
+
+    let rules_path = std::env::temp_dir().join(format!("ip-access-{}.rules", std::process::id()));
+    std::fs::write(&rules_path, "deny 127.0.0.0/8\n").unwrap();
+    let access = Arc::new(IpAccessControl::load(&rules_path, false).unwrap());
+
+    let state = Arc::new(AppState {
+        store: EventStore::new(24 * 60 * 60),
+        config: StoreConfig::default().into(),
+        meta_url: String::new(),
+        client: reqwest::Client::new(),
+        peer: None,
+    });
+    let app = api::router(state, None).layer(axum::middleware::from_fn_with_state(
+        Arc::clone(&access),
+        ip_filter,
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind");
+    let addr = listener.local_addr().expect("failed to get addr");
+    tokio::spawn(stellar_events_api::server::serve(
+        listener,
+        app,
+        Default::default(),
+    ));
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .get(format!("http://{}/health", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "access_denied");
+
+    // Public access allowed, admin restricted to another network.
+    std::fs::write(&rules_path, "allow 127.0.0.1\nadmin-allow 10.0.0.0/8\n").unwrap();
+    access.reload().unwrap();
+    let resp = client
+        .get(format!("http://{}/health", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let resp = client
+        .get(format!("http://{}/admin", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+
+    // A broken file keeps the previous rules.
+    std::fs::write(&rules_path, "allow nonsense\n").unwrap();
+    assert!(access.reload().is_err());
+    let resp = client
+        .get(format!("http://{}/health", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    std::fs::remove_file(&rules_path).unwrap();
+}
+
+// --- Admin endpoints ---
+
+/// Helper: start a server exposing the admin endpoints, signed with `secret`.
+async fn start_admin_server(events: Vec<ExtractedEvent>, secret: &str) -> (String, Arc<AppState>) {
+    let store = EventStore::new(24 * 60 * 60);
+    if !events.is_empty() {
+        store
+            .insert_events(events)
+            .expect("failed to insert events");
+    }
+    let state = Arc::new(AppState {
+        store,
+        config: StoreConfig::default().into(),
+        meta_url: String::new(),
+        client: reqwest::Client::new(),
+        peer: None,
+    });
+
+    let app = api::router(Arc::clone(&state), None).merge(api::admin::router(
+        Arc::clone(&state),
+        api::admin::AdminAuth::new(secret),
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind");
+    let addr = listener.local_addr().expect("failed to get addr");
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    (format!("http://{}", addr), state)
+}
+
+#[tokio::test]
+async fn test_admin_store_stats() {
+    let mut events = make_test_events(3, 100);
+    events.extend(make_test_events(1, 101));
+    let (base_url, _state) = start_admin_server(events, "s3cret").await;
+
+    // Reads are not signed.
+    let resp = reqwest::get(format!("{}/admin/store", base_url))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "store_stats");
+    assert_eq!(body["partition_count"], 2);
+    assert_eq!(body["event_count"], 4);
+    let partitions = body["partitions"].as_array().unwrap();
+    assert_eq!(partitions[0]["ledger"], 100);
+    assert_eq!(partitions[0]["event_count"], 3);
+    assert_eq!(partitions[0]["storage"], "memory");
+    assert!(partitions[0]["expires_at"].as_str().is_some());
+    assert!(
+        partitions[0]["approx_bytes"].as_u64().unwrap()
+            > partitions[1]["approx_bytes"].as_u64().unwrap()
+    );
+    assert_eq!(
+        body["total_bytes"].as_u64().unwrap(),
+        body["partition_bytes"].as_u64().unwrap() + body["index_bytes"].as_u64().unwrap()
+    );
+    assert!(body["index_bytes"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn test_admin_purge_requires_signature() {
+    let mut events = make_test_events(2, 100);
+    events.extend(make_test_events(2, 101));
+    let (base_url, state) = start_admin_server(events, "s3cret").await;
+    let client = reqwest::Client::new();
+    let body = r#"{"start":100,"end":100}"#;
+
+    // Unsigned.
+    let resp = client
+        .post(format!("{}/admin/purge", base_url))
+        .header("content-type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["error"]["code"], "unauthorized");
+
+    // Signed with the wrong secret.
+    let now = chrono::Utc::now().timestamp();
+    let bad =
+        api::admin::AdminAuth::new("wrong").sign(now, "POST", "/admin/purge", body.as_bytes());
+    let resp = client
+        .post(format!("{}/admin/purge", base_url))
+        .header("content-type", "application/json")
+        .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+        .header(api::admin::SIGNATURE_HEADER, bad)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    // Stale timestamp.
+    let old = now - 3600;
+    let sig =
+        api::admin::AdminAuth::new("s3cret").sign(old, "POST", "/admin/purge", body.as_bytes());
+    let resp = client
+        .post(format!("{}/admin/purge", base_url))
+        .header("content-type", "application/json")
+        .header(api::admin::TIMESTAMP_HEADER, old.to_string())
+        .header(api::admin::SIGNATURE_HEADER, sig)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+    assert_eq!(state.store.cached_ledger_count(), 2);
+
+    // Valid signature.
+    let sig =
+        api::admin::AdminAuth::new("s3cret").sign(now, "POST", "/admin/purge", body.as_bytes());
+    let resp = client
+        .post(format!("{}/admin/purge", base_url))
+        .header("content-type", "application/json")
+        .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+        .header(api::admin::SIGNATURE_HEADER, sig)
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["purged"], 1);
+    assert_eq!(state.store.cached_ledger_count(), 1);
+    assert!(!state.store.is_ledger_cached(100).unwrap());
+}
+
+#[tokio::test]
+async fn test_admin_idempotency_key_replay() {
+    let mut events = make_test_events(2, 100);
+    events.extend(make_test_events(2, 101));
+    let (base_url, state) = start_admin_server(events, "s3cret").await;
+    let client = reqwest::Client::new();
+    let auth = api::admin::AdminAuth::new("s3cret");
+
+    let purge = |body: &'static str, key: &'static str| {
+        let client = client.clone();
+        let url = format!("{}/admin/purge", base_url);
+        let now = chrono::Utc::now().timestamp();
+        let sig = auth.sign(now, "POST", "/admin/purge", body.as_bytes());
+        async move {
+            client
+                .post(url)
+                .header("content-type", "application/json")
+                .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+                .header(api::admin::SIGNATURE_HEADER, sig)
+                .header("idempotency-key", key)
+                .body(body)
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+
+    let resp = purge(r#"{"start":100,"end":101}"#, "purge-1").await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("idempotent-replayed").is_none());
+    let first: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(first["purged"], 2);
+
+    // Re-ingest so a second execution would be observable.
+    state
+        .store
+        .insert_events(make_test_events(2, 100))
+        .expect("failed to insert events");
+
+    // A retry replays the stored response without purging again.
+    let resp = purge(r#"{"start":100,"end":101}"#, "purge-1").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["idempotent-replayed"], "true");
+    let replayed: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(replayed, first);
+    assert!(state.store.is_ledger_cached(100).unwrap());
+
+    // Reusing the key for a different request is a conflict.
+    let resp = purge(r#"{"start":100,"end":100}"#, "purge-1").await;
+    assert_eq!(resp.status(), 409);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["error"]["code"], "conflict");
+    assert!(state.store.is_ledger_cached(100).unwrap());
+
+    // A new key executes normally.
+    let resp = purge(r#"{"start":100,"end":100}"#, "purge-2").await;
+    assert_eq!(resp.status(), 200);
+    assert!(!state.store.is_ledger_cached(100).unwrap());
+}
+
+#[tokio::test]
+async fn test_admin_partition_export_import() {
+    let mut events = make_test_events(3, 100);
+    events.extend(make_test_events(2, 101));
+    events.extend(make_test_events(1, 200));
+    let (source_url, _) = start_admin_server(events, "s3cret").await;
+    let (target_url, target) = start_admin_server(make_test_events(1, 101), "s3cret").await;
+    let client = reqwest::Client::new();
+    let export_path = "/admin/partitions/export?start=100&end=150";
+
+    // Exports serialize the whole range, so they must be signed too.
+    let resp = client
+        .get(format!("{}{}", source_url, export_path))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let request = client.get(format!("{}{}", source_url, export_path));
+    let resp = api::admin::AdminAuth::new("s3cret")
+        .sign_get(request, export_path)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/zstd");
+    let snapshot = resp.bytes().await.unwrap();
+
+    // Imports must be signed like any other mutating admin request.
+    let resp = client
+        .post(format!("{}/admin/partitions/import", target_url))
+        .body(snapshot.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let now = chrono::Utc::now().timestamp();
+    let sig = api::admin::AdminAuth::new("s3cret").sign(
+        now,
+        "POST",
+        "/admin/partitions/import",
+        &snapshot,
+    );
+    let resp = client
+        .post(format!("{}/admin/partitions/import", target_url))
+        .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+        .header(api::admin::SIGNATURE_HEADER, sig)
+        .body(snapshot)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["imported"], 1);
+    assert_eq!(json["skipped"], 1);
+    assert!(target.store.is_ledger_cached(100).unwrap());
+    assert!(!target.store.is_ledger_cached(200).unwrap());
+
+    // Imported events are queryable.
+    let resp = client
+        .get(format!("{}/events?q={}", target_url, q_param("ledger:100")))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_backfill_reads_through_peer() {
+    let mut events = make_test_events(3, 100);
+    events.extend(make_test_events(1, 101));
+    let (peer_url, _) = start_admin_server(events, "s3cret").await;
+
+    // The origin is unreachable, so ledger 100 can only come from the peer.
+    let store = EventStore::new(24 * 60 * 60);
+    store.record_ledger_cached(102, 0).unwrap();
+    let state = Arc::new(AppState {
+        store,
+        config: StoreConfig::default().into(),
+        meta_url: "http://127.0.0.1:1".to_string(),
+        client: reqwest::Client::new(),
+        peer: Some(stellar_events_api::snapshot::Peer {
+            url: peer_url,
+            auth: api::admin::AdminAuth::new("s3cret"),
+        }),
+    });
+    let app = api::router(Arc::clone(&state), None);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind");
+    let addr = listener.local_addr().expect("failed to get addr");
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let resp = reqwest::Client::new()
+        .get(format!(
+            "http://{}/events?debug=true&q={}",
+            addr,
+            q_param("ledger:100")
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 3);
+    assert_eq!(body["meta"]["ledgers_backfilled"], 2);
+    assert!(state.store.is_ledger_cached(101).unwrap());
+}
+
+#[tokio::test]
+async fn test_search_qualifier() {
+    let events = make_multi_type_events();
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let count = |q: &'static str| {
+        let client = client.clone();
+        let base_url = base_url.clone();
+        async move {
+            let resp = client
+                .get(format!("{}/events?limit=100&q={}", base_url, q_param(q)))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 200);
+            let body: serde_json::Value = resp.json().await.unwrap();
+            body["data"].as_array().unwrap().len()
+        }
+    };
+
+    // Case-insensitive substring match on topic symbols.
+    assert_eq!(count("ledger:100 search:TRANS").await, 2);
+    assert_eq!(count("ledger:100 search:metric").await, 1);
+    assert_eq!(count("ledger:100 search:nothing_like_this").await, 0);
+    assert_eq!(count("ledger:100 (search:mint OR search:diag)").await, 2);
+
+    // Unpinned queries skip partitions without a matching token.
+    assert_eq!(count("search:mint").await, 1);
+    assert_eq!(count("search:nothing_like_this").await, 0);
+}
+
+#[tokio::test]
+async fn test_list_contracts() {
+    let mut events = make_multi_type_events();
+    let mut later = make_multi_type_events().remove(0);
+    later.ledger_sequence = 105;
+    later.ledger_closed_at = 1700000025;
+    events.push(later);
+
+    let store = EventStore::new(24 * 60 * 60);
+    store.insert_events(events).unwrap();
+    let ca = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    let cb = "CBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB";
+
+    let summaries = store.contract_summaries();
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[0].contract_id, ca);
+    assert_eq!(summaries[0].event_count, 4);
+    assert_eq!(summaries[0].event_types.contract, 3);
+    assert_eq!(summaries[0].event_types.diagnostic, 1);
+    assert_eq!(summaries[0].first_seen_ledger, 100);
+    assert_eq!(summaries[0].last_seen_ledger, 105);
+    assert_eq!(summaries[1].contract_id, cb);
+    assert_eq!(summaries[1].event_count, 1);
+
+    // Dropping a ledger removes its events from the summaries.
+    store.purge_range(105, 105);
+    let summaries = store.contract_summaries();
+    assert_eq!(summaries[0].event_count, 3);
+    assert_eq!(summaries[0].event_types.contract, 2);
+    assert_eq!(summaries[0].last_seen_ledger, 100);
+    store.purge_range(100, 100);
+    assert!(store.contract_summaries().is_empty());
+
+    let mut events = make_multi_type_events();
+    events.truncate(3);
+    let base_url = start_test_server(events).await;
+    let body: serde_json::Value = reqwest::get(format!("{}/contracts", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["object"], "list");
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["object"], "contract");
+    assert_eq!(data[0]["event_count"], 1);
+    assert_eq!(data[0]["id"], ca);
+    assert_eq!(data[0]["url"], format!("/events?q=contract:{}", ca));
+    assert_eq!(
+        data[0]["events_by_type"],
+        serde_json::json!({ "contract": 1, "system": 0, "diagnostic": 0 })
+    );
+    assert_eq!(data[0]["first_seen_ledger"], 100);
+    assert_eq!(data[0]["last_seen_ledger"], 100);
+    assert_eq!(
+        data[0]["last_activity_at"],
+        chrono::DateTime::from_timestamp(1700000000, 0)
+            .unwrap()
+            .to_rfc3339()
+    );
+}
+
+#[tokio::test]
+async fn test_list_transaction_events() {
+    let base_url = start_test_server(make_multi_type_events()).await;
+    let client = reqwest::Client::new();
+
+    // Uppercase hashes are accepted.
+    let resp = client
+        .get(format!(
+            "{}/transactions/{}/events",
+            base_url,
+            "C".repeat(64)
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(
+        body["url"],
+        format!("/transactions/{}/events", "c".repeat(64))
+    );
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert!(data.iter().all(|e| e["tx"] == "c".repeat(64)));
+    assert_eq!(data[0]["type"], "contract");
+    assert_eq!(data[1]["type"], "diagnostic");
+
+    let resp = client
+        .get(format!(
+            "{}/transactions/{}/events",
+            base_url,
+            "d".repeat(64)
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    let resp = client
+        .get(format!("{}/transactions/xyz/events", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["param"], "hash");
+}
+
+#[tokio::test]
+async fn test_list_ledgers() {
+    let mut events = make_test_events(3, 100);
+    events.extend(make_test_events(1, 101));
+    events.extend(make_test_events(2, 102));
+    let base_url = start_test_server(events).await;
+
+    let body: serde_json::Value = reqwest::get(format!("{}/ledgers?limit=2", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["object"], "ledger");
+    assert_eq!(data[0]["ledger"], 102);
+    assert_eq!(data[0]["event_count"], 2);
+    assert_eq!(data[0]["url"], "/events?q=ledger:102");
+    assert!(data[0]["closed_at"].is_string());
+    assert!(data[0]["expires_at"].is_string());
+    assert_eq!(data[1]["ledger"], 101);
+    assert_eq!(body["next"], "/ledgers?limit=2&before=101");
+
+    let body: serde_json::Value = reqwest::get(format!("{}/ledgers?limit=2&before=101", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["ledger"], 100);
+    assert_eq!(data[0]["event_count"], 3);
+    assert!(body.get("next").is_none());
+
+    let resp = reqwest::get(format!("{}/ledgers?limit=0", base_url))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_count_events() {
+    let mut events = make_multi_type_events();
+    events.extend(make_test_events(3, 101));
+    let base_url = start_test_server(events).await;
+
+    let body: serde_json::Value = reqwest::get(format!(
+        "{}/events/count?q={}",
+        base_url,
+        q_param("type:contract")
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(body["object"], "event_count");
+    assert_eq!(body["total"], 6);
+    assert_eq!(
+        body["ledgers"],
+        serde_json::json!([
+            { "ledger": 100, "count": 3 },
+            { "ledger": 101, "count": 3 }
+        ])
+    );
+
+    let body: serde_json::Value = reqwest::get(format!(
+        "{}/events/count?q={}",
+        base_url,
+        q_param("ledger:100 type:diagnostic")
+    ))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["ledgers"].as_array().unwrap().len(), 1);
+
+    let body: serde_json::Value = reqwest::get(format!("{}/events/count", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["total"], 8);
+}
+
+#[tokio::test]
+async fn test_stats() {
+    let mut events = make_multi_type_events();
+    let mut later = make_test_events(2, 101);
+    for event in &mut later {
+        event.ledger_closed_at = 1700000065;
+    }
+    events.extend(later);
+
+    let store = EventStore::new(24 * 60 * 60);
+    store.insert_events(events).unwrap();
+    store.record_ledger_cached(102, 0).unwrap();
+
+    let stats = store.activity_stats(2);
+    assert_eq!(
+        stats.per_minute,
+        vec![(1699999980, 5, 1), (1700000040, 2, 1)]
+    );
+    assert_eq!(stats.ledger_buckets, vec![1, 2, 0, 0, 0]);
+    assert_eq!(
+        stats.top_topics,
+        vec![("transfer".to_string(), 4), ("core_metrics".to_string(), 1)]
+    );
+
+    // Aggregates shrink as ledgers are dropped.
+    store.purge_range(101, 102);
+    let stats = store.activity_stats(10);
+    assert_eq!(stats.per_minute, vec![(1699999980, 5, 1)]);
+    assert_eq!(stats.ledger_buckets, vec![0, 1, 0, 0, 0]);
+    assert_eq!(stats.event_types.contract, 3);
+    assert_eq!(stats.event_types.system, 1);
+    assert_eq!(stats.event_types.diagnostic, 1);
+    assert_eq!(stats.top_topics.len(), 4);
+
+    let base_url = start_test_server(make_multi_type_events()).await;
+    let body: serde_json::Value = reqwest::get(format!("{}/stats?top=1", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["object"], "stats");
+    assert_eq!(body["ledgers"], 1);
+    assert_eq!(body["events"], 5);
+    assert_eq!(body["events_per_minute"][0]["events"], 5);
+    assert_eq!(
+        body["events_per_ledger"][1],
+        serde_json::json!({ "min": 1, "max": 9, "ledgers": 1 })
+    );
+    assert_eq!(body["events_per_ledger"][4]["max"], serde_json::Value::Null);
+    assert_eq!(
+        body["events_by_type"],
+        serde_json::json!({ "contract": 3, "system": 1, "diagnostic": 1 })
+    );
+    assert_eq!(
+        body["top_contracts"],
+        serde_json::json!([{
+            "contract": "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+            "events": 3
+        }])
+    );
+    assert_eq!(
+        body["top_topics"],
+        serde_json::json!([{ "symbol": "transfer", "events": 2 }])
+    );
+
+    let resp = reqwest::get(format!("{}/stats?top=0", base_url))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_stale_cursor_rejected() {
+    let (base_url, state) = start_admin_server(make_test_events(5, 100), "s3cret").await;
+
+    let page = |cursor: String| {
+        let base_url = base_url.clone();
+        async move {
+            reqwest::get(format!("{}/events?limit=2&before={}", base_url, cursor))
+                .await
+                .unwrap()
+        }
+    };
+    let json: serde_json::Value = reqwest::get(format!("{}/events?limit=2", base_url))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let next = json["next"].as_str().unwrap().to_string();
+    assert_eq!(page(next.clone()).await.status(), 200);
+
+    // Re-ingesting the ledger invalidates cursors into it, but not bare
+    // event IDs.
+    state
+        .store
+        .replace_ledger(100, None, make_test_events(5, 100), CacheSource::Sync);
+    let resp = page(next.clone()).await;
+    assert_eq!(resp.status(), 400);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["error"]["code"], "stale_cursor");
+    assert_eq!(json["error"]["param"], "before");
+    let id = next.split('.').next().unwrap().to_string();
+    assert_eq!(page(id).await.status(), 200);
+
+    let resp = page(format!("{}.zz", next)).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_list_events_ndjson() {
+    let base_url = start_test_server(make_test_events(5, 100)).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/events?limit=3", base_url))
+        .header("accept", "application/x-ndjson, application/json;q=0.5")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/x-ndjson");
+    let next = resp.headers()["x-next-cursor"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = resp.text().await.unwrap();
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|e| e["object"] == "event"));
+    // The cursor is the last event's ID, with the ledger's cursor token.
+    assert_eq!(lines[2]["id"], next.split('.').next().unwrap());
+
+    // The JSON envelope is still the default.
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({ "limit": 10 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()["content-type"], "application/json");
+
+    let resp = client
+        .post(format!("{}/events", base_url))
+        .header("accept", "application/x-ndjson")
+        .json(&serde_json::json!({ "limit": 10 }))
+        .send()
+        .await
+        .unwrap();
+    let next = resp.headers()["x-next-cursor"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = resp.text().await.unwrap();
+    assert_eq!(body.lines().count(), 5);
+    let last: serde_json::Value = serde_json::from_str(body.lines().last().unwrap()).unwrap();
+    assert_eq!(last["id"], next.split('.').next().unwrap());
+
+    let resp = client
+        .get(format!("{}/events?limit=3&debug=true", base_url))
+        .header("accept", "application/x-ndjson")
+        .send()
+        .await
+        .unwrap();
+    let meta: serde_json::Value =
+        serde_json::from_str(resp.headers()["x-query-meta"].to_str().unwrap()).unwrap();
+    assert!(meta["elapsed_ms"].is_number());
+    assert_eq!(resp.text().await.unwrap().lines().count(), 3);
+}
+
+#[tokio::test]
+async fn test_xdr_encoding_without_retained_xdr() {
+    // Fixture events carry only XDR-JSON, so the XDR is re-encoded from it.
+    let base_url = start_test_server(make_multi_type_events()).await;
+    let client = reqwest::Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!("{}/events?q=type:system&encoding=xdr", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let event = &body["data"][0];
+    // ScVal::Symbol("core_metrics")
+    assert_eq!(event["topics"][0], "AAAADwAAAAxjb3JlX21ldHJpY3M=");
+    // `{}` is not an ScVal.
+    assert_eq!(event["data"], "");
+}
+
+#[tokio::test]
+async fn test_list_events_include_total() {
+    let mut events = make_test_events(5, 100);
+    events.extend(make_test_events(3, 101));
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/events?limit=2&include_total=true", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()["x-total-count"], "8");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["total"], 8);
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    // The total covers every match, not just those after the cursor.
+    let body: serde_json::Value = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({
+            "q": "ledger:100",
+            "limit": 2,
+            "after": body["next"],
+            "order": "asc",
+            "include_total": true,
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["total"], 5);
+
+    // Omitted unless requested.
+    let body: serde_json::Value = client
+        .get(format!("{}/events", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(body.get("total").is_none());
+
+    let resp = client
+        .get(format!("{}/events?include_total=yes", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_list_events_sparse_fields() {
+    let base_url = start_test_server(make_test_events(3, 100)).await;
+    let client = reqwest::Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!("{}/events?fields=ledger,tx", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let event = body["data"][0].as_object().unwrap();
+    let keys: Vec<&str> = event.keys().map(String::as_str).collect();
+    assert_eq!(keys, ["object", "id", "ledger", "tx"]);
+    assert!(body["next"].is_string());
+
+    let body: serde_json::Value = client
+        .post(format!("{}/events", base_url))
+        .json(&serde_json::json!({ "fields": "topics" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["data"][0].as_object().unwrap().len(), 3);
+    assert_eq!(body["data"][0]["topics"][0]["symbol"], "transfer");
+
+    let resp = client
+        .get(format!("{}/events?fields=ledger,amount", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["param"], "fields");
+}
+
+#[tokio::test]
+async fn test_list_topics() {
+    let ca = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    let mut later = make_multi_type_events().remove(0);
+    later.ledger_sequence = 105;
+
+    let store = EventStore::new(24 * 60 * 60);
+    store.insert_events(make_multi_type_events()).unwrap();
+    store.insert_events(vec![later]).unwrap();
+    assert_eq!(
+        store.topic_counts(Some(ca)),
+        vec![
+            ("transfer".to_string(), 2),
+            ("diag".to_string(), 1),
+            ("mint".to_string(), 1)
+        ]
+    );
+    assert_eq!(store.topic_counts(None)[0], ("transfer".to_string(), 3));
+
+    // Per-contract counts shrink as ledgers are dropped.
+    store.purge_range(105, 105);
+    assert_eq!(store.topic_counts(Some(ca))[0], ("diag".to_string(), 1));
+    assert!(store.topic_counts(Some("CUNKNOWN")).is_empty());
+
+    let base_url = start_test_server(make_multi_type_events()).await;
+    let client = reqwest::Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!("{}/topics", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["object"], "list");
+    let symbols: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["symbol"].as_str().unwrap())
+        .collect();
+    assert_eq!(symbols, ["transfer", "core_metrics", "diag", "mint"]);
+    assert_eq!(body["data"][0]["object"], "topic");
+    assert_eq!(body["data"][0]["event_count"], 2);
+
+    // The url lists the events with that first topic.
+    let url = body["data"][0]["url"].as_str().unwrap();
+    let events: serde_json::Value = client
+        .get(format!("{}{}", base_url, url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(events["data"].as_array().unwrap().len(), 2);
+
+    let body: serde_json::Value = client
+        .get(format!("{}/topics?contract={}&prefix=m", base_url, ca))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["symbol"], "mint");
+
+    let resp = client
+        .get(format!("{}/topics?limit=0", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_suggest_topics() {
+    let ca = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    let store = EventStore::new(24 * 60 * 60);
+    store.insert_events(make_multi_type_events()).unwrap();
+    assert_eq!(
+        store.topics_with_prefix(None, "d"),
+        vec![("diag".to_string(), 1)]
+    );
+    assert!(store.topics_with_prefix(Some(ca), "core").is_empty());
+
+    let base_url = start_test_server(make_multi_type_events()).await;
+    let client = reqwest::Client::new();
+    let suggest = |query: &'static str| {
+        let client = client.clone();
+        let base_url = base_url.clone();
+        async move {
+            client
+                .get(format!("{}/topics/suggest?{}", base_url, query))
+                .send()
+                .await
+                .unwrap()
+        }
+    };
+
+    let body: serde_json::Value = suggest("prefix=tra").await.json().await.unwrap();
+    assert_eq!(body["url"], "/topics/suggest?prefix=tra");
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["symbol"], "transfer");
+    assert_eq!(body["data"][0]["event_count"], 2);
+
+    // An empty prefix suggests every symbol, most frequent first, up to limit.
+    let body: serde_json::Value = suggest("prefix=&limit=2").await.json().await.unwrap();
+    let symbols: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["symbol"].as_str().unwrap())
+        .collect();
+    assert_eq!(symbols, ["transfer", "core_metrics"]);
+
+    let body: serde_json::Value =
+        suggest("prefix=co&contract=CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA")
+            .await
+            .json()
+            .await
+            .unwrap();
+    assert!(body["data"].as_array().unwrap().is_empty());
+
+    assert_eq!(suggest("").await.status(), 400);
+    assert_eq!(suggest("prefix=t&limit=101").await.status(), 400);
+}
+
+#[tokio::test]
+async fn test_latest_events() {
+    let mut events = make_test_events(5, 100);
+    events.extend(make_test_events(3, 101));
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!("{}/events/latest?limit=4", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["object"], "latest_events");
+    assert_eq!(body["ledger"]["object"], "ledger");
+    assert_eq!(body["ledger"]["ledger"], 101);
+    assert_eq!(body["ledger"]["event_count"], 3);
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 4);
+    assert_eq!(data[0]["ledger"], 101);
+    assert_eq!(data[3]["ledger"], 100);
+    assert_eq!(body["poll"], data[0]["id"]);
+
+    // `next` pages through older events on /events.
+    let older: serde_json::Value = client
+        .get(format!(
+            "{}/events?before={}&limit=100",
+            base_url,
+            body["next"].as_str().unwrap()
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(older["data"].as_array().unwrap().len(), 4);
+
+    let resp = client
+        .get(format!("{}/events/latest?order=asc", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    // With nothing cached there is no ledger and no events.
+    let base_url = start_test_server(vec![]).await;
+    let body: serde_json::Value = client
+        .get(format!("{}/events/latest", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(body["ledger"].is_null());
+    assert!(body["data"].as_array().unwrap().is_empty());
+    assert!(body.get("poll").is_none());
+}
+
+#[tokio::test]
+async fn test_list_assets() {
+    let passphrase = StoreConfig::default().network_passphrase;
+    let usdc = "USDC:GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN";
+    let native_sac = sac::contract_id("native", &passphrase).unwrap();
+    let usdc_sac = sac::contract_id(usdc, &passphrase).unwrap();
+    let sac_event = |contract: &str, index: u32, name: &str, asset: &str| {
+        ExtractedEvent::builder()
+            .ledger(100)
+            .event_index(index)
+            .contract(contract)
+            .symbol_topic(name)
+            .topic(serde_json::json!({"address": "GABC"}))
+            .topic(serde_json::json!({"string": asset}))
+            .build()
+    };
+    let events = vec![
+        sac_event(&usdc_sac, 0, "transfer", usdc),
+        sac_event(&usdc_sac, 1, "mint", usdc),
+        sac_event(&native_sac, 2, "transfer", "native"),
+        // A look-alike event from a contract that is not the asset's SAC.
+        sac_event(
+            "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+            3,
+            "transfer",
+            usdc,
+        ),
+    ];
+
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/assets", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["object"], "list");
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+
+    assert_eq!(data[0]["object"], "asset");
+    assert_eq!(data[0]["asset"], usdc);
+    assert_eq!(data[0]["code"], "USDC");
+    assert_eq!(
+        data[0]["issuer"],
+        "GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN"
+    );
+    assert_eq!(data[0]["contract"], usdc_sac.as_str());
+    assert_eq!(data[0]["event_count"], 2);
+
+    assert_eq!(data[1]["asset"], "native");
+    assert_eq!(data[1]["code"], "XLM");
+    assert!(data[1]["issuer"].is_null());
+    assert_eq!(data[1]["contract"], native_sac.as_str());
+    assert_eq!(data[1]["event_count"], 1);
+
+    // The url lists the asset contract's events.
+    let url = data[0]["url"].as_str().unwrap();
+    let events: serde_json::Value = client
+        .get(format!("{}{}", base_url, url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(events["data"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_health_probes() {
+    let state = Arc::new(AppState {
+        store: EventStore::new(24 * 60 * 60),
+        config: StoreConfig::default().into(),
+        meta_url: String::new(),
+        client: reqwest::Client::new(),
+        peer: None,
+    });
+    let app = api::router(Arc::clone(&state), None);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let ready = || async {
+        let resp = client
+            .get(format!("{}/health/ready", base_url))
+            .send()
+            .await
+            .unwrap();
+        let status = resp.status();
+        (status, resp.json::<serde_json::Value>().await.unwrap())
+    };
+
+    let resp = client
+        .get(format!("{}/health/live", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let (status, body) = ready().await;
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["reason"], "no ledgers synced yet");
+
+    state.store.insert_events(make_test_events(1, 100)).unwrap();
+    state
+        .store
+        .set_sync_state("last_synced_ledger", "100")
+        .unwrap();
+    let (status, body) = ready().await;
+    assert_eq!(status, 503);
+    assert_eq!(body["reason"], "network tip not yet known");
+
+    state.store.set_sync_state("network_tip", "150").unwrap();
+    let (status, body) = ready().await;
+    assert_eq!(status, 503);
+    assert_eq!(body["lag_ledgers"], 50);
+    assert_eq!(body["max_lag_ledgers"], 10);
+
+    state.store.set_sync_state("network_tip", "105").unwrap();
+    let (status, body) = ready().await;
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["latest_ledger"], 100);
+    assert!(body.get("reason").is_none());
+
+    // A refreshed config for another network marks the instance not ready.
+    let other_network = StoreConfig {
+        network_passphrase: "Test SDF Network ; September 2015".to_string(),
+        ..Default::default()
+    };
+    assert!(state.config.replace(other_network).is_err());
+    let (status, body) = ready().await;
+    assert_eq!(status, 503);
+    assert_eq!(body["reason"], "meta store config is for another network");
+
+    state.config.replace(StoreConfig::default()).unwrap();
+    let (status, _) = ready().await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn test_admin_backfill_job() {
+    let mut events = make_test_events(2, 100);
+    events.extend(make_test_events(2, 101));
+    let (base_url, _state) = start_admin_server(events, "s3cret").await;
+    let client = reqwest::Client::new();
+    let auth = api::admin::AdminAuth::new("s3cret");
+    let post = |body: &'static str| {
+        let now = chrono::Utc::now().timestamp();
+        client
+            .post(format!("{}/admin/backfill", base_url))
+            .header("content-type", "application/json")
+            .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+            .header(
+                api::admin::SIGNATURE_HEADER,
+                auth.sign(now, "POST", "/admin/backfill", body.as_bytes()),
+            )
+            .body(body)
+            .send()
+    };
+
+    let resp = post(r#"{"start":101,"end":100}"#).await.unwrap();
+    assert_eq!(resp.status(), 400);
+    let resp = post(r#"{"start":0,"end":4000000000}"#).await.unwrap();
+    assert_eq!(resp.status(), 400);
+
+    let resp = post(r#"{"start":100,"end":102}"#).await.unwrap();
+    assert_eq!(resp.status(), 202);
+    let job: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(job["object"], "backfill_job");
+    assert_eq!(job["total"], 3);
+    let id = job["id"].as_str().unwrap().to_string();
+
+    // Ledger 102 is not cached and has no origin to be fetched from.
+    let mut job = job;
+    for _ in 0..100 {
+        job = client
+            .get(format!("{}/admin/backfill/{}", base_url, id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if job["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "completed");
+    assert_eq!(job["processed"], 3);
+    assert_eq!(job["skipped"], 2);
+    assert_eq!(job["fetched"], 0);
+    assert_eq!(job["failed"], 1);
+    assert!(job["completed_at"].is_i64());
+
+    let resp = client
+        .get(format!("{}/admin/backfill/bf_missing", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_list_events_etag() {
+    let base_url = start_test_server(make_test_events(5, 100)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/events?limit=2", base_url);
+
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+    // Same request, same tag.
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.headers()["etag"], etag.as_str());
+
+    let resp = client
+        .get(&url)
+        .header("if-none-match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 304);
+    assert_eq!(resp.headers()["etag"], etag.as_str());
+    assert!(resp.bytes().await.unwrap().is_empty());
+
+    let resp = client
+        .get(&url)
+        .header("if-none-match", format!("\"other\", W/{}", etag))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 304);
+
+    // A different page or representation has a different tag.
+    let resp = client
+        .get(format!("{}/events?limit=3", base_url))
+        .header("if-none-match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_ne!(resp.headers()["etag"], etag.as_str());
+    let resp = client
+        .get(&url)
+        .header("accept", "application/x-ndjson")
+        .header("if-none-match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = client
+        .get(format!("{}/events?limit=2&debug=true", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.headers().get("etag").is_none());
+}
+
+#[tokio::test]
+async fn test_list_account_events() {
+    let account = stellar_strkey::ed25519::PublicKey([7; 32]).to_string();
+    let other = stellar_strkey::ed25519::PublicKey([8; 32]).to_string();
+    let address = |a: &str| serde_json::json!({ "address": a });
+    let events = vec![
+        ExtractedEvent::builder()
+            .ledger(100)
+            .tx_index(0)
+            .symbol_topic("transfer")
+            .topic(address(&account))
+            .topic(address(&other))
+            .build(),
+        ExtractedEvent::builder()
+            .ledger(100)
+            .tx_index(1)
+            .symbol_topic("mint")
+            .topic(address(&account))
+            .build(),
+        ExtractedEvent::builder()
+            .ledger(100)
+            .tx_index(2)
+            .symbol_topic("transfer")
+            .topic(address(&other))
+            .build(),
+    ];
+    let base_url = start_test_server(events).await;
+    let client = reqwest::Client::new();
+    let get = |path: String| {
+        let client = client.clone();
+        async move { client.get(path).send().await.unwrap() }
+    };
+
+    let resp = get(format!("{}/accounts/{}/events", base_url, account)).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["url"], format!("/accounts/{}/events", account));
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    let q = urlencoding::encode(r#"topic0:{"symbol":"transfer"}"#);
+    let body: serde_json::Value = get(format!("{}/accounts/{}/events?q={}", base_url, account, q))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data"][0]["topics"][1]["address"], account);
+
+    let contract = "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    for bad in [contract, "not-an-address"] {
+        let resp = get(format!("{}/accounts/{}/events", base_url, bad)).await;
+        assert_eq!(resp.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn test_additional_network_routes() {
+    let state = |ledger: u32, network_passphrase: &str| {
+        let store = EventStore::new(24 * 60 * 60);
+        store.insert_events(make_test_events(2, ledger)).unwrap();
+        Arc::new(AppState {
+            store,
+            config: StoreConfig {
+                network_passphrase: network_passphrase.to_string(),
+                ..StoreConfig::default()
+            }
+            .into(),
+            meta_url: String::new(),
+            client: reqwest::Client::new(),
+            peer: None,
+        })
+    };
+    let app = api::router(
+        state(1000, "Public Global Stellar Network ; September 2015"),
+        None,
+    )
+    .merge(api::networks_router(vec![(
+        "testnet".to_string(),
+        state(2000, "Test SDF Network ; September 2015"),
+    )]));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/networks/testnet/events?limit=1", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["x-stellar-network-passphrase"],
+        "Test SDF Network ; September 2015"
+    );
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"][0]["ledger"], 2000);
+    assert!(body["url"]
+        .as_str()
+        .unwrap()
+        .starts_with("/networks/testnet/events"));
+
+    // The main network is still served at the root.
+    let body: serde_json::Value = client
+        .get(format!("{}/events?limit=1", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["data"][0]["ledger"], 1000);
+    assert!(body["url"].as_str().unwrap().starts_with("/events"));
+
+    let body: serde_json::Value = client
+        .get(format!("{}/networks", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["data"][0]["name"], "testnet");
+    assert_eq!(body["data"][0]["url"], "/networks/testnet");
+}
+
+#[tokio::test]
+async fn test_sync_status() {
+    let base = start_test_server(make_test_events(1, 1000)).await;
+    let body: serde_json::Value = reqwest::get(format!("{}/sync", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["object"], "sync_status");
+    assert!(body["last_synced_ledger"].is_null());
+    assert!(body.get("history_backfill").is_none());
+}
@@ -0,0 +1,142 @@
+//! Serving under `--base-path`. The base path is process-wide, so these tests
+//! run in their own binary.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use stellar_events_api::api;
+use stellar_events_api::api::base_path;
+use stellar_events_api::db::EventStore;
+use stellar_events_api::ledger::events::ExtractedEvent;
+use stellar_events_api::ledger::path::StoreConfig;
+use stellar_events_api::AppState;
+
+const BASE_PATH: &str = "/stellar-events";
+
+/// Start a server with admin endpoints, nested under [`BASE_PATH`] as the
+/// binary does, and return its origin.
+async fn start_server(secret: &str) -> String {
+    base_path::init(base_path::normalize(BASE_PATH).unwrap());
+
+    let store = EventStore::new(24 * 60 * 60);
+    store
+        .insert_events(vec![
+            ExtractedEvent::builder()
+                .ledger(1000)
+                .event_index(0)
+                .build(),
+            ExtractedEvent::builder()
+                .ledger(1000)
+                .event_index(1)
+                .build(),
+        ])
+        .unwrap();
+    let state = Arc::new(AppState {
+        store,
+        config: StoreConfig::default().into(),
+        meta_url: String::new(),
+        client: reqwest::Client::new(),
+        peer: None,
+    });
+    let app = api::router(Arc::clone(&state), None).merge(api::admin::router(
+        Arc::clone(&state),
+        api::admin::AdminAuth::new(secret),
+    ));
+    let app = axum::Router::new().nest(base_path::get(), app);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    origin
+}
+
+#[tokio::test]
+async fn test_routes_and_links_under_base_path() {
+    let origin = start_server("s3cret").await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/events", origin))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.status(),
+        404,
+        "routes are only served under the prefix"
+    );
+
+    let resp = client
+        .get(format!("{}{}/events?limit=1", origin, BASE_PATH))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["url"], "/stellar-events/events");
+    let event = &body["data"][0];
+    let event_url = event["url"].as_str().unwrap();
+    assert!(event_url.starts_with("/stellar-events/events/evt_"));
+
+    // Links resolve against the origin.
+    let resp = client
+        .get(format!("{}{}", origin, event_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = client
+        .get(format!("{}{}/ledgers?limit=1", origin, BASE_PATH))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["url"], "/stellar-events/ledgers?limit=1");
+    assert_eq!(
+        body["data"][0]["url"],
+        "/stellar-events/events?q=ledger:1000"
+    );
+
+    let resp = client
+        .get(format!("{}{}/events/evt_nope", origin, BASE_PATH))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"]["doc_url"]
+        .as_str()
+        .unwrap()
+        .starts_with("/stellar-events/errors/"));
+}
+
+#[tokio::test]
+async fn test_admin_signature_covers_base_path() {
+    let origin = start_server("s3cret").await;
+    let auth = api::admin::AdminAuth::new("s3cret");
+    let client = reqwest::Client::new();
+
+    let body = br#"{"start":1,"end":2}"#;
+    let post = |signed_path: &str| {
+        let now = chrono::Utc::now().timestamp();
+        client
+            .post(format!("{}{}/admin/purge", origin, BASE_PATH))
+            .header("content-type", "application/json")
+            .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+            .header(
+                api::admin::SIGNATURE_HEADER,
+                auth.sign(now, "POST", signed_path, body),
+            )
+            .body(body.to_vec())
+            .send()
+    };
+
+    let resp = post("/admin/purge").await.unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = post("/stellar-events/admin/purge").await.unwrap();
+    assert_eq!(resp.status(), 200);
+}
@@ -4,7 +4,6 @@
 //! HTTP request → backfill → fetch from origin → zstd decompress →
 //! XDR parse → event extraction → store insert → query → JSON response.
 
-use std::io::Cursor;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -13,144 +12,10 @@ use stellar_events_api::db::{EventQueryParams, EventStore};
 use stellar_events_api::ledger::events::extract_events;
 use stellar_events_api::ledger::fetch::{fetch_ledger_raw, parse_ledger_batch};
 use stellar_events_api::ledger::path::StoreConfig;
+use stellar_events_api::sync::{discover_latest_ledger, run_sync, SyncOptions, TipSource};
+use stellar_events_api::test_util::{build_test_ledger_compressed, start_mock_s3};
 use stellar_events_api::AppState;
 
-use stellar_xdr::curr::*;
-
-/// Build a single contract event.
-fn build_contract_event(contract_byte: u8, event_idx: u32) -> ContractEvent {
-    let mut hash = [0u8; 32];
-    hash[0] = contract_byte;
-
-    ContractEvent {
-        ext: ExtensionPoint::V0,
-        contract_id: Some(ContractId(Hash(hash))),
-        type_: ContractEventType::Contract,
-        body: ContractEventBody::V0(ContractEventV0 {
-            topics: vec![ScVal::Symbol("transfer".try_into().unwrap())]
-                .try_into()
-                .unwrap(),
-            data: ScVal::I128(Int128Parts {
-                hi: 0,
-                lo: (event_idx as u64 + 1) * 1000,
-            }),
-        }),
-    }
-}
-
-/// Build a zstd-compressed XDR LedgerCloseMetaBatch.
-fn build_test_ledger_compressed(ledger_seq: u32, num_txs: usize, events_per_tx: usize) -> Vec<u8> {
-    let mut tx_metas = Vec::new();
-
-    for tx_idx in 0..num_txs {
-        let mut events = Vec::new();
-        for evt_idx in 0..events_per_tx {
-            events.push(build_contract_event(tx_idx as u8, evt_idx as u32));
-        }
-
-        let mut tx_hash = [0u8; 32];
-        tx_hash[0] = tx_idx as u8;
-
-        let trm = TransactionResultMeta {
-            result: TransactionResultPair {
-                transaction_hash: Hash(tx_hash),
-                result: TransactionResult {
-                    fee_charged: 100,
-                    result: TransactionResultResult::TxSuccess(VecM::default()),
-                    ext: TransactionResultExt::V0,
-                },
-            },
-            fee_processing: LedgerEntryChanges(VecM::default()),
-            tx_apply_processing: TransactionMeta::V3(TransactionMetaV3 {
-                ext: ExtensionPoint::V0,
-                tx_changes_before: LedgerEntryChanges(VecM::default()),
-                operations: VecM::default(),
-                tx_changes_after: LedgerEntryChanges(VecM::default()),
-                soroban_meta: Some(SorobanTransactionMeta {
-                    ext: SorobanTransactionMetaExt::V0,
-                    events: events.try_into().unwrap(),
-                    return_value: ScVal::Void,
-                    diagnostic_events: VecM::default(),
-                }),
-            }),
-        };
-
-        tx_metas.push(trm);
-    }
-
-    let header = LedgerHeader {
-        ledger_version: 21,
-        previous_ledger_hash: Hash([0; 32]),
-        scp_value: StellarValue {
-            tx_set_hash: Hash([0; 32]),
-            close_time: TimePoint(1700000000),
-            upgrades: VecM::default(),
-            ext: StellarValueExt::Basic,
-        },
-        tx_set_result_hash: Hash([0; 32]),
-        bucket_list_hash: Hash([0; 32]),
-        ledger_seq,
-        total_coins: 0,
-        fee_pool: 0,
-        inflation_seq: 0,
-        id_pool: 0,
-        base_fee: 100,
-        base_reserve: 5000000,
-        max_tx_set_size: 100,
-        skip_list: [Hash([0; 32]), Hash([0; 32]), Hash([0; 32]), Hash([0; 32])],
-        ext: LedgerHeaderExt::V0,
-    };
-
-    let meta = LedgerCloseMeta::V1(LedgerCloseMetaV1 {
-        ext: LedgerCloseMetaExt::V0,
-        ledger_header: LedgerHeaderHistoryEntry {
-            hash: Hash([0; 32]),
-            header,
-            ext: LedgerHeaderHistoryEntryExt::V0,
-        },
-        tx_set: GeneralizedTransactionSet::V1(TransactionSetV1 {
-            previous_ledger_hash: Hash([0; 32]),
-            phases: VecM::default(),
-        }),
-        tx_processing: tx_metas.try_into().unwrap(),
-        upgrades_processing: VecM::default(),
-        scp_info: VecM::default(),
-        total_byte_size_of_live_soroban_state: 0,
-        evicted_keys: VecM::default(),
-        unused: VecM::default(),
-    });
-
-    let batch = LedgerCloseMetaBatch {
-        start_sequence: ledger_seq,
-        end_sequence: ledger_seq,
-        ledger_close_metas: vec![meta].try_into().unwrap(),
-    };
-
-    let xdr_bytes = batch.to_xdr(Limits::none()).unwrap();
-    zstd::encode_all(Cursor::new(&xdr_bytes), 3).unwrap()
-}
-
-/// Start a mock S3 server that serves the given data for any path.
-async fn start_mock_s3(compressed_data: Vec<u8>) -> String {
-    let data = axum::body::Bytes::from(compressed_data);
-
-    let app = axum::Router::new().fallback(move || {
-        let d = data.clone();
-        async move { d }
-    });
-
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
-    let addr = listener.local_addr().unwrap();
-    let url = format!("http://{}", addr);
-
-    tokio::spawn(async move {
-        axum::serve(listener, app).await.unwrap();
-    });
-
-    tokio::time::sleep(Duration::from_millis(10)).await;
-    url
-}
-
 /// Start a test server with an empty store (except for a seeded latest_ledger).
 async fn start_cold_server(mock_url: &str, seed_latest: u32) -> String {
     let store = EventStore::new(24 * 60 * 60);
@@ -160,9 +25,10 @@ async fn start_cold_server(mock_url: &str, seed_latest: u32) -> String {
 
     let state = Arc::new(AppState {
         store,
-        config: StoreConfig::default(),
+        config: StoreConfig::default().into(),
         meta_url: mock_url.to_string(),
         client: reqwest::Client::new(),
+        peer: None,
     });
 
     let app = api::router(state, None);
@@ -294,6 +160,7 @@ async fn test_cold_fetch_breakdown() {
                 ledger: Some(1000),
                 ..Default::default()
             }],
+            ..Default::default()
         };
         let s = std::time::Instant::now();
         let result = store.query_single_ledger(1000, &params).unwrap();
@@ -359,3 +226,927 @@ async fn test_cold_fetch_breakdown() {
     eprintln!("  TOTAL:            {:>6}µs", total);
     eprintln!();
 }
+
+/// The raw endpoint returns the transaction meta that the event was decoded
+/// from.
+#[tokio::test]
+async fn test_get_event_raw() {
+    use stellar_xdr::curr::{Limits, ReadXdr, TransactionMeta};
+
+    let compressed = build_test_ledger_compressed(1000, 3, 2);
+    let mock_url = start_mock_s3(compressed).await;
+    let base_url = start_cold_server(&mock_url, 1001).await;
+    let client = reqwest::Client::new();
+
+    // Each test transaction's events come from the contract whose ID starts
+    // with the transaction's index.
+    let mut contract = [0u8; 32];
+    contract[0] = 2;
+    let tx2_contract = stellar_strkey::Contract(contract).to_string();
+
+    let resp = client
+        .get(format!("{}/events?q=ledger:1000&limit=100", base_url))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let event = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["contract"] == tx2_contract.as_str())
+        .unwrap()
+        .clone();
+
+    let resp = client
+        .get(format!(
+            "{}{}/raw",
+            base_url,
+            event["url"].as_str().unwrap()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let raw: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(raw["object"], "event_raw");
+    assert_eq!(raw["id"], event["id"]);
+    assert_eq!(raw["tx"], event["tx"]);
+    assert_eq!(raw["tx_index"], 2);
+
+    let meta = TransactionMeta::from_xdr_base64(
+        raw["transaction_meta_xdr"].as_str().unwrap(),
+        Limits::none(),
+    )
+    .unwrap();
+    let TransactionMeta::V3(v3) = meta else {
+        panic!("expected TransactionMeta::V3");
+    };
+    assert_eq!(v3.soroban_meta.unwrap().events.len(), 2);
+
+    let resp = client
+        .get(format!("{}/events/evt_invalid/raw", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+/// A transient server error from the origin is retried instead of failing
+/// the fetch.
+#[tokio::test]
+async fn test_fetch_retries_server_errors() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use stellar_events_api::ledger::fetch::fetch_ledger_raw_cached;
+
+    let compressed = axum::body::Bytes::from(build_test_ledger_compressed(1000, 2, 1));
+    let requests = Arc::new(AtomicUsize::new(0));
+    let app = {
+        let requests = Arc::clone(&requests);
+        axum::Router::new().fallback(move || {
+            let (data, requests) = (compressed.clone(), requests.clone());
+            async move {
+                if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                }
+                data.into_response()
+            }
+        })
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mock_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let raw = fetch_ledger_raw_cached(&client, &mock_url, &StoreConfig::default(), 1000, None)
+        .await
+        .unwrap();
+    assert!(!raw.is_empty());
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+/// Disk-cached batches are served without a request until they go stale, and
+/// are then revalidated with a conditional request.
+#[tokio::test]
+async fn test_disk_cache_conditional_refetch() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use stellar_events_api::ledger::disk_cache::DiskCache;
+    use stellar_events_api::ledger::fetch::fetch_ledger_raw_cached;
+
+    const ETAG: &str = "\"batch-v1\"";
+    let compressed = axum::body::Bytes::from(build_test_ledger_compressed(1000, 2, 1));
+    let full = Arc::new(AtomicUsize::new(0));
+    let not_modified = Arc::new(AtomicUsize::new(0));
+
+    let app = {
+        let (full, not_modified) = (Arc::clone(&full), Arc::clone(&not_modified));
+        axum::Router::new().fallback(move |headers: HeaderMap| {
+            let (data, full, not_modified) =
+                (compressed.clone(), full.clone(), not_modified.clone());
+            async move {
+                if headers.get("if-none-match").is_some_and(|v| v == ETAG) {
+                    not_modified.fetch_add(1, Ordering::SeqCst);
+                    return StatusCode::NOT_MODIFIED.into_response();
+                }
+                full.fetch_add(1, Ordering::SeqCst);
+                ([("etag", ETAG)], data).into_response()
+            }
+        })
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mock_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let dir = std::env::temp_dir().join(format!(
+        "stellar-events-api-conditional-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let client = reqwest::Client::new();
+    let config = StoreConfig::default();
+
+    // Fresh entries are served from disk without contacting the origin.
+    let cache = DiskCache::open(&dir, 1 << 20).unwrap();
+    let first = fetch_ledger_raw_cached(&client, &mock_url, &config, 1000, Some(&cache))
+        .await
+        .unwrap();
+    let second = fetch_ledger_raw_cached(&client, &mock_url, &config, 1000, Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(full.load(Ordering::SeqCst), 1);
+    assert_eq!(not_modified.load(Ordering::SeqCst), 0);
+
+    // Stale entries are revalidated; a 304 keeps the cached copy.
+    let cache = DiskCache::open(&dir, 1 << 20)
+        .unwrap()
+        .with_revalidation(Duration::ZERO);
+    let third = fetch_ledger_raw_cached(&client, &mock_url, &config, 1000, Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(first, third);
+    assert_eq!(full.load(Ordering::SeqCst), 1);
+    assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Sync with an end ledger stops once the range is ingested and leaves the
+/// fetched ledgers cached for serving.
+#[tokio::test]
+async fn test_sync_stops_at_end_ledger() {
+    let compressed = build_test_ledger_compressed(1000, 2, 1);
+    let mock_url = start_mock_s3(compressed).await;
+    let client = reqwest::Client::new();
+
+    let state = Arc::new(AppState {
+        store: EventStore::new(24 * 60 * 60),
+        config: StoreConfig::default().into(),
+        meta_url: mock_url.clone(),
+        client: client.clone(),
+        peer: None,
+    });
+
+    let options = SyncOptions {
+        start_ledger: Some(1000),
+        end_ledger: Some(1004),
+        parallel_fetches: 3,
+        ..Default::default()
+    };
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        run_sync(client, mock_url, Arc::clone(&state), options),
+    )
+    .await
+    .expect("sync should stop at the end ledger");
+
+    for seq in 1000..=1004 {
+        assert!(state.store.is_ledger_cached(seq).unwrap());
+    }
+    assert!(!state.store.is_ledger_cached(1005).unwrap());
+    assert_eq!(
+        state.store.get_sync_state("last_synced_ledger").unwrap(),
+        Some("1004".to_string())
+    );
+}
+
+/// Start a mock server that answers every request with the given JSON body.
+async fn start_mock_json(body: serde_json::Value) -> String {
+    let app = axum::Router::new().fallback(move || {
+        let b = body.clone();
+        async move { axum::Json(b) }
+    });
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let url = format!("http://{}", addr);
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    url
+}
+
+#[tokio::test]
+async fn test_discover_latest_ledger_sources() {
+    let client = reqwest::Client::new();
+
+    let horizon = start_mock_json(serde_json::json!({"history_latest_ledger": 5000})).await;
+    assert_eq!(
+        discover_latest_ledger(&client, &TipSource::Horizon(horizon)).await,
+        Some(5000)
+    );
+
+    let rpc = start_mock_json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "result": {"id": "abc", "protocolVersion": 22, "sequence": 6000}
+    }))
+    .await;
+    assert_eq!(
+        discover_latest_ledger(&client, &TipSource::Rpc(rpc.clone())).await,
+        Some(6000)
+    );
+
+    // A Horizon-shaped response is not a valid RPC response.
+    let wrong = start_mock_json(serde_json::json!({"history_latest_ledger": 5000})).await;
+    assert_eq!(
+        discover_latest_ledger(&client, &TipSource::Rpc(wrong)).await,
+        None
+    );
+}
+
+/// GET /ledgers/{seq} fetches an uncached ledger and reports its header.
+#[tokio::test]
+async fn test_get_ledger_detail() {
+    let compressed = build_test_ledger_compressed(1000, 3, 2);
+    let mock_url = start_mock_s3(compressed).await;
+    let base_url = start_cold_server(&mock_url, 1001).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/ledgers/1000", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "ledger");
+    assert_eq!(body["ledger"], 1000);
+    assert_eq!(body["event_count"], 6);
+    assert_eq!(body["tx_count"], 3);
+    assert_eq!(body["protocol_version"], 21);
+    assert_eq!(body["closed_at"], "2023-11-14T22:13:20+00:00");
+    assert_eq!(body["status"], "cached");
+
+    let resp = client
+        .get(format!("{}/ledgers/latest", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_list_events_xdr_encoding() {
+    use stellar_xdr::curr::{Limits, ReadXdr, ScVal};
+
+    let compressed = build_test_ledger_compressed(1000, 1, 2);
+    let mock_url = start_mock_s3(compressed).await;
+    let base_url = start_cold_server(&mock_url, 1001).await;
+    let client = reqwest::Client::new();
+
+    let list = |encoding: &'static str| {
+        let client = client.clone();
+        let url = format!(
+            "{}/events?q=ledger:1000&order=asc&encoding={}",
+            base_url, encoding
+        );
+        async move {
+            let body: serde_json::Value =
+                client.get(url).send().await.unwrap().json().await.unwrap();
+            body["data"].as_array().unwrap().clone()
+        }
+    };
+    let json = list("json").await;
+    let xdr = list("xdr").await;
+    assert_eq!(json.len(), 2);
+    assert_eq!(xdr.len(), 2);
+
+    // The XDR decodes to the same values as the XDR-JSON rendering.
+    for (j, x) in json.iter().zip(&xdr) {
+        assert_eq!(j["id"], x["id"]);
+        let decode = |v: &serde_json::Value| {
+            let val = ScVal::from_xdr_base64(v.as_str().unwrap(), Limits::none()).unwrap();
+            serde_json::to_value(val).unwrap()
+        };
+        let topics: Vec<_> = x["topics"].as_array().unwrap().iter().map(decode).collect();
+        assert_eq!(serde_json::Value::from(topics), j["topics"]);
+        assert_eq!(decode(&x["data"]), j["data"]);
+    }
+
+    let id = xdr[0]["id"].as_str().unwrap();
+    let event: serde_json::Value = client
+        .get(format!("{}/events/{}?encoding=xdr", base_url, id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(event["data"], xdr[0]["data"]);
+
+    let resp = client
+        .get(format!("{}/events?encoding=base64", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_expand_transaction() {
+    let compressed = build_test_ledger_compressed(1000, 2, 2);
+    let mock_url = start_mock_s3(compressed).await;
+    let base_url = start_cold_server(&mock_url, 1001).await;
+    let client = reqwest::Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/events?q=ledger:1000&order=asc&expand=transaction",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 4);
+    let transaction = &data[0]["transaction"];
+    assert_eq!(
+        transaction["source_account"],
+        stellar_strkey::ed25519::PublicKey([1; 32]).to_string()
+    );
+    assert_eq!(transaction["operation_count"], 1);
+    assert_eq!(transaction["max_fee"], 200);
+    assert_eq!(transaction["fee_charged"], 100);
+    assert!(transaction.get("fee_account").is_none());
+    assert_eq!(
+        data[2]["transaction"]["source_account"],
+        stellar_strkey::ed25519::PublicKey([2; 32]).to_string()
+    );
+
+    // Single events expand the same way, and only when asked.
+    let id = data[0]["id"].as_str().unwrap();
+    let event: serde_json::Value = client
+        .get(format!("{}/events/{}?expand=transaction", base_url, id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(event["transaction"], *transaction);
+    let event: serde_json::Value = client
+        .get(format!("{}/events/{}", base_url, id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(event.get("transaction").is_none());
+
+    let resp = client
+        .get(format!("{}/events?expand=ledger", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_admin_reload_ledger() {
+    let compressed = build_test_ledger_compressed(1000, 3, 2);
+    let mock_url = start_mock_s3(compressed).await;
+
+    // A stale, empty partition for the ledger is already cached.
+    let store = EventStore::new(24 * 60 * 60);
+    store.record_ledger_cached(1000, 0).unwrap();
+    let state = Arc::new(AppState {
+        store,
+        config: StoreConfig::default().into(),
+        meta_url: mock_url,
+        client: reqwest::Client::new(),
+        peer: None,
+    });
+    let auth = api::admin::AdminAuth::new("s3cret");
+    let app = api::router(Arc::clone(&state), None)
+        .merge(api::admin::router(Arc::clone(&state), auth.clone()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let client = reqwest::Client::new();
+    let post = |path: &str| {
+        let now = chrono::Utc::now().timestamp();
+        client
+            .post(format!("{}{}", base_url, path))
+            .header(api::admin::TIMESTAMP_HEADER, now.to_string())
+            .header(
+                api::admin::SIGNATURE_HEADER,
+                auth.sign(now, "POST", path, b""),
+            )
+            .send()
+    };
+
+    let resp = post("/admin/ledgers/1000/reload").await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "ledger");
+    assert_eq!(body["ledger"], 1000);
+    assert_eq!(body["event_count"], 6);
+    assert_eq!(body["tx_count"], 3);
+    let contract_events = |state: &AppState| -> u64 {
+        state
+            .store
+            .contract_summaries()
+            .iter()
+            .map(|c| c.event_count)
+            .sum()
+    };
+    assert_eq!(contract_events(&state), 6);
+
+    // Reloading again replaces the partition rather than adding to it.
+    let resp = post("/admin/ledgers/1000/reload").await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        contract_events(&state),
+        6,
+        "per-contract counts must not double"
+    );
+
+    let resp = post("/admin/ledgers/latest/reload").await.unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_export_backfills_range() {
+    let compressed = build_test_ledger_compressed(1000, 3, 2);
+    let mock_url = start_mock_s3(compressed).await;
+    let base_url = start_cold_server(&mock_url, 2000).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(format!("{}/export?start=1000&end=1000", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+    assert!(resp.headers()["content-disposition"]
+        .to_str()
+        .unwrap()
+        .contains("events-1000-1000.ndjson"));
+    let body = resp.text().await.unwrap();
+    let events: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 6, "uncached ledger should be backfilled");
+    assert!(events.iter().all(|e| e["ledger"] == 1000));
+    let listed: serde_json::Value = client
+        .get(format!(
+            "{}/events?q=ledger:1000&order=asc&limit=100",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let listed: Vec<&serde_json::Value> = listed["data"].as_array().unwrap().iter().collect();
+    assert_eq!(
+        events.iter().map(|e| &e["id"]).collect::<Vec<_>>(),
+        listed.iter().map(|e| &e["id"]).collect::<Vec<_>>(),
+        "export should match /events order"
+    );
+
+    let resp = client
+        .get(format!(
+            "{}/export?start=1000&end=1000&format=csv",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = resp.text().await.unwrap();
+    let mut lines = body.lines();
+    assert_eq!(
+        lines.next(),
+        Some("id,ledger,at,tx,type,contract,topics,data")
+    );
+    assert_eq!(lines.count(), 6);
+
+    for query in [
+        "start=1000",
+        "start=1001&end=1000",
+        "start=1000&end=20000",
+        "start=1000&end=1000&format=xml",
+    ] {
+        let resp = client
+            .get(format!("{}/export?{}", base_url, query))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400, "{} should be rejected", query);
+    }
+}
+
+/// Ledgers can be read from a Stellar RPC server's `getLedgers` method, and a
+/// ledger the server does not have is reported as not found.
+#[tokio::test]
+async fn test_fetch_ledger_from_rpc() {
+    use stellar_events_api::sync::fetch_and_extract_rpc;
+    use stellar_xdr::curr::{Limits, WriteXdr};
+
+    let raw = zstd::decode_all(&build_test_ledger_compressed(1000, 2, 3)[..]).unwrap();
+    let meta = parse_ledger_batch(&raw).unwrap().ledger_close_metas[0]
+        .to_xdr_base64(Limits::none())
+        .unwrap();
+    let app = axum::Router::new().fallback(move |body: axum::Json<serde_json::Value>| {
+        let meta = meta.clone();
+        async move {
+            let start = body["params"]["startLedger"].as_u64().unwrap();
+            if start != 1000 {
+                return axum::Json(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": { "code": -32600, "message": "start is after the latest ledger" },
+                }));
+            }
+            axum::Json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "ledgers": [{ "sequence": 1000, "metadataXdr": meta }],
+                    "latestLedger": 1000,
+                },
+            }))
+        }
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rpc_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let config = StoreConfig::default();
+    let (info, events) = fetch_and_extract_rpc(&client, &rpc_url, &config, 1000)
+        .await
+        .unwrap();
+    assert!(info.is_some());
+    assert_eq!(events.len(), 6);
+    assert!(events.iter().all(|e| e.ledger_sequence == 1000));
+
+    let missing = fetch_and_extract_rpc(&client, &rpc_url, &config, 1001).await;
+    assert!(matches!(
+        missing,
+        Err(stellar_events_api::Error::LedgerNotFound(1001))
+    ));
+}
+
+/// A `file://` meta URL reads the config and ledgers from a local directory
+/// laid out like the bucket.
+#[tokio::test]
+async fn test_fetch_from_local_directory() {
+    use stellar_events_api::ledger::fetch::fetch_config;
+    use stellar_events_api::sync::fetch_and_extract;
+
+    let dir = std::env::temp_dir().join(format!(
+        "stellar-events-api-local-meta-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let default = StoreConfig::default();
+    let ledger_path = dir.join(default.path_for_ledger(1000));
+    std::fs::create_dir_all(ledger_path.parent().unwrap()).unwrap();
+    std::fs::write(
+        dir.join(".config.json"),
+        serde_json::json!({
+            "networkPassphrase": default.network_passphrase,
+            "ledgersPerBatch": default.ledgers_per_batch,
+            "batchesPerPartition": default.batches_per_partition,
+            "compression": "zstd",
+            "version": "1.0",
+        })
+        .to_string(),
+    )
+    .unwrap();
+    std::fs::write(&ledger_path, build_test_ledger_compressed(1000, 2, 3)).unwrap();
+
+    let client = reqwest::Client::new();
+    let meta_url = format!("file://{}", dir.display());
+    let config = fetch_config(&client, &meta_url).await.unwrap();
+    assert_eq!(config.network_passphrase, default.network_passphrase);
+
+    let (_, events) = fetch_and_extract(&client, &meta_url, &config, 1000)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 6);
+    assert!(matches!(
+        fetch_and_extract(&client, &meta_url, &config, 1001).await,
+        Err(stellar_events_api::Error::LedgerNotFound(1001))
+    ));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Refreshing the store config revalidates it with the `ETag` from the
+/// previous fetch.
+#[tokio::test]
+async fn test_refetch_config_conditional() {
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use stellar_events_api::ledger::fetch::refetch_config;
+
+    const ETAG: &str = "\"config-v1\"";
+    let app = axum::Router::new().fallback(|headers: HeaderMap| async move {
+        if headers.get("if-none-match").is_some_and(|v| v == ETAG) {
+            return StatusCode::NOT_MODIFIED.into_response();
+        }
+        (
+            [("etag", ETAG)],
+            serde_json::json!({
+                "networkPassphrase": "Public Global Stellar Network ; September 2015",
+                "ledgersPerBatch": 1,
+                "batchesPerPartition": 64000,
+                "compression": "zstd",
+                "version": "1.0",
+            })
+            .to_string(),
+        )
+            .into_response()
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mock_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let (config, etag) = refetch_config(&client, &mock_url, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(config.batches_per_partition, 64000);
+    assert_eq!(etag.as_deref(), Some(ETAG));
+    assert!(refetch_config(&client, &mock_url, etag.as_deref())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+/// Concurrent fetches of the same ledger share one request to the origin.
+#[tokio::test]
+async fn test_concurrent_fetches_coalesce() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use stellar_events_api::sync::fetch_and_extract;
+
+    let compressed = axum::body::Bytes::from(build_test_ledger_compressed(1000, 2, 1));
+    let requests = Arc::new(AtomicUsize::new(0));
+    let app = {
+        let requests = Arc::clone(&requests);
+        axum::Router::new().fallback(move || {
+            let (data, requests) = (compressed.clone(), requests.clone());
+            async move {
+                requests.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                data
+            }
+        })
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mock_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let config = StoreConfig::default();
+    let results = futures::future::join_all(
+        (0..5).map(|_| fetch_and_extract(&client, &mock_url, &config, 1000)),
+    )
+    .await;
+    assert!(results.iter().all(|r| r.as_ref().unwrap().1.len() == 2));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // Once finished, the next fetch goes to the origin again.
+    fetch_and_extract(&client, &mock_url, &config, 1000)
+        .await
+        .unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+/// Batches parsed as their response streams in match the buffered parse, and
+/// are still written to the disk cache.
+#[tokio::test]
+async fn test_streamed_batch_parse() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use stellar_events_api::ledger::disk_cache::DiskCache;
+    use stellar_events_api::ledger::fetch::fetch_ledger_batch_cached;
+
+    let compressed = build_test_ledger_compressed(1000, 20, 10);
+    let expected = parse_ledger_batch(&zstd::decode_all(&compressed[..]).unwrap()).unwrap();
+    let compressed = axum::body::Bytes::from(compressed);
+    let requests = Arc::new(AtomicUsize::new(0));
+    let app = {
+        let requests = Arc::clone(&requests);
+        axum::Router::new().fallback(move || {
+            let (data, requests) = (compressed.clone(), requests.clone());
+            async move {
+                requests.fetch_add(1, Ordering::SeqCst);
+                // Send the body in small chunks.
+                let chunks: Vec<Result<axum::body::Bytes, std::io::Error>> = data
+                    .chunks(256)
+                    .map(|c| Ok(axum::body::Bytes::copy_from_slice(c)))
+                    .collect();
+                axum::body::Body::from_stream(futures::stream::iter(chunks))
+            }
+        })
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mock_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let dir = std::env::temp_dir().join(format!(
+        "stellar-events-api-streamed-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let cache = DiskCache::open(&dir, 1 << 20).unwrap();
+    let client = reqwest::Client::new();
+    let config = StoreConfig::default();
+
+    let streamed = fetch_ledger_batch_cached(&client, &mock_url, &config, 1000, Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(streamed, expected);
+    let cached = fetch_ledger_batch_cached(&client, &mock_url, &config, 1000, Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(cached, expected);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Sync checks each ledger's previous ledger hash against the header of the
+/// ledger before it, recording a ledger that breaks the chain.
+#[tokio::test]
+async fn test_sync_detects_hash_chain_break() {
+    use stellar_events_api::ledger::events::extract_ledger_info;
+    use stellar_events_api::sync::{
+        HASH_CHAIN_BREAKS_KEY, HASH_CHAIN_BREAK_LEDGER_KEY, LAST_SYNCED_HASH_KEY,
+    };
+    use stellar_events_api::test_util::build_chained_test_ledger_compressed;
+
+    let dir = std::env::temp_dir().join(format!(
+        "stellar-events-api-hash-chain-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let config = StoreConfig::default();
+    let hash_of = |compressed: &[u8], seq: u32| {
+        let batch = parse_ledger_batch(&zstd::decode_all(compressed).unwrap()).unwrap();
+        extract_ledger_info(&batch, seq).unwrap().hash
+    };
+
+    // 2001 follows on from 2000, 2002 does not, and 2003 follows on from 2002.
+    let mut previous = [0; 32];
+    let mut last_hash = [0; 32];
+    for seq in 2000..=2003 {
+        let compressed = build_chained_test_ledger_compressed(seq, 1, 1, previous);
+        last_hash = hash_of(&compressed, seq);
+        previous = if seq == 2001 { [7; 32] } else { last_hash };
+        let path = dir.join(config.path_for_ledger(seq));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, compressed).unwrap();
+    }
+
+    let client = reqwest::Client::new();
+    let meta_url = format!("file://{}", dir.display());
+    let state = Arc::new(AppState {
+        store: EventStore::new(24 * 60 * 60),
+        config: config.into(),
+        meta_url: meta_url.clone(),
+        client: client.clone(),
+        peer: None,
+    });
+    let options = SyncOptions {
+        start_ledger: Some(2000),
+        end_ledger: Some(2003),
+        ..Default::default()
+    };
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        run_sync(client, meta_url, Arc::clone(&state), options),
+    )
+    .await
+    .expect("sync should stop at the end ledger");
+
+    let read = |key: &str| state.store.get_sync_state(key).unwrap();
+    assert_eq!(read(HASH_CHAIN_BREAKS_KEY), Some("1".to_string()));
+    assert_eq!(read(HASH_CHAIN_BREAK_LEDGER_KEY), Some("2002".to_string()));
+    assert_eq!(read(LAST_SYNCED_HASH_KEY), Some(hex::encode(last_hash)));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// A ledger sync skips because it is already cached breaks the chain of
+/// ledgers sync fetched, so the ledger after it is not checked against an
+/// older ledger's hash.
+#[tokio::test]
+async fn test_sync_skipping_cached_ledger_is_not_a_hash_chain_break() {
+    use stellar_events_api::db::CacheSource;
+    use stellar_events_api::ledger::events::extract_ledger_info;
+    use stellar_events_api::sync::HASH_CHAIN_BREAKS_KEY;
+    use stellar_events_api::test_util::build_chained_test_ledger_compressed;
+
+    let dir = std::env::temp_dir().join(format!(
+        "stellar-events-api-hash-chain-skip-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let config = StoreConfig::default();
+    let mut previous = [0; 32];
+    for seq in 3000..=3002 {
+        let compressed = build_chained_test_ledger_compressed(seq, 1, 1, previous);
+        let batch = parse_ledger_batch(&zstd::decode_all(&compressed[..]).unwrap()).unwrap();
+        previous = extract_ledger_info(&batch, seq).unwrap().hash;
+        let path = dir.join(config.path_for_ledger(seq));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, compressed).unwrap();
+    }
+
+    let client = reqwest::Client::new();
+    let meta_url = format!("file://{}", dir.display());
+    let state = Arc::new(AppState {
+        store: EventStore::new(24 * 60 * 60),
+        config: config.into(),
+        meta_url: meta_url.clone(),
+        client: client.clone(),
+        peer: None,
+    });
+    state
+        .store
+        .insert_ledger(3001, None, Vec::new(), CacheSource::Sync)
+        .unwrap();
+    let options = SyncOptions {
+        start_ledger: Some(3000),
+        end_ledger: Some(3002),
+        parallel_fetches: 1,
+        ..Default::default()
+    };
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        run_sync(client, meta_url, Arc::clone(&state), options),
+    )
+    .await
+    .expect("sync should stop at the end ledger");
+
+    assert!(state.store.is_ledger_cached(3002).unwrap());
+    assert_eq!(
+        state.store.get_sync_state(HASH_CHAIN_BREAKS_KEY).unwrap(),
+        None
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}